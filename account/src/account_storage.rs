@@ -15,17 +15,19 @@ use starcoin_storage::storage::{KeyCodec, ValueCodec};
 use starcoin_storage::{
     define_storage,
     storage::{CodecKVStore, ColumnFamilyName, StorageInstance},
+    ColumnFamily,
 };
 use starcoin_types::account_address::AccountAddress;
 use starcoin_types::account_config::token_code::TokenCode;
 use std::convert::TryFrom;
 use std::path::Path;
 
-pub const SETTING_PREFIX_NAME: ColumnFamilyName = "account_settings";
-pub const ENCRYPTED_PRIVATE_KEY_PREFIX_NAME: ColumnFamilyName = "encrypted_private_key";
-pub const PUBLIC_KEY_PREFIX_NAME: ColumnFamilyName = "public_key";
-pub const ACCEPTED_TOKEN_PREFIX_NAME: ColumnFamilyName = "accepted_token";
-pub const GLOBAL_PREFIX_NAME: ColumnFamilyName = "global";
+pub const SETTING_PREFIX_NAME: ColumnFamilyName = ColumnFamily::AccountSettings.name();
+pub const ENCRYPTED_PRIVATE_KEY_PREFIX_NAME: ColumnFamilyName =
+    ColumnFamily::EncryptedPrivateKey.name();
+pub const PUBLIC_KEY_PREFIX_NAME: ColumnFamilyName = ColumnFamily::PublicKey.name();
+pub const ACCEPTED_TOKEN_PREFIX_NAME: ColumnFamilyName = ColumnFamily::AcceptedToken.name();
+pub const GLOBAL_PREFIX_NAME: ColumnFamilyName = ColumnFamily::AccountGlobal.name();
 
 define_storage!(
     AccountSettingStore,
@@ -6,14 +6,17 @@ use starcoin_crypto::HashValue;
 use starcoin_state_api::ChainStateReader;
 use starcoin_statedb::ChainStateDB;
 use starcoin_time_service::TimeService;
+use starcoin_types::account_address::AccountAddress;
 use starcoin_types::block::BlockIdAndNumber;
+use starcoin_types::filter::Filter;
 use starcoin_types::startup_info::{ChainInfo, ChainStatus};
-use starcoin_types::transaction::RichTransactionInfo;
+use starcoin_types::transaction::{RichTransactionInfo, TransactionInfo, TransactionReceipt};
 use starcoin_types::{
-    block::{Block, BlockHeader, BlockInfo, BlockNumber},
+    block::{Block, BlockHeader, BlockInfo, BlockNumber, BlockOverview},
     transaction::Transaction,
     U256,
 };
+use starcoin_vm_types::genesis_config::{BlockBytesLimit, DifficultyFloor};
 use starcoin_vm_types::on_chain_resource::Epoch;
 use std::collections::HashMap;
 
@@ -25,6 +28,15 @@ use starcoin_vm_types::contract_event::ContractEvent;
 pub struct VerifiedBlock(pub Block);
 pub type MintedUncleNumber = u64;
 
+/// The identifier of a block on the chain, the `Latest` is the current head.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockId {
+    Hash(HashValue),
+    Number(BlockNumber),
+    Latest,
+    Genesis,
+}
+
 pub trait ChainReader {
     fn info(&self) -> ChainInfo;
     fn status(&self) -> ChainStatus;
@@ -33,16 +45,25 @@ pub trait ChainReader {
     fn current_header(&self) -> BlockHeader;
     fn get_header(&self, hash: HashValue) -> Result<Option<BlockHeader>>;
     fn get_header_by_number(&self, number: BlockNumber) -> Result<Option<BlockHeader>>;
+    /// Get up to `count` headers from `from` back toward genesis, the header of `from` is inclusive,
+    /// and the headers are ordered from `from` to its ancestors.
+    fn get_header_chain(&self, from: HashValue, count: u64) -> Result<Vec<BlockHeader>>;
+    /// Get up to `count` latest headers, ordered from the head to its ancestors.
+    fn get_latest_headers(&self, count: u64) -> Result<Vec<BlockHeader>>;
     fn get_block_by_number(&self, number: BlockNumber) -> Result<Option<Block>>;
     /// Get latest `count` blocks before `number`. if `number` is absent, use head block number.
     /// the block of `number` is inclusive.
     fn get_blocks_by_number(&self, number: Option<BlockNumber>, count: u64) -> Result<Vec<Block>>;
     fn get_block(&self, hash: HashValue) -> Result<Option<Block>>;
+    /// Get the block by the hash, number or tag in `id`, return None if it is not on the chain.
+    fn get_block_by_id(&self, id: BlockId) -> Result<Option<Block>>;
     /// Get block hash by block number, if not exist, return None
     fn get_hash_by_number(&self, number: BlockNumber) -> Result<Option<HashValue>>;
     fn get_transaction(&self, hash: HashValue) -> Result<Option<Transaction>>;
     /// Get transaction info by transaction's hash
     fn get_transaction_info(&self, txn_hash: HashValue) -> Result<Option<RichTransactionInfo>>;
+    /// Get transaction receipt by transaction's hash, return None if the txn is not on the chain.
+    fn get_transaction_receipt(&self, txn_hash: HashValue) -> Result<Option<TransactionReceipt>>;
 
     /// get transaction info by global index in chain.
     fn get_transaction_info_by_global_index(
@@ -50,8 +71,24 @@ pub trait ChainReader {
         transaction_global_index: u64,
     ) -> Result<Option<RichTransactionInfo>>;
 
+    /// get transaction info by version, the version is the leaf index in txn accumulator,
+    /// return None if the version is beyond the chain head.
+    fn get_transaction_info_by_version(&self, version: u64) -> Result<Option<TransactionInfo>>;
+
     fn chain_state_reader(&self) -> &dyn ChainStateReader;
     fn get_block_info(&self, block_id: Option<HashValue>) -> Result<Option<BlockInfo>>;
+    /// Get the txn accumulator root after the block `at` is executed, or the head if `at` is None.
+    /// It's read from the persisted block info, return Error if the block info does not exist.
+    fn get_txn_accumulator_root(&self, at: Option<HashValue>) -> Result<HashValue>;
+    /// Get the overview of the block `hash` on the chain, from the header, the block info and
+    /// the txn info ids of the block, the body is not read.
+    fn get_block_overview(&self, hash: HashValue) -> Result<Option<BlockOverview>>;
+    /// Get the txns of the block `block_id` paired with their txn infos, in the execution order.
+    /// The block metadata txn is the first one, so a block without user txns returns only it.
+    fn get_block_transactions(
+        &self,
+        block_id: HashValue,
+    ) -> Result<Vec<(Transaction, TransactionInfo)>>;
     fn get_total_difficulty(&self) -> Result<U256>;
     fn exist_block(&self, block_id: HashValue) -> Result<bool>;
     fn epoch(&self) -> &Epoch;
@@ -65,6 +102,12 @@ pub trait ChainReader {
     fn get_block_info_by_number(&self, number: BlockNumber) -> Result<Option<BlockInfo>>;
 
     fn time_service(&self) -> &dyn TimeService;
+    /// The max milliseconds of a block timestamp ahead of the `time_service`.
+    fn max_future_block_time(&self) -> u64;
+    /// The floor of the retargeted block difficulty, None if the chain has no floor.
+    fn difficulty_floor(&self) -> Option<DifficultyFloor>;
+    /// The max bytes of the bcs encoded block, shared by the block template and the verifier.
+    fn block_bytes_limit(&self) -> BlockBytesLimit;
     fn fork(&self, block_id: HashValue) -> Result<Self>
     where
         Self: Sized;
@@ -94,6 +137,32 @@ pub trait ChainReader {
         event_index: Option<u64>,
         access_path: Option<AccessPath>,
     ) -> Result<Option<TransactionInfoWithProof>>;
+
+    /// Get the proof of the txn `txn_hash` against the `txn_accumulator_root` of the block which
+    /// includes it, for the light clients. Return None if the txn is not on the chain.
+    fn get_transaction_proof_by_hash(
+        &self,
+        txn_hash: HashValue,
+    ) -> Result<Option<TransactionInfoWithProof>>;
+
+    /// Get the account state blobs of `addrs` at the block `at`, in the order of `addrs`.
+    /// The unknown accounts are `None`.
+    fn get_account_states(
+        &self,
+        addrs: &[AccountAddress],
+        at: HashValue,
+    ) -> Result<Vec<Option<Vec<u8>>>>;
+
+    /// Get the events matching `filter` in the blocks `from..=to` by the event index,
+    /// the block range of `filter` is replaced by `from` and `to`.
+    /// The filter must have addresses or event keys, and the count of events is bounded by
+    /// `filter.limit`.
+    fn get_events_by_filter(
+        &self,
+        filter: &Filter,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Vec<ContractEvent>>;
 }
 
 pub trait ChainWriter {
@@ -102,8 +171,13 @@ pub trait ChainWriter {
     fn connect(&mut self, executed_block: ExecutedBlock) -> Result<ExecutedBlock>;
 
     /// Verify, Execute and Connect block to current chain.
+    /// The result of the execution is given by `ExecutedBlock::apply_result`.
     fn apply(&mut self, block: Block) -> Result<ExecutedBlock>;
 
+    /// Roll back the head block, the chain moves to the parent of it and the reverted header is
+    /// returned. The data of the reverted block is kept in storage, as a block of another branch.
+    fn revert_head(&mut self) -> Result<BlockHeader>;
+
     fn chain_state(&mut self) -> &ChainStateDB;
 }
 
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2
 #![deny(clippy::integer_arithmetic)]
 
-use anyhow::{bail, format_err, Result};
+use anyhow::{bail, ensure, format_err, Result};
 use serde::{Deserialize, Serialize};
 use starcoin_accumulator::proof::AccumulatorProof;
 use starcoin_state_api::StateWithProof;
@@ -19,7 +19,9 @@ pub struct ExcludedTxns {
     pub untouched_txns: Vec<SignedUserTransaction>,
 }
 
-pub use chain::{Chain, ChainReader, ChainWriter, ExecutedBlock, MintedUncleNumber, VerifiedBlock};
+pub use chain::{
+    BlockId, Chain, ChainReader, ChainWriter, ExecutedBlock, MintedUncleNumber, VerifiedBlock,
+};
 pub use errors::*;
 pub use service::{ChainAsyncService, ReadableChainService, WriteableChainService};
 use starcoin_crypto::hash::PlainCryptoHash;
@@ -49,6 +51,24 @@ pub struct TransactionInfoWithProof {
 }
 
 impl TransactionInfoWithProof {
+    /// Verify the txn `txn_hash` is included by the txn accumulator of root `expect_root`, the
+    /// leaf index is the `transaction_global_index` of the txn info.
+    pub fn verify_transaction(&self, expect_root: HashValue, txn_hash: HashValue) -> Result<()> {
+        ensure!(
+            self.transaction_info.transaction_hash() == txn_hash,
+            "transaction hash mismatch, expect: {}, got: {}",
+            txn_hash,
+            self.transaction_info.transaction_hash()
+        );
+        self.proof
+            .verify(
+                expect_root,
+                self.transaction_info.id(),
+                self.transaction_info.transaction_global_index,
+            )
+            .map_err(|e| format_err!("transaction info proof verify failed: {}", e))
+    }
+
     pub fn verify(
         &self,
         expect_root: HashValue,
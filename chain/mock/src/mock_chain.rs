@@ -5,7 +5,7 @@ use anyhow::Result;
 use crypto::HashValue;
 use logger::prelude::*;
 use starcoin_account_api::AccountInfo;
-use starcoin_chain::{BlockChain, ChainReader, ChainWriter};
+use starcoin_chain::{BlockChain, ChainReader, ChainWriter, ForkChoice, TotalDifficultyForkChoice};
 use starcoin_config::ChainNetwork;
 use starcoin_consensus::Consensus;
 use starcoin_genesis::Genesis;
@@ -94,9 +94,12 @@ impl MockChain {
             self.head.get_storage(),
             None,
         )?;
-        let branch_total_difficulty = branch.get_total_difficulty()?;
-        let head_total_difficulty = self.head.get_total_difficulty()?;
-        if branch_total_difficulty > head_total_difficulty {
+        let head = self.head.current_header();
+        let selected = TotalDifficultyForkChoice.select_head(
+            &[head.clone(), branch.current_header()],
+            self.head.get_storage().as_ref(),
+        )?;
+        if selected != head.id() {
             self.head = branch;
             debug!("Change to new head: {:?}", self.head.current_header());
             self.net
@@ -104,8 +107,9 @@ impl MockChain {
                 .adjust(new_block.header().timestamp());
         } else {
             debug!(
-                "New block({:?})'s total_difficulty({:?}) <= head's total_difficulty({:?})",
-                new_block_id, branch_total_difficulty, head_total_difficulty
+                "New block({:?}) is not selected as head, keep head: {:?}",
+                new_block_id,
+                head.id()
             );
         }
         Ok(())
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use anyhow::{bail, format_err, Result};
+use bcs_ext::BCSCodec;
 use crypto::HashValue;
 use logger::prelude::*;
 use starcoin_accumulator::{node::AccumulatorStoreType, Accumulator, MerkleAccumulator};
@@ -21,18 +22,24 @@ use starcoin_types::{
     },
     U256,
 };
-use std::{convert::TryInto, sync::Arc};
+use std::{cmp::min, convert::TryInto, sync::Arc, time::Instant};
 use storage::Store;
 
+/// The max bytes of the length prefix of the txns in the encoded body.
+const MAX_TXNS_LEN_PREFIX_BYTES: u64 = 5;
+
 pub struct OpenedBlock {
     previous_block_info: BlockInfo,
     block_meta: BlockMetadata,
     gas_limit: u64,
+    block_bytes_limit: u64,
+    execution_deadline: Option<Instant>,
 
     state: ChainStateDB,
     txn_accumulator: MerkleAccumulator,
 
     gas_used: u64,
+    block_bytes: u64,
     included_user_txns: Vec<SignedUserTransaction>,
     uncles: Vec<BlockHeader>,
     chain_id: ChainId,
@@ -76,14 +83,23 @@ impl OpenedBlock {
             chain_id,
             previous_header.gas_used(),
         );
+        // the header of the new block has the same fields as the parent without author auth key,
+        // so the parent header is an upper bound of it.
+        let block_bytes = (previous_header.encode()?.len()
+            + BlockBody::new(vec![], Some(uncles.clone())).encode()?.len())
+            as u64
+            + MAX_TXNS_LEN_PREFIX_BYTES;
         let mut opened_block = Self {
             previous_block_info: block_info,
             block_meta,
             gas_limit: block_gas_limit,
+            block_bytes_limit: u64::MAX,
+            execution_deadline: None,
 
             state: chain_state,
             txn_accumulator,
             gas_used: 0,
+            block_bytes,
             included_user_txns: vec![],
             uncles,
             chain_id,
@@ -103,6 +119,25 @@ impl OpenedBlock {
         self.gas_limit
     }
 
+    /// Limit the block to `block_bytes_limit`, the lowest of the limits applies, the block is not
+    /// limited without one.
+    pub fn with_block_bytes_limit(mut self, block_bytes_limit: u64) -> Self {
+        self.block_bytes_limit = min(block_bytes_limit, self.block_bytes_limit);
+        self
+    }
+
+    /// Stop executing the txns at `deadline`, the txns left are untouched, so the block is
+    /// sealed with the txns executed in time.
+    pub fn with_execution_deadline(mut self, deadline: Instant) -> Self {
+        self.execution_deadline = Some(deadline);
+        self
+    }
+
+    /// The upper bound of the encoded bytes of the block with the included txns.
+    pub fn block_bytes(&self) -> u64 {
+        self.block_bytes
+    }
+
     // TODO: should use check_sub or not
     pub fn gas_left(&self) -> u64 {
         debug_assert!(self.gas_limit >= self.gas_used);
@@ -131,32 +166,53 @@ impl OpenedBlock {
     }
 
     /// Try to add `user_txns` into this block.
-    /// Return any txns  not included, either txn is discarded, or block gas limit is reached.
+    /// Return any txns  not included, either txn is discarded, or block gas limit or block bytes
+    /// limit is reached.
     /// If error occurs during the processing, the `open_block` should be dropped,
     /// as the internal state may be corrupted.
     /// TODO: make the function can be called again even last call returns error.  
-    pub fn push_txns(&mut self, user_txns: Vec<SignedUserTransaction>) -> Result<ExcludedTxns> {
+    pub fn push_txns(&mut self, mut user_txns: Vec<SignedUserTransaction>) -> Result<ExcludedTxns> {
+        // the txns beyond the bytes limit are not executed, as if all the txns are included.
+        let mut txn_bytes = Vec::with_capacity(user_txns.len());
+        let mut selected_bytes = self.block_bytes;
+        for txn in &user_txns {
+            let bytes = txn.encode()?.len() as u64;
+            if selected_bytes + bytes > self.block_bytes_limit {
+                break;
+            }
+            selected_bytes += bytes;
+            txn_bytes.push(bytes);
+        }
+        let oversize_user_txns = user_txns.split_off(txn_bytes.len());
+
+        let (mut untouched_user_txns, discard_txns) = match self.execution_deadline {
+            Some(deadline) => self.push_txns_until(user_txns, txn_bytes, deadline)?,
+            None => self.push_txns_in_batch(user_txns, txn_bytes)?,
+        };
+        untouched_user_txns.extend(oversize_user_txns);
+        Ok(ExcludedTxns {
+            discarded_txns: discard_txns,
+            untouched_txns: untouched_user_txns,
+        })
+    }
+
+    /// Execute `user_txns` in a single batch, return the untouched and discarded txns.
+    fn push_txns_in_batch(
+        &mut self,
+        user_txns: Vec<SignedUserTransaction>,
+        txn_bytes: Vec<u64>,
+    ) -> Result<(Vec<SignedUserTransaction>, Vec<SignedUserTransaction>)> {
         let mut txns: Vec<_> = user_txns
-            .iter()
-            .cloned()
+            .into_iter()
             .map(Transaction::UserTransaction)
             .collect();
 
-        let txn_outputs = {
-            let gas_left = self.gas_limit.checked_sub(self.gas_used).ok_or_else(|| {
-                format_err!(
-                    "block gas_used {} exceed block gas_limit:{}",
-                    self.gas_used,
-                    self.gas_limit
-                )
-            })?;
-            execute_block_transactions(
-                &self.state,
-                txns.clone(),
-                gas_left,
-                self.vm_metrics.clone(),
-            )?
-        };
+        let txn_outputs = execute_block_transactions(
+            &self.state,
+            txns.clone(),
+            self.checked_gas_left()?,
+            self.vm_metrics.clone(),
+        )?;
 
         let untouched_user_txns: Vec<SignedUserTransaction> = if txn_outputs.len() >= txns.len() {
             vec![]
@@ -168,31 +224,97 @@ impl OpenedBlock {
 
         let mut discard_txns: Vec<SignedUserTransaction> = Vec::new();
         debug_assert_eq!(txns.len(), txn_outputs.len());
-        for (txn, output) in txns.into_iter().zip(txn_outputs.into_iter()) {
-            let txn_hash = txn.id();
-            match output.status() {
-                TransactionStatus::Discard(status) => {
-                    debug!("discard txn {}, vm status: {:?}", txn_hash, status);
-                    discard_txns.push(txn.try_into().expect("user txn"));
-                }
-                TransactionStatus::Keep(status) => {
-                    if status != &KeptVMStatus::Executed {
-                        debug!("txn {:?} execute error: {:?}", txn_hash, status);
+        for ((txn, output), bytes) in txns.into_iter().zip(txn_outputs.into_iter()).zip(txn_bytes) {
+            if let Some(discard_txn) = self.push_txn_output(txn, output, bytes)? {
+                discard_txns.push(discard_txn);
+            }
+        }
+        Ok((untouched_user_txns, discard_txns))
+    }
+
+    /// Execute `user_txns` one by one until the `deadline`, the txns not started before the
+    /// deadline are untouched. The txn in execution is not interrupted, so the deadline may be
+    /// exceeded by the execution time of one txn.
+    fn push_txns_until(
+        &mut self,
+        user_txns: Vec<SignedUserTransaction>,
+        txn_bytes: Vec<u64>,
+        deadline: Instant,
+    ) -> Result<(Vec<SignedUserTransaction>, Vec<SignedUserTransaction>)> {
+        let mut untouched_user_txns: Vec<SignedUserTransaction> = Vec::new();
+        let mut discard_txns: Vec<SignedUserTransaction> = Vec::new();
+        let mut txns = user_txns.into_iter().zip(txn_bytes);
+        for (user_txn, bytes) in txns.by_ref() {
+            if Instant::now() >= deadline {
+                info!(
+                    "Block execution deadline is reached, {} txns are included",
+                    self.included_user_txns.len()
+                );
+                untouched_user_txns.push(user_txn);
+                break;
+            }
+            let txn = Transaction::UserTransaction(user_txn);
+            let mut txn_outputs = execute_block_transactions(
+                &self.state,
+                vec![txn.clone()],
+                self.checked_gas_left()?,
+                self.vm_metrics.clone(),
+            )?;
+            match txn_outputs.pop() {
+                Some(output) => {
+                    if let Some(discard_txn) = self.push_txn_output(txn, output, bytes)? {
+                        discard_txns.push(discard_txn);
                     }
-                    let gas_used = output.gas_used();
-                    self.push_txn_and_state(txn_hash, output)?;
-                    self.gas_used += gas_used;
-                    self.included_user_txns
-                        .push(txn.try_into().expect("user txn"));
                 }
-            };
+                // the block gas limit is reached.
+                None => {
+                    untouched_user_txns.push(txn.try_into().expect("user txn"));
+                    break;
+                }
+            }
         }
-        Ok(ExcludedTxns {
-            discarded_txns: discard_txns,
-            untouched_txns: untouched_user_txns,
+        untouched_user_txns.extend(txns.map(|(user_txn, _)| user_txn));
+        Ok((untouched_user_txns, discard_txns))
+    }
+
+    fn checked_gas_left(&self) -> Result<u64> {
+        self.gas_limit.checked_sub(self.gas_used).ok_or_else(|| {
+            format_err!(
+                "block gas_used {} exceed block gas_limit:{}",
+                self.gas_used,
+                self.gas_limit
+            )
         })
     }
 
+    /// Include the txn by its output, return the txn if it is discarded.
+    fn push_txn_output(
+        &mut self,
+        txn: Transaction,
+        output: TransactionOutput,
+        bytes: u64,
+    ) -> Result<Option<SignedUserTransaction>> {
+        let txn_hash = txn.id();
+        match output.status() {
+            TransactionStatus::Discard(status) => {
+                debug!("discard txn {}, vm status: {:?}", txn_hash, status);
+                return Ok(Some(txn.try_into().expect("user txn")));
+            }
+            TransactionStatus::Keep(status) => {
+                if status != &KeptVMStatus::Executed {
+                    debug!("txn {:?} execute error: {:?}", txn_hash, status);
+                }
+            }
+        };
+        let gas_used = output.gas_used();
+        self.push_txn_and_state(txn_hash, output)?;
+        self.gas_used += gas_used;
+        self.block_bytes += bytes;
+        self.included_user_txns
+            .push(txn.try_into().expect("user txn"));
+        Ok(None)
+    }
+
     /// Run blockmeta first
     fn initialize(&mut self) -> Result<()> {
         let block_metadata_txn = Transaction::BlockMetadata(self.block_meta.clone());
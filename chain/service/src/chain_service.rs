@@ -251,12 +251,11 @@ impl ChainReaderServiceInner {
         storage: Arc<dyn Store>,
         vm_metrics: Option<VMMetrics>,
     ) -> Result<Self> {
-        let net = config.net();
-        let main = BlockChain::new(
-            net.time_service(),
-            startup_info.main,
+        let main = Self::open_chain(
+            &config,
             storage.clone(),
             vm_metrics.clone(),
+            startup_info.main,
         )?;
         Ok(Self {
             config,
@@ -277,15 +276,33 @@ impl ChainReaderServiceInner {
     }
 
     pub fn switch_main(&mut self, new_head_id: HashValue) -> Result<()> {
-        let net = self.config.net();
-        self.main = BlockChain::new(
-            net.time_service(),
-            new_head_id,
+        self.main = Self::open_chain(
+            &self.config,
             self.storage.clone(),
             self.vm_metrics.clone(),
+            new_head_id,
         )?;
         Ok(())
     }
+
+    /// Open the chain at the `head_id` with the max future block time of the sync config, and
+    /// the difficulty floor and block bytes limit of the genesis config, the same as the chain of
+    /// the block connector.
+    fn open_chain(
+        config: &NodeConfig,
+        storage: Arc<dyn Store>,
+        vm_metrics: Option<VMMetrics>,
+        head_id: HashValue,
+    ) -> Result<BlockChain> {
+        let net = config.net();
+        let mut chain = BlockChain::new(net.time_service(), head_id, storage, vm_metrics)?;
+        if let Some(max_future_block_time) = config.sync.max_future_block_time() {
+            chain.set_max_future_block_time(max_future_block_time);
+        }
+        chain.set_difficulty_floor(net.genesis_config().difficulty_floor);
+        chain.set_block_bytes_limit(net.genesis_config().block_bytes_limit);
+        Ok(chain)
+    }
 }
 
 impl ReadableChainService for ChainReaderServiceInner {
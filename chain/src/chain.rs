@@ -1,49 +1,87 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::verifier::{BlockVerifier, FullVerifier};
+use crate::integrity::{IntegrityIssue, IntegrityReport};
+use crate::metrics::{ChainMetrics, ChainMetricsCounters};
+use crate::verifier::{BlockVerifier, FullVerifier, StaticVerifier};
 use anyhow::{bail, ensure, format_err, Result};
+use bcs_ext::BCSCodec;
 use consensus::Consensus;
 use crypto::hash::PlainCryptoHash;
 use crypto::HashValue;
 use logger::prelude::*;
+use logger::structured_log::block_logger;
 use sp_utils::stop_watch::{watch, CHAIN_WATCH_NAME};
 use starcoin_accumulator::inmemory::InMemoryAccumulator;
 use starcoin_accumulator::{
     accumulator_info::AccumulatorInfo, node::AccumulatorStoreType, Accumulator, MerkleAccumulator,
 };
 use starcoin_chain_api::{
-    verify_block, ChainReader, ChainWriter, ConnectBlockError, EventWithProof, ExcludedTxns,
-    ExecutedBlock, MintedUncleNumber, TransactionInfoWithProof, VerifiedBlock, VerifyBlockField,
+    verify_block, BlockId, ChainReader, ChainWriter, ConnectBlockError, EventWithProof,
+    ExcludedTxns, ExecutedBlock, MintedUncleNumber, TransactionInfoWithProof, VerifiedBlock,
+    VerifyBlockField,
 };
 use starcoin_executor::VMMetrics;
 use starcoin_open_block::OpenedBlock;
 use starcoin_state_api::{AccountStateReader, ChainStateReader, ChainStateWriter};
-use starcoin_statedb::ChainStateDB;
+use starcoin_statedb::{ChainStateDB, StateProof};
 use starcoin_time_service::TimeService;
 use starcoin_types::block::BlockIdAndNumber;
 use starcoin_types::contract_event::ContractEventInfo;
 use starcoin_types::filter::Filter;
 use starcoin_types::startup_info::{ChainInfo, ChainStatus};
-use starcoin_types::transaction::RichTransactionInfo;
+use starcoin_types::transaction::{
+    RichTransactionInfo, TransactionInfo, TransactionReceipt, TransactionStatus,
+};
 use starcoin_types::{
     account_address::AccountAddress,
-    block::{Block, BlockHeader, BlockInfo, BlockNumber, BlockTemplate},
+    block::{
+        Block, BlockHeader, BlockInfo, BlockNumber, BlockOverview, BlockTemplate,
+        ALLOWED_FUTURE_BLOCKTIME,
+    },
     contract_event::ContractEvent,
     error::BlockExecutorError,
-    transaction::{SignedUserTransaction, Transaction},
+    transaction::{SignedUserTransaction, Transaction, TransactionOutput},
     U256,
 };
 use starcoin_vm_types::access_path::AccessPath;
 use starcoin_vm_types::account_config::genesis_address;
-use starcoin_vm_types::genesis_config::ConsensusStrategy;
+use starcoin_vm_types::genesis_config::{BlockBytesLimit, ConsensusStrategy, DifficultyFloor};
 use starcoin_vm_types::on_chain_resource::Epoch;
-use std::cmp::min;
+use std::cmp::{max, min};
+use std::collections::hash_map::Entry;
 use std::iter::Extend;
 use std::option::Option::{None, Some};
+use std::time::Instant;
 use std::{collections::HashMap, sync::Arc};
+use storage::event_index::EventIndex;
 use storage::Store;
 
+/// Max count of blocks deleted in one write batch when pruning.
+const PRUNE_BATCH_SIZE: usize = 1000;
+/// Max count of events returned by one indexed event query.
+const MAX_EVENTS_PER_QUERY: usize = 1000;
+/// The blocks read from the event index at a time, the query stops once it has enough events.
+const EVENT_INDEX_SCAN_BLOCKS: u64 = 1000;
+
+/// How often the storage is flushed by `apply`, it's flushed once `every_blocks` blocks or
+/// `every_bytes` bytes of blocks are applied since the last flush, whichever comes first.
+/// The default flushes every block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FlushCadence {
+    pub every_blocks: u64,
+    pub every_bytes: Option<u64>,
+}
+
+impl Default for FlushCadence {
+    fn default() -> Self {
+        Self {
+            every_blocks: 1,
+            every_bytes: None,
+        }
+    }
+}
+
 pub struct ChainStatusWithBlock {
     pub status: ChainStatus,
     pub head: Block,
@@ -60,6 +98,14 @@ pub struct BlockChain {
     uncles: HashMap<HashValue, MintedUncleNumber>,
     epoch: Epoch,
     vm_metrics: Option<VMMetrics>,
+    metrics: Arc<ChainMetricsCounters>,
+    flush_cadence: FlushCadence,
+    /// The count and bytes of the blocks applied since the last flush.
+    unflushed_blocks: u64,
+    unflushed_bytes: u64,
+    max_future_block_time: u64,
+    difficulty_floor: Option<DifficultyFloor>,
+    block_bytes_limit: BlockBytesLimit,
 }
 
 impl BlockChain {
@@ -95,6 +141,7 @@ impl BlockChain {
             .get_genesis()?
             .ok_or_else(|| format_err!("Can not find genesis hash in storage."))?;
         watch(CHAIN_WATCH_NAME, "n1253");
+        let metrics = Arc::new(ChainMetricsCounters::new(head_block.header().number()));
         let mut chain = Self {
             genesis_hash: genesis,
             time_service,
@@ -117,6 +164,13 @@ impl BlockChain {
             uncles: HashMap::new(),
             epoch,
             vm_metrics,
+            metrics,
+            flush_cadence: FlushCadence::default(),
+            unflushed_blocks: 0,
+            unflushed_bytes: 0,
+            max_future_block_time: ALLOWED_FUTURE_BLOCKTIME,
+            difficulty_floor: None,
+            block_bytes_limit: BlockBytesLimit::default(),
         };
         watch(CHAIN_WATCH_NAME, "n1251");
         match uncles {
@@ -255,6 +309,7 @@ impl BlockChain {
 
         let strategy = epoch.strategy();
         let difficulty = strategy.calculate_next_difficulty(self)?;
+        let number = previous_header.number() + 1;
         let mut opened_block = OpenedBlock::new(
             self.storage.clone(),
             previous_header,
@@ -266,6 +321,9 @@ impl BlockChain {
             strategy,
             None,
         )?;
+        if let Some(max_block_bytes) = self.block_bytes_limit.max_block_bytes_at(number) {
+            opened_block = opened_block.with_block_bytes_limit(max_block_bytes);
+        }
         let excluded_txns = opened_block.push_txns(user_txns)?;
         let template = opened_block.finalize()?;
         Ok((template, excluded_txns))
@@ -331,11 +389,111 @@ impl BlockChain {
     where
         V: BlockVerifier,
     {
+        let header = block.header();
+        let block_id = header.id();
+        // the genesis block has no parent, it's only executed by `new_with_genesis` when the chain
+        // is bootstrapped, and a chain always has its genesis, so it can not be applied again.
+        verify_block!(
+            VerifyBlockField::Header,
+            !header.is_genesis(),
+            "Can not apply the genesis block {}, the genesis of the chain is {}",
+            block_id,
+            self.genesis_hash,
+        );
+        // check the id before any verifier, the `None` verifier included.
+        StaticVerifier::verify_header_id(header)?;
+        let logger = block_logger(
+            header.number(),
+            header.parent_hash(),
+            block.transactions().len(),
+        );
+        let block_bytes = match self.flush_cadence.every_bytes {
+            Some(_) => block.encode()?.len() as u64,
+            None => 0,
+        };
+        let apply_begin = Instant::now();
         let verified_block = self.verify_with_verifier::<V>(block)?;
         watch(CHAIN_WATCH_NAME, "n1");
+        let execute_begin = Instant::now();
         let executed_block = self.execute(verified_block)?;
+        slog_info!(logger, "block executed";
+            "block_id" => block_id.to_string(),
+            "execute_ms" => execute_begin.elapsed().as_millis() as u64,
+        );
+        let commit_begin = Instant::now();
+        // the block is committed by execute, make it durable by the WAL mode of the storage
+        // before the head moves to it, or later by the flush cadence.
+        self.unflushed_blocks = self.unflushed_blocks.saturating_add(1);
+        self.unflushed_bytes = self.unflushed_bytes.saturating_add(block_bytes);
+        if self.unflushed_blocks >= self.flush_cadence.every_blocks
+            || self
+                .flush_cadence
+                .every_bytes
+                .map(|every_bytes| self.unflushed_bytes >= every_bytes)
+                .unwrap_or(false)
+        {
+            self.flush()?;
+        }
         watch(CHAIN_WATCH_NAME, "n2");
-        self.connect(executed_block)
+        let executed_block = self.connect(executed_block)?;
+        slog_info!(logger, "block applied";
+            "block_id" => block_id.to_string(),
+            "commit_ms" => commit_begin.elapsed().as_millis() as u64,
+        );
+        self.metrics
+            .on_block_applied(executed_block.header().number(), apply_begin.elapsed());
+        Ok(executed_block)
+    }
+
+    /// Set how often the storage is flushed by `apply`, the forks of the chain inherit it.
+    /// The blocks applied since the last flush are not flushed by it, call `flush` for them.
+    pub fn set_flush_cadence(&mut self, flush_cadence: FlushCadence) {
+        self.flush_cadence = flush_cadence;
+    }
+
+    /// Set the max milliseconds of a block timestamp ahead of the time service, to tolerate the
+    /// clock skew between nodes, `ALLOWED_FUTURE_BLOCKTIME` by default. The forks inherit it.
+    pub fn set_max_future_block_time(&mut self, max_future_block_time: u64) {
+        self.max_future_block_time = max_future_block_time;
+    }
+
+    /// Set the floor of the retargeted block difficulty, usually the `difficulty_floor` of the
+    /// genesis config. The chain has no floor by default, the forks inherit it.
+    pub fn set_difficulty_floor(&mut self, difficulty_floor: Option<DifficultyFloor>) {
+        self.difficulty_floor = difficulty_floor;
+    }
+
+    /// Set the max bytes of the block, usually the `block_bytes_limit` of the genesis config.
+    /// The chain has the default limit from genesis by default, the forks inherit it.
+    pub fn set_block_bytes_limit(&mut self, block_bytes_limit: BlockBytesLimit) {
+        self.block_bytes_limit = block_bytes_limit;
+    }
+
+    /// Copy the settings and share the metrics of `other`, for a chain reopened from it by `fork`
+    /// or `revert_head`, so the reopened chain verifies and flushes the blocks the same way.
+    fn inherit_settings(&mut self, other: &Self) {
+        self.metrics = other.metrics.clone();
+        self.flush_cadence = other.flush_cadence;
+        self.max_future_block_time = other.max_future_block_time;
+        self.difficulty_floor = other.difficulty_floor;
+        self.block_bytes_limit = other.block_bytes_limit;
+    }
+
+    /// Flush the storage if any block is applied since the last flush.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.unflushed_blocks == 0 {
+            return Ok(());
+        }
+        self.storage.flush()?;
+        self.unflushed_blocks = 0;
+        self.unflushed_bytes = 0;
+        self.metrics.on_flush();
+        Ok(())
+    }
+
+    /// The snapshot of the chain metrics, the metrics are shared with the forks of the chain.
+    pub fn metrics(&self) -> ChainMetrics {
+        self.metrics.snapshot()
     }
 
     //TODO remove this function.
@@ -469,9 +627,21 @@ impl BlockChain {
             "events' length should be equal to txn infos' length"
         );
         let txn_info_ids: Vec<_> = txn_infos.iter().map(|info| info.id()).collect();
-        for (info_id, events) in txn_info_ids.iter().zip(txn_events.into_iter()) {
+        let mut event_indexes = vec![];
+        for (transaction_index, (info_id, events)) in
+            txn_info_ids.iter().zip(txn_events.into_iter()).enumerate()
+        {
+            let transaction_global_index = transaction_global_index
+                .checked_add(transaction_index as u64)
+                .expect("transaction_global_index overflow.");
+            event_indexes.extend(EventIndex::new_txn_indexes(
+                *info_id,
+                transaction_global_index,
+                &events,
+            ));
             storage.save_contract_events(*info_id, events)?;
         }
+        storage.save_event_indexes(block.header().number(), block_id, event_indexes)?;
 
         storage.save_transaction_infos(
             txn_infos
@@ -516,6 +686,328 @@ impl BlockChain {
     pub fn get_block_accumulator(&self) -> &MerkleAccumulator {
         &self.block_accumulator
     }
+
+    /// Export the blocks from `from` to `to` in order, `to` is the current head if None. The
+    /// numbers are resolved by a snapshot of the block accumulator at the current head, so the
+    /// blocks applied after this call do not change the stream.
+    pub fn export_blocks(
+        &self,
+        from: BlockNumber,
+        to: Option<BlockNumber>,
+    ) -> impl Iterator<Item = Result<Block>> {
+        let to = to.unwrap_or_else(|| self.current_header().number());
+        let block_accumulator = info_2_accumulator(
+            self.status.status.info.get_block_accumulator_info().clone(),
+            AccumulatorStoreType::Block,
+            self.storage.as_ref(),
+        );
+        let storage = self.storage.clone();
+        (from..=to).map(move |number| {
+            let block_id = block_accumulator
+                .get_leaf(number)?
+                .ok_or_else(|| format_err!("Can not find block hash by number {}", number))?;
+            storage
+                .get_block_by_hash(block_id)?
+                .ok_or_else(|| format_err!("Can not find block by hash {:?}", block_id))
+        })
+    }
+
+    /// Apply the exported `blocks` in order, and return the count of the applied blocks. The
+    /// genesis block is skipped after it's checked to be the genesis of this chain.
+    pub fn import_blocks<I>(&mut self, blocks: I) -> Result<u64>
+    where
+        I: IntoIterator<Item = Result<Block>>,
+    {
+        let mut imported = 0u64;
+        for block in blocks {
+            let block = block?;
+            if block.header().is_genesis() {
+                ensure!(
+                    block.id() == self.genesis_hash,
+                    "The genesis block {} is not the genesis of the chain {}",
+                    block.id(),
+                    self.genesis_hash,
+                );
+                continue;
+            }
+            self.apply(block)?;
+            imported = imported.saturating_add(1);
+        }
+        Ok(imported)
+    }
+
+    /// Get the account state blob of `addr` at the block `at`, with the proof to the state root
+    /// of the block, it can be verified by `StateProof::verify_account_state`.
+    pub fn get_account_state_with_proof(
+        &self,
+        addr: AccountAddress,
+        at: HashValue,
+    ) -> Result<(Option<Vec<u8>>, StateProof)> {
+        let header = self
+            .get_header(at)?
+            .ok_or_else(|| format_err!("Can not find block {} on the chain", at))?;
+        self.statedb
+            .fork_at(header.state_root())
+            .get_account_state_with_proof(&addr)
+    }
+
+    /// Execute `txn` on a fork of the head state, the output is neither applied nor saved,
+    /// so it can be used to estimate the gas and preview the effects of the txn.
+    pub fn simulate_transaction(&self, txn: SignedUserTransaction) -> Result<TransactionOutput> {
+        let statedb = self.statedb.fork();
+        starcoin_executor::execute_transactions(
+            &statedb,
+            vec![Transaction::UserTransaction(txn)],
+            self.vm_metrics.clone(),
+        )?
+        .pop()
+        .ok_or_else(|| format_err!("Simulate transaction should have output"))
+    }
+
+    /// Return a read-only view of the head state with the effects of `pending_txns` layered
+    /// on top, such as the pending txns of the txpool. The txns are executed in order on a fork
+    /// of the head state and the discarded txns are skipped, nothing is saved to the storage.
+    pub fn pending_state_view(
+        &self,
+        pending_txns: Vec<SignedUserTransaction>,
+    ) -> Result<impl ChainStateReader> {
+        let statedb = self.statedb.fork();
+        for txn in pending_txns {
+            let txn_hash = txn.id();
+            let output = starcoin_executor::execute_transactions(
+                &statedb,
+                vec![Transaction::UserTransaction(txn)],
+                self.vm_metrics.clone(),
+            )?
+            .pop()
+            .ok_or_else(|| format_err!("Execute pending txn {} should have output", txn_hash))?;
+            let (write_set, _, _, status) = output.into_inner();
+            match status {
+                TransactionStatus::Keep(_) => {
+                    statedb.apply_write_set(write_set)?;
+                    statedb.commit()?;
+                }
+                TransactionStatus::Discard(status) => {
+                    debug!("Skip the discarded pending txn {}: {:?}", txn_hash, status);
+                }
+            }
+        }
+        Ok(statedb)
+    }
+
+    /// Delete the blocks, txns and txn infos of the main chain blocks whose number is below
+    /// `height`, the headers, block infos and accumulators are kept to verify the chain.
+    /// The genesis block is never pruned, and `height` can not exceed the finalized number, nor
+    /// the start of current epoch, whose blocks are required to verify the uncles.
+    /// The pruning starts from the height of the last call, and the state nodes only reachable
+    /// from the states before the parent of the block `height` are deleted too, so it must not
+    /// run while blocks are applied to the storage.
+    pub fn prune_below(&mut self, height: BlockNumber) -> Result<()> {
+        let head_number = self.current_header().number();
+        ensure!(
+            height <= head_number,
+            "Can not prune above the head block, height: {}, head: {}",
+            height,
+            head_number
+        );
+        let finalized_number = self.finalized_number()?;
+        let epoch_start_number = self.epoch.start_block_number();
+        ensure!(
+            height <= min(finalized_number, epoch_start_number),
+            "Can not prune the unfinalized blocks, height: {}, finalized: {}, current epoch start: {}",
+            height,
+            finalized_number,
+            epoch_start_number
+        );
+        let pruned_number = self.storage.get_pruned_number()?.unwrap_or(1).max(1);
+        if height <= pruned_number {
+            debug!(
+                "The blocks below {} are already pruned, height: {}",
+                pruned_number, height
+            );
+            return Ok(());
+        }
+        let mut block_ids = Vec::with_capacity(PRUNE_BATCH_SIZE);
+        for number in pruned_number..height {
+            block_ids.push(self.get_hash_by_number_ensure(number)?);
+            if block_ids.len() >= PRUNE_BATCH_SIZE {
+                self.storage
+                    .delete_block_bodies(std::mem::take(&mut block_ids))?;
+            }
+        }
+        if !block_ids.is_empty() {
+            self.storage.delete_block_bodies(block_ids)?;
+        }
+        self.storage.compact_block_bodies()?;
+        let deleted_nodes = self.prune_state_below(height)?;
+        self.storage.save_pruned_number(height)?;
+        info!(
+            "Pruned the blocks below {} and {} state nodes, head: {}",
+            height, deleted_nodes, head_number
+        );
+        Ok(())
+    }
+
+    /// Delete the state nodes unreachable from the states of the stored blocks whose number is
+    /// not below `height - 1`, the parent state is kept to execute the block `height` again.
+    /// A node shared by the states is walked once, so the walk is bounded by the count of the
+    /// distinct nodes kept. The blocks applied meanwhile keep their nodes, see
+    /// `Store::prune_state_nodes`.
+    fn prune_state_below(&self, height: BlockNumber) -> Result<u64> {
+        let state_store = self.storage.clone().into_super_arc();
+        self.storage.prune_state_nodes(&mut |reachable| {
+            for block_id in self.storage.get_block_header_ids()? {
+                let header = match self.storage.get_block_header_by_hash(block_id)? {
+                    Some(header) if header.number() + 1 >= height => header,
+                    _ => continue,
+                };
+                state_tree::collect_state_nodes(
+                    state_store.clone(),
+                    header.state_root(),
+                    reachable,
+                )?;
+                // the body of the parent of the block `height` is pruned.
+                if header.number() < height {
+                    continue;
+                }
+                let txn_info_ids = self.storage.get_block_txn_info_ids(block_id)?;
+                for txn_info in self
+                    .storage
+                    .get_transaction_infos(txn_info_ids)?
+                    .into_iter()
+                    .flatten()
+                {
+                    state_tree::collect_state_nodes(
+                        state_store.clone(),
+                        txn_info.state_root_hash(),
+                        reachable,
+                    )?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// The highest finalized block number, the genesis is finalized if nothing is finalized.
+    /// The finalized number is shared by all the chains of the storage.
+    pub fn finalized_number(&self) -> Result<BlockNumber> {
+        Ok(self.storage.get_finalized_number()?.unwrap_or(0))
+    }
+
+    /// Finalize the main chain up to the block `number`, the head is never reverted below it.
+    /// The finalized number only moves forward, a lower `number` is ignored.
+    pub fn finalize(&self, number: BlockNumber) -> Result<BlockNumber> {
+        let head_number = self.current_header().number();
+        ensure!(
+            number <= head_number,
+            "Can not finalize above the head block, number: {}, head: {}",
+            number,
+            head_number
+        );
+        let finalized_number = self.finalized_number()?;
+        if number <= finalized_number {
+            return Ok(finalized_number);
+        }
+        self.storage.save_finalized_number(number)?;
+        debug!(
+            "Finalized the blocks up to {}, head: {}",
+            number, head_number
+        );
+        Ok(number)
+    }
+
+    /// Finalize the blocks `depth` blocks below the head, return the finalized number.
+    pub fn finalize_by_depth(&self, depth: u64) -> Result<BlockNumber> {
+        match self.current_header().number().checked_sub(depth) {
+            Some(number) => self.finalize(number),
+            None => self.finalized_number(),
+        }
+    }
+
+    /// Walk from the head to genesis by the parent links, and check the number index, block
+    /// info, txn infos and transactions of every block. The problems are collected in the
+    /// report instead of failing, only the storage errors are returned. Nothing is written.
+    /// The pruned blocks have no body, their txn infos are not checked.
+    pub fn verify_integrity(&self) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport::default();
+        let mut next = Some(self.current_header().id());
+        while let Some(block_id) = next.take() {
+            let header = match self.storage.get_block_header_by_hash(block_id)? {
+                Some(header) => header,
+                None => {
+                    report
+                        .issues
+                        .push(IntegrityIssue::MissingHeader { block_id });
+                    break;
+                }
+            };
+            report.checked_blocks = report.checked_blocks.saturating_add(1);
+
+            let number = header.number();
+            let indexed = self.get_hash_by_number(number)?;
+            if indexed != Some(block_id) {
+                report.issues.push(IntegrityIssue::MismatchedNumberIndex {
+                    number,
+                    block_id,
+                    indexed,
+                });
+            }
+            if self.storage.get_block_info(block_id)?.is_none() {
+                report
+                    .issues
+                    .push(IntegrityIssue::MissingBlockInfo { block_id });
+            }
+            if self.storage.get_block(block_id)?.is_some() {
+                self.verify_block_txn_infos(block_id, &mut report)?;
+            }
+
+            if header.is_genesis() {
+                if block_id == self.genesis_hash {
+                    report.reach_genesis = true;
+                } else {
+                    report
+                        .issues
+                        .push(IntegrityIssue::MismatchedGenesis { block_id });
+                }
+            } else {
+                next = Some(header.parent_hash());
+            }
+        }
+        Ok(report)
+    }
+
+    fn verify_block_txn_infos(
+        &self,
+        block_id: HashValue,
+        report: &mut IntegrityReport,
+    ) -> Result<()> {
+        let txn_info_ids = match self.storage.get_block_txn_info_ids(block_id) {
+            Ok(txn_info_ids) => txn_info_ids,
+            Err(_) => {
+                report
+                    .issues
+                    .push(IntegrityIssue::MissingTransactionInfoIds { block_id });
+                return Ok(());
+            }
+        };
+        for txn_info_id in txn_info_ids {
+            match self.storage.get_transaction_info(txn_info_id)? {
+                Some(txn_info) => {
+                    let txn_hash = txn_info.transaction_hash();
+                    if self.storage.get_transaction(txn_hash)?.is_none() {
+                        report
+                            .issues
+                            .push(IntegrityIssue::MissingTransaction { block_id, txn_hash });
+                    }
+                }
+                None => report.issues.push(IntegrityIssue::DanglingTransactionInfo {
+                    block_id,
+                    txn_info_id,
+                }),
+            }
+        }
+        Ok(())
+    }
 }
 
 impl ChainReader for BlockChain {
@@ -545,6 +1037,39 @@ impl ChainReader for BlockChain {
             .and_then(|block_header| self.exist_header_filter(block_header))
     }
 
+    fn get_header_chain(&self, from: HashValue, count: u64) -> Result<Vec<BlockHeader>> {
+        let mut headers = vec![];
+        let mut next = Some(from);
+        while let Some(hash) = next {
+            if headers.len() as u64 >= count {
+                break;
+            }
+            let header = self
+                .get_header(hash)?
+                .ok_or_else(|| format_err!("Can not find block header by hash {}", hash))?;
+            next = if header.is_genesis() {
+                None
+            } else {
+                Some(header.parent_hash())
+            };
+            headers.push(header);
+        }
+        Ok(headers)
+    }
+
+    fn get_latest_headers(&self, count: u64) -> Result<Vec<BlockHeader>> {
+        let head_number = self.current_header().number();
+        let ids = self.get_block_ids(head_number, true, count)?;
+        let header_opts = self.storage.get_block_headers(ids.clone())?;
+        header_opts
+            .into_iter()
+            .zip(ids)
+            .map(|(header, id)| {
+                header.ok_or_else(|| format_err!("Can not find block header by hash {}", id))
+            })
+            .collect()
+    }
+
     fn get_header_by_number(&self, number: BlockNumber) -> Result<Option<BlockHeader>> {
         self.get_hash_by_number(number)
             .and_then(|block_id| match block_id {
@@ -592,6 +1117,15 @@ impl ChainReader for BlockChain {
             .and_then(|block| self.exist_block_filter(block))
     }
 
+    fn get_block_by_id(&self, id: BlockId) -> Result<Option<Block>> {
+        match id {
+            BlockId::Hash(hash) => self.get_block(hash),
+            BlockId::Number(number) => self.get_block_by_number(number),
+            BlockId::Latest => Ok(Some(self.head_block().block)),
+            BlockId::Genesis => self.get_block(self.genesis_hash),
+        }
+    }
+
     fn get_hash_by_number(&self, number: BlockNumber) -> Result<Option<HashValue>> {
         self.block_accumulator.get_leaf(number)
     }
@@ -616,6 +1150,23 @@ impl ChainReader for BlockChain {
         Ok(None)
     }
 
+    fn get_transaction_receipt(&self, txn_hash: HashValue) -> Result<Option<TransactionReceipt>> {
+        match self.get_transaction_info(txn_hash)? {
+            None => Ok(None),
+            Some(txn_info) => {
+                let events = self
+                    .storage
+                    .get_contract_events(txn_info.id())?
+                    .unwrap_or_default();
+                Ok(Some(TransactionReceipt::new(
+                    txn_info.block_id(),
+                    &txn_info.transaction_info,
+                    events,
+                )))
+            }
+        }
+    }
+
     fn get_transaction_info_by_global_index(
         &self,
         transaction_global_index: u64,
@@ -626,6 +1177,15 @@ impl ChainReader for BlockChain {
         }
     }
 
+    fn get_transaction_info_by_version(&self, version: u64) -> Result<Option<TransactionInfo>> {
+        if version >= self.txn_accumulator.num_leaves() {
+            return Ok(None);
+        }
+        Ok(self
+            .get_transaction_info_by_global_index(version)?
+            .map(|info| info.transaction_info))
+    }
+
     fn chain_state_reader(&self) -> &dyn ChainStateReader {
         &self.statedb
     }
@@ -637,6 +1197,43 @@ impl ChainReader for BlockChain {
         }
     }
 
+    fn get_txn_accumulator_root(&self, at: Option<HashValue>) -> Result<HashValue> {
+        let block_info = self
+            .get_block_info(at)?
+            .ok_or_else(|| format_err!("Can not find block info by id {:?}", at))?;
+        Ok(block_info.get_txn_accumulator_info().accumulator_root)
+    }
+
+    fn get_block_overview(&self, hash: HashValue) -> Result<Option<BlockOverview>> {
+        let header = match self.get_header(hash)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let block_info = self
+            .storage
+            .get_block_info(hash)?
+            .ok_or_else(|| format_err!("Can not find block info by id:{}", hash))?;
+        let txn_count = self.storage.get_block_txn_info_ids(hash)?.len() as u64;
+        Ok(Some(BlockOverview::new(&header, &block_info, txn_count)))
+    }
+
+    fn get_block_transactions(
+        &self,
+        block_id: HashValue,
+    ) -> Result<Vec<(Transaction, TransactionInfo)>> {
+        self.storage
+            .get_block_transaction_infos(block_id)?
+            .into_iter()
+            .map(|txn_info| {
+                let txn_hash = txn_info.transaction_hash();
+                let txn = self.storage.get_transaction(txn_hash)?.ok_or_else(|| {
+                    format_err!("Can not find txn {} of block {}", txn_hash, block_id)
+                })?;
+                Ok((txn, txn_info.transaction_info))
+            })
+            .collect()
+    }
+
     fn get_total_difficulty(&self) -> Result<U256> {
         Ok(self.status.status.total_difficulty())
     }
@@ -674,6 +1271,18 @@ impl ChainReader for BlockChain {
         self.time_service.as_ref()
     }
 
+    fn max_future_block_time(&self) -> u64 {
+        self.max_future_block_time
+    }
+
+    fn difficulty_floor(&self) -> Option<DifficultyFloor> {
+        self.difficulty_floor
+    }
+
+    fn block_bytes_limit(&self) -> BlockBytesLimit {
+        self.block_bytes_limit
+    }
+
     fn fork(&self, block_id: HashValue) -> Result<Self> {
         ensure!(
             self.exist_block(block_id)?,
@@ -696,13 +1305,15 @@ impl ChainReader for BlockChain {
         } else {
             None
         };
-        BlockChain::new_with_uncles(
+        let mut chain = BlockChain::new_with_uncles(
             self.time_service.clone(),
             head,
             uncles,
             self.storage.clone(),
             self.vm_metrics.clone(),
-        )
+        )?;
+        chain.inherit_settings(self);
+        Ok(chain)
     }
 
     fn epoch_uncles(&self) -> &HashMap<HashValue, MintedUncleNumber> {
@@ -846,6 +1457,145 @@ impl ChainReader for BlockChain {
             state_proof,
         }))
     }
+
+    fn get_transaction_proof_by_hash(
+        &self,
+        txn_hash: HashValue,
+    ) -> Result<Option<TransactionInfoWithProof>> {
+        match self.get_transaction_info(txn_hash)? {
+            Some(txn_info) => self.get_transaction_proof(
+                txn_info.block_id(),
+                txn_info.transaction_global_index,
+                None,
+                None,
+            ),
+            None => Ok(None),
+        }
+    }
+
+    fn get_account_states(
+        &self,
+        addrs: &[AccountAddress],
+        at: HashValue,
+    ) -> Result<Vec<Option<Vec<u8>>>> {
+        let header = self
+            .get_header(at)?
+            .ok_or_else(|| format_err!("Can not find block {} on the chain", at))?;
+        self.statedb
+            .fork_at(header.state_root())
+            .get_account_states(addrs)
+    }
+
+    fn get_events_by_filter(
+        &self,
+        filter: &Filter,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Vec<ContractEvent>> {
+        ensure!(
+            !filter.addrs.is_empty() || !filter.event_keys.is_empty(),
+            "the event filter should have addresses or event keys"
+        );
+        let to = min(to, self.current_header().number());
+        let filter = Filter {
+            from_block: from,
+            to_block: to,
+            ..filter.clone()
+        };
+        let limit = min(
+            filter.limit.unwrap_or(MAX_EVENTS_PER_QUERY),
+            MAX_EVENTS_PER_QUERY,
+        );
+        // the events are indexed by the creator address of the event key.
+        let mut addresses: Vec<AccountAddress> = if filter.event_keys.is_empty() {
+            filter.addrs.clone()
+        } else {
+            filter
+                .event_keys
+                .iter()
+                .map(|event_key| event_key.get_creator_address())
+                .collect()
+        };
+        addresses.sort();
+        addresses.dedup();
+
+        let mut result = vec![];
+        let mut txn_events = HashMap::new();
+        let (mut low, mut high) = (from, to);
+        while low <= high && result.len() < limit {
+            // read the blocks from the start of the range, or from the end if reverse.
+            let (start, end) = if filter.reverse {
+                (
+                    max(high.saturating_sub(EVENT_INDEX_SCAN_BLOCKS - 1), low),
+                    high,
+                )
+            } else {
+                (
+                    low,
+                    min(low.saturating_add(EVENT_INDEX_SCAN_BLOCKS - 1), high),
+                )
+            };
+            let mut main_block_ids = HashMap::new();
+            let mut indexes = vec![];
+            for address in &addresses {
+                for (key, block_indexes) in self.storage.get_event_indexes(*address, start, end)? {
+                    let main_block_id = match main_block_ids.entry(key.block_number) {
+                        Entry::Occupied(entry) => *entry.get(),
+                        Entry::Vacant(entry) => {
+                            *entry.insert(self.get_hash_by_number(key.block_number)?)
+                        }
+                    };
+                    // the indexes of the blocks on the forks are skipped.
+                    if main_block_id == Some(key.block_id) {
+                        indexes.extend(
+                            block_indexes
+                                .into_iter()
+                                .map(|index| (key.block_number, index)),
+                        );
+                    }
+                }
+            }
+            indexes.sort_by_key(|(_, index)| (index.transaction_global_index, index.event_index));
+            if filter.reverse {
+                indexes.reverse();
+            }
+            for (number, index) in indexes {
+                let events = match txn_events.entry(index.txn_info_id) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(
+                        self.storage
+                            .get_contract_events(index.txn_info_id)?
+                            .unwrap_or_default(),
+                    ),
+                };
+                let event = events.get(index.event_index as usize).ok_or_else(|| {
+                    format_err!(
+                        "Can not find the event {} of txn info {}",
+                        index.event_index,
+                        index.txn_info_id
+                    )
+                })?;
+                if filter.matching(number, event) {
+                    result.push(event.clone());
+                    if result.len() >= limit {
+                        break;
+                    }
+                }
+            }
+            if filter.reverse {
+                if start == low {
+                    break;
+                }
+                high = start - 1;
+            } else {
+                if end == high {
+                    break;
+                }
+                low = end + 1;
+            }
+        }
+        Ok(result)
+    }
 }
 
 impl BlockChain {
@@ -995,6 +1745,41 @@ impl ChainWriter for BlockChain {
         self.apply_with_verifier::<FullVerifier>(block)
     }
 
+    fn revert_head(&mut self) -> Result<BlockHeader> {
+        let head = self.current_header();
+        ensure!(
+            head.number() > 0,
+            "Can not revert the genesis block {}",
+            head.id()
+        );
+        let finalized_number = self.finalized_number()?;
+        ensure!(
+            head.number() > finalized_number,
+            "Can not revert the finalized block {}, finalized number: {}",
+            head.id(),
+            finalized_number
+        );
+        let parent_hash = head.parent_hash();
+        let parent = self
+            .storage
+            .get_block_by_hash(parent_hash)?
+            .ok_or_else(|| format_err!("Can not find parent block by hash {}", parent_hash))?;
+        // the state, accumulators and uncles of the parent are loaded from storage again.
+        let mut chain = Self::new_with_uncles(
+            self.time_service.clone(),
+            parent,
+            None,
+            self.storage.clone(),
+            self.vm_metrics.clone(),
+        )?;
+        chain.inherit_settings(self);
+        chain
+            .metrics
+            .set_head_number(chain.current_header().number());
+        *self = chain;
+        Ok(head)
+    }
+
     fn chain_state(&mut self) -> &ChainStateDB {
         &self.statedb
     }
@@ -0,0 +1,38 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{format_err, Result};
+use crypto::HashValue;
+use starcoin_types::block::BlockHeader;
+use starcoin_types::U256;
+use storage::Store;
+
+/// The rule to select the main chain head from the tips of the forks.
+pub trait ForkChoice: Send + Sync {
+    /// Select the head from `candidates`, the first candidate should be the current head,
+    /// so the current head is kept when the candidates are equally preferred.
+    fn select_head(&self, candidates: &[BlockHeader], storage: &dyn Store) -> Result<HashValue>;
+}
+
+/// The heaviest chain rule, select the tip with the max total difficulty.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TotalDifficultyForkChoice;
+
+impl ForkChoice for TotalDifficultyForkChoice {
+    fn select_head(&self, candidates: &[BlockHeader], storage: &dyn Store) -> Result<HashValue> {
+        let mut selected: Option<(HashValue, U256)> = None;
+        for header in candidates {
+            let total_difficulty = storage
+                .get_block_info(header.id())?
+                .ok_or_else(|| format_err!("Can not find block info by id {}", header.id()))?
+                .total_difficulty;
+            match selected {
+                Some((_, max_total_difficulty)) if total_difficulty <= max_total_difficulty => {}
+                _ => selected = Some((header.id(), total_difficulty)),
+            }
+        }
+        selected
+            .map(|(id, _)| id)
+            .ok_or_else(|| format_err!("Fork choice candidates is empty"))
+    }
+}
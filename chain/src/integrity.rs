@@ -0,0 +1,55 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crypto::HashValue;
+use starcoin_types::block::BlockNumber;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IntegrityIssue {
+    /// The header of a block on the walk is missing, the walk stops at it.
+    MissingHeader {
+        block_id: HashValue,
+    },
+    /// The block accumulator leaf of `number` does not point to the block.
+    MismatchedNumberIndex {
+        number: BlockNumber,
+        block_id: HashValue,
+        indexed: Option<HashValue>,
+    },
+    MissingBlockInfo {
+        block_id: HashValue,
+    },
+    /// The block body exists, but the txn info id list of it is missing.
+    MissingTransactionInfoIds {
+        block_id: HashValue,
+    },
+    /// A txn info id of the block does not resolve to a txn info.
+    DanglingTransactionInfo {
+        block_id: HashValue,
+        txn_info_id: HashValue,
+    },
+    /// The transaction of a txn info of the block is missing.
+    MissingTransaction {
+        block_id: HashValue,
+        txn_hash: HashValue,
+    },
+    /// The walk ends at a genesis block other than the genesis of the chain.
+    MismatchedGenesis {
+        block_id: HashValue,
+    },
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IntegrityReport {
+    /// Count of the blocks walked from the head.
+    pub checked_blocks: u64,
+    /// The genesis is reached from the head by the parent links.
+    pub reach_genesis: bool,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.reach_genesis && self.issues.is_empty()
+    }
+}
@@ -2,6 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0
 #![deny(clippy::integer_arithmetic)]
 mod chain;
+pub mod fork_choice;
+pub mod integrity;
+mod metrics;
 pub mod verifier;
-pub use chain::BlockChain;
+pub use chain::{BlockChain, FlushCadence};
+pub use fork_choice::{ForkChoice, TotalDifficultyForkChoice};
+pub use integrity::{IntegrityIssue, IntegrityReport};
+pub use metrics::ChainMetrics;
 pub use starcoin_chain_api::{ChainReader, ChainWriter};
@@ -0,0 +1,62 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use starcoin_types::block::BlockNumber;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// A snapshot of the chain metrics, the block rate is derived from `blocks_applied` by the
+/// dashboards.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ChainMetrics {
+    /// The count of blocks applied since the chain is created.
+    pub blocks_applied: u64,
+    /// The number of the current head block.
+    pub head_number: BlockNumber,
+    /// The millis of the last applied block, from verify to connect.
+    pub last_apply_duration_ms: u64,
+    /// The count of storage flushes by the applied blocks, see `FlushCadence`.
+    pub flushes: u64,
+}
+
+/// The counters of the chain metrics, they are shared by the chain and its forks.
+#[derive(Debug, Default)]
+pub(crate) struct ChainMetricsCounters {
+    blocks_applied: AtomicU64,
+    head_number: AtomicU64,
+    last_apply_duration_ms: AtomicU64,
+    flushes: AtomicU64,
+}
+
+impl ChainMetricsCounters {
+    pub fn new(head_number: BlockNumber) -> Self {
+        Self {
+            head_number: AtomicU64::new(head_number),
+            ..Default::default()
+        }
+    }
+
+    pub fn on_block_applied(&self, head_number: BlockNumber, duration: Duration) {
+        self.blocks_applied.fetch_add(1, Ordering::Relaxed);
+        self.set_head_number(head_number);
+        self.last_apply_duration_ms
+            .store(duration.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn on_flush(&self) {
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_head_number(&self, head_number: BlockNumber) {
+        self.head_number.store(head_number, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ChainMetrics {
+        ChainMetrics {
+            blocks_applied: self.blocks_applied.load(Ordering::Relaxed),
+            head_number: self.head_number.load(Ordering::Relaxed),
+            last_apply_duration_ms: self.last_apply_duration_ms.load(Ordering::Relaxed),
+            flushes: self.flushes.load(Ordering::Relaxed),
+        }
+    }
+}
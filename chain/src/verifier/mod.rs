@@ -2,13 +2,16 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use anyhow::{format_err, Result};
+use bcs_ext::BCSCodec;
 use consensus::{Consensus, ConsensusVerifyError};
+use crypto::hash::CryptoHash;
 use logger::prelude::debug;
 use sp_utils::stop_watch::{watch, CHAIN_WATCH_NAME};
 use starcoin_chain_api::{
     verify_block, ChainReader, ConnectBlockError, VerifiedBlock, VerifyBlockField,
 };
-use starcoin_types::block::{Block, BlockHeader, ALLOWED_FUTURE_BLOCKTIME};
+use starcoin_types::block::{Block, BlockHeader};
+use starcoin_vm_types::genesis_config::BlockBytesLimit;
 use std::{collections::HashSet, str::FromStr};
 
 #[derive(Debug)]
@@ -41,6 +44,20 @@ impl FromStr for Verifier {
 
 pub struct StaticVerifier;
 impl StaticVerifier {
+    /// The id of header is cached when it's built, recompute it from the header fields, so a
+    /// block is never indexed by a hash which does not match its content.
+    pub fn verify_header_id(header: &BlockHeader) -> Result<()> {
+        let header_hash = header.crypto_hash();
+        verify_block!(
+            VerifyBlockField::Header,
+            header_hash == header.id(),
+            "verify block header id mismatch, expect: {}, got: {}",
+            header.id(),
+            header_hash,
+        );
+        Ok(())
+    }
+
     pub fn verify_body_hash(block: &Block) -> Result<()> {
         //verify body
         let body_hash = block.body.hash();
@@ -53,6 +70,41 @@ impl StaticVerifier {
         );
         Ok(())
     }
+
+    /// The size is measured by the bcs encoding, the same as the block sent on the wire.
+    pub fn verify_block_bytes(block_bytes_limit: BlockBytesLimit, block: &Block) -> Result<()> {
+        let max_block_bytes = match block_bytes_limit.max_block_bytes_at(block.header().number()) {
+            Some(max_block_bytes) => max_block_bytes,
+            None => return Ok(()),
+        };
+        let block_bytes = block.encode()?.len() as u64;
+        verify_block!(
+            VerifyBlockField::Body,
+            block_bytes <= max_block_bytes,
+            "block {} is oversize, bytes: {}, max: {}",
+            block.id(),
+            block_bytes,
+            max_block_bytes,
+        );
+        Ok(())
+    }
+
+    /// A txn is expired once the block timestamp in seconds reaches its expiration, same as the
+    /// txn prologue, so the block including it would fail in execution.
+    pub fn verify_txn_expiration(block: &Block) -> Result<()> {
+        let block_timestamp_secs = block.header().timestamp() / 1000;
+        for txn in block.transactions() {
+            verify_block!(
+                VerifyBlockField::Body,
+                txn.expiration_timestamp_secs() > block_timestamp_secs,
+                "txn {} is expired at {}, block timestamp: {}",
+                txn.id(),
+                txn.expiration_timestamp_secs(),
+                block_timestamp_secs,
+            );
+        }
+        Ok(())
+    }
 }
 
 //TODO this trait should move to consensus?
@@ -61,16 +113,25 @@ pub trait BlockVerifier {
     where
         R: ChainReader;
 
+    /// Verify the consensus seal of header, apart from the structural checks of `verify_header`.
+    fn verify_seal<R>(current_chain: &R, new_block_header: &BlockHeader) -> Result<()>
+    where
+        R: ChainReader;
+
     fn verify_block<R>(current_chain: &R, new_block: Block) -> Result<VerifiedBlock>
     where
         R: ChainReader,
     {
         watch(CHAIN_WATCH_NAME, "n11");
+        // verify the size first, it's cheaper than the seal.
+        StaticVerifier::verify_block_bytes(current_chain.block_bytes_limit(), &new_block)?;
         //verify header
         let new_block_header = new_block.header();
         Self::verify_header(current_chain, new_block_header)?;
+        Self::verify_seal(current_chain, new_block_header)?;
         watch(CHAIN_WATCH_NAME, "n12");
         StaticVerifier::verify_body_hash(&new_block)?;
+        StaticVerifier::verify_txn_expiration(&new_block)?;
         watch(CHAIN_WATCH_NAME, "n13");
         //verify uncles
         Self::verify_uncles(
@@ -146,6 +207,7 @@ pub trait BlockVerifier {
             // uncle's parent exist in current chain is check in can_be_uncle, so this fork should bean success.
             let uncle_branch = current_chain.fork(uncle.parent_hash())?;
             Self::verify_header(&uncle_branch, uncle)?;
+            Self::verify_seal(&uncle_branch, uncle)?;
             uncle_ids.insert(uncle_id);
         }
         Ok(())
@@ -208,10 +270,12 @@ impl BlockVerifier for BasicVerifier {
         let now = current_chain.time_service().now_millis();
         verify_block!(
             VerifyBlockField::Header,
-            new_block_header.timestamp() <= ALLOWED_FUTURE_BLOCKTIME.saturating_add(now),
-            "Invalid block: block timestamp too new, now:{}, block time:{}",
-            now,
             new_block_header.timestamp()
+                <= current_chain.max_future_block_time().saturating_add(now),
+            "Invalid block: block timestamp too new, now:{}, block time:{}, max skew ms:{}",
+            now,
+            new_block_header.timestamp(),
+            current_chain.max_future_block_time(),
         );
 
         let epoch = current_chain.epoch();
@@ -251,10 +315,28 @@ impl BlockVerifier for BasicVerifier {
         );
         Ok(())
     }
+
+    fn verify_seal<R>(_current_chain: &R, _new_block_header: &BlockHeader) -> Result<()>
+    where
+        R: ChainReader,
+    {
+        Ok(())
+    }
 }
 
 pub struct ConsensusVerifier;
 
+impl ConsensusVerifier {
+    fn map_consensus_err(e: anyhow::Error) -> anyhow::Error {
+        match e.downcast::<ConsensusVerifyError>() {
+            Ok(e) => {
+                ConnectBlockError::VerifyBlockFailed(VerifyBlockField::Consensus, e.into()).into()
+            }
+            Err(e) => e,
+        }
+    }
+}
+
 impl BlockVerifier for ConsensusVerifier {
     fn verify_header<R>(current_chain: &R, new_block_header: &BlockHeader) -> Result<()>
     where
@@ -262,17 +344,20 @@ impl BlockVerifier for ConsensusVerifier {
     {
         let epoch = current_chain.epoch();
         let consensus = epoch.strategy();
-        if let Err(e) = consensus.verify(current_chain, new_block_header) {
-            return match e.downcast::<ConsensusVerifyError>() {
-                Ok(e) => Err(ConnectBlockError::VerifyBlockFailed(
-                    VerifyBlockField::Consensus,
-                    e.into(),
-                )
-                .into()),
-                Err(e) => Err(e),
-            };
-        }
-        Ok(())
+        consensus
+            .verify_difficulty(current_chain, new_block_header)
+            .map_err(Self::map_consensus_err)
+    }
+
+    fn verify_seal<R>(current_chain: &R, new_block_header: &BlockHeader) -> Result<()>
+    where
+        R: ChainReader,
+    {
+        let epoch = current_chain.epoch();
+        let consensus = epoch.strategy();
+        consensus
+            .verify_seal(new_block_header)
+            .map_err(Self::map_consensus_err)
     }
 }
 
@@ -286,6 +371,13 @@ impl BlockVerifier for FullVerifier {
         BasicVerifier::verify_header(current_chain, new_block_header)?;
         ConsensusVerifier::verify_header(current_chain, new_block_header)
     }
+
+    fn verify_seal<R>(current_chain: &R, new_block_header: &BlockHeader) -> Result<()>
+    where
+        R: ChainReader,
+    {
+        ConsensusVerifier::verify_seal(current_chain, new_block_header)
+    }
 }
 
 pub struct NoneVerifier;
@@ -298,6 +390,13 @@ impl BlockVerifier for NoneVerifier {
         Ok(())
     }
 
+    fn verify_seal<R>(_current_chain: &R, _new_block_header: &BlockHeader) -> Result<()>
+    where
+        R: ChainReader,
+    {
+        Ok(())
+    }
+
     fn verify_block<R>(_current_chain: &R, new_block: Block) -> Result<VerifiedBlock>
     where
         R: ChainReader,
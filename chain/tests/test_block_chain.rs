@@ -1,26 +1,40 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::Result;
+use anyhow::{format_err, Result};
+use bcs_ext::BCSCodec;
+use consensus::dev::DevConsensus;
 use consensus::Consensus;
-use crypto::{ed25519::Ed25519PrivateKey, Genesis, PrivateKey};
+use crypto::{ed25519::Ed25519PrivateKey, Genesis, HashValue, PrivateKey};
 use starcoin_account_api::AccountInfo;
+use starcoin_accumulator::inmemory::InMemoryAccumulator;
 use starcoin_accumulator::Accumulator;
-use starcoin_chain::BlockChain;
+use starcoin_chain::verifier::ConsensusVerifier;
+use starcoin_chain::{BlockChain, FlushCadence, IntegrityIssue};
 use starcoin_chain::{ChainReader, ChainWriter};
+use starcoin_chain_api::{BlockId, ConnectBlockError, VerifyBlockField};
 use starcoin_chain_mock::MockChain;
 use starcoin_config::NodeConfig;
 use starcoin_config::{BuiltinNetworkID, ChainNetwork};
+use starcoin_state_api::AccountStateReader;
+use starcoin_statedb::ChainStateDB;
 use starcoin_transaction_builder::{build_transfer_from_association, DEFAULT_EXPIRATION_TIME};
 use starcoin_types::account_address;
-use starcoin_types::block::{Block, BlockHeader};
+use starcoin_types::block::{Block, BlockBody, BlockHeader, BlockNumber, ALLOWED_FUTURE_BLOCKTIME};
 use starcoin_types::filter::Filter;
 use starcoin_types::identifier::Identifier;
 use starcoin_types::language_storage::TypeTag;
-use starcoin_vm_types::account_config::genesis_address;
+use starcoin_types::transaction::{SignedUserTransaction, Transaction, TransactionStatus};
+use starcoin_types::U256;
+use starcoin_vm_types::account_config::{association_address, genesis_address};
+use starcoin_vm_types::genesis_config::{
+    BlockBytesLimit, DifficultyFloor, DEFAULT_MAX_BLOCK_BYTES,
+};
 use starcoin_vm_types::language_storage::StructTag;
+use starcoin_vm_types::vm_status::KeptVMStatus;
 use std::str::FromStr;
 use std::sync::Arc;
+use storage::{BlockStore, IntoSuper, Store};
 
 #[stest::test(timeout = 120)]
 fn test_chain_filter_events() {
@@ -126,6 +140,89 @@ fn test_chain_filter_events() {
     }
 }
 
+#[stest::test(timeout = 120)]
+fn test_get_events_by_filter() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(10)?;
+    let chain = mock_chain.head();
+
+    let event_type_tag = TypeTag::Struct(StructTag {
+        address: genesis_address(),
+        module: Identifier::from_str("Block").unwrap(),
+        name: Identifier::from_str("NewBlockEvent").unwrap(),
+        type_params: vec![],
+    });
+    let by_address = Filter {
+        addrs: vec![genesis_address()],
+        type_tags: vec![event_type_tag.clone()],
+        reverse: false,
+        ..Default::default()
+    };
+    let evts = chain.get_events_by_filter(&by_address, 1, 5)?;
+    assert_eq!(evts.len(), 5);
+    let expect = chain.filter_events(Filter {
+        from_block: 1,
+        to_block: 5,
+        ..by_address.clone()
+    })?;
+    assert_eq!(
+        evts,
+        expect.into_iter().map(|evt| evt.event).collect::<Vec<_>>()
+    );
+
+    // query by the event key, the latest events are returned first.
+    let by_event_key = Filter {
+        event_keys: vec![*evts[0].key()],
+        limit: Some(3),
+        reverse: true,
+        ..Default::default()
+    };
+    let evts = chain.get_events_by_filter(&by_event_key, 0, 20)?;
+    assert_eq!(evts.len(), 3);
+    let expect = chain.filter_events(Filter {
+        from_block: 0,
+        to_block: 20,
+        ..by_event_key.clone()
+    })?;
+    assert_eq!(
+        evts,
+        expect.into_iter().map(|evt| evt.event).collect::<Vec<_>>()
+    );
+    for evt in &evts {
+        assert_eq!(evt.key(), &by_event_key.event_keys[0]);
+        assert_eq!(evt.type_tag(), &event_type_tag);
+    }
+
+    // the blocks of a fork have the same numbers, their events are not returned.
+    let fork_point = chain
+        .get_header_by_number(5)?
+        .expect("block 5 should exist")
+        .id();
+    let mut fork = mock_chain.fork(Some(fork_point))?;
+    fork.produce_and_apply_times(3)?;
+    let evts = chain.get_events_by_filter(&by_address, 1, 10)?;
+    assert_eq!(evts.len(), 10);
+    let expect = chain.filter_events(Filter {
+        from_block: 1,
+        to_block: 10,
+        ..by_address.clone()
+    })?;
+    assert_eq!(
+        evts,
+        expect.into_iter().map(|evt| evt.event).collect::<Vec<_>>()
+    );
+
+    let unknown = Filter {
+        addrs: vec![account_address::AccountAddress::random()],
+        ..Default::default()
+    };
+    assert!(chain.get_events_by_filter(&unknown, 0, 10)?.is_empty());
+    assert!(chain
+        .get_events_by_filter(&Filter::default(), 0, 10)
+        .is_err());
+    Ok(())
+}
+
 #[stest::test]
 fn test_block_chain() -> Result<()> {
     let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
@@ -140,6 +237,22 @@ fn test_block_chain() -> Result<()> {
     Ok(())
 }
 
+#[stest::test]
+fn test_block_chain_in_memory() -> Result<()> {
+    let mut chain = test_helper::gen_blockchain_in_memory_for_test()?;
+    assert_eq!(chain.current_header().number(), 0);
+    let miner_account = AccountInfo::random();
+    let (block_template, _) =
+        chain.create_block_template(*miner_account.address(), None, vec![], vec![], None)?;
+    let block = chain
+        .consensus()
+        .create_block(block_template, chain.time_service().as_ref())?;
+    chain.apply(block.clone())?;
+    assert_eq!(chain.current_header().id(), block.id());
+    assert_eq!(chain.get_block_by_number(1)?, Some(block));
+    Ok(())
+}
+
 #[stest::test(timeout = 480)]
 fn test_halley_consensus() {
     let mut mock_chain =
@@ -149,6 +262,39 @@ fn test_halley_consensus() {
     assert_eq!(mock_chain.head().current_header().number(), times);
 }
 
+#[stest::test(timeout = 480)]
+fn test_apply_tampered_nonce() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_builtin(BuiltinNetworkID::Halley))?;
+    let block = mock_chain.produce()?;
+    let consensus = mock_chain.head().consensus();
+    // tamper the nonce until the seal is broken, the difficulty is still the expected one.
+    let mut nonce = block.header().nonce();
+    let tampered_block = loop {
+        nonce = nonce.wrapping_add(1);
+        let header = block.header().as_builder().with_nonce(nonce).build();
+        if consensus.verify_seal(&header).is_err() {
+            break Block::new(header, block.body.clone());
+        }
+    };
+    let mut chain = mock_chain.fork_new_branch(None)?;
+    for result in [
+        chain.apply_with_verifier::<ConsensusVerifier>(tampered_block.clone()),
+        chain.apply(tampered_block),
+    ] {
+        let err = result.expect_err("the block with a tampered nonce should be rejected");
+        assert!(matches!(
+            err.downcast_ref::<ConnectBlockError>(),
+            Some(ConnectBlockError::VerifyBlockFailed(
+                VerifyBlockField::Consensus,
+                _
+            ))
+        ));
+    }
+    assert_eq!(chain.current_header().number(), 0);
+    mock_chain.apply(block)?;
+    Ok(())
+}
+
 #[stest::test(timeout = 240)]
 fn test_dev_consensus() {
     let mut mock_chain = MockChain::new(ChainNetwork::new_builtin(BuiltinNetworkID::Dev)).unwrap();
@@ -233,6 +379,23 @@ fn test_uncle() {
     assert_eq!(mock_chain.head().current_epoch_uncles_size(), 1);
 }
 
+#[stest::test(timeout = 120)]
+fn test_uncle_counted_in_block_metadata() {
+    let (mut mock_chain, _, uncle_block_header) = gen_uncle();
+    let miner = mock_chain.miner().clone();
+    let parent_gas_used = mock_chain.head().current_header().gas_used();
+    let block = product_a_block(mock_chain.head(), &miner, vec![uncle_block_header]);
+    mock_chain.apply(block.clone()).unwrap();
+    // the uncle reward is distributed on chain by the uncle count of the block metadata.
+    let (_, _, author, _, uncles, _, _, _) = block.to_metadata(parent_gas_used).into_inner();
+    assert_eq!(author, *miner.address());
+    assert_eq!(uncles, 1);
+
+    let block = product_a_block(mock_chain.head(), &miner, Vec::new());
+    let (_, _, _, _, uncles, _, _, _) = block.to_metadata(0).into_inner();
+    assert_eq!(uncles, 0);
+}
+
 #[stest::test(timeout = 120)]
 fn test_uncle_exist() {
     let (mut mock_chain, _, uncle_block_header) = gen_uncle();
@@ -486,3 +649,1279 @@ fn test_get_blocks_by_number() -> Result<()> {
     );
     Ok(())
 }
+
+#[stest::test(timeout = 480)]
+fn test_prune_below() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(50)?;
+    let head = mock_chain.head().current_header();
+    let mut chain = mock_chain.fork_new_branch(None)?;
+    assert!(chain.prune_below(head.number() + 1).is_err());
+    // nothing is finalized yet.
+    assert!(chain.prune_below(40).is_err());
+    chain.finalize(head.number())?;
+    // the blocks of current epoch are required to verify the uncles.
+    assert!(chain.epoch().start_block_number() < 49);
+    assert!(chain.prune_below(49).is_err());
+
+    chain.prune_below(30)?;
+    assert_eq!(chain.get_storage().get_pruned_number()?, Some(30));
+    // prune from the last pruned height.
+    chain.prune_below(40)?;
+    let storage = chain.get_storage();
+    assert_eq!(storage.get_pruned_number()?, Some(40));
+    let read_balance = |number: BlockNumber| -> Result<Option<u128>> {
+        let header = chain.get_header_by_number(number)?.unwrap();
+        let statedb =
+            ChainStateDB::new(storage.clone().into_super_arc(), Some(header.state_root()));
+        AccountStateReader::new(&statedb).get_balance(association_address())
+    };
+    for number in 1..40 {
+        let header = chain.get_header_by_number(number)?.unwrap();
+        assert!(chain.get_block_by_number(number)?.is_none());
+        assert!(storage.get_block_txn_info_ids(header.id()).is_err());
+    }
+    assert!(read_balance(10).is_err());
+    // the parent state of the lowest kept block is kept.
+    assert!(read_balance(39)?.is_some());
+    for number in 40..=head.number() {
+        assert!(chain.get_block_by_number(number)?.is_some());
+        assert!(read_balance(number)?.is_some());
+    }
+    // a lower height is ignored.
+    chain.prune_below(20)?;
+    assert_eq!(storage.get_pruned_number()?, Some(40));
+    assert!(chain.get_block_by_number(0)?.is_some());
+    assert_eq!(chain.current_header(), head);
+
+    mock_chain.produce_and_apply()?;
+    assert_eq!(
+        mock_chain.head().current_header().number(),
+        head.number() + 1
+    );
+    Ok(())
+}
+
+#[stest::test]
+fn test_revert_head() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(3)?;
+    let mut chain = mock_chain.fork_new_branch(None)?;
+    let status = chain.status();
+    let state_root = chain.chain_state_reader().state_root();
+    let txn_leaves = status.info().get_txn_accumulator_info().num_leaves;
+
+    let block = mock_chain.produce()?;
+    chain.apply(block.clone())?;
+    assert_eq!(chain.current_header().number(), 4);
+    assert!(chain
+        .get_transaction_info_by_global_index(txn_leaves)?
+        .is_some());
+
+    assert_eq!(chain.revert_head()?, block.header().clone());
+    assert_eq!(chain.status(), status);
+    assert_eq!(chain.chain_state_reader().state_root(), state_root);
+    assert_eq!(chain.get_hash_by_number(4)?, None);
+    assert!(chain
+        .get_transaction_info_by_global_index(txn_leaves)?
+        .is_none());
+    // the reverted block can be applied again.
+    chain.apply(block.clone())?;
+    assert_eq!(chain.current_header(), block.header().clone());
+
+    for _ in 0..4 {
+        chain.revert_head()?;
+    }
+    assert_eq!(chain.current_header().number(), 0);
+    assert!(chain.revert_head().is_err());
+    Ok(())
+}
+
+#[stest::test]
+fn test_revert_head_finalized() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(4)?;
+    let mut chain = mock_chain.fork_new_branch(None)?;
+    assert_eq!(chain.finalized_number()?, 0);
+    assert!(chain.finalize(5).is_err());
+    assert_eq!(chain.finalize_by_depth(2)?, 2);
+    // the finalized number never moves back.
+    assert_eq!(chain.finalize(1)?, 2);
+    assert_eq!(chain.finalize_by_depth(10)?, 2);
+
+    chain.revert_head()?;
+    chain.revert_head()?;
+    assert_eq!(chain.current_header().number(), 2);
+    assert!(chain.revert_head().is_err());
+    assert_eq!(chain.current_header().number(), 2);
+    Ok(())
+}
+
+#[stest::test]
+fn test_account_state_with_proof() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(3)?;
+    let chain = mock_chain.head();
+    let header = chain.get_header_by_number(2)?.unwrap();
+
+    let (state, proof) = chain.get_account_state_with_proof(genesis_address(), header.id())?;
+    assert!(state.is_some());
+    proof.verify_account_state(header.state_root(), genesis_address(), state.as_deref())?;
+    // the proof is bound to the state root and the account state.
+    assert!(proof
+        .verify_account_state(header.state_root(), genesis_address(), None)
+        .is_err());
+    assert!(proof
+        .verify_account_state(
+            chain.current_header().state_root(),
+            genesis_address(),
+            state.as_deref()
+        )
+        .is_err());
+
+    let absent = account_address::AccountAddress::random();
+    let (state, proof) = chain.get_account_state_with_proof(absent, header.id())?;
+    assert!(state.is_none());
+    proof.verify_account_state(header.state_root(), absent, None)?;
+    assert!(proof
+        .verify_account_state(header.state_root(), genesis_address(), None)
+        .is_err());
+    Ok(())
+}
+
+#[stest::test]
+fn test_get_account_states() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(3)?;
+    let chain = mock_chain.head();
+    let header = chain.get_header_by_number(2)?.unwrap();
+
+    let addrs = vec![
+        genesis_address(),
+        account_address::AccountAddress::random(),
+        association_address(),
+        account_address::AccountAddress::random(),
+    ];
+    let states = chain.get_account_states(&addrs, header.id())?;
+    assert_eq!(states.len(), addrs.len());
+    assert!(states[0].is_some());
+    assert!(states[1].is_none());
+    assert!(states[2].is_some());
+    assert!(states[3].is_none());
+    for (addr, state) in addrs.iter().zip(states) {
+        let (expect, _) = chain.get_account_state_with_proof(*addr, header.id())?;
+        assert_eq!(state, expect);
+    }
+
+    assert!(chain.get_account_states(&[], header.id())?.is_empty());
+    assert!(chain
+        .get_account_states(&addrs, HashValue::random())
+        .is_err());
+    Ok(())
+}
+
+#[stest::test]
+fn test_transaction_receipt() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner_account = AccountInfo::random();
+    let signed_txn = build_transfer_from_association(
+        *miner_account.address(),
+        0,
+        10000,
+        config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+        config.net(),
+    )
+    .as_signed_user_txn()?
+    .clone();
+    let (template, excluded) = block_chain.create_block_template(
+        *miner_account.address(),
+        None,
+        vec![signed_txn.clone()],
+        vec![],
+        None,
+    )?;
+    assert!(excluded.discarded_txns.is_empty(), "txn is discarded.");
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    block_chain.apply(block.clone())?;
+
+    let txn_infos = block_chain
+        .get_storage()
+        .get_block_transaction_infos(block.id())?;
+    // the block metadata txn and the transfer txn.
+    assert_eq!(txn_infos.len(), 2);
+    for txn_info in txn_infos {
+        let receipt = block_chain
+            .get_transaction_receipt(txn_info.transaction_hash())?
+            .expect("receipt should exist");
+        assert_eq!(receipt.block_id, block.id());
+        assert_eq!(receipt.transaction_hash, txn_info.transaction_hash());
+        assert_eq!(receipt.status, KeptVMStatus::Executed);
+        assert_eq!(receipt.gas_used, txn_info.gas_used());
+        assert_eq!(Some(receipt.events), block_chain.get_events(txn_info.id())?);
+    }
+    let receipt = block_chain
+        .get_transaction_receipt(signed_txn.id())?
+        .unwrap();
+    assert!(receipt.gas_used > 0);
+    assert!(!receipt.events.is_empty());
+
+    assert!(block_chain
+        .get_transaction_receipt(HashValue::random())?
+        .is_none());
+    Ok(())
+}
+
+#[stest::test]
+fn test_transaction_info_by_version() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(5)?;
+    let chain = mock_chain.head();
+    let storage = chain.get_storage();
+
+    let mut version = 0u64;
+    for number in 0..=chain.current_header().number() {
+        let block_id = chain.get_hash_by_number_ensure(number)?;
+        for txn_info in storage.get_block_transaction_infos(block_id)? {
+            assert_eq!(txn_info.transaction_global_index, version);
+            assert_eq!(
+                chain.get_transaction_info_by_version(version)?,
+                Some(txn_info.transaction_info)
+            );
+            version += 1;
+        }
+    }
+    assert_eq!(version, chain.get_txn_accumulator().num_leaves());
+    assert!(chain.get_transaction_info_by_version(version)?.is_none());
+    Ok(())
+}
+
+#[stest::test]
+fn test_get_header_chain() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(20)?;
+    let chain = mock_chain.head();
+    let head = chain.current_header();
+
+    let headers = chain.get_header_chain(head.id(), 5)?;
+    assert_eq!(headers.len(), 5);
+    for (i, header) in headers.iter().enumerate() {
+        assert_eq!(header.number(), head.number() - i as u64);
+        assert_eq!(
+            Some(header.clone()),
+            chain.get_header_by_number(header.number())?
+        );
+    }
+    for pair in headers.windows(2) {
+        assert_eq!(pair[0].parent_hash(), pair[1].id());
+    }
+
+    // stop at genesis.
+    let from = chain.get_header_by_number(10)?.unwrap();
+    let headers = chain.get_header_chain(from.id(), 100)?;
+    assert_eq!(headers.len(), 11);
+    assert_eq!(headers.first().unwrap().id(), from.id());
+    assert!(headers.last().unwrap().is_genesis());
+
+    assert!(chain.get_header_chain(head.id(), 0)?.is_empty());
+    assert!(chain.get_header_chain(HashValue::random(), 1).is_err());
+    Ok(())
+}
+
+#[stest::test]
+fn test_get_latest_headers() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(30)?;
+    let chain = mock_chain.head();
+    let head = chain.current_header();
+
+    let headers = chain.get_latest_headers(20)?;
+    assert_eq!(headers.len(), 20);
+    assert_eq!(headers.first().unwrap().id(), head.id());
+    for (i, header) in headers.iter().enumerate() {
+        assert_eq!(header.number(), head.number() - i as u64);
+    }
+    assert_eq!(headers, chain.get_header_chain(head.id(), 20)?);
+
+    // bounded by genesis.
+    let headers = chain.get_latest_headers(100)?;
+    assert_eq!(headers.len() as u64, head.number() + 1);
+    assert!(headers.last().unwrap().is_genesis());
+    Ok(())
+}
+
+#[stest::test]
+fn test_apply_genesis_again() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    // the genesis is executed by new_with_genesis, and the normal block goes on top of it.
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let genesis = block_chain.get_block_by_id(BlockId::Genesis)?.unwrap();
+    let miner = *AccountInfo::random().address();
+    let (template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    block_chain.apply(block.clone())?;
+    assert_eq!(block.header().parent_hash(), genesis.id());
+
+    let err = block_chain
+        .apply(genesis)
+        .expect_err("the genesis block should not be applied again");
+    assert!(matches!(
+        err.downcast_ref::<ConnectBlockError>(),
+        Some(ConnectBlockError::VerifyBlockFailed(
+            VerifyBlockField::Header,
+            _
+        ))
+    ));
+    assert_eq!(block_chain.current_header(), block.header().clone());
+    Ok(())
+}
+
+#[stest::test]
+fn test_apply_block_with_mismatched_id() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let genesis_header = block_chain.current_header();
+    let miner = *AccountInfo::random().address();
+    let (template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+
+    // tamper the header, but keep the id of the original header.
+    let tampered_header = block
+        .header()
+        .as_builder()
+        .with_gas_used(block.header().gas_used().saturating_add(1))
+        .build_with_stale_id();
+    assert_eq!(tampered_header.id(), block.id());
+    let tampered_block = Block::new(tampered_header, block.body.clone());
+    let err = block_chain
+        .apply(tampered_block)
+        .expect_err("the block with mismatched id should be rejected");
+    assert!(matches!(
+        err.downcast_ref::<ConnectBlockError>(),
+        Some(ConnectBlockError::VerifyBlockFailed(
+            VerifyBlockField::Header,
+            _
+        ))
+    ));
+    assert_eq!(block_chain.current_header(), genesis_header);
+
+    // the id is not poisoned by the tampered block.
+    block_chain.apply(block.clone())?;
+    assert_eq!(block_chain.current_header(), block.header().clone());
+    Ok(())
+}
+
+#[stest::test]
+fn test_export_and_import_blocks() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(10)?;
+    let head = mock_chain.head().current_header();
+    let blocks = mock_chain.head().export_blocks(0, None);
+    // the blocks applied after the export do not change the stream.
+    mock_chain.produce_and_apply_times(3)?;
+    let blocks = blocks.collect::<Result<Vec<_>>>()?;
+    assert_eq!(blocks.len() as u64, head.number() + 1);
+    assert!(blocks[0].header().is_genesis());
+    assert_eq!(blocks.last().unwrap().header(), &head);
+
+    let mut new_chain = test_helper::gen_blockchain_for_test(mock_chain.net())?;
+    let imported = new_chain.import_blocks(blocks.into_iter().map(Ok))?;
+    assert_eq!(imported, head.number());
+    assert_eq!(new_chain.current_header(), head);
+    assert_eq!(
+        new_chain.get_block_info(Some(head.id()))?,
+        mock_chain.head().get_block_info(Some(head.id()))?
+    );
+
+    // the range beyond the head is an error.
+    let mut blocks = mock_chain.head().export_blocks(20, Some(20));
+    assert!(blocks.next().unwrap().is_err());
+    Ok(())
+}
+
+#[stest::test]
+fn test_get_block_by_id() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    let genesis = mock_chain.head().get_block_by_number(0)?.unwrap();
+    assert_eq!(
+        mock_chain.head().get_block_by_id(BlockId::Latest)?,
+        Some(genesis.clone())
+    );
+    mock_chain.produce_and_apply_times(5)?;
+    let chain = mock_chain.head();
+    let head = chain.head_block().block;
+    assert_eq!(head.header().number(), 5);
+    // the latest tracks the current head.
+    assert_eq!(chain.get_block_by_id(BlockId::Latest)?, Some(head.clone()));
+    assert_eq!(chain.get_block_by_id(BlockId::Genesis)?, Some(genesis));
+
+    let block = chain.get_block_by_number(3)?.unwrap();
+    assert_eq!(
+        chain.get_block_by_id(BlockId::Number(3))?,
+        Some(block.clone())
+    );
+    assert_eq!(
+        chain.get_block_by_id(BlockId::Hash(block.id()))?,
+        Some(block)
+    );
+    assert_eq!(chain.get_block_by_id(BlockId::Number(6))?, None);
+    assert_eq!(
+        chain.get_block_by_id(BlockId::Hash(HashValue::random()))?,
+        None
+    );
+    Ok(())
+}
+
+#[stest::test]
+fn test_block_template_difficulty() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(3)?;
+    let chain = mock_chain.head();
+    let (template, _) =
+        chain.create_block_template(*mock_chain.miner().address(), None, vec![], vec![], None)?;
+    assert_eq!(
+        template.difficulty,
+        chain.consensus().calculate_next_difficulty(chain)?
+    );
+    Ok(())
+}
+
+#[stest::test]
+fn test_replay_pre_difficulty_floor_chain() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    let times = 5;
+    mock_chain.produce_and_apply_times(times)?;
+    let blocks = (1..=times)
+        .map(|number| {
+            mock_chain
+                .head()
+                .get_block_by_number(number)?
+                .ok_or_else(|| format_err!("Can not find block by number {}", number))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // a floor above the difficulty of the mined blocks, activated after them.
+    let mined_difficulty = blocks[0].header().difficulty();
+    let floor = DifficultyFloor::new(mined_difficulty.as_u64() * 2, times + 1);
+
+    let mut replay_chain = test_helper::gen_blockchain_for_test(mock_chain.net())?;
+    replay_chain.set_difficulty_floor(Some(floor));
+    for block in blocks {
+        // the blocks before the activation keep the difficulty they were mined with.
+        assert_eq!(
+            replay_chain
+                .consensus()
+                .calculate_next_difficulty(&replay_chain)?,
+            block.header().difficulty()
+        );
+        replay_chain.apply(block)?;
+    }
+    assert_eq!(replay_chain.current_header().number(), times);
+
+    let (template, _) = replay_chain.create_block_template(
+        *mock_chain.miner().address(),
+        None,
+        vec![],
+        vec![],
+        None,
+    )?;
+    assert_eq!(template.difficulty, U256::from(floor.min_difficulty));
+    Ok(())
+}
+
+#[stest::test]
+fn test_revert_head_keeps_difficulty_floor() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(2)?;
+    let block1 = mock_chain
+        .head()
+        .get_block_by_number(1)?
+        .ok_or_else(|| format_err!("Can not find block by number 1"))?;
+    let block2 = mock_chain
+        .head()
+        .get_block_by_number(2)?
+        .ok_or_else(|| format_err!("Can not find block by number 2"))?;
+    let mined_difficulty = block2.header().difficulty();
+
+    let mut chain = test_helper::gen_blockchain_for_test(mock_chain.net())?;
+    chain.apply(block1)?;
+    chain.apply(block2.clone())?;
+    let floor = DifficultyFloor::new(mined_difficulty.as_u64() * 2, 1);
+    chain.set_difficulty_floor(Some(floor));
+    chain.revert_head()?;
+    assert_eq!(chain.difficulty_floor(), Some(floor));
+    // the block mined below the floor is rejected after the revert.
+    assert!(chain.apply(block2).is_err());
+    assert_eq!(chain.current_header().number(), 1);
+    Ok(())
+}
+
+#[stest::test]
+fn test_block_timestamp() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner = *AccountInfo::random().address();
+    let time_service = config.net().time_service();
+    for _ in 0..3 {
+        let parent = block_chain.current_header();
+        let (template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+        let block = block_chain
+            .consensus()
+            .create_block(template, time_service.as_ref())?;
+        // the template takes the time of the chain's time service.
+        assert!(block.header().timestamp() > parent.timestamp());
+        assert!(block.header().timestamp() <= time_service.now_millis());
+        block_chain.apply(block)?;
+    }
+
+    let parent = block_chain.current_header();
+    let (mut template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+    template.timestamp = parent.timestamp();
+    let block = block_chain
+        .consensus()
+        .create_block(template, time_service.as_ref())?;
+    let err = block_chain.apply(block).unwrap_err();
+    assert!(err.to_string().contains("too old"), "{}", err);
+
+    let (mut template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+    // the sealing sleeps on the mock time service, so leave some room for it.
+    template.timestamp = time_service.now_millis() + ALLOWED_FUTURE_BLOCKTIME * 10;
+    let block = block_chain
+        .consensus()
+        .create_block(template, time_service.as_ref())?;
+    let err = block_chain.apply(block).unwrap_err();
+    assert!(err.to_string().contains("too new"), "{}", err);
+    assert_eq!(block_chain.current_header(), parent);
+    Ok(())
+}
+
+#[stest::test]
+fn test_max_future_timestamp_skew() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner = *AccountInfo::random().address();
+    let time_service = config.net().time_service();
+    let max_future_block_time = 120 * 1000;
+    block_chain.set_max_future_block_time(max_future_block_time);
+    assert_eq!(block_chain.max_future_block_time(), max_future_block_time);
+    let parent = block_chain.current_header();
+
+    // beyond the skew, the sealing sleeps on the mock time service, so leave some room for it.
+    let (mut template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+    template.timestamp = time_service.now_millis() + max_future_block_time * 10;
+    let block = block_chain
+        .consensus()
+        .create_block(template, time_service.as_ref())?;
+    let err = block_chain.apply(block).unwrap_err();
+    assert!(err.to_string().contains("too new"), "{}", err);
+    assert_eq!(block_chain.current_header(), parent);
+
+    // within the skew, but beyond the default one.
+    let (mut template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+    template.timestamp = time_service.now_millis() + ALLOWED_FUTURE_BLOCKTIME * 2;
+    let block = block_chain
+        .consensus()
+        .create_block(template, time_service.as_ref())?;
+    block_chain.apply(block.clone())?;
+    assert_eq!(block_chain.current_header(), block.header().clone());
+    Ok(())
+}
+
+#[stest::test]
+fn test_mine_blocks_by_dev_consensus() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner = *AccountInfo::random().address();
+    let time_service = config.net().time_service();
+    let dev_consensus = DevConsensus::new();
+    let start = time_service.now_millis();
+    let count = 10;
+    for _ in 0..count {
+        let (template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+        let block = dev_consensus.create_block(template, time_service.as_ref())?;
+        block_chain.apply(block)?;
+    }
+    assert_eq!(block_chain.current_header().number(), count);
+    // the seal only steps one millisecond, instead of sleeping the block time of the dummy.
+    assert_eq!(time_service.now_millis() - start, count);
+    Ok(())
+}
+
+#[stest::test]
+fn test_get_txn_accumulator_root() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(3)?;
+    let chain = mock_chain.head();
+    let mut txn_info_ids = vec![];
+    let mut version = 0;
+    while let Some(txn_info) = chain.get_transaction_info_by_version(version)? {
+        txn_info_ids.push(txn_info.id());
+        version += 1;
+    }
+    assert_eq!(
+        chain.get_txn_accumulator_root(None)?,
+        InMemoryAccumulator::from_leaves(txn_info_ids.as_slice()).root_hash()
+    );
+
+    // the root at a block only includes the txns up to the block.
+    let block_id = chain.get_hash_by_number(1)?.unwrap();
+    let num_leaves = chain
+        .get_block_info(Some(block_id))?
+        .unwrap()
+        .get_txn_accumulator_info()
+        .get_num_leaves();
+    assert_eq!(
+        chain.get_txn_accumulator_root(Some(block_id))?,
+        InMemoryAccumulator::from_leaves(&txn_info_ids[..num_leaves as usize]).root_hash()
+    );
+    assert!(chain
+        .get_txn_accumulator_root(Some(HashValue::random()))
+        .is_err());
+    Ok(())
+}
+
+#[stest::test]
+fn test_chain_metrics() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    let metrics = mock_chain.head().metrics();
+    assert_eq!(metrics.blocks_applied, 0);
+    assert_eq!(metrics.head_number, 0);
+
+    let count = 5;
+    mock_chain.produce_and_apply_times(count)?;
+    let metrics = mock_chain.head().metrics();
+    assert_eq!(metrics.blocks_applied, count);
+    assert_eq!(metrics.head_number, count);
+
+    // the fork shares the counters, so the blocks applied to the branch are counted too.
+    let mut fork = mock_chain
+        .head()
+        .fork(mock_chain.head().current_header().id())?;
+    let block = mock_chain.produce()?;
+    fork.apply(block)?;
+    let metrics = mock_chain.head().metrics();
+    assert_eq!(metrics.blocks_applied, count + 1);
+    assert_eq!(metrics.head_number, count + 1);
+    Ok(())
+}
+
+#[stest::test]
+fn test_flush_cadence() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    let count = 10;
+    mock_chain.produce_and_apply_times(count)?;
+    // the default cadence flushes every block.
+    assert_eq!(mock_chain.head().metrics().flushes, count);
+    let blocks = (1..=count)
+        .map(|number| Ok(mock_chain.head().get_block_by_number(number)?.unwrap()))
+        .collect::<Result<Vec<_>>>()?;
+
+    // the clock of the new chain is not behind the blocks.
+    let head_timestamp = mock_chain.head().current_header().timestamp();
+    let mut chain = test_helper::gen_blockchain_in_memory_for_test()?;
+    chain.time_service().adjust(head_timestamp);
+    chain.set_flush_cadence(FlushCadence {
+        every_blocks: 3,
+        every_bytes: None,
+    });
+    for block in blocks.clone() {
+        chain.apply(block)?;
+    }
+    assert_eq!(chain.metrics().flushes, count / 3);
+    // the last block is only flushed by the explicit flush.
+    chain.flush()?;
+    assert_eq!(chain.metrics().flushes, count / 3 + 1);
+    chain.flush()?;
+    assert_eq!(chain.metrics().flushes, count / 3 + 1);
+    assert_eq!(chain.status(), mock_chain.head().status());
+
+    let mut chain = test_helper::gen_blockchain_in_memory_for_test()?;
+    chain.time_service().adjust(head_timestamp);
+    chain.set_flush_cadence(FlushCadence {
+        every_blocks: u64::MAX,
+        every_bytes: Some(1),
+    });
+    for block in blocks {
+        chain.apply(block)?;
+    }
+    assert_eq!(chain.metrics().flushes, count);
+    assert_eq!(chain.status(), mock_chain.head().status());
+    Ok(())
+}
+
+#[stest::test]
+fn test_apply_oversize_block() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner = *AccountInfo::random().address();
+    let (template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+
+    let txn: SignedUserTransaction = build_transfer_from_association(
+        miner,
+        0,
+        10000,
+        config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+        config.net(),
+    )
+    .try_into()?;
+    let count = DEFAULT_MAX_BLOCK_BYTES / txn.encode()?.len() as u64 + 1;
+    let body = BlockBody::new(vec![txn; count as usize], None);
+    let oversize_block = Block::new(block.header().clone(), body);
+    let err = block_chain
+        .apply(oversize_block)
+        .expect_err("the oversize block should be rejected");
+    assert!(matches!(
+        err.downcast_ref::<ConnectBlockError>(),
+        Some(ConnectBlockError::VerifyBlockFailed(
+            VerifyBlockField::Body,
+            _
+        ))
+    ));
+    assert_eq!(block_chain.current_header().number(), 0);
+    block_chain.apply(block)?;
+    Ok(())
+}
+
+#[stest::test]
+fn test_block_bytes_limit() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let net = config.net();
+    let mut block_chain = test_helper::gen_blockchain_for_test(net)?;
+    assert_eq!(
+        block_chain.block_bytes_limit(),
+        net.genesis_config().block_bytes_limit
+    );
+    let miner = *AccountInfo::random().address();
+    let txns = (0..3)
+        .map(|seq_number| {
+            build_transfer_from_association(
+                miner,
+                seq_number,
+                10000,
+                net.time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+                net,
+            )
+            .try_into()
+        })
+        .collect::<Result<Vec<SignedUserTransaction>>>()?;
+    let (empty_template, _) =
+        block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+    let empty_block = block_chain
+        .consensus()
+        .create_block(empty_template, net.time_service().as_ref())?;
+    // there is room for only one of the txns.
+    let txn_bytes = txns[0].encode()?.len() as u64;
+    let max_block_bytes = empty_block.encode()?.len() as u64 + txn_bytes + txn_bytes / 2;
+    block_chain.set_block_bytes_limit(BlockBytesLimit::new(max_block_bytes, 1));
+    let (template, excluded_txns) =
+        block_chain.create_block_template(miner, None, txns, vec![], None)?;
+    assert_eq!(template.body.transactions.len(), 1);
+    assert_eq!(excluded_txns.untouched_txns.len(), 2);
+    let block = block_chain
+        .consensus()
+        .create_block(template, net.time_service().as_ref())?;
+    let block_bytes = block.encode()?.len() as u64;
+    assert!(block_bytes <= max_block_bytes);
+
+    // the verifier reads the same limit as the template.
+    let mut lower_limit_chain = test_helper::gen_blockchain_for_test(net)?;
+    lower_limit_chain.set_block_bytes_limit(BlockBytesLimit::new(block_bytes - 1, 1));
+    let err = lower_limit_chain
+        .apply(block.clone())
+        .expect_err("the block above the limit should be rejected");
+    assert!(matches!(
+        err.downcast_ref::<ConnectBlockError>(),
+        Some(ConnectBlockError::VerifyBlockFailed(
+            VerifyBlockField::Body,
+            _
+        ))
+    ));
+    // the blocks before the activation are not limited.
+    let mut not_activated_chain = test_helper::gen_blockchain_for_test(net)?;
+    not_activated_chain.set_block_bytes_limit(BlockBytesLimit::new(block_bytes - 1, 2));
+    not_activated_chain.apply(block.clone())?;
+    block_chain.apply(block)?;
+    Ok(())
+}
+
+#[stest::test]
+fn test_block_template_skip_discarded_txn() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let receiver = *AccountInfo::random().address();
+    let build_txn = |seq_number: u64| {
+        build_transfer_from_association(
+            receiver,
+            seq_number,
+            10000,
+            config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+            config.net(),
+        )
+        .as_signed_user_txn()
+        .unwrap()
+        .clone()
+    };
+    let valid_txn1 = build_txn(0);
+    // the sequence number is too new, the txn is discarded by the vm.
+    let invalid_txn = build_txn(100);
+    let valid_txn2 = build_txn(1);
+    let (template, excluded) = block_chain.create_block_template(
+        receiver,
+        None,
+        vec![valid_txn1.clone(), invalid_txn.clone(), valid_txn2.clone()],
+        vec![],
+        None,
+    )?;
+    assert_eq!(excluded.discarded_txns, vec![invalid_txn]);
+    assert!(excluded.untouched_txns.is_empty());
+    assert_eq!(template.body.transactions, vec![valid_txn1, valid_txn2]);
+
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    block_chain.apply(block.clone())?;
+    assert_eq!(block_chain.current_header(), block.header().clone());
+    Ok(())
+}
+
+#[stest::test]
+fn test_simulate_transaction() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let head = block_chain.current_header();
+    let state_root = block_chain.chain_state_reader().state_root();
+    let receiver = *AccountInfo::random().address();
+    let signed_txn = build_transfer_from_association(
+        receiver,
+        0,
+        10000,
+        config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+        config.net(),
+    )
+    .as_signed_user_txn()?
+    .clone();
+
+    let output = block_chain.simulate_transaction(signed_txn)?;
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(KeptVMStatus::Executed)
+    );
+    assert!(output.gas_used() > 0);
+    assert!(!output.events().is_empty());
+    assert!(!output.write_set().is_empty());
+
+    assert_eq!(block_chain.current_header(), head);
+    assert_eq!(block_chain.chain_state_reader().state_root(), state_root);
+    assert!(!block_chain.chain_state_reader().exist_account(&receiver)?);
+    Ok(())
+}
+
+#[stest::test]
+fn test_verify_integrity() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(10)?;
+    let chain = mock_chain.head();
+    let report = chain.verify_integrity()?;
+    assert!(report.is_ok(), "{:?}", report);
+    assert_eq!(report.checked_blocks, chain.current_header().number() + 1);
+
+    // corrupt the txn info id list of a block.
+    let storage = chain.get_storage();
+    let block_id = chain.get_hash_by_number(5)?.unwrap();
+    let mut txn_info_ids = storage.get_block_txn_info_ids(block_id)?;
+    let dangling_id = HashValue::random();
+    txn_info_ids.push(dangling_id);
+    storage.save_block_txn_info_ids(block_id, txn_info_ids)?;
+
+    let report = chain.verify_integrity()?;
+    assert!(!report.is_ok());
+    assert!(report.reach_genesis);
+    assert_eq!(
+        report.issues,
+        vec![IntegrityIssue::DanglingTransactionInfo {
+            block_id,
+            txn_info_id: dangling_id,
+        }]
+    );
+    Ok(())
+}
+
+#[stest::test]
+fn test_pending_state_view() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let receiver = account_address::AccountAddress::random();
+    let expiration = config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME;
+    // the pending txns submitted to the txpool, the second one depends on the first one.
+    let pending_txns = (0..2u64)
+        .map(|seq_number| {
+            build_transfer_from_association(receiver, seq_number, 10000, expiration, config.net())
+                .as_signed_user_txn()
+                .map(|txn| txn.clone())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let pending_view = block_chain.pending_state_view(pending_txns)?;
+    let pending_reader = AccountStateReader::new(&pending_view);
+    assert_eq!(pending_reader.get_balance(&receiver)?, Some(20000));
+    assert_eq!(
+        pending_reader.get_sequence_number(association_address())?,
+        2
+    );
+
+    // the committed state is not changed.
+    let committed_reader = AccountStateReader::new(block_chain.chain_state());
+    assert_eq!(committed_reader.get_balance(&receiver)?, None);
+    assert_eq!(
+        committed_reader.get_sequence_number(association_address())?,
+        0
+    );
+    Ok(())
+}
+
+#[stest::test]
+fn test_get_block_overview() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner_account = AccountInfo::random();
+    let signed_txn = build_transfer_from_association(
+        *miner_account.address(),
+        0,
+        10000,
+        config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+        config.net(),
+    )
+    .as_signed_user_txn()?
+    .clone();
+    let (template, _) = block_chain.create_block_template(
+        *miner_account.address(),
+        None,
+        vec![signed_txn],
+        vec![],
+        None,
+    )?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    block_chain.apply(block.clone())?;
+
+    let overview = block_chain
+        .get_block_overview(block.id())?
+        .expect("overview should exist");
+    let header = block.header();
+    let block_info = block_chain.get_block_info(Some(block.id()))?.unwrap();
+    assert_eq!(overview.id, block.id());
+    assert_eq!(overview.number, header.number());
+    assert_eq!(overview.parent_hash, header.parent_hash());
+    assert_eq!(overview.timestamp, header.timestamp());
+    assert_eq!(overview.state_root, header.state_root());
+    assert_eq!(overview.txn_accumulator_root, header.txn_accumulator_root());
+    // the block metadata txn and the transfer txn.
+    assert_eq!(overview.txn_count, block.transactions().len() as u64 + 1);
+    assert_eq!(overview.gas_used, header.gas_used());
+    assert!(overview.gas_used > 0);
+    assert_eq!(overview.total_difficulty, block_info.total_difficulty);
+
+    assert!(block_chain
+        .get_block_overview(HashValue::random())?
+        .is_none());
+    Ok(())
+}
+
+#[stest::test]
+fn test_get_block_transactions() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner_account = AccountInfo::random();
+    let signed_txns = (0..2)
+        .map(|seq_num| {
+            Ok(build_transfer_from_association(
+                *miner_account.address(),
+                seq_num,
+                10000,
+                config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+                config.net(),
+            )
+            .as_signed_user_txn()?
+            .clone())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let (template, _) = block_chain.create_block_template(
+        *miner_account.address(),
+        None,
+        signed_txns.clone(),
+        vec![],
+        None,
+    )?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    block_chain.apply(block.clone())?;
+
+    let txns = block_chain.get_block_transactions(block.id())?;
+    let txn_infos = block_chain
+        .get_storage()
+        .get_block_transaction_infos(block.id())?;
+    assert_eq!(txns.len(), 3);
+    assert!(matches!(txns[0].0, Transaction::BlockMetadata(_)));
+    for (i, signed_txn) in signed_txns.into_iter().enumerate() {
+        assert_eq!(txns[i + 1].0, Transaction::UserTransaction(signed_txn));
+    }
+    for ((txn, txn_info), rich_txn_info) in txns.iter().zip(txn_infos.iter()) {
+        assert_eq!(txn.id(), txn_info.transaction_hash());
+        assert_eq!(txn_info, &rich_txn_info.transaction_info);
+    }
+
+    // the block without user txns only has the block metadata txn.
+    let (template, _) =
+        block_chain.create_block_template(*miner_account.address(), None, vec![], vec![], None)?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    block_chain.apply(block.clone())?;
+    let txns = block_chain.get_block_transactions(block.id())?;
+    assert_eq!(txns.len(), 1);
+    assert!(matches!(txns[0].0, Transaction::BlockMetadata(_)));
+    Ok(())
+}
+
+#[stest::test]
+fn test_apply_result() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner_account = AccountInfo::random();
+    let signed_txn = build_transfer_from_association(
+        *miner_account.address(),
+        0,
+        10000,
+        config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+        config.net(),
+    )
+    .as_signed_user_txn()?
+    .clone();
+    let (template, _) = block_chain.create_block_template(
+        *miner_account.address(),
+        None,
+        vec![signed_txn],
+        vec![],
+        None,
+    )?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    let result = block_chain.apply(block.clone())?.apply_result();
+
+    let header = block.header();
+    assert_eq!(result.block_id, block.id());
+    assert_eq!(result.gas_used, header.gas_used());
+    assert!(result.gas_used > 0);
+    // the block metadata txn and the transfer txn.
+    assert_eq!(result.executed_txns, 2);
+    assert_eq!(result.state_root, header.state_root());
+    assert_eq!(
+        result.state_root,
+        block_chain.chain_state_reader().state_root()
+    );
+    assert_eq!(result.accumulator_root, header.txn_accumulator_root());
+    assert_eq!(
+        result.accumulator_root,
+        block_chain.get_txn_accumulator_root(None)?
+    );
+    Ok(())
+}
+
+#[stest::test]
+fn test_apply_block_with_mismatched_body() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner_account = AccountInfo::random();
+    let expiration = config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME;
+    let txns = (0..2u64)
+        .map(|seq_number| {
+            build_transfer_from_association(
+                *miner_account.address(),
+                seq_number,
+                10000,
+                expiration,
+                config.net(),
+            )
+            .as_signed_user_txn()
+            .map(|txn| txn.clone())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let (template, _) =
+        block_chain.create_block_template(*miner_account.address(), None, txns, vec![], None)?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    assert_eq!(block.transactions().len(), 2);
+
+    // the header is kept, but the txns of the body are swapped.
+    let mut swapped_txns = block.transactions().to_vec();
+    swapped_txns.swap(0, 1);
+    let tampered_block = Block::new(
+        block.header().clone(),
+        BlockBody::new(swapped_txns, block.body.uncles.clone()),
+    );
+    let err = block_chain
+        .apply(tampered_block)
+        .expect_err("the block with mismatched body should be rejected");
+    assert!(matches!(
+        err.downcast_ref::<ConnectBlockError>(),
+        Some(ConnectBlockError::VerifyBlockFailed(
+            VerifyBlockField::Body,
+            _
+        ))
+    ));
+    // the block is rejected before execution, so the head is not changed.
+    assert_eq!(block_chain.current_header().number(), 0);
+
+    block_chain.apply(block)?;
+    assert_eq!(block_chain.current_header().number(), 1);
+    Ok(())
+}
+
+#[stest::test]
+fn test_apply_block_with_tampered_author() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner = *AccountInfo::random().address();
+    let (template, _) = block_chain.create_block_template(miner, None, vec![], vec![], None)?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+
+    // the metadata txn is built from the header, so the author of the header is credited.
+    let metadata = block.to_metadata(block_chain.current_header().gas_used());
+    assert_eq!(metadata.author(), miner);
+    assert_eq!(metadata.timestamp(), block.header().timestamp());
+    assert_eq!(metadata.parent_hash(), block.header().parent_hash());
+
+    // the author is replaced after the block is sealed, the executed state does not match.
+    let tampered_header = block
+        .header()
+        .as_builder()
+        .with_author(*AccountInfo::random().address())
+        .build();
+    let tampered_block = Block::new(tampered_header, block.body.clone());
+    let err = block_chain
+        .apply(tampered_block)
+        .expect_err("the block with tampered author should be rejected");
+    assert!(matches!(
+        err.downcast_ref::<ConnectBlockError>(),
+        Some(ConnectBlockError::VerifyBlockFailed(
+            VerifyBlockField::State,
+            _
+        ))
+    ));
+    assert_eq!(block_chain.current_header().number(), 0);
+
+    block_chain.apply(block)?;
+    assert_eq!(block_chain.current_header().author(), miner);
+    Ok(())
+}
+
+#[stest::test]
+fn test_apply_block_with_expired_txn() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner_account = AccountInfo::random();
+    let now_secs = config.net().time_service().now_secs();
+    let signed_txn = build_transfer_from_association(
+        *miner_account.address(),
+        0,
+        10000,
+        now_secs + 1,
+        config.net(),
+    )
+    .as_signed_user_txn()?
+    .clone();
+    let (template, _) = block_chain.create_block_template(
+        *miner_account.address(),
+        None,
+        vec![signed_txn.clone()],
+        vec![],
+        None,
+    )?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    assert_eq!(block.transactions(), &[signed_txn.clone()]);
+
+    // the txn is expired at the block timestamp of its expiration.
+    let header = block
+        .header()
+        .as_builder()
+        .with_timestamp(signed_txn.expiration_timestamp_secs() * 1000)
+        .build();
+    let expired_block = Block::new(header, block.body.clone());
+    let err = block_chain
+        .apply(expired_block)
+        .expect_err("the block with expired txn should be rejected");
+    assert!(matches!(
+        err.downcast_ref::<ConnectBlockError>(),
+        Some(ConnectBlockError::VerifyBlockFailed(
+            VerifyBlockField::Body,
+            _
+        ))
+    ));
+
+    block_chain.apply(block)?;
+    assert_eq!(block_chain.current_header().number(), 1);
+    Ok(())
+}
+
+#[stest::test]
+fn test_apply_block_structured_log() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner_account = AccountInfo::random();
+    let (template, _) =
+        block_chain.create_block_template(*miner_account.address(), None, vec![], vec![], None)?;
+    let block = block_chain
+        .consensus()
+        .create_block(template, config.net().time_service().as_ref())?;
+    let capture = logger::structured_log::capture_for_test();
+    block_chain.apply(block.clone())?;
+
+    // the other tests may log at the same time, so find the records by the block id.
+    let block_records = capture
+        .records()
+        .iter()
+        .filter(|record| record.contains(&format!("block_id={}", block.id())))
+        .cloned()
+        .collect::<Vec<_>>();
+    let block_fields = [
+        "block_number=1".to_string(),
+        format!("parent_hash={}", block.header().parent_hash()),
+        "txn_count=0".to_string(),
+    ];
+    let expected = [
+        ("block executed", "execute_ms="),
+        ("block applied", "commit_ms="),
+    ];
+    for (message, timing) in expected {
+        let record = block_records
+            .iter()
+            .find(|record| record.starts_with(message))
+            .unwrap_or_else(|| panic!("record {} should be logged", message));
+        assert!(record.contains(timing));
+        for field in &block_fields {
+            assert!(
+                record.contains(field.as_str()),
+                "{} misses {}",
+                record,
+                field
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use starcoin_chain::{ChainReader, ForkChoice, TotalDifficultyForkChoice};
+use starcoin_chain_mock::MockChain;
+use starcoin_config::ChainNetwork;
+
+#[stest::test]
+fn test_heaviest_chain_fork_choice() -> Result<()> {
+    let mut mock_chain = MockChain::new(ChainNetwork::new_test())?;
+    mock_chain.produce_and_apply_times(2)?;
+    let ancestor = mock_chain.head().current_header();
+    let mut light_branch = mock_chain.fork(None)?;
+    let mut tie_branch = mock_chain.fork(None)?;
+    mock_chain.produce_and_apply_times(3)?;
+    light_branch.produce_and_apply_times(1)?;
+    tie_branch.produce_and_apply_times(1)?;
+
+    let storage = mock_chain.head().get_storage();
+    let heavy = mock_chain.head().current_header();
+    let light = light_branch.head().current_header();
+    let tie = tie_branch.head().current_header();
+    assert!(
+        mock_chain.head().get_total_difficulty()? > light_branch.head().get_total_difficulty()?
+    );
+    assert_eq!(
+        light_branch.head().get_total_difficulty()?,
+        tie_branch.head().get_total_difficulty()?
+    );
+
+    let fork_choice = TotalDifficultyForkChoice;
+    let candidates = vec![ancestor.clone(), light.clone(), heavy.clone()];
+    assert_eq!(
+        fork_choice.select_head(&candidates, storage.as_ref())?,
+        heavy.id()
+    );
+    let candidates = vec![heavy.clone(), ancestor, light.clone()];
+    assert_eq!(
+        fork_choice.select_head(&candidates, storage.as_ref())?,
+        heavy.id()
+    );
+    // keep the first candidate if the total difficulty is equal.
+    let candidates = vec![light.clone(), tie.clone()];
+    assert_eq!(
+        fork_choice.select_head(&candidates, storage.as_ref())?,
+        light.id()
+    );
+    let candidates = vec![tie.clone(), light];
+    assert_eq!(
+        fork_choice.select_head(&candidates, storage.as_ref())?,
+        tie.id()
+    );
+    assert!(fork_choice.select_head(&[], storage.as_ref()).is_err());
+    Ok(())
+}
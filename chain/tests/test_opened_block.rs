@@ -1,4 +1,5 @@
 use anyhow::Result;
+use bcs_ext::BCSCodec;
 use crypto::keygen::KeyGen;
 use logger::prelude::*;
 use starcoin_account_api::AccountInfo;
@@ -9,7 +10,10 @@ use starcoin_state_api::StateReaderExt;
 use starcoin_transaction_builder::{
     build_transfer_from_association, build_transfer_txn, DEFAULT_EXPIRATION_TIME,
 };
+use starcoin_types::block::BlockHeaderExtra;
+use starcoin_types::transaction::SignedUserTransaction;
 use starcoin_types::{account_address, account_config, U256};
+use std::time::{Duration, Instant};
 use std::{convert::TryInto, sync::Arc};
 
 #[stest::test]
@@ -109,3 +113,114 @@ pub fn test_open_block() -> Result<()> {
 
     Ok(())
 }
+
+#[stest::test]
+pub fn test_open_block_bytes_limit() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let header = chain.current_header();
+    let account_reader = chain.chain_state_reader();
+    let association_sequence_num =
+        account_reader.get_sequence_number(account_config::association_address())?;
+    let txns: Vec<SignedUserTransaction> = (0..2u64)
+        .map(|i| {
+            build_transfer_from_association(
+                *AccountInfo::random().address(),
+                association_sequence_num + i,
+                50_000_000,
+                config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+                config.net(),
+            )
+            .try_into()
+        })
+        .collect::<Result<_>>()?;
+
+    let opened_block = OpenedBlock::new(
+        chain.get_storage(),
+        header,
+        10000000,
+        *AccountInfo::random().address(),
+        config.net().time_service().now_millis(),
+        vec![],
+        U256::from(0),
+        chain.consensus(),
+        None,
+    )?;
+    // the limit only fits the first txn.
+    let first_txn_bytes = txns[0].encode()?.len() as u64;
+    let block_bytes_limit = opened_block.block_bytes() + first_txn_bytes;
+    let mut opened_block = opened_block.with_block_bytes_limit(block_bytes_limit);
+    let excluded = opened_block.push_txns(txns.clone())?;
+    assert_eq!(excluded.discarded_txns.len(), 0);
+    assert_eq!(excluded.untouched_txns, vec![txns[1].clone()]);
+    assert_eq!(opened_block.included_user_txns(), &txns[0..1]);
+    assert_eq!(opened_block.block_bytes(), block_bytes_limit);
+
+    // the encoded block of the template does not exceed the limit.
+    let template = opened_block.finalize()?;
+    let block = template.into_block(0, BlockHeaderExtra::new([0u8; 4]));
+    assert!(block.encode()?.len() as u64 <= block_bytes_limit);
+    Ok(())
+}
+
+#[stest::test]
+pub fn test_open_block_execution_deadline() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let account_reader = chain.chain_state_reader();
+    let association_sequence_num =
+        account_reader.get_sequence_number(account_config::association_address())?;
+    let txns: Vec<SignedUserTransaction> = (0..3u64)
+        .map(|i| {
+            build_transfer_from_association(
+                *AccountInfo::random().address(),
+                association_sequence_num + i,
+                50_000_000,
+                config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+                config.net(),
+            )
+            .try_into()
+        })
+        .collect::<Result<_>>()?;
+    let author = *AccountInfo::random().address();
+    let now_millis = config.net().time_service().now_millis();
+    let open_block = || {
+        OpenedBlock::new(
+            chain.get_storage(),
+            chain.current_header(),
+            10000000,
+            author,
+            now_millis,
+            vec![],
+            U256::from(0),
+            chain.consensus(),
+            None,
+        )
+    };
+
+    // the txns executed one by one before the deadline have the same state as in a batch.
+    let mut batch_block = open_block()?;
+    batch_block.push_txns(txns.clone())?;
+    let mut opened_block =
+        open_block()?.with_execution_deadline(Instant::now() + Duration::from_secs(60));
+    let excluded = opened_block.push_txns(txns.clone())?;
+    assert_eq!(excluded.discarded_txns.len(), 0);
+    assert_eq!(excluded.untouched_txns.len(), 0);
+    assert_eq!(opened_block.included_user_txns(), txns.as_slice());
+    assert_eq!(opened_block.gas_used(), batch_block.gas_used());
+    assert_eq!(opened_block.state_root(), batch_block.state_root());
+    assert_eq!(
+        opened_block.accumulator_root(),
+        batch_block.accumulator_root()
+    );
+
+    // no txn is executed after the deadline, the block is sealed without them.
+    let mut opened_block = open_block()?.with_execution_deadline(Instant::now());
+    let excluded = opened_block.push_txns(txns.clone())?;
+    assert_eq!(excluded.discarded_txns.len(), 0);
+    assert_eq!(excluded.untouched_txns, txns);
+    assert!(opened_block.included_user_txns().is_empty());
+    let template = opened_block.finalize()?;
+    assert!(template.body.transactions.is_empty());
+    Ok(())
+}
@@ -152,3 +152,54 @@ fn test_transaction_info_and_proof() -> Result<()> {
 
     Ok(())
 }
+
+#[stest::test]
+fn test_transaction_proof_by_hash() -> Result<()> {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let mut block_chain = test_helper::gen_blockchain_for_test(config.net())?;
+    let miner_account = AccountInfo::random();
+    let mut blocks = vec![];
+    for seq_number in 0..2 {
+        let txn = peer_to_peer_txn_sent_as_association(
+            AccountAddress::random(),
+            seq_number,
+            10000,
+            config.net().time_service().now_secs() + DEFAULT_EXPIRATION_TIME,
+            config.net(),
+        );
+        let (template, _) = block_chain.create_block_template(
+            *miner_account.address(),
+            None,
+            vec![txn],
+            vec![],
+            None,
+        )?;
+        let block = block_chain
+            .consensus()
+            .create_block(template, config.net().time_service().as_ref())?;
+        block_chain.apply(block.clone())?;
+        blocks.push(block);
+    }
+
+    let block = &blocks[0];
+    let txn_hash = block.transactions()[0].id();
+    let txn_proof = block_chain
+        .get_transaction_proof_by_hash(txn_hash)?
+        .expect("the proof of the included txn should exist");
+    assert_eq!(txn_proof.transaction_info.block_id(), block.id());
+    txn_proof.verify_transaction(block.header().txn_accumulator_root(), txn_hash)?;
+
+    // the proof is bound to the txn and the accumulator root of its block.
+    let other_txn_hash = blocks[1].transactions()[0].id();
+    assert!(txn_proof
+        .verify_transaction(block.header().txn_accumulator_root(), other_txn_hash)
+        .is_err());
+    assert!(txn_proof
+        .verify_transaction(blocks[1].header().txn_accumulator_root(), txn_hash)
+        .is_err());
+
+    assert!(block_chain
+        .get_transaction_proof_by_hash(HashValue::random())?
+        .is_none());
+    Ok(())
+}
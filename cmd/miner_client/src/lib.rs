@@ -1,9 +1,11 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
+mod backoff;
 mod cpu_solver;
 pub mod job_bus_client;
 pub mod job_client;
 pub mod miner;
+pub mod solo;
 mod solver;
 pub mod stratum_client;
 pub mod stratum_client_service;
@@ -36,6 +36,8 @@ fn main() {
             plugin_path: opts.plugin_path,
             miner_thread: opts.thread_num,
             enable_stderr: true,
+            min_reconnect_delay: None,
+            max_reconnect_delay: None,
         }
     };
     let user = opts.user;
@@ -0,0 +1,47 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+use crate::miner::MinerClientService;
+use crate::stratum_client::StratumJobClient;
+use crate::stratum_client_service::{StratumClientService, StratumClientServiceServiceFactory};
+use anyhow::Result;
+use logger::prelude::*;
+use starcoin_config::MinerClientConfig;
+use starcoin_service_registry::{RegistryAsyncService, RegistryService};
+use starcoin_stratum::rpc::LoginRequest;
+use starcoin_time_service::RealTimeService;
+use std::future::Future;
+use std::sync::Arc;
+
+pub const SOLO_MINER_USER: &str = "solo";
+
+/// Run a cpu miner in process, which subscribes jobs from the stratum endpoint `config.server`,
+/// solves them with `config.miner_thread` threads, and submits the seals back.
+/// The miner stops when `shutdown` is completed.
+pub async fn run<F>(config: MinerClientConfig, shutdown: F) -> Result<()>
+where
+    F: Future<Output = ()>,
+{
+    let registry = RegistryService::launch();
+    registry.put_shared(config).await?;
+    let stratum_cli_srv = registry
+        .register_by_factory::<StratumClientService, StratumClientServiceServiceFactory>()
+        .await?;
+    let login = LoginRequest {
+        login: SOLO_MINER_USER.into(),
+        pass: SOLO_MINER_USER.into(),
+        agent: "stc-solo-miner".into(),
+        algo: None,
+    };
+    let job_client =
+        StratumJobClient::new(stratum_cli_srv, Arc::new(RealTimeService::new()), login);
+    registry.put_shared(job_client).await?;
+    registry
+        .register::<MinerClientService<StratumJobClient>>()
+        .await?;
+    info!("Solo miner started");
+
+    shutdown.await;
+    registry.shutdown_system().await?;
+    info!("Solo miner stopped");
+    Ok(())
+}
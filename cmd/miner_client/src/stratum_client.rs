@@ -9,7 +9,6 @@ use futures::stream::{BoxStream, StreamExt};
 use logger::prelude::error;
 use starcoin_service_registry::ServiceRef;
 use starcoin_stratum::rpc::LoginRequest;
-use starcoin_stratum::target_hex_to_difficulty;
 use starcoin_time_service::TimeService;
 use starcoin_types::genesis_config::ConsensusStrategy;
 use starcoin_types::system_events::{MintBlockEvent, MintEventExtra};
@@ -49,7 +48,7 @@ impl JobClient for StratumJobClient {
                 .map(|s| {
                     s.filter_map(|job| {
                         let blob = hex::decode(&job.blob);
-                        let diff = target_hex_to_difficulty(&job.target);
+                        let diff = job.difficulty();
                         let extra = job.get_extra();
                         let event = if let (Ok(blob), Ok(diff), Ok(extra)) = (blob, diff, extra) {
                             Some(MintBlockEvent {
@@ -1,6 +1,7 @@
+use crate::backoff::Backoff;
 use anyhow::anyhow;
 use anyhow::Result;
-use futures::{select, Sink, SinkExt, Stream, StreamExt, TryStreamExt};
+use futures::{select, Future, FutureExt, Sink, SinkExt, Stream, StreamExt, TryStreamExt};
 use futures_channel::mpsc;
 use futures_channel::oneshot;
 use jsonrpc_core::{Params, Version};
@@ -147,20 +148,19 @@ fn build_request_string<T: ?Sized + Serialize>(
 
 pub struct StratumClientService {
     sender: Option<mpsc::UnboundedSender<Request>>,
-    tcp_stream: Option<std::net::TcpStream>,
+    addr: String,
+    backoff: Backoff,
 }
 
-struct Inner {
-    request_channel: mpsc::UnboundedReceiver<Request>,
-    connections: HashMap<String, mpsc::UnboundedSender<StratumJob>>,
-    stream: Option<Pin<Box<dyn Stream<Item = String> + Send>>>,
-    pending_requests: HashMap<u32, PendingRequest>,
-    sink: Pin<Box<dyn Sink<String, Error = anyhow::Error> + Send>>,
+type RequestSink = Pin<Box<dyn Sink<String, Error = anyhow::Error> + Send>>;
+
+struct Connection {
+    stream: Pin<Box<dyn Stream<Item = String> + Send>>,
+    sink: RequestSink,
 }
 
-impl Inner {
-    pub fn new(tcp_stream: TcpStream) -> (Inner, mpsc::UnboundedSender<Request>) {
-        let (s, channel) = mpsc::unbounded::<Request>();
+impl Connection {
+    fn new(tcp_stream: TcpStream) -> Self {
         let (sink, stream) = StreamCodec::stream_incoming().framed(tcp_stream).split();
         let sink = Box::pin(sink.sink_map_err(|e| anyhow!(format!("{}", e))));
         let stream = Box::pin(
@@ -169,13 +169,33 @@ impl Inner {
                 .take_while(|x| futures::future::ready(x.is_ok()))
                 .map(|x| x.expect("Stream is closed upon first error")),
         );
+        Self { stream, sink }
+    }
+}
+
+struct Inner {
+    addr: String,
+    backoff: Backoff,
+    request_channel: mpsc::UnboundedReceiver<Request>,
+    /// The login requests and their job channels, they are sent again after reconnected.
+    logins: Vec<(LoginRequest, mpsc::UnboundedSender<StratumJob>)>,
+    connections: HashMap<String, mpsc::UnboundedSender<StratumJob>>,
+    pending_requests: HashMap<u32, PendingRequest>,
+    request_id: u32,
+}
+
+impl Inner {
+    pub fn new(addr: String, backoff: Backoff) -> (Inner, mpsc::UnboundedSender<Request>) {
+        let (s, channel) = mpsc::unbounded::<Request>();
         (
             Self {
+                addr,
+                backoff,
                 connections: Default::default(),
-                stream: Some(stream),
+                logins: vec![],
                 pending_requests: Default::default(),
-                sink,
                 request_channel: channel,
+                request_id: 0,
             },
             s,
         )
@@ -192,13 +212,9 @@ impl Inner {
                 if let Some(pending_request) = self.pending_requests.remove(&output.id) {
                     match output.result {
                         OutputResponse::StratumJob(job) => {
-                            if let PendingRequest::LoginRequest(sender) = pending_request {
-                                let (mut s, r) = mpsc::unbounded();
-                                s.send(job.job).await?;
-                                self.connections.insert(job.id, s);
-                                sender
-                                    .send(r)
-                                    .map_err(|_| anyhow!("write channel failed"))?;
+                            if let PendingRequest::LoginRequest(mut sender) = pending_request {
+                                sender.send(job.job).await?;
+                                self.connections.insert(job.id, sender);
                             }
                         }
                         OutputResponse::Status(status) => {
@@ -221,55 +237,167 @@ impl Inner {
         Ok(())
     }
 
-    pub async fn start(mut self) {
-        let mut stream_fuse = self.stream.take().expect("stream must exist").fuse();
-        //move out
-        let mut request_id: u32 = 0;
+    fn next_request_id(&mut self) -> u32 {
+        self.request_id = self.request_id.wrapping_add(1);
+        self.request_id
+    }
+
+    async fn send_login(
+        &mut self,
+        sink: &mut RequestSink,
+        login_req: &LoginRequest,
+        sender: mpsc::UnboundedSender<StratumJob>,
+    ) -> Result<()> {
+        let request_id = self.next_request_id();
+        let message = build_request_string("login", login_req, request_id)
+            .expect("build stratum login request failed never happen");
+        debug!("stratum client send request:{}", message);
+        sink.send(message).await?;
+        self.pending_requests
+            .insert(request_id, PendingRequest::LoginRequest(sender));
+        Ok(())
+    }
+
+    /// Serve the requests on the connection, return false if the request channel is closed.
+    async fn serve(&mut self, conn: Connection) -> bool {
+        let Connection { stream, mut sink } = conn;
+        self.logins.retain(|(_, sender)| !sender.is_closed());
+        for (login_req, sender) in self.logins.clone() {
+            if let Err(err) = self.send_login(&mut sink, &login_req, sender).await {
+                error!("stratum send login request failed: {}", err);
+                return true;
+            }
+        }
+        let mut stream_fuse = stream.fuse();
         loop {
             select! {
-                req = self.request_channel.select_next_some() =>{
-                    request_id+=1;
+                req = self.request_channel.next() => {
                     match req {
-                        Request::LoginRequest(login_req, s)=>{
-                            let message = build_request_string("login", &login_req, request_id).expect("build stratum login request failed never happen");
-                            debug!("stratum client send request:{}",message);
-                            if let Err(err) = self.sink.send(message).await{
+                        Some(Request::LoginRequest(login_req, s)) => {
+                            let (sender, receiver) = mpsc::unbounded();
+                            if s.send(receiver).is_err() {
+                                continue;
+                            }
+                            self.logins.push((login_req.clone(), sender.clone()));
+                            if let Err(err) = self.send_login(&mut sink, &login_req, sender).await {
                                 error!("stratum send request failed: {}", err);
-                                continue
+                                return true;
                             }
-                            self.pending_requests.insert(request_id, PendingRequest::LoginRequest(s));
                         }
-                        Request::SubmitSealRequest(seal_req)=>{
+                        Some(Request::SubmitSealRequest(seal_req)) => {
+                            let request_id = self.next_request_id();
                             let message = build_request_string("submit", &seal_req, request_id).expect("build stratum login request failed never happen");
                             debug!("stratum send request:{}",message);
-                            if let Err(err) = self.sink.send(message).await{
+                            if let Err(err) = sink.send(message).await {
                                 error!("stratum send request failed: {}", err);
-                                continue
+                                return true;
                             }
                         }
+                        None => return false,
                     }
                 },
 
-                resp = stream_fuse.select_next_some() => {
-                    if let Err(err) = self.process_output(resp).await{
-                        debug!("process output error:{:?}", err);
+                resp = stream_fuse.next() => {
+                    match resp {
+                        Some(resp) => {
+                            if let Err(err) = self.process_output(resp).await {
+                                debug!("process output error:{:?}", err);
+                            }
+                        }
+                        None => {
+                            warn!("stratum connection to {} is closed", self.addr);
+                            return true;
+                        }
                     }
                 },
             }
         }
     }
+
+    /// Handle the request received while disconnected, return false if the request channel is
+    /// closed. The login is sent after reconnected, and the seal is dropped.
+    fn handle_offline_request(&mut self, req: Option<Request>) -> bool {
+        match req {
+            Some(Request::LoginRequest(login_req, s)) => {
+                let (sender, receiver) = mpsc::unbounded();
+                if s.send(receiver).is_ok() {
+                    self.logins.push((login_req, sender));
+                }
+                true
+            }
+            Some(Request::SubmitSealRequest(_)) => {
+                warn!(
+                    "stratum client is not connected to {}, drop the seal",
+                    self.addr
+                );
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wait for `fut` while disconnected and keep handling the requests, return None if the
+    /// request channel is closed before `fut` is ready.
+    async fn wait_offline<F: Future>(&mut self, fut: F) -> Option<F::Output> {
+        let fut = fut.fuse();
+        futures::pin_mut!(fut);
+        loop {
+            select! {
+                output = fut => return Some(output),
+                req = self.request_channel.next() => {
+                    if !self.handle_offline_request(req) {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn start(mut self) {
+        loop {
+            match self
+                .wait_offline(TcpStream::connect(self.addr.clone()))
+                .await
+            {
+                Some(Ok(tcp_stream)) => {
+                    info!("stratum client connected to {}", self.addr);
+                    self.backoff.reset();
+                    if !self.serve(Connection::new(tcp_stream)).await {
+                        break;
+                    }
+                    // the worker ids are assigned again by the server after relogin.
+                    self.connections.clear();
+                    self.pending_requests.clear();
+                }
+                Some(Err(err)) => {
+                    error!("stratum client connect to {} failed: {}", self.addr, err);
+                }
+                None => break,
+            }
+            let delay = self.backoff.next_delay();
+            info!("stratum client reconnect after {:?}", delay);
+            if self.wait_offline(tokio::time::sleep(delay)).await.is_none() {
+                break;
+            }
+        }
+        info!("stratum client stopped");
+    }
 }
 
 impl ActorService for StratumClientService {
     fn started(&mut self, _ctx: &mut ServiceContext<Self>) -> Result<()> {
-        let tcp_stream = TcpStream::from_std(
-            self.tcp_stream
-                .take()
-                .ok_or_else(|| anyhow!("stratum client not got a tcp stream"))?,
-        )?;
-        let (inner, sender) = Inner::new(tcp_stream);
+        let (inner, sender) = Inner::new(self.addr.clone(), self.backoff.clone());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
         self.sender = Some(sender);
-        std::thread::spawn(move || futures::executor::block_on(inner.start()));
+        std::thread::spawn(move || runtime.block_on(inner.start()));
+        Ok(())
+    }
+
+    fn stopped(&mut self, _ctx: &mut ServiceContext<Self>) -> Result<()> {
+        // drop the request sender, so the connection thread exits, even if it's reconnecting.
+        self.sender = None;
         Ok(())
     }
 }
@@ -314,11 +442,40 @@ pub struct StratumClientServiceServiceFactory;
 impl ServiceFactory<StratumClientService> for StratumClientServiceServiceFactory {
     fn create(ctx: &mut ServiceContext<StratumClientService>) -> Result<StratumClientService> {
         let cfg = ctx.get_shared::<MinerClientConfig>()?;
+        let backoff = Backoff::new(cfg.min_reconnect_delay(), cfg.max_reconnect_delay());
         let addr = cfg.server.unwrap_or_else(|| "127.0.0.1:9880".into());
-        let tcp_stream = Some(std::net::TcpStream::connect(&addr)?);
         Ok(StratumClientService {
             sender: None,
-            tcp_stream,
+            addr,
+            backoff,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    #[stest::test(timeout = 30)]
+    async fn test_stop_while_disconnected() {
+        // nothing listens on the port, so the client keeps reconnecting.
+        let addr = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().to_string()
+        };
+        let backoff = Backoff::new(Duration::from_secs(60), Duration::from_secs(60));
+        let (inner, sender) = Inner::new(addr, backoff);
+        let task = tokio::spawn(inner.start());
+        // wait the first connection fails, then the client waits the reconnect delay.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!task.is_finished());
+
+        drop(sender);
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .expect("the client should stop before the reconnect delay")
+            .unwrap();
+    }
+}
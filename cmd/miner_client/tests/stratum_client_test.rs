@@ -0,0 +1,139 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use futures::channel::oneshot;
+use futures::StreamExt;
+use jsonrpc_core::Version;
+use starcoin_config::MinerClientConfig;
+use starcoin_miner_client::solo;
+use starcoin_miner_client::stratum_client_service::{
+    LoginRequest, MethodCall, Output, OutputResponse, StratumClientService,
+    StratumClientServiceServiceFactory, StratumJob, StratumJobResponse,
+};
+use starcoin_service_registry::{RegistryAsyncService, RegistryService};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+fn login_response(id: u32, round: u8, target: &str) -> String {
+    let worker_id = hex::encode([round; 4]);
+    let output = Output {
+        jsonrpc: Some(Version::V2),
+        result: OutputResponse::StratumJob(StratumJobResponse {
+            login: None,
+            id: worker_id.clone(),
+            status: "OK".into(),
+            job: StratumJob {
+                height: 0,
+                id: worker_id,
+                target: target.into(),
+                job_id: hex::encode([round; 8]),
+                blob: hex::encode([round; 76]),
+                nonce_range: None,
+                clean_jobs: false,
+            },
+        }),
+        id,
+        error: None,
+    };
+    let mut message = serde_json::to_string(&output).unwrap();
+    message.push('\n');
+    message
+}
+
+/// The mock server drops the first connection after login, and keeps the second one.
+fn start_mock_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        for round in 0..2u8 {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let call: MethodCall = serde_json::from_str(&line).unwrap();
+            assert_eq!(call.method, "login");
+            stream
+                .write_all(login_response(call.id, round, "b88d0600").as_bytes())
+                .unwrap();
+            if round == 1 {
+                std::thread::sleep(Duration::from_secs(10));
+            }
+        }
+    });
+    addr
+}
+
+#[stest::test(timeout = 30)]
+async fn test_stratum_client_reconnect() {
+    let addr = start_mock_server();
+    let config = MinerClientConfig {
+        server: Some(addr),
+        min_reconnect_delay: Some(10),
+        max_reconnect_delay: Some(100),
+        ..Default::default()
+    };
+    let registry = RegistryService::launch();
+    registry.put_shared(config).await.unwrap();
+    let stratum_cli_srv = registry
+        .register_by_factory::<StratumClientService, StratumClientServiceServiceFactory>()
+        .await
+        .unwrap();
+    let login = LoginRequest {
+        login: "test".into(),
+        pass: "test".into(),
+        agent: "stc-miner".into(),
+        algo: None,
+    };
+    let mut jobs = stratum_cli_srv.send(login).await.unwrap().await.unwrap();
+
+    let job = jobs.next().await.unwrap();
+    assert_eq!(job.job_id, hex::encode([0u8; 8]));
+    // the client login again after reconnected, and receives the job of the new connection.
+    let job = jobs.next().await.unwrap();
+    assert_eq!(job.job_id, hex::encode([1u8; 8]));
+
+    registry.shutdown_system().await.unwrap();
+}
+
+#[stest::test(timeout = 60)]
+async fn test_solo_miner() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let (submit_tx, submit_rx) = oneshot::channel::<MethodCall>();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let call: MethodCall = serde_json::from_str(&line).unwrap();
+        assert_eq!(call.method, "login");
+        // the target of difficulty 1, every nonce is a solution.
+        stream
+            .write_all(login_response(call.id, 0, "ffffffffffffffff").as_bytes())
+            .unwrap();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                return;
+            }
+            let call: MethodCall = serde_json::from_str(&line).unwrap();
+            if call.method == "submit" {
+                let _ = submit_tx.send(call);
+                return;
+            }
+        }
+    });
+
+    let config = MinerClientConfig {
+        server: Some(addr),
+        miner_thread: 2,
+        ..Default::default()
+    };
+    solo::run(config, async move {
+        let call = submit_rx.await.unwrap();
+        assert_eq!(call.method, "submit");
+    })
+    .await
+    .unwrap();
+}
@@ -100,6 +100,8 @@ fn main() -> anyhow::Result<()> {
         None,
     )
     .expect("create block chain should success.");
+    chain2.set_difficulty_floor(net.genesis_config().difficulty_floor);
+    chain2.set_block_bytes_limit(net.genesis_config().block_bytes_limit);
 
     //read from first chain
 
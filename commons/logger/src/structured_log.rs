@@ -4,13 +4,20 @@
 use anyhow::Result;
 use arc_swap::ArcSwap;
 use lazy_static::lazy_static;
-use slog::{o, Discard, Drain, Logger};
+use slog::{o, Discard, Drain};
+#[cfg(any(test, feature = "testing"))]
+use slog::{Key, OwnedKVList, Record, Serializer, KV};
+use std::fmt::Display;
+#[cfg(any(test, feature = "testing"))]
+use std::fmt::{Arguments, Write as FmtWrite};
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
+pub use slog::Logger;
+
 const TIMESTAMP_FORMAT: &str = "%+";
 
 // Defined global slog logger
@@ -116,3 +123,80 @@ where
 {
     f(&(*G_GLOBAL_SLOG_LOGGER.load()))
 }
+
+/// A child of the current logger whose records carry the fields of the block, the block is
+/// identified by its number and the head it's built on, which are known from the mint task to
+/// the applied block, so the records of one block are correlatable across the services.
+pub fn block_logger(block_number: u64, parent_hash: impl Display, txn_count: usize) -> Logger {
+    with_logger(|logger| {
+        logger.new(o!(
+            "block_number" => block_number,
+            "parent_hash" => parent_hash.to_string(),
+            "txn_count" => txn_count,
+        ))
+    })
+}
+
+#[cfg(any(test, feature = "testing"))]
+struct RecordFormatter(String);
+
+#[cfg(any(test, feature = "testing"))]
+impl Serializer for RecordFormatter {
+    fn emit_arguments(&mut self, key: Key, val: &Arguments) -> slog::Result {
+        let _ = write!(self.0, " {}={}", key, val);
+        Ok(())
+    }
+}
+
+/// Keep every record as `message key=value ...`, for the tests checking the records.
+#[cfg(any(test, feature = "testing"))]
+struct CaptureDrain {
+    records: Arc<Mutex<Vec<String>>>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Drain for CaptureDrain {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> std::result::Result<(), Self::Err> {
+        let mut formatter = RecordFormatter(record.msg().to_string());
+        let _ = record.kv().serialize(record, &mut formatter);
+        let _ = values.serialize(record, &mut formatter);
+        self.records.lock().unwrap().push(formatter.0);
+        Ok(())
+    }
+}
+
+/// The records captured by `capture_for_test`, the previous logger is restored on drop.
+#[cfg(any(test, feature = "testing"))]
+pub struct CaptureGuard {
+    records: Arc<Mutex<Vec<String>>>,
+    previous: Arc<Logger>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl CaptureGuard {
+    pub fn records(&self) -> Vec<String> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        G_GLOBAL_SLOG_LOGGER.store(self.previous.clone());
+    }
+}
+
+/// Replace the structured logger by a logger capturing all the records until the guard is
+/// dropped, the records are only captured by the loggers created in the meantime.
+#[cfg(any(test, feature = "testing"))]
+pub fn capture_for_test() -> CaptureGuard {
+    let records = Arc::new(Mutex::new(vec![]));
+    let drain = CaptureDrain {
+        records: records.clone(),
+    };
+    let previous = G_GLOBAL_SLOG_LOGGER.swap(Arc::new(Logger::root(drain.fuse(), o!())));
+    CaptureGuard { records, previous }
+}
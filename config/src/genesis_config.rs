@@ -22,7 +22,9 @@ use starcoin_vm_types::gas_schedule::{
     latest_cost_table, CostTable, G_GAS_CONSTANTS_V1, G_GAS_CONSTANTS_V2, G_GAS_CONSTANTS_V3,
     G_LATEST_GAS_SCHEDULE, G_TEST_GAS_CONSTANTS,
 };
-use starcoin_vm_types::genesis_config::{ChainId, ConsensusStrategy, StdlibVersion};
+use starcoin_vm_types::genesis_config::{
+    BlockBytesLimit, ChainId, ConsensusStrategy, DifficultyFloor, StdlibVersion,
+};
 use starcoin_vm_types::on_chain_config::{
     instruction_table_v1, native_table_v1, native_table_v2, ConsensusConfig, DaoConfig,
     TransactionPublishOption, VMConfig, Version,
@@ -618,6 +620,14 @@ pub struct GenesisConfig {
     pub time_service_type: TimeServiceType,
     /// transaction timeout
     pub transaction_timeout: u64,
+    /// The floor of the retargeted block difficulty and the block number it is activated at,
+    /// the builtin networks have no floor.
+    #[serde(default)]
+    pub difficulty_floor: Option<DifficultyFloor>,
+    /// The max bytes of the bcs encoded block and the block number it is activated at, the
+    /// builtin networks use the default limit from genesis.
+    #[serde(default)]
+    pub block_bytes_limit: BlockBytesLimit,
 }
 
 impl GenesisConfig {
@@ -762,6 +772,8 @@ pub static G_TEST_CONFIG: Lazy<GenesisConfig> = Lazy::new(|| {
             min_action_delay: 60 * 60 * 1000, // 1h
         },
         transaction_timeout: ONE_DAY,
+        difficulty_floor: None,
+        block_bytes_limit: BlockBytesLimit::default(),
     }
 });
 
@@ -811,6 +823,8 @@ pub static G_DEV_CONFIG: Lazy<GenesisConfig> = Lazy::new(|| {
             min_action_delay: 60 * 60 * 1000, // 1h
         },
         transaction_timeout: ONE_DAY,
+        difficulty_floor: None,
+        block_bytes_limit: BlockBytesLimit::default(),
     }
 });
 
@@ -867,6 +881,8 @@ pub static G_HALLEY_CONFIG: Lazy<GenesisConfig> = Lazy::new(|| {
             min_action_delay: 60 * 60 * 1000, // 1h
         },
         transaction_timeout: ONE_DAY,
+        difficulty_floor: None,
+        block_bytes_limit: BlockBytesLimit::default(),
     }
 });
 
@@ -927,6 +943,8 @@ pub static G_PROXIMA_CONFIG: Lazy<GenesisConfig> = Lazy::new(|| {
             min_action_delay: 60 * 1000, // 1 minute
         },
         transaction_timeout: ONE_DAY,
+        difficulty_floor: None,
+        block_bytes_limit: BlockBytesLimit::default(),
     }
 });
 
@@ -987,6 +1005,8 @@ pub static G_BARNARD_CONFIG: Lazy<GenesisConfig> = Lazy::new(|| {
             min_action_delay: 60 * 60 * 24 * 1000, // 1d
         },
         transaction_timeout: ONE_DAY,
+        difficulty_floor: None,
+        block_bytes_limit: BlockBytesLimit::default(),
     }
 });
 
@@ -1051,5 +1071,7 @@ pub static G_MAIN_CONFIG: Lazy<GenesisConfig> = Lazy::new(|| {
             min_action_delay: 60 * 60 * 24 * 1000, // 1d
         },
         transaction_timeout: ONE_DAY,
+        difficulty_floor: None,
+        block_bytes_limit: BlockBytesLimit::default(),
     }
 });
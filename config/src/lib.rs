@@ -46,7 +46,6 @@ mod txpool_config;
 use thiserror::Error;
 
 use crate::account_provider_config::AccountProviderConfig;
-use crate::stratum_config::StratumConfig;
 pub use api_config::{Api, ApiSet};
 pub use api_quota::{ApiQuotaConfig, QuotaDuration};
 pub use available_port::{
@@ -67,7 +66,8 @@ pub use rpc_config::{
 };
 pub use starcoin_crypto::ed25519::genesis_key_pair;
 pub use starcoin_time_service::{MockTimeService, RealTimeService, TimeService};
-pub use storage_config::{RocksdbConfig, StorageConfig, DEFAULT_CACHE_SIZE};
+pub use storage_config::{RocksdbConfig, StorageConfig, WalMode, DEFAULT_CACHE_SIZE};
+pub use stratum_config::StratumConfig;
 pub use txpool_config::TxPoolConfig;
 
 pub static G_CRATE_VERSION: &str = clap::crate_version!();
@@ -520,6 +520,13 @@ impl NodeConfig {
         self.account_provider.merge_with_opt(opt, base)?;
         Ok(())
     }
+
+    /// Check the config before the services launch, so the invalid config is reported early.
+    pub fn validate(&self) -> Result<()> {
+        self.miner.validate()?;
+        self.stratum.validate()?;
+        Ok(())
+    }
 }
 
 #[derive(Error, Debug)]
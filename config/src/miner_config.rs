@@ -2,10 +2,16 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{BaseConfig, ConfigModule, StarcoinOpt};
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_MIN_RECONNECT_DELAY: u64 = 1000;
+const DEFAULT_MAX_RECONNECT_DELAY: u64 = 60000;
+const DEFAULT_PACEMAKER_EVENT_CAPACITY: usize = 1024;
+const DEFAULT_MAX_TXNS_PER_SENDER: u64 = 100;
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, Parser)]
 #[serde(deny_unknown_fields)]
@@ -20,6 +26,37 @@ pub struct MinerConfig {
     /// Node local block_gas_limit, use min(config.block_gas_limit, onchain.block_gas_limit)
     pub block_gas_limit: Option<u64>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(long = "miner-max-txns-per-block")]
+    /// The max number of transactions the miner selects from the pool for a block,
+    /// no limit in default. It is applied before execution, together with the block_gas_limit.
+    pub max_txns_per_block: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(long = "miner-max-txns-per-sender")]
+    /// The max number of transactions of one sender in a block, default is 100.
+    /// The slots left by the senders beyond it are offered to the other senders.
+    pub max_txns_per_sender: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(long = "miner-max-block-bytes")]
+    /// Node local max bytes of the block, the `block_bytes_limit` of the genesis config still
+    /// applies if it's lower.
+    /// The txns beyond it are left in the pool for the later blocks.
+    pub max_block_bytes: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(long = "miner-block-template-timeout")]
+    /// The max milliseconds of the transaction execution when creating a block template,
+    /// no limit in default. The block is sealed with the transactions executed in time.
+    pub block_template_timeout: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(long = "miner-min-gas-price")]
+    /// The min gas price of the transactions the miner selects for a block, no floor in default.
+    /// The transactions below it are left in the pool, set it to 0 to accept every gas price.
+    pub min_gas_price: Option<u64>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     #[clap(long = "disable-miner-client")]
     /// Don't start a miner client in node. The main network miner client is disable in default.
@@ -31,6 +68,12 @@ pub struct MinerConfig {
     /// Miner client thread number, not work for dev network, default is 1
     pub miner_thread: Option<u16>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(long = "pacemaker-event-capacity")]
+    /// The max number of pending events of the block generate pacemaker, default is 1024.
+    /// The transaction events beyond it are dropped, and the rest are coalesced.
+    pub pacemaker_event_capacity: Option<usize>,
+
     #[serde(skip)]
     #[clap(skip)]
     base: Option<Arc<BaseConfig>>,
@@ -49,6 +92,23 @@ impl MinerConfig {
         self.disable_mint_empty_block
             .unwrap_or_else(|| self.base().net().is_dev())
     }
+    pub fn pacemaker_event_capacity(&self) -> usize {
+        self.pacemaker_event_capacity
+            .unwrap_or(DEFAULT_PACEMAKER_EVENT_CAPACITY)
+    }
+    pub fn max_txns_per_block(&self) -> Option<u64> {
+        self.max_txns_per_block
+    }
+    pub fn max_txns_per_sender(&self) -> u64 {
+        self.max_txns_per_sender
+            .unwrap_or(DEFAULT_MAX_TXNS_PER_SENDER)
+    }
+    pub fn block_template_timeout(&self) -> Option<Duration> {
+        self.block_template_timeout.map(Duration::from_millis)
+    }
+    pub fn min_gas_price(&self) -> Option<u64> {
+        self.min_gas_price
+    }
     pub fn miner_client_config(&self) -> Option<MinerClientConfig> {
         if self.disable_miner_client() {
             return None;
@@ -58,8 +118,37 @@ impl MinerConfig {
             plugin_path: None,
             miner_thread: self.miner_thread.unwrap_or(1),
             enable_stderr: true,
+            min_reconnect_delay: None,
+            max_reconnect_delay: None,
         })
     }
+
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.pacemaker_event_capacity() > 0,
+            "Invalid miner config: pacemaker_event_capacity must be greater than 0"
+        );
+        ensure!(
+            self.max_txns_per_block != Some(0),
+            "Invalid miner config: max_txns_per_block must be greater than 0"
+        );
+        ensure!(
+            self.max_txns_per_sender != Some(0),
+            "Invalid miner config: max_txns_per_sender must be greater than 0"
+        );
+        ensure!(
+            self.max_block_bytes != Some(0),
+            "Invalid miner config: max_block_bytes must be greater than 0"
+        );
+        ensure!(
+            self.block_template_timeout != Some(0),
+            "Invalid miner config: block_template_timeout must be greater than 0"
+        );
+        if let Some(miner_client_config) = self.miner_client_config() {
+            miner_client_config.validate()?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
@@ -68,12 +157,56 @@ pub struct MinerClientConfig {
     pub plugin_path: Option<String>,
     pub miner_thread: u16,
     pub enable_stderr: bool,
+    /// The first delay in milliseconds before reconnecting to the stratum server, default is 1000.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_reconnect_delay: Option<u64>,
+    /// The max delay in milliseconds between reconnecting attempts, default is 60000.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_reconnect_delay: Option<u64>,
 }
 
 impl MinerClientConfig {
     pub fn miner_thread(&self) -> u16 {
         self.miner_thread
     }
+
+    /// Check the miner thread number and the stratum server, the server should be `host:port`.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.miner_thread > 0,
+            "Invalid miner config: miner_thread must be greater than 0, please set --miner-thread"
+        );
+        if let Some(server) = self.server.as_ref() {
+            let valid = server
+                .rsplit_once(':')
+                .map(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+                .unwrap_or(false);
+            ensure!(
+                valid,
+                "Invalid miner config: stratum server `{}` should be in the form of host:port",
+                server
+            );
+        }
+        Ok(())
+    }
+
+    pub fn min_reconnect_delay(&self) -> Duration {
+        Duration::from_millis(
+            self.min_reconnect_delay
+                .unwrap_or(DEFAULT_MIN_RECONNECT_DELAY),
+        )
+    }
+
+    /// The max reconnect delay is never less than the min reconnect delay.
+    pub fn max_reconnect_delay(&self) -> Duration {
+        std::cmp::max(
+            Duration::from_millis(
+                self.max_reconnect_delay
+                    .unwrap_or(DEFAULT_MAX_RECONNECT_DELAY),
+            ),
+            self.min_reconnect_delay(),
+        )
+    }
 }
 
 impl Default for MinerClientConfig {
@@ -83,6 +216,8 @@ impl Default for MinerClientConfig {
             plugin_path: None,
             miner_thread: 1,
             enable_stderr: false,
+            min_reconnect_delay: None,
+            max_reconnect_delay: None,
         }
     }
 }
@@ -102,6 +237,24 @@ impl ConfigModule for MinerConfig {
         if opt.miner.block_gas_limit.is_some() {
             self.block_gas_limit = opt.miner.block_gas_limit;
         }
+        if opt.miner.max_txns_per_block.is_some() {
+            self.max_txns_per_block = opt.miner.max_txns_per_block;
+        }
+        if opt.miner.max_txns_per_sender.is_some() {
+            self.max_txns_per_sender = opt.miner.max_txns_per_sender;
+        }
+        if opt.miner.max_block_bytes.is_some() {
+            self.max_block_bytes = opt.miner.max_block_bytes;
+        }
+        if opt.miner.block_template_timeout.is_some() {
+            self.block_template_timeout = opt.miner.block_template_timeout;
+        }
+        if opt.miner.min_gas_price.is_some() {
+            self.min_gas_price = opt.miner.min_gas_price;
+        }
+        if opt.miner.pacemaker_event_capacity.is_some() {
+            self.pacemaker_event_capacity = opt.miner.pacemaker_event_capacity;
+        }
 
         Ok(())
     }
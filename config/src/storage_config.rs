@@ -5,10 +5,77 @@ use crate::{BaseConfig, ConfigModule, StarcoinOpt};
 use anyhow::Result;
 use clap::Parser;
 use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Arc;
 
+/// How the writes of the db go through the RocksDB write-ahead log (WAL).
+/// The durability is traded for the write throughput:
+/// - `Async`: the writes are appended to the WAL without fsync, a process crash loses nothing,
+///   but an os crash or power loss may lose the latest writes.
+/// - `Sync`: every write fsyncs the WAL before it returns, nothing acknowledged is lost even on
+///   power loss, at the cost of a disk sync per write.
+/// - `Disabled`: the writes skip the WAL and live in the memtables until they are flushed, any
+///   crash loses the unflushed writes. Only for the ephemeral or dev instances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WalMode {
+    Async,
+    Sync,
+    Disabled,
+}
+
+impl Default for WalMode {
+    fn default() -> Self {
+        Self::Async
+    }
+}
+
+impl Display for WalMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display = match self {
+            Self::Async => "async",
+            Self::Sync => "sync",
+            Self::Disabled => "disabled",
+        };
+        write!(f, "{}", display)
+    }
+}
+
+impl FromStr for WalMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "async" => Ok(Self::Async),
+            "sync" => Ok(Self::Sync),
+            "disabled" => Ok(Self::Disabled),
+            mode => Err(format!("Unknown wal mode: {}", mode)),
+        }
+    }
+}
+
+impl Serialize for WalMode {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+    where
+        S: Serializer,
+    {
+        self.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for WalMode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as Deserializer<'de>>::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = <String>::deserialize(deserializer)?;
+        WalMode::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
 /// Port selected RocksDB options for tuning underlying rocksdb instance of DiemDB.
 /// see https://github.com/facebook/rocksdb/blob/master/include/rocksdb/options.h
 /// for detailed explanations.
@@ -34,6 +101,30 @@ pub struct RocksdbConfig {
     pub wal_bytes_per_sync: u64,
     #[clap(name = "rocksdb-bytes-per-sync", long, help = "rocksdb bytes per sync")]
     pub bytes_per_sync: u64,
+    #[clap(
+        name = "rocksdb-read-retries",
+        long,
+        help = "rocksdb max retries of a read on the transient error, the writes are not retried"
+    )]
+    pub read_retries: u32,
+    #[clap(
+        name = "rocksdb-read-retry-backoff",
+        long,
+        help = "rocksdb backoff in milliseconds before the first read retry, doubled on every retry"
+    )]
+    pub read_retry_backoff: u64,
+    #[clap(
+        name = "rocksdb-wal-mode",
+        long,
+        help = "rocksdb WAL mode of the writes: async, sync or disabled, see `WalMode`"
+    )]
+    pub wal_mode: WalMode,
+    #[clap(
+        name = "rocksdb-self-test",
+        long,
+        help = "rocksdb scan every column family on open, so a corrupted db fails at open"
+    )]
+    pub self_test: bool,
 }
 
 impl RocksdbConfig {
@@ -61,6 +152,10 @@ impl Default for RocksdbConfig {
             bytes_per_sync: 1u64 << 20,
             // For wal sync every size to be 1MB
             wal_bytes_per_sync: 1u64 << 20,
+            read_retries: 3,
+            read_retry_backoff: 10,
+            wal_mode: WalMode::Async,
+            self_test: false,
         }
     }
 }
@@ -102,6 +197,46 @@ pub struct StorageConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[clap(name = "rocksdb-bytes-per-sync", long, help = "rocksdb bytes per sync")]
     pub bytes_per_sync: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "rocksdb-read-retries",
+        long,
+        help = "rocksdb max retries of a read on the transient error, the writes are not retried"
+    )]
+    pub read_retries: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "rocksdb-read-retry-backoff",
+        long,
+        help = "rocksdb backoff in milliseconds before the first read retry, doubled on every retry"
+    )]
+    pub read_retry_backoff: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "cache-snapshot",
+        long,
+        help = "save the cache to a snapshot file on shutdown, and warm the cache by it on start"
+    )]
+    pub cache_snapshot: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "rocksdb-wal-mode",
+        long,
+        help = "rocksdb WAL mode of the writes: async, sync or disabled, see `WalMode`"
+    )]
+    pub wal_mode: Option<WalMode>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "rocksdb-self-test",
+        long,
+        help = "rocksdb scan every column family on open, so a corrupted db fails at open"
+    )]
+    pub self_test: Option<bool>,
 }
 
 impl StorageConfig {
@@ -124,11 +259,25 @@ impl StorageConfig {
             wal_bytes_per_sync: self
                 .wal_bytes_per_sync
                 .unwrap_or(default.wal_bytes_per_sync),
+            read_retries: self.read_retries.unwrap_or(default.read_retries),
+            read_retry_backoff: self
+                .read_retry_backoff
+                .unwrap_or(default.read_retry_backoff),
+            wal_mode: self.wal_mode.unwrap_or(default.wal_mode),
+            self_test: self.self_test.unwrap_or(default.self_test),
         }
     }
     pub fn cache_size(&self) -> usize {
         self.cache_size.unwrap_or(DEFAULT_CACHE_SIZE)
     }
+    /// The path of the cache snapshot file, None if the cache snapshot is disabled.
+    pub fn cache_snapshot_path(&self) -> Option<PathBuf> {
+        if self.cache_snapshot.unwrap_or(false) {
+            Some(self.dir().join("cache.snapshot"))
+        } else {
+            None
+        }
+    }
 }
 
 impl ConfigModule for StorageConfig {
@@ -149,6 +298,21 @@ impl ConfigModule for StorageConfig {
         if opt.storage.wal_bytes_per_sync.is_some() {
             self.wal_bytes_per_sync = opt.storage.wal_bytes_per_sync;
         }
+        if opt.storage.read_retries.is_some() {
+            self.read_retries = opt.storage.read_retries;
+        }
+        if opt.storage.read_retry_backoff.is_some() {
+            self.read_retry_backoff = opt.storage.read_retry_backoff;
+        }
+        if opt.storage.cache_snapshot.is_some() {
+            self.cache_snapshot = opt.storage.cache_snapshot;
+        }
+        if opt.storage.wal_mode.is_some() {
+            self.wal_mode = opt.storage.wal_mode;
+        }
+        if opt.storage.self_test.is_some() {
+            self.self_test = opt.storage.self_test;
+        }
         Ok(())
     }
 }
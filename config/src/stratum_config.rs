@@ -2,13 +2,16 @@ use crate::{
     get_available_port_from, get_random_available_port, BaseConfig, ConfigModule, Parser,
     StarcoinOpt,
 };
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use serde::{Deserialize, Serialize};
 use starcoin_logger::prelude::*;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 
 const DEFAULT_STRATUM_PORT: u16 = 9880;
+const DEFAULT_BIND_RETRY_TIMES: u64 = 3;
+const BIND_RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
 // UNSPECIFIED is 0.0.0.0
 const DEFAULT_STRATUM_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
 
@@ -28,6 +31,23 @@ pub struct StratumConfig {
     /// Stratum address, default is 0.0.0.0
     pub address: Option<IpAddr>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(name = "getwork-port", long)]
+    /// Serve the getwork http interface on this port, it is disabled if not set.
+    /// The getwork interface shares the stratum address and runs alongside the stratum server.
+    pub getwork_port: Option<u16>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(name = "stratum-bind-retry-times", long)]
+    /// Retry times when the stratum port fails to bind, the delay doubles from 100ms, default is 3.
+    pub bind_retry_times: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(name = "stratum-bind-fallback", long)]
+    /// Listen on a random available port if the stratum port still fails to bind after the
+    /// retries, default is false.
+    pub bind_fallback: Option<bool>,
+
     #[clap(skip)]
     #[serde(skip)]
     base: Option<Arc<BaseConfig>>,
@@ -42,7 +62,6 @@ impl StratumConfig {
             return None;
         }
         let base = self.base();
-        let address = self.address.unwrap_or(DEFAULT_STRATUM_ADDRESS).to_string();
         let port = self.port.unwrap_or_else(|| {
             if base.net().is_test() {
                 get_random_available_port()
@@ -52,7 +71,48 @@ impl StratumConfig {
                 DEFAULT_STRATUM_PORT
             }
         });
-        format!("{}:{}", address, port).parse::<SocketAddr>().ok()
+        Some(self.socket_address(port))
+    }
+
+    pub fn bind_retry_times(&self) -> u64 {
+        self.bind_retry_times.unwrap_or(DEFAULT_BIND_RETRY_TIMES)
+    }
+
+    /// The delay before the `retry`th (start from 0) bind retry.
+    pub fn bind_retry_delay(&self, retry: u64) -> Duration {
+        let shift = std::cmp::min(retry, 10) as u32;
+        BIND_RETRY_BASE_DELAY * (1u32 << shift)
+    }
+
+    pub fn bind_fallback(&self) -> bool {
+        self.bind_fallback.unwrap_or(false)
+    }
+
+    /// Port 0 of the stratum address, for the OS to assign an available port when the configured
+    /// port fails to bind.
+    pub fn get_fallback_address(&self) -> SocketAddr {
+        self.socket_address(0)
+    }
+
+    pub fn get_getwork_address(&self) -> Option<SocketAddr> {
+        self.getwork_port.map(|port| self.socket_address(port))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let (false, Some(port), Some(getwork_port)) =
+            (self.disable, self.port, self.getwork_port)
+        {
+            ensure!(
+                port != getwork_port,
+                "Invalid stratum config: the getwork port {} is the same as the stratum port",
+                getwork_port
+            );
+        }
+        Ok(())
+    }
+
+    fn socket_address(&self, port: u16) -> SocketAddr {
+        SocketAddr::new(self.address.unwrap_or(DEFAULT_STRATUM_ADDRESS), port)
     }
 }
 
@@ -68,6 +128,15 @@ impl ConfigModule for StratumConfig {
         if opt.stratum.port.is_some() {
             self.port = opt.stratum.port;
         }
+        if opt.stratum.getwork_port.is_some() {
+            self.getwork_port = opt.stratum.getwork_port;
+        }
+        if opt.stratum.bind_retry_times.is_some() {
+            self.bind_retry_times = opt.stratum.bind_retry_times;
+        }
+        if opt.stratum.bind_fallback.is_some() {
+            self.bind_fallback = opt.stratum.bind_fallback;
+        }
         info!(
             "Stratum listen address: {:?}, port:{:?}",
             self.address, self.port
@@ -8,6 +8,8 @@ use network_api::PeerStrategy;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+pub const DEFAULT_MAX_REORG_DEPTH: u64 = 1000;
+
 #[derive(Clone, Default, Debug, Deserialize, PartialEq, Eq, Serialize, Parser)]
 #[serde(deny_unknown_fields)]
 pub struct SyncConfig {
@@ -28,6 +30,51 @@ pub struct SyncConfig {
         help = "max retry times once sync block failed, default 15."
     )]
     max_retry_times: Option<u64>,
+
+    /// max depth of the main chain blocks rolled back when switching to a branch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "max-reorg-depth",
+        long,
+        help = "max count of main chain blocks rolled back by a reorg, default 1000."
+    )]
+    max_reorg_depth: Option<u64>,
+
+    /// finalize the main chain blocks deeper than the max reorg depth on every new head
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "auto-finalize",
+        long,
+        help = "finalize the blocks deeper than the max reorg depth on every new head, default false."
+    )]
+    auto_finalize: Option<bool>,
+
+    /// flush the storage once the sync applies this count of blocks since the last flush
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "sync-flush-every-blocks",
+        long,
+        help = "flush the storage every this count of blocks applied by the sync, default 1."
+    )]
+    flush_every_blocks: Option<u64>,
+
+    /// flush the storage once the sync applies this bytes of blocks since the last flush
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "sync-flush-every-bytes",
+        long,
+        help = "flush the storage every this bytes of blocks applied by the sync, no limit."
+    )]
+    flush_every_bytes: Option<u64>,
+
+    /// max seconds of a block timestamp ahead of the local time, to tolerate the clock skew
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(
+        name = "max-future-timestamp-skew",
+        long,
+        help = "max seconds of a block timestamp ahead of the local time, default 30."
+    )]
+    max_future_timestamp_skew: Option<u64>,
 }
 
 impl SyncConfig {
@@ -38,6 +85,35 @@ impl SyncConfig {
     pub fn max_retry_times(&self) -> u64 {
         self.max_retry_times.unwrap_or(15)
     }
+
+    /// The main chain blocks deeper than this are treated as final, a branch which requires
+    /// rolling them back is refused.
+    pub fn max_reorg_depth(&self) -> u64 {
+        self.max_reorg_depth.unwrap_or(DEFAULT_MAX_REORG_DEPTH)
+    }
+
+    /// The finalized blocks can not be reset to, so they are only finalized by the explicit
+    /// request unless it's enabled.
+    pub fn auto_finalize(&self) -> bool {
+        self.auto_finalize.unwrap_or(false)
+    }
+
+    /// The larger cadence flushes less often, at the cost of bigger memtables and longer flushes.
+    /// The blocks of a sync batch are always flushed at the end of the batch.
+    pub fn flush_every_blocks(&self) -> u64 {
+        self.flush_every_blocks.unwrap_or(1)
+    }
+
+    pub fn flush_every_bytes(&self) -> Option<u64> {
+        self.flush_every_bytes
+    }
+
+    /// The max milliseconds of a block timestamp ahead of the local time, converted from the
+    /// seconds of `max-future-timestamp-skew`. None means the default of the chain.
+    pub fn max_future_block_time(&self) -> Option<u64> {
+        self.max_future_timestamp_skew
+            .map(|skew_secs| skew_secs.saturating_mul(1000))
+    }
 }
 
 impl ConfigModule for SyncConfig {
@@ -50,6 +126,26 @@ impl ConfigModule for SyncConfig {
             self.max_retry_times = opt.sync.max_retry_times;
         }
 
+        if opt.sync.max_reorg_depth.is_some() {
+            self.max_reorg_depth = opt.sync.max_reorg_depth;
+        }
+
+        if opt.sync.auto_finalize.is_some() {
+            self.auto_finalize = opt.sync.auto_finalize;
+        }
+
+        if opt.sync.flush_every_blocks.is_some() {
+            self.flush_every_blocks = opt.sync.flush_every_blocks;
+        }
+
+        if opt.sync.flush_every_bytes.is_some() {
+            self.flush_every_bytes = opt.sync.flush_every_bytes;
+        }
+
+        if opt.sync.max_future_timestamp_skew.is_some() {
+            self.max_future_timestamp_skew = opt.sync.max_future_timestamp_skew;
+        }
+
         Ok(())
     }
 }
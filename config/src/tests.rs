@@ -187,3 +187,58 @@ fn test_check_method_in_api_sets() {
     assert!(!ApiSet::UnsafeContext.check_rpc_method("unknown"));
     assert!(!ApiSet::UnsafeContext.check_rpc_method(""));
 }
+
+#[test]
+fn test_validate_config() -> Result<()> {
+    let config = NodeConfig::random_for_test();
+    config.validate()?;
+
+    let mut config = NodeConfig::random_for_test();
+    config.miner.miner_thread = Some(0);
+    assert!(config.validate().is_err());
+    // the miner thread is unused when the miner client is disabled.
+    config.miner.disable_miner_client = Some(true);
+    config.validate()?;
+
+    let mut config = NodeConfig::random_for_test();
+    config.stratum.address = Some(std::net::IpAddr::from_str("::1")?);
+    config.stratum.port = Some(9880);
+    config.validate()?;
+    assert_eq!(
+        config.stratum.get_address(),
+        Some(std::net::SocketAddr::from_str("[::1]:9880")?)
+    );
+    config.stratum.getwork_port = Some(9880);
+    assert!(config.validate().is_err());
+    config.stratum.disable = true;
+    config.validate()?;
+    Ok(())
+}
+
+#[test]
+fn test_validate_miner_client_config() {
+    let mut config = MinerClientConfig {
+        server: Some("127.0.0.1:9880".to_string()),
+        ..Default::default()
+    };
+    assert!(config.validate().is_ok());
+    config.server = Some("localhost:9880".to_string());
+    assert!(config.validate().is_ok());
+    for server in [
+        "127.0.0.1",
+        "127.0.0.1:",
+        ":9880",
+        "127.0.0.1:port",
+        "127.0.0.1:65536",
+    ] {
+        config.server = Some(server.to_string());
+        assert!(
+            config.validate().is_err(),
+            "server {} should be invalid",
+            server
+        );
+    }
+    config.server = None;
+    config.miner_thread = 0;
+    assert!(config.validate().is_err());
+}
@@ -56,11 +56,29 @@ pub trait Consensus {
         nonce
     }
 
+    /// Verify the header by consensus, both the difficulty and the seal.
     fn verify(&self, reader: &dyn ChainReader, header: &BlockHeader) -> Result<()> {
+        self.verify_difficulty(reader, header)?;
+        self.verify_seal(header)
+    }
+
+    /// Verify the header difficulty is the one decided by consensus, the seal is verified by
+    /// `verify_seal`.
+    fn verify_difficulty(&self, reader: &dyn ChainReader, header: &BlockHeader) -> Result<()> {
         let difficulty = self.calculate_next_difficulty(reader)?;
         self.verify_header_difficulty(difficulty, header)
     }
 
+    /// Verify the seal of header, the pow hash of nonce and extra must meet the header difficulty.
+    fn verify_seal(&self, header: &BlockHeader) -> Result<()> {
+        self.verify_blob(
+            header.as_pow_header_blob(),
+            header.nonce(),
+            *header.extra(),
+            header.difficulty(),
+        )
+    }
+
     /// Calculate the Pow hash for header
     fn calculate_pow_hash(
         &self,
@@ -96,23 +114,6 @@ pub trait Consensus {
             }
             .into());
         }
-        let nonce = header.nonce();
-        let extra = header.extra();
-        let pow_header_blob = header.as_pow_header_blob();
-        let pow_hash: U256 = self
-            .calculate_pow_hash(&pow_header_blob, nonce, extra)?
-            .into();
-        let target = difficult_to_target(difficulty);
-        if pow_hash > target {
-            return Err(ConsensusVerifyError::VerifyNonceError {
-                target,
-                real: pow_hash,
-                nonce,
-                extra: *extra,
-                diff: difficulty,
-            }
-            .into());
-        }
         Ok(())
     }
 
@@ -3,12 +3,13 @@
 
 #![allow(clippy::integer_arithmetic)]
 
-use crate::consensus::Consensus;
-use crate::difficulty::{get_next_target_helper, BlockDiffInfo};
+use crate::consensus::{Consensus, ConsensusVerifyError};
+use crate::difficulty::{clamp_difficulty, get_next_target_helper, BlockDiffInfo};
 use crate::{difficult_to_target, target_to_difficulty, G_CRYPTONIGHT};
 use starcoin_crypto::hash::PlainCryptoHash;
 use starcoin_time_service::{duration_since_epoch, MockTimeService, TimeService, TimeServiceType};
-use starcoin_types::block::{BlockHeader, BlockHeaderBuilder, RawBlockHeader};
+use starcoin_types::block::{BlockHeader, BlockHeaderBuilder, BlockHeaderExtra, RawBlockHeader};
+use starcoin_types::genesis_config::{ConsensusStrategy, DifficultyFloor};
 use starcoin_types::U256;
 use std::collections::VecDeque;
 
@@ -42,7 +43,46 @@ fn verify_header_test() {
     let header = header.as_builder().with_nonce(nonce).build();
     G_CRYPTONIGHT
         .verify_header_difficulty(header.difficulty(), &header)
-        .unwrap()
+        .unwrap();
+    G_CRYPTONIGHT.verify_seal(&header).unwrap()
+}
+
+#[stest::test]
+fn verify_seal_test() {
+    let strategy = ConsensusStrategy::Keccak;
+    let header = BlockHeaderBuilder::random()
+        .with_difficulty(1000.into())
+        .with_extra(BlockHeaderExtra::new([0u8; 4]))
+        .build();
+    let time_service = TimeServiceType::RealTimeService.new_time_service();
+    let nonce = strategy.solve_consensus_nonce(
+        &header.as_pow_header_blob(),
+        header.difficulty(),
+        time_service.as_ref(),
+    );
+    let header = header.as_builder().with_nonce(nonce).build();
+    strategy
+        .verify_header_difficulty(header.difficulty(), &header)
+        .unwrap();
+    strategy.verify_seal(&header).unwrap();
+
+    // tamper the nonce until the seal is broken, the structural checks are not affected.
+    let mut tampered = nonce;
+    let header = loop {
+        tampered = tampered.wrapping_add(1);
+        let header = header.as_builder().with_nonce(tampered).build();
+        if strategy.verify_seal(&header).is_err() {
+            break header;
+        }
+    };
+    strategy
+        .verify_header_difficulty(header.difficulty(), &header)
+        .unwrap();
+    let err = strategy.verify_seal(&header).unwrap_err();
+    assert!(matches!(
+        err.downcast::<ConsensusVerifyError>().unwrap(),
+        ConsensusVerifyError::VerifyNonceError { .. }
+    ));
 }
 
 #[stest::test]
@@ -77,6 +117,42 @@ fn simulate_blocks(time_plan: u64, init_difficulty: U256) -> u64 {
     blocks[0].timestamp - blocks[1].timestamp
 }
 
+#[stest::test]
+fn test_min_difficulty_floor() {
+    let floor = DifficultyFloor::new(10, 5);
+    let min_difficulty = U256::from(floor.min_difficulty);
+    let time_plan = 10_000;
+    let mut diff: U256 = 1000.into();
+    let mut blocks = VecDeque::new();
+    let mut now = 0u64;
+    let mut unclamped_below_floor = false;
+    for number in 1..100 {
+        // every block takes a thousand times of the plan, as a stalled chain restarts.
+        now += time_plan * 1000;
+        blocks.push_front(BlockDiffInfo::new(now, difficult_to_target(diff)));
+        blocks.truncate(24);
+        let next_target =
+            get_next_target_helper(blocks.iter().cloned().collect(), time_plan).unwrap();
+        let next_diff = target_to_difficulty(next_target);
+        diff = clamp_difficulty(Some(floor), number, next_diff);
+        if number < floor.activation_number {
+            // the blocks before the activation keep the retargeted difficulty.
+            assert_eq!(diff, next_diff);
+        } else {
+            unclamped_below_floor |= next_diff < min_difficulty;
+            assert!(diff >= min_difficulty);
+        }
+    }
+    assert!(unclamped_below_floor);
+    assert_eq!(diff, min_difficulty);
+    // the difficulty above the floor is kept.
+    assert_eq!(
+        clamp_difficulty(Some(floor), 100, min_difficulty + 1),
+        min_difficulty + 1
+    );
+    assert_eq!(clamp_difficulty(None, 100, U256::one()), U256::one());
+}
+
 #[stest::test]
 fn test_next_target_zero_one() {
     assert!(
@@ -0,0 +1,60 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::consensus::Consensus;
+use crate::dummy::DummyConsensus;
+use anyhow::Result;
+use starcoin_chain_api::ChainReader;
+use starcoin_crypto::HashValue;
+use starcoin_time_service::TimeService;
+use starcoin_types::block::{BlockHeader, BlockHeaderExtra};
+use starcoin_types::U256;
+
+/// The instant seal consensus for the local development and CI, it follows the rules of
+/// `ConsensusStrategy::Dummy`, so the sealed blocks are accepted by the chains of the dummy
+/// strategy, but the seal returns immediately instead of sleeping the simulated block time.
+#[derive(Default)]
+pub struct DevConsensus {
+    dummy: DummyConsensus,
+}
+
+impl DevConsensus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Consensus for DevConsensus {
+    fn calculate_next_difficulty(&self, chain: &dyn ChainReader) -> Result<U256> {
+        self.dummy.calculate_next_difficulty(chain)
+    }
+
+    fn solve_consensus_nonce(
+        &self,
+        _mining_hash: &[u8],
+        _difficulty: U256,
+        time_service: &dyn TimeService,
+    ) -> u32 {
+        // every nonce is a solution, only step the time by one millisecond, so the template of
+        // the next block gets a timestamp greater than this one.
+        time_service.sleep(1);
+        0
+    }
+
+    fn verify_difficulty(&self, _reader: &dyn ChainReader, _header: &BlockHeader) -> Result<()> {
+        Ok(())
+    }
+
+    fn verify_seal(&self, _header: &BlockHeader) -> Result<()> {
+        Ok(())
+    }
+
+    fn calculate_pow_hash(
+        &self,
+        _mining_hash: &[u8],
+        _nonce: u32,
+        _extra: &BlockHeaderExtra,
+    ) -> Result<HashValue> {
+        Ok(HashValue::zero())
+    }
+}
@@ -4,9 +4,10 @@ use crate::difficult_to_target;
 use anyhow::{bail, format_err, Result};
 use starcoin_chain_api::ChainReader;
 use starcoin_logger::prelude::*;
-use starcoin_types::block::BlockHeader;
+use starcoin_types::block::{BlockHeader, BlockNumber};
 use starcoin_types::{U256, U512};
-use std::cmp::Ordering;
+use starcoin_vm_types::genesis_config::DifficultyFloor;
+use std::cmp::{max, Ordering};
 use std::convert::TryFrom;
 
 /// Get the target of next pow work
@@ -54,6 +55,18 @@ pub fn get_next_work_required(chain: &dyn ChainReader) -> Result<U256> {
     Ok(target)
 }
 
+/// Clamp the `difficulty` of the block `number` to the `floor`, if it is activated at the block.
+pub fn clamp_difficulty(
+    floor: Option<DifficultyFloor>,
+    number: BlockNumber,
+    difficulty: U256,
+) -> U256 {
+    match floor.and_then(|floor| floor.min_difficulty_at(number)) {
+        Some(min_difficulty) => max(difficulty, U256::from(min_difficulty)),
+        None => difficulty,
+    }
+}
+
 pub fn get_next_target_helper(blocks: Vec<BlockDiffInfo>, time_plan: u64) -> Result<U256> {
     if blocks.is_empty() {
         bail!("block diff info is empty")
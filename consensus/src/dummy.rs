@@ -47,7 +47,7 @@ impl Consensus for DummyConsensus {
         time
     }
 
-    fn verify(&self, _reader: &dyn ChainReader, _header: &BlockHeader) -> Result<()> {
+    fn verify_difficulty(&self, _reader: &dyn ChainReader, _header: &BlockHeader) -> Result<()> {
         Ok(())
     }
 
@@ -4,6 +4,7 @@
 #![deny(clippy::integer_arithmetic)]
 use crate::argon::ArgonConsensus;
 use crate::cn::CryptoNightConsensus;
+use crate::dev::DevConsensus;
 use crate::dummy::DummyConsensus;
 use crate::keccak::KeccakConsensus;
 use anyhow::Result;
@@ -23,6 +24,7 @@ pub mod cn;
 mod consensus;
 #[cfg(test)]
 mod consensus_test;
+pub mod dev;
 pub mod difficulty;
 pub mod dummy;
 pub mod keccak;
@@ -53,15 +55,24 @@ static G_DUMMY: Lazy<DummyConsensus> = Lazy::new(DummyConsensus::new);
 static G_ARGON: Lazy<ArgonConsensus> = Lazy::new(ArgonConsensus::new);
 static G_KECCAK: Lazy<KeccakConsensus> = Lazy::new(KeccakConsensus::new);
 pub static G_CRYPTONIGHT: Lazy<CryptoNightConsensus> = Lazy::new(CryptoNightConsensus::new);
+pub static G_DEV: Lazy<DevConsensus> = Lazy::new(DevConsensus::new);
 
 impl Consensus for ConsensusStrategy {
+    /// Both the block template and the header verification get the difficulty here,
+    /// so the difficulty floor of the chain is enforced on both sides.
     fn calculate_next_difficulty(&self, reader: &dyn ChainReader) -> Result<U256> {
-        match self {
+        let difficulty = match self {
             ConsensusStrategy::Dummy => G_DUMMY.calculate_next_difficulty(reader),
             ConsensusStrategy::Argon => G_ARGON.calculate_next_difficulty(reader),
             ConsensusStrategy::Keccak => G_KECCAK.calculate_next_difficulty(reader),
             ConsensusStrategy::CryptoNight => G_CRYPTONIGHT.calculate_next_difficulty(reader),
-        }
+        }?;
+        let next_number = reader.current_header().number().saturating_add(1);
+        Ok(difficulty::clamp_difficulty(
+            reader.difficulty_floor(),
+            next_number,
+            difficulty,
+        ))
     }
 
     fn solve_consensus_nonce(
@@ -86,12 +97,26 @@ impl Consensus for ConsensusStrategy {
         }
     }
 
-    fn verify(&self, reader: &dyn ChainReader, header: &BlockHeader) -> Result<()> {
+    fn verify_difficulty(&self, reader: &dyn ChainReader, header: &BlockHeader) -> Result<()> {
+        match self {
+            ConsensusStrategy::Dummy => G_DUMMY.verify_difficulty(reader, header),
+            // verify against the clamped difficulty of the strategy dispatch, rather than the
+            // raw difficulty of the pow consensus.
+            ConsensusStrategy::Argon
+            | ConsensusStrategy::Keccak
+            | ConsensusStrategy::CryptoNight => {
+                let difficulty = self.calculate_next_difficulty(reader)?;
+                self.verify_header_difficulty(difficulty, header)
+            }
+        }
+    }
+
+    fn verify_seal(&self, header: &BlockHeader) -> Result<()> {
         match self {
-            ConsensusStrategy::Dummy => G_DUMMY.verify(reader, header),
-            ConsensusStrategy::Argon => G_ARGON.verify(reader, header),
-            ConsensusStrategy::Keccak => G_KECCAK.verify(reader, header),
-            ConsensusStrategy::CryptoNight => G_CRYPTONIGHT.verify(reader, header),
+            ConsensusStrategy::Dummy => G_DUMMY.verify_seal(header),
+            ConsensusStrategy::Argon => G_ARGON.verify_seal(header),
+            ConsensusStrategy::Keccak => G_KECCAK.verify_seal(header),
+            ConsensusStrategy::CryptoNight => G_CRYPTONIGHT.verify_seal(header),
         }
     }
 
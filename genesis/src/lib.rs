@@ -386,6 +386,26 @@ mod tests {
         do_test_genesis(&net, temp_dir.path())
     }
 
+    #[stest::test]
+    pub fn test_genesis_build_deterministic() -> Result<()> {
+        let net = ChainNetwork::new_test();
+        let genesis1 = Genesis::build(&net)?;
+        let genesis2 = Genesis::build(&net)?;
+        assert_eq!(genesis1.block().id(), genesis2.block().id());
+
+        let storage = Arc::new(Storage::new(StorageInstance::new_cache_instance())?);
+        let chain_info = genesis1.execute_genesis_block(&net, storage.clone())?;
+        let state_root = genesis1.block().header().state_root();
+        assert_eq!(chain_info.status().head().state_root(), state_root);
+        let state_db = ChainStateDB::new(storage.into_super_arc(), Some(state_root));
+        let balance = AccountStateReader::new(&state_db).get_balance(&association_address())?;
+        assert!(
+            balance.unwrap_or_default() > 0,
+            "association should be funded."
+        );
+        Ok(())
+    }
+
     pub fn do_test_genesis(net: &ChainNetwork, data_dir: &Path) -> Result<()> {
         let storage1 = Arc::new(Storage::new(StorageInstance::new_cache_instance())?);
         let (chain_info1, genesis1) =
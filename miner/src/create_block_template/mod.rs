@@ -13,18 +13,22 @@ use starcoin_account_service::AccountService;
 use starcoin_chain::BlockChain;
 use starcoin_chain::{ChainReader, ChainWriter};
 use starcoin_config::ChainNetwork;
-use starcoin_config::NodeConfig;
+use starcoin_config::{MinerConfig, NodeConfig};
 use starcoin_open_block::OpenedBlock;
 use starcoin_service_registry::{
     ActorService, EventHandler, ServiceContext, ServiceFactory, ServiceHandler, ServiceRequest,
 };
+use starcoin_state_api::StateReaderExt;
 use starcoin_storage::{BlockStore, Storage, Store};
 use starcoin_txpool::TxPoolService;
 use starcoin_txpool_api::TxPoolSyncService;
 use starcoin_vm_types::transaction::SignedUserTransaction;
 use std::cmp::min;
+use std::collections::hash_map::Entry;
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, sync::Arc};
 use types::{
+    account_address::AccountAddress,
     block::{BlockHeader, BlockTemplate, ExecutedBlock},
     system_events::{NewBranch, NewHeadBlock},
 };
@@ -84,7 +88,7 @@ impl ServiceFactory<Self> for BlockBuilderService {
             storage,
             startup_info.main,
             txpool,
-            config.miner.block_gas_limit,
+            BlockBuilderConfig::new(&config.miner),
             miner_account,
             metrics,
             vm_metrics,
@@ -181,13 +185,37 @@ impl TemplateTxProvider for TxPoolService {
     }
 }
 
+/// The local limits of the block template, a `None` limit is not applied.
+#[derive(Clone, Debug, Default)]
+pub struct BlockBuilderConfig {
+    pub block_gas_limit: Option<u64>,
+    pub max_txns_per_block: Option<u64>,
+    pub max_txns_per_sender: Option<u64>,
+    pub max_block_bytes: Option<u64>,
+    pub template_timeout: Option<Duration>,
+    pub min_gas_price: Option<u64>,
+}
+
+impl BlockBuilderConfig {
+    pub fn new(config: &MinerConfig) -> Self {
+        Self {
+            block_gas_limit: config.block_gas_limit,
+            max_txns_per_block: config.max_txns_per_block(),
+            max_txns_per_sender: Some(config.max_txns_per_sender()),
+            max_block_bytes: config.max_block_bytes,
+            template_timeout: config.block_template_timeout(),
+            min_gas_price: config.min_gas_price(),
+        }
+    }
+}
+
 pub struct Inner<P> {
     storage: Arc<dyn Store>,
     chain: BlockChain,
     tx_provider: P,
     parent_uncle: HashMap<HashValue, Vec<HashValue>>,
     uncles: HashMap<HashValue, BlockHeader>,
-    local_block_gas_limit: Option<u64>,
+    config: BlockBuilderConfig,
     miner_account: AccountInfo,
     metrics: Option<BlockBuilderMetrics>,
     vm_metrics: Option<VMMetrics>,
@@ -202,17 +230,19 @@ where
         storage: Arc<dyn Store>,
         block_id: HashValue,
         tx_provider: P,
-        local_block_gas_limit: Option<u64>,
+        config: BlockBuilderConfig,
         miner_account: AccountInfo,
         metrics: Option<BlockBuilderMetrics>,
         vm_metrics: Option<VMMetrics>,
     ) -> Result<Self> {
-        let chain = BlockChain::new(
+        let mut chain = BlockChain::new(
             net.time_service(),
             block_id,
             storage.clone(),
             vm_metrics.clone(),
         )?;
+        chain.set_difficulty_floor(net.genesis_config().difficulty_floor);
+        chain.set_block_bytes_limit(net.genesis_config().block_bytes_limit);
 
         Ok(Inner {
             storage,
@@ -220,7 +250,7 @@ where
             tx_provider,
             parent_uncle: HashMap::new(),
             uncles: HashMap::new(),
-            local_block_gas_limit,
+            config,
             miner_account,
             metrics,
             vm_metrics,
@@ -246,12 +276,15 @@ where
         if self.chain.can_connect(&block) {
             self.chain.connect(block)?;
         } else {
-            self.chain = BlockChain::new(
+            let mut chain = BlockChain::new(
                 self.chain.time_service(),
                 block.header().id(),
                 self.storage.clone(),
                 self.vm_metrics.clone(),
             )?;
+            chain.set_difficulty_floor(self.chain.difficulty_floor());
+            chain.set_block_bytes_limit(self.chain.block_bytes_limit());
+            self.chain = chain;
             //current block possible bean uncle.
             self.uncles.insert(current_id, current_header);
 
@@ -297,18 +330,172 @@ where
         }
     }
 
-    pub fn create_block_template(&self) -> Result<BlockTemplateResponse> {
+    /// Skip the txns whose gas price is below the `min_gas_price`, they are left in the pool.
+    fn filter_by_gas_price(&self, txns: Vec<SignedUserTransaction>) -> Vec<SignedUserTransaction> {
+        let min_gas_price = match self.config.min_gas_price {
+            Some(min_gas_price) => min_gas_price,
+            None => return txns,
+        };
+        txns.into_iter()
+            .filter(|txn| txn.gas_unit_price() >= min_gas_price)
+            .collect()
+    }
+
+    /// Skip the txns expired at the block timestamp, the txn prologue rejects a txn once the
+    /// block timestamp in seconds reaches its expiration, same as the txpool.
+    fn filter_by_expiration(
+        &self,
+        txns: Vec<SignedUserTransaction>,
+        block_timestamp_millis: u64,
+    ) -> Vec<SignedUserTransaction> {
+        let block_timestamp_secs = block_timestamp_millis / 1000;
+        txns.into_iter()
+            .filter(|txn| txn.expiration_timestamp_secs() > block_timestamp_secs)
+            .collect()
+    }
+
+    /// Keep only the highest gas price txn of the same sender and sequence number, the pool may
+    /// offer both the replaced txn and its fee bump, but only one of them can be executed.
+    /// The kept txn takes the position of the first one, so the order of the senders is unchanged.
+    fn dedup_by_sequence_number(
+        &self,
+        txns: Vec<SignedUserTransaction>,
+    ) -> Vec<SignedUserTransaction> {
+        let mut positions: HashMap<(AccountAddress, u64), usize> = HashMap::new();
+        let mut deduped: Vec<SignedUserTransaction> = Vec::with_capacity(txns.len());
+        for txn in txns {
+            match positions.entry((txn.sender(), txn.sequence_number())) {
+                Entry::Occupied(entry) => {
+                    let kept = &mut deduped[*entry.get()];
+                    if txn.gas_unit_price() > kept.gas_unit_price() {
+                        *kept = txn;
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(deduped.len());
+                    deduped.push(txn);
+                }
+            }
+        }
+        deduped
+    }
+
+    /// Keep the contiguous txns from the on-chain sequence number of every sender, the txns after
+    /// a nonce gap are doomed to fail, so they are left in the pool for the later blocks.
+    /// The txns of a sender are expected in ascending sequence number, as the pool returns them.
+    fn filter_by_sequence_number(
+        &self,
+        txns: Vec<SignedUserTransaction>,
+    ) -> Vec<SignedUserTransaction> {
+        let state = self.chain.chain_state_reader();
+        // the account not on chain yet starts from 0, let the vm decide.
+        let on_chain_sequence_number = |sender: AccountAddress| {
+            state
+                .get_account_resource(sender)
+                .ok()
+                .flatten()
+                .map(|resource| resource.sequence_number())
+                .unwrap_or(0)
+        };
+        let mut next_sequence_numbers = HashMap::new();
+        txns.into_iter()
+            .filter(|txn| {
+                let sender = txn.sender();
+                let next_sequence_number = next_sequence_numbers
+                    .entry(sender)
+                    .or_insert_with(|| on_chain_sequence_number(sender));
+                if txn.sequence_number() == *next_sequence_number {
+                    *next_sequence_number += 1;
+                    true
+                } else {
+                    false
+                }
+            })
+            .collect()
+    }
+
+    /// Keep at most `max_txns_per_sender` txns of every sender, so a sender can not take all the
+    /// slots of a block. The txns of a sender are contiguous after `filter_by_sequence_number`,
+    /// so the kept ones are still contiguous.
+    fn filter_by_sender_limit(
+        &self,
+        txns: Vec<SignedUserTransaction>,
+    ) -> Vec<SignedUserTransaction> {
+        let max_txns_per_sender = match self.config.max_txns_per_sender {
+            Some(max_txns_per_sender) => max_txns_per_sender,
+            None => return txns,
+        };
+        let mut sender_txns: HashMap<AccountAddress, u64> = HashMap::new();
+        txns.into_iter()
+            .filter(|txn| {
+                let count = sender_txns.entry(txn.sender()).or_insert(0);
+                *count += 1;
+                *count <= max_txns_per_sender
+            })
+            .collect()
+    }
+
+    fn block_gas_limit(&self) -> u64 {
         let on_chain_block_gas_limit = self.chain.epoch().block_gas_limit();
-        let block_gas_limit = self
-            .local_block_gas_limit
+        self.config
+            .block_gas_limit
             .map(|block_gas_limit| min(block_gas_limit, on_chain_block_gas_limit))
-            .unwrap_or(on_chain_block_gas_limit);
+            .unwrap_or(on_chain_block_gas_limit)
+    }
 
+    /// Return the max count of the txns selected for the block, and the count fetched from the
+    /// provider for the selection.
+    fn max_txns(&self, block_gas_limit: u64) -> (u64, u64) {
         //TODO use a GasConstant value to replace 200.
         // block_gas_limit / min_gas_per_txn
-        let max_txns = (block_gas_limit / 200) * 2;
+        let max_gas_txns = (block_gas_limit / 200) * 2;
+        // the local limit is applied on the selection, so the txns beyond it are never executed.
+        let max_txns = self
+            .config
+            .max_txns_per_block
+            .map(|max_txns_per_block| min(max_txns_per_block, max_gas_txns))
+            .unwrap_or(max_gas_txns);
+        // the slots left by the capped senders are offered to the others, so fetch more txns.
+        let fetch_txns = if self.config.max_txns_per_sender.is_some() {
+            max_gas_txns
+        } else {
+            max_txns
+        };
+        (max_txns, fetch_txns)
+    }
+
+    pub fn create_block_template(&self) -> Result<BlockTemplateResponse> {
+        let (_, fetch_txns) = self.max_txns(self.block_gas_limit());
+        let txns = self.tx_provider.get_txns(fetch_txns);
+        let (response, discarded_txns) = self.build_block_template(txns)?;
+        for invalid_txn in discarded_txns {
+            self.tx_provider.remove_invalid_txn(invalid_txn.id());
+        }
+        Ok(response)
+    }
+
+    /// Create the block template by `txns` instead of the txns of the provider, they go through
+    /// the same selection and execution. The provider is not touched, even the discarded txns are
+    /// not removed from it.
+    pub fn create_block_template_with(
+        &self,
+        txns: Vec<SignedUserTransaction>,
+    ) -> Result<BlockTemplateResponse> {
+        Ok(self.build_block_template(txns)?.0)
+    }
 
-        let txns = self.tx_provider.get_txns(max_txns);
+    /// Return the block template, and the txns discarded in the execution.
+    fn build_block_template(
+        &self,
+        txns: Vec<SignedUserTransaction>,
+    ) -> Result<(BlockTemplateResponse, Vec<SignedUserTransaction>)> {
+        // the timeout counts from the start of the assembly, including the txns selection.
+        let deadline = self
+            .config
+            .template_timeout
+            .map(|template_timeout| Instant::now() + template_timeout);
+        let block_gas_limit = self.block_gas_limit();
+        let (max_txns, _) = self.max_txns(block_gas_limit);
 
         let author = *self.miner_account.address();
         let previous_header = self.chain.current_header();
@@ -321,6 +508,13 @@ where
             );
             now_millis = previous_header.timestamp() + 1;
         }
+
+        // the expired and the underpriced txns are skipped first, so such a fee bump does not
+        // replace a valid txn, and the later txns of the sender are not executed after the gap.
+        let txns = self.filter_by_expiration(self.filter_by_gas_price(txns), now_millis);
+        let txns = self.dedup_by_sequence_number(txns);
+        let mut txns = self.filter_by_sender_limit(self.filter_by_sequence_number(txns));
+        txns.truncate(max_txns as usize);
         info!(
             "[CreateBlockTemplate] previous_header: {:?}, block_gas_limit: {}, max_txns: {}, txn len: {}, uncles len: {}, timestamp: {}",
             previous_header,
@@ -346,15 +540,28 @@ where
             strategy,
             self.vm_metrics.clone(),
         )?;
+        // the limit of the chain, the same as its verifier, and the node local one.
+        if let Some(max_block_bytes) = self
+            .chain
+            .block_bytes_limit()
+            .max_block_bytes_at(previous_header.number() + 1)
+        {
+            opened_block = opened_block.with_block_bytes_limit(max_block_bytes);
+        }
+        if let Some(max_block_bytes) = self.config.max_block_bytes {
+            opened_block = opened_block.with_block_bytes_limit(max_block_bytes);
+        }
+        if let Some(deadline) = deadline {
+            opened_block = opened_block.with_execution_deadline(deadline);
+        }
         let excluded_txns = opened_block.push_txns(txns)?;
         let template = opened_block.finalize()?;
-        for invalid_txn in excluded_txns.discarded_txns {
-            self.tx_provider.remove_invalid_txn(invalid_txn.id());
-        }
-
-        Ok(BlockTemplateResponse {
-            parent: previous_header,
-            template,
-        })
+        Ok((
+            BlockTemplateResponse {
+                parent: previous_header,
+                template,
+            },
+            excluded_txns.discarded_txns,
+        ))
     }
 }
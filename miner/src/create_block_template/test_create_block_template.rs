@@ -2,23 +2,50 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::create_block_template::{
-    BlockBuilderService, BlockTemplateRequest, EmptyProvider, Inner,
+    BlockBuilderConfig, BlockBuilderService, BlockTemplateRequest, EmptyProvider, Inner,
+    TemplateTxProvider,
 };
 use anyhow::Result;
 use consensus::Consensus;
+use crypto::HashValue;
 use logger::prelude::*;
 use starcoin_account_api::AccountInfo;
 use starcoin_account_service::AccountService;
 use starcoin_chain::BlockChain;
 use starcoin_chain::{ChainReader, ChainWriter};
-use starcoin_config::ChainNetworkID;
-use starcoin_config::{temp_dir, NodeConfig, StarcoinOpt};
+use starcoin_config::{genesis_key_pair, temp_dir, NodeConfig, StarcoinOpt};
+use starcoin_config::{ChainNetwork, ChainNetworkID};
 use starcoin_genesis::Genesis as StarcoinGenesis;
 use starcoin_service_registry::{RegistryAsyncService, RegistryService};
-use starcoin_storage::BlockStore;
+use starcoin_storage::{BlockStore, Store};
 use starcoin_time_service::MockTimeService;
 use starcoin_txpool::TxPoolService;
+use starcoin_txpool_api::TxPoolSyncService;
+use starcoin_vm_types::account_config::association_address;
+use starcoin_vm_types::transaction::SignedUserTransaction;
 use std::sync::Arc;
+use types::account_address::AccountAddress;
+
+/// Build the `Inner` without the metrics.
+fn new_inner<P: TemplateTxProvider>(
+    net: &ChainNetwork,
+    storage: Arc<dyn Store>,
+    block_id: HashValue,
+    tx_provider: P,
+    config: BlockBuilderConfig,
+    miner_account: AccountInfo,
+) -> Result<Inner<P>> {
+    Inner::new(
+        net,
+        storage,
+        block_id,
+        tx_provider,
+        config,
+        miner_account,
+        None,
+        None,
+    )
+}
 
 #[stest::test]
 fn test_create_block_template() {
@@ -40,15 +67,13 @@ fn test_create_block_template_by_net(net: ChainNetworkID) {
         .expect("init storage by genesis fail.");
     let genesis_id = genesis.block().id();
     let miner_account = AccountInfo::random();
-    let inner = Inner::new(
+    let inner = new_inner(
         node_config.net(),
         storage,
         genesis_id,
         EmptyProvider,
-        None,
+        BlockBuilderConfig::default(),
         miner_account,
-        None,
-        None,
     )
     .unwrap();
 
@@ -81,15 +106,13 @@ fn test_switch_main() {
     for i in 0..times {
         let mut main = BlockChain::new(net.time_service(), head_id, storage.clone(), None).unwrap();
 
-        let mut tmp_inner = Inner::new(
+        let mut tmp_inner = new_inner(
             net,
             storage.clone(),
             head_id,
             txpool.clone(),
-            None,
+            BlockBuilderConfig::default(),
             miner_account.clone(),
-            None,
-            None,
         )
         .unwrap();
 
@@ -120,15 +143,13 @@ fn test_switch_main() {
             BlockChain::new(net.time_service(), head_id, storage.clone(), None).unwrap();
 
         let block_template = if i == 0 {
-            let tmp = Inner::new(
+            let tmp = new_inner(
                 net,
                 storage.clone(),
                 head_id,
                 txpool.clone(),
-                None,
+                BlockBuilderConfig::default(),
                 miner_account.clone(),
-                None,
-                None,
             )
             .unwrap();
 
@@ -198,15 +219,13 @@ fn test_do_uncles() {
     for _i in 0..times {
         let mut main = BlockChain::new(net.time_service(), head_id, storage.clone(), None).unwrap();
 
-        let mut tmp_inner = Inner::new(
+        let mut tmp_inner = new_inner(
             net,
             storage.clone(),
             head_id,
             txpool.clone(),
-            None,
+            BlockBuilderConfig::default(),
             miner_account.clone(),
-            None,
-            None,
         )
         .unwrap();
 
@@ -226,15 +245,13 @@ fn test_do_uncles() {
     for _i in 0..times {
         let mut branch =
             BlockChain::new(net.time_service(), genesis_id, storage.clone(), None).unwrap();
-        let inner = Inner::new(
+        let inner = new_inner(
             net,
             storage.clone(),
             genesis_id,
             txpool.clone(),
-            None,
+            BlockBuilderConfig::default(),
             miner_account.clone(),
-            None,
-            None,
         )
         .unwrap();
 
@@ -297,15 +314,13 @@ fn test_new_head() {
 
     let txpool = TxPoolService::new(node_config.clone(), storage.clone(), chain_header, None);
 
-    let mut main_inner = Inner::new(
+    let mut main_inner = new_inner(
         node_config.net(),
         storage,
         genesis_id,
         txpool,
-        None,
+        BlockBuilderConfig::default(),
         miner_account,
-        None,
-        None,
     )
     .unwrap();
 
@@ -342,15 +357,13 @@ fn test_new_branch() {
     let miner_account = AccountInfo::random();
     // main
 
-    let mut main_inner = Inner::new(
+    let mut main_inner = new_inner(
         node_config.net(),
         storage.clone(),
         genesis_id,
         txpool.clone(),
-        None,
+        BlockBuilderConfig::default(),
         miner_account.clone(),
-        None,
-        None,
     )
     .unwrap();
     for _i in 0..times {
@@ -369,15 +382,13 @@ fn test_new_branch() {
     for i in 0..(times * 2) {
         let mut branch =
             BlockChain::new(net.time_service(), new_head_id, storage.clone(), None).unwrap();
-        let inner = Inner::new(
+        let inner = new_inner(
             net,
             storage.clone(),
             new_head_id,
             txpool.clone(),
-            None,
+            BlockBuilderConfig::default(),
             miner_account.clone(),
-            None,
-            None,
         )
         .unwrap();
         let block_template = inner.create_block_template().unwrap().template;
@@ -433,15 +444,13 @@ fn test_create_block_template_by_adjust_time() -> Result<()> {
     let node_config = Arc::new(NodeConfig::random_for_test());
 
     let (storage, _, genesis) = StarcoinGenesis::init_storage_for_test(node_config.net())?;
-    let mut inner = Inner::new(
+    let mut inner = new_inner(
         node_config.net(),
         storage,
         genesis.block().id(),
         EmptyProvider,
-        None,
+        BlockBuilderConfig::default(),
         AccountInfo::random(),
-        None,
-        None,
     )?;
     let template = inner.create_block_template()?.template;
     let previous_block_time = template.timestamp;
@@ -469,3 +478,385 @@ fn test_create_block_template_by_adjust_time() -> Result<()> {
     inner.chain.apply(block)?;
     Ok(())
 }
+
+#[stest::test]
+fn test_create_block_template_with_max_txns() -> Result<()> {
+    let node_config = Arc::new(NodeConfig::random_for_test());
+    let (storage, _, genesis) = StarcoinGenesis::init_storage_for_test(node_config.net())?;
+    let genesis_id = genesis.block().id();
+    let chain_header = storage
+        .get_block_header_by_hash(genesis_id)?
+        .expect("genesis header should exist.");
+    let txpool = TxPoolService::new(node_config.clone(), storage.clone(), chain_header, None);
+
+    let max_txns_per_block = 3;
+    let net = node_config.net();
+    let expiration_timestamp_secs =
+        net.time_service().now_secs() + starcoin_transaction_builder::DEFAULT_EXPIRATION_TIME;
+    let txns = (0..max_txns_per_block * 2)
+        .map(|seq_num| {
+            starcoin_transaction_builder::peer_to_peer_txn_sent_as_association(
+                AccountAddress::random(),
+                seq_num,
+                10000,
+                expiration_timestamp_secs,
+                net,
+            )
+        })
+        .collect::<Vec<_>>();
+    for result in txpool.add_txns(txns) {
+        result?;
+    }
+
+    let inner = new_inner(
+        net,
+        storage,
+        genesis_id,
+        txpool,
+        BlockBuilderConfig {
+            max_txns_per_block: Some(max_txns_per_block),
+            ..Default::default()
+        },
+        AccountInfo::random(),
+    )?;
+    let template = inner.create_block_template()?.template;
+    // the block metadata txn is not in the body.
+    assert_eq!(template.body.transactions.len() as u64, max_txns_per_block);
+    Ok(())
+}
+
+struct FixedProvider(Vec<SignedUserTransaction>);
+
+impl TemplateTxProvider for FixedProvider {
+    fn get_txns(&self, _max: u64) -> Vec<SignedUserTransaction> {
+        self.0.clone()
+    }
+
+    fn remove_invalid_txn(&self, _txn_hash: HashValue) {}
+}
+
+#[stest::test]
+fn test_create_block_template_with_nonce_gap() -> Result<()> {
+    let node_config = Arc::new(NodeConfig::random_for_test());
+    let (storage, _, genesis) = StarcoinGenesis::init_storage_for_test(node_config.net())?;
+    let net = node_config.net();
+    let expiration_timestamp_secs =
+        net.time_service().now_secs() + starcoin_transaction_builder::DEFAULT_EXPIRATION_TIME;
+    // the txn of sequence number 2 is missing.
+    let txns = [0, 1, 3, 4]
+        .iter()
+        .map(|seq_num| {
+            starcoin_transaction_builder::peer_to_peer_txn_sent_as_association(
+                AccountAddress::random(),
+                *seq_num,
+                10000,
+                expiration_timestamp_secs,
+                net,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let inner = new_inner(
+        net,
+        storage,
+        genesis.block().id(),
+        FixedProvider(txns),
+        BlockBuilderConfig::default(),
+        AccountInfo::random(),
+    )?;
+    let template = inner.create_block_template()?.template;
+    let sequence_numbers = template
+        .body
+        .transactions
+        .iter()
+        .map(|txn| txn.sequence_number())
+        .collect::<Vec<_>>();
+    assert_eq!(sequence_numbers, vec![0, 1]);
+    Ok(())
+}
+
+#[stest::test]
+fn test_create_block_template_with_max_txns_per_sender() -> Result<()> {
+    let node_config = Arc::new(NodeConfig::random_for_test());
+    let (storage, _, genesis) = StarcoinGenesis::init_storage_for_test(node_config.net())?;
+    let net = node_config.net();
+    let expiration_timestamp_secs =
+        net.time_service().now_secs() + starcoin_transaction_builder::DEFAULT_EXPIRATION_TIME;
+    // the association has 100 pending txns, and the other two senders have 3 txns each.
+    let mut txns = (0..100)
+        .map(|seq_num| {
+            starcoin_transaction_builder::peer_to_peer_txn_sent_as_association(
+                AccountAddress::random(),
+                seq_num,
+                10000,
+                expiration_timestamp_secs,
+                net,
+            )
+        })
+        .collect::<Vec<_>>();
+    let (private_key, public_key) = genesis_key_pair();
+    let senders = [AccountAddress::random(), AccountAddress::random()];
+    for sender in &senders {
+        for seq_num in 0..3 {
+            let raw_txn = starcoin_transaction_builder::build_transfer_txn(
+                *sender,
+                AccountAddress::random(),
+                seq_num,
+                10000,
+                1,
+                10000000,
+                expiration_timestamp_secs,
+                net.chain_id(),
+            );
+            txns.push(raw_txn.sign(&private_key, public_key.clone())?.into_inner());
+        }
+    }
+
+    let max_txns_per_sender = 10;
+    let inner = new_inner(
+        net,
+        storage,
+        genesis.block().id(),
+        FixedProvider(txns.clone()),
+        BlockBuilderConfig {
+            max_txns_per_sender: Some(max_txns_per_sender),
+            ..Default::default()
+        },
+        AccountInfo::random(),
+    )?;
+    let selected = inner.filter_by_sender_limit(inner.filter_by_sequence_number(txns));
+    let count_of = |sender: AccountAddress| {
+        selected.iter().filter(|txn| txn.sender() == sender).count() as u64
+    };
+    assert_eq!(count_of(association_address()), max_txns_per_sender);
+    for sender in senders {
+        assert_eq!(count_of(sender), 3);
+    }
+    assert_eq!(selected.len() as u64, max_txns_per_sender + 6);
+
+    // the txns of the other senders are not signed by their keys, so only the association's
+    // txns are executed.
+    let template = inner.create_block_template()?.template;
+    assert_eq!(template.body.transactions.len() as u64, max_txns_per_sender);
+    Ok(())
+}
+
+#[stest::test]
+fn test_create_block_template_with_replaced_txn() -> Result<()> {
+    let node_config = Arc::new(NodeConfig::random_for_test());
+    let (storage, _, genesis) = StarcoinGenesis::init_storage_for_test(node_config.net())?;
+    let net = node_config.net();
+    let expiration_timestamp_secs =
+        net.time_service().now_secs() + starcoin_transaction_builder::DEFAULT_EXPIRATION_TIME;
+    let (private_key, public_key) = genesis_key_pair();
+    // the txn of sequence number 0 is replaced by a txn of higher gas price.
+    let txns = [(0, 1), (0, 2), (1, 1)]
+        .iter()
+        .map(|(seq_num, gas_price)| {
+            let raw_txn = starcoin_transaction_builder::build_transfer_txn(
+                association_address(),
+                AccountAddress::random(),
+                *seq_num,
+                10000,
+                *gas_price,
+                10000000,
+                expiration_timestamp_secs,
+                net.chain_id(),
+            );
+            Ok(raw_txn.sign(&private_key, public_key.clone())?.into_inner())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let inner = new_inner(
+        net,
+        storage,
+        genesis.block().id(),
+        FixedProvider(txns.clone()),
+        BlockBuilderConfig::default(),
+        AccountInfo::random(),
+    )?;
+    let template = inner.create_block_template()?.template;
+    let included = template
+        .body
+        .transactions
+        .iter()
+        .map(|txn| (txn.sequence_number(), txn.gas_unit_price()))
+        .collect::<Vec<_>>();
+    assert_eq!(included, vec![(0, 2), (1, 1)]);
+    assert_eq!(template.body.transactions[0], txns[1]);
+    Ok(())
+}
+
+#[stest::test]
+fn test_create_block_template_with_expired_txn() -> Result<()> {
+    let node_config = Arc::new(NodeConfig::random_for_test());
+    let (storage, _, genesis) = StarcoinGenesis::init_storage_for_test(node_config.net())?;
+    let net = node_config.net();
+    let expiration_timestamp_secs =
+        net.time_service().now_secs() + starcoin_transaction_builder::DEFAULT_EXPIRATION_TIME;
+    let (private_key, public_key) = genesis_key_pair();
+    // the expired txn of sequence number 0 has a higher gas price, but it can not replace the
+    // valid one.
+    let txns = [
+        (0, 2, 1),
+        (0, 1, expiration_timestamp_secs),
+        (1, 1, expiration_timestamp_secs),
+    ]
+    .iter()
+    .map(|(seq_num, gas_price, expiration)| {
+        let raw_txn = starcoin_transaction_builder::build_transfer_txn(
+            association_address(),
+            AccountAddress::random(),
+            *seq_num,
+            10000,
+            *gas_price,
+            10000000,
+            *expiration,
+            net.chain_id(),
+        );
+        Ok(raw_txn.sign(&private_key, public_key.clone())?.into_inner())
+    })
+    .collect::<Result<Vec<_>>>()?;
+
+    let inner = new_inner(
+        net,
+        storage,
+        genesis.block().id(),
+        FixedProvider(txns.clone()),
+        BlockBuilderConfig::default(),
+        AccountInfo::random(),
+    )?;
+    // the txn expires at the block timestamp of its expiration.
+    let block_timestamp_millis = expiration_timestamp_secs * 1000;
+    let selected = inner.filter_by_expiration(txns.clone(), block_timestamp_millis - 1);
+    assert_eq!(selected, txns[1..].to_vec());
+    assert!(inner
+        .filter_by_expiration(txns.clone(), block_timestamp_millis)
+        .is_empty());
+
+    let template = inner.create_block_template()?.template;
+    assert_eq!(template.body.transactions, txns[1..].to_vec());
+    Ok(())
+}
+
+#[stest::test]
+fn test_create_block_template_with_min_gas_price() -> Result<()> {
+    let node_config = Arc::new(NodeConfig::random_for_test());
+    let (storage, _, genesis) = StarcoinGenesis::init_storage_for_test(node_config.net())?;
+    let net = node_config.net();
+    let expiration_timestamp_secs =
+        net.time_service().now_secs() + starcoin_transaction_builder::DEFAULT_EXPIRATION_TIME;
+    let (private_key, public_key) = genesis_key_pair();
+    let min_gas_price = 2;
+    // the txns above and at the floor are selected, the one below it is left.
+    let txns = [(0, 3), (1, min_gas_price), (2, 1)]
+        .iter()
+        .map(|(seq_num, gas_price)| {
+            let raw_txn = starcoin_transaction_builder::build_transfer_txn(
+                association_address(),
+                AccountAddress::random(),
+                *seq_num,
+                10000,
+                *gas_price,
+                10000000,
+                expiration_timestamp_secs,
+                net.chain_id(),
+            );
+            Ok(raw_txn.sign(&private_key, public_key.clone())?.into_inner())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let inner = new_inner(
+        net,
+        storage,
+        genesis.block().id(),
+        FixedProvider(txns.clone()),
+        BlockBuilderConfig {
+            min_gas_price: Some(min_gas_price),
+            ..Default::default()
+        },
+        AccountInfo::random(),
+    )?;
+    assert_eq!(inner.filter_by_gas_price(txns.clone()), txns[..2].to_vec());
+
+    let template = inner.create_block_template()?.template;
+    assert_eq!(template.body.transactions, txns[..2].to_vec());
+    Ok(())
+}
+
+fn association_txns(net: &ChainNetwork, seq_nums: &[u64]) -> Vec<SignedUserTransaction> {
+    let expiration_timestamp_secs =
+        net.time_service().now_secs() + starcoin_transaction_builder::DEFAULT_EXPIRATION_TIME;
+    seq_nums
+        .iter()
+        .map(|seq_num| {
+            starcoin_transaction_builder::peer_to_peer_txn_sent_as_association(
+                AccountAddress::random(),
+                *seq_num,
+                10000,
+                expiration_timestamp_secs,
+                net,
+            )
+        })
+        .collect()
+}
+
+#[stest::test]
+fn test_create_block_template_with_gas_limit() -> Result<()> {
+    let node_config = Arc::new(NodeConfig::random_for_test());
+    let (storage, _, genesis) = StarcoinGenesis::init_storage_for_test(node_config.net())?;
+    let net = node_config.net();
+    let txns = association_txns(net, &[0, 1, 2, 3]);
+    let new_inner = |local_block_gas_limit: Option<u64>| {
+        new_inner(
+            net,
+            storage.clone(),
+            genesis.block().id(),
+            EmptyProvider,
+            BlockBuilderConfig {
+                block_gas_limit: local_block_gas_limit,
+                ..Default::default()
+            },
+            AccountInfo::random(),
+        )
+    };
+
+    // the txns do not come from the provider, which has no txn.
+    let template = new_inner(None)?
+        .create_block_template_with(txns[..1].to_vec())?
+        .template;
+    assert_eq!(template.body.transactions, txns[..1].to_vec());
+    let txn_gas_used = template.gas_used;
+
+    // the txns beyond the gas limit are not included.
+    let template = new_inner(Some(txn_gas_used * 2 + txn_gas_used / 2))?
+        .create_block_template_with(txns.clone())?
+        .template;
+    assert_eq!(template.body.transactions, txns[..2].to_vec());
+    Ok(())
+}
+
+#[stest::test]
+fn test_create_block_template_with_txns_order() -> Result<()> {
+    let node_config = Arc::new(NodeConfig::random_for_test());
+    let (storage, _, genesis) = StarcoinGenesis::init_storage_for_test(node_config.net())?;
+    let net = node_config.net();
+    let inner = new_inner(
+        net,
+        storage,
+        genesis.block().id(),
+        EmptyProvider,
+        BlockBuilderConfig::default(),
+        AccountInfo::random(),
+    )?;
+    let txns = association_txns(net, &[0, 1, 2]);
+    let template = inner.create_block_template_with(txns.clone())?.template;
+    assert_eq!(template.body.transactions, txns);
+
+    // the txns of a sender are expected in ascending sequence number, the ones before their
+    // predecessors are skipped.
+    let reversed = txns.iter().rev().cloned().collect::<Vec<_>>();
+    let template = inner.create_block_template_with(reversed)?.template;
+    assert_eq!(template.body.transactions, txns[..1].to_vec());
+    Ok(())
+}
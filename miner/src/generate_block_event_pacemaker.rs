@@ -3,26 +3,70 @@
 
 use crate::GenerateBlockEvent;
 use anyhow::Result;
+use crypto::HashValue;
 use logger::prelude::*;
 use starcoin_config::NodeConfig;
 use starcoin_service_registry::{ActorService, EventHandler, ServiceContext, ServiceFactory};
 use starcoin_txpool_api::PropagateTransactions;
 use std::sync::Arc;
+use std::time::Duration;
 use types::{
     sync_status::SyncStatus,
     system_events::{NewHeadBlock, SyncStatusChangeEvent},
 };
 
+/// The transaction events in this window are coalesced into one GenerateBlockEvent.
+const TXN_EVENT_WINDOW: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug)]
+struct TxnEventWindowEnd;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PacemakerStrategy {
+    /// Mint on every head change and sync status change, include the empty blocks.
+    Continuous,
+    /// Also mint on the new transactions, and skip the empty blocks,
+    /// see MinerConfig::disable_mint_empty_block.
+    OnDemand,
+}
+
+impl PacemakerStrategy {
+    pub fn from_config(config: &NodeConfig) -> Self {
+        if config.miner.is_disable_mint_empty_block() {
+            PacemakerStrategy::OnDemand
+        } else {
+            PacemakerStrategy::Continuous
+        }
+    }
+}
+
+/// Switch the pacemaker strategy at runtime, broadcast it by the bus,
+/// both the pacemaker and the MinerService follow it.
+#[derive(Clone, Debug)]
+pub struct SwitchPacemaker(pub PacemakerStrategy);
+
 pub struct GenerateBlockEventPacemaker {
     config: Arc<NodeConfig>,
+    strategy: PacemakerStrategy,
     sync_status: Option<SyncStatus>,
+    /// The head of the last NewHeadBlock, a repeated head does not trigger again.
+    head_id: Option<HashValue>,
+    /// A GenerateBlockEvent for transactions has been sent in the current window.
+    txn_event_window: bool,
+    /// Transaction events are received after the GenerateBlockEvent of the current window.
+    txn_event_pending: bool,
 }
 
 impl ServiceFactory<Self> for GenerateBlockEventPacemaker {
     fn create(ctx: &mut ServiceContext<GenerateBlockEventPacemaker>) -> Result<Self> {
+        let config = ctx.get_shared::<Arc<NodeConfig>>()?;
         Ok(Self {
-            config: ctx.get_shared::<Arc<NodeConfig>>()?,
+            strategy: PacemakerStrategy::from_config(&config),
+            config,
             sync_status: None,
+            head_id: None,
+            txn_event_window: false,
+            txn_event_pending: false,
         })
     }
 }
@@ -32,6 +76,18 @@ impl GenerateBlockEventPacemaker {
         ctx.broadcast(GenerateBlockEvent::new_break(force));
     }
 
+    /// The first transaction event of a window triggers immediately, the later ones in the window
+    /// are coalesced and trigger once when the window ends.
+    fn on_txn_event(&mut self, ctx: &mut ServiceContext<Self>) {
+        if self.txn_event_window {
+            self.txn_event_pending = true;
+            return;
+        }
+        self.send_event(false, ctx);
+        self.txn_event_window = true;
+        ctx.run_later(TXN_EVENT_WINDOW, |ctx| ctx.notify(TxnEventWindowEnd));
+    }
+
     pub fn is_synced(&self) -> bool {
         match self.sync_status.as_ref() {
             Some(sync_status) => sync_status.is_synced(),
@@ -42,10 +98,13 @@ impl GenerateBlockEventPacemaker {
 
 impl ActorService for GenerateBlockEventPacemaker {
     fn started(&mut self, ctx: &mut ServiceContext<Self>) -> Result<()> {
+        // the mailbox is bounded, the events beyond the capacity are dropped by the bus.
+        ctx.set_mailbox_capacity(self.config.miner.pacemaker_event_capacity());
         ctx.subscribe::<SyncStatusChangeEvent>();
         ctx.subscribe::<NewHeadBlock>();
+        ctx.subscribe::<SwitchPacemaker>();
         //if mint empty block is disabled, trigger mint event for on demand mint (Dev)
-        if self.config.miner.is_disable_mint_empty_block() {
+        if self.strategy == PacemakerStrategy::OnDemand {
             ctx.subscribe::<PropagateTransactions>();
         }
         Ok(())
@@ -54,20 +113,48 @@ impl ActorService for GenerateBlockEventPacemaker {
     fn stopped(&mut self, ctx: &mut ServiceContext<Self>) -> Result<()> {
         ctx.unsubscribe::<SyncStatusChangeEvent>();
         ctx.unsubscribe::<NewHeadBlock>();
-        if self.config.miner.is_disable_mint_empty_block() {
+        ctx.unsubscribe::<SwitchPacemaker>();
+        if self.strategy == PacemakerStrategy::OnDemand {
             ctx.unsubscribe::<PropagateTransactions>();
         }
         Ok(())
     }
 }
 
+impl EventHandler<Self, SwitchPacemaker> for GenerateBlockEventPacemaker {
+    fn handle_event(&mut self, msg: SwitchPacemaker, ctx: &mut ServiceContext<Self>) {
+        let strategy = msg.0;
+        if self.strategy == strategy {
+            return;
+        }
+        info!(
+            "[pacemaker] Switch strategy from {:?} to {:?}",
+            self.strategy, strategy
+        );
+        match strategy {
+            PacemakerStrategy::OnDemand => ctx.subscribe::<PropagateTransactions>(),
+            PacemakerStrategy::Continuous => {
+                ctx.unsubscribe::<PropagateTransactions>();
+                self.txn_event_pending = false;
+            }
+        }
+        self.strategy = strategy;
+    }
+}
+
 impl EventHandler<Self, NewHeadBlock> for GenerateBlockEventPacemaker {
     fn handle_event(
         &mut self,
-        _msg: NewHeadBlock,
+        msg: NewHeadBlock,
         ctx: &mut ServiceContext<GenerateBlockEventPacemaker>,
     ) {
+        let head_id = msg.0.header().id();
+        if self.head_id == Some(head_id) {
+            return;
+        }
+        self.head_id = Some(head_id);
         if self.is_synced() {
+            // break the current task, the miner should always mint on the latest head.
             self.send_event(true, ctx)
         } else {
             debug!("[pacemaker] Ignore NewHeadBlock event because the node has not been synchronized yet.")
@@ -78,13 +165,25 @@ impl EventHandler<Self, NewHeadBlock> for GenerateBlockEventPacemaker {
 impl EventHandler<Self, PropagateTransactions> for GenerateBlockEventPacemaker {
     fn handle_event(&mut self, _msg: PropagateTransactions, ctx: &mut ServiceContext<Self>) {
         if self.is_synced() {
-            self.send_event(false, ctx)
+            self.on_txn_event(ctx)
         } else {
             debug!("[pacemaker] Ignore PropagateNewTransactions event because the node has not been synchronized yet.")
         }
     }
 }
 
+impl EventHandler<Self, TxnEventWindowEnd> for GenerateBlockEventPacemaker {
+    fn handle_event(&mut self, _msg: TxnEventWindowEnd, ctx: &mut ServiceContext<Self>) {
+        self.txn_event_window = false;
+        if std::mem::take(&mut self.txn_event_pending)
+            && self.strategy == PacemakerStrategy::OnDemand
+            && self.is_synced()
+        {
+            self.on_txn_event(ctx)
+        }
+    }
+}
+
 impl EventHandler<Self, SyncStatusChangeEvent> for GenerateBlockEventPacemaker {
     fn handle_event(&mut self, msg: SyncStatusChangeEvent, ctx: &mut ServiceContext<Self>) {
         let is_synced = msg.0.is_synced();
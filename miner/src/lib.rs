@@ -1,10 +1,12 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::generate_block_event_pacemaker::{PacemakerStrategy, SwitchPacemaker};
 use crate::metrics::MinerMetrics;
+use crate::stats::MinerStatsCollector;
 use crate::task::MintTask;
-use anyhow::Result;
-use consensus::Consensus;
+use anyhow::{bail, Result};
+use consensus::{Consensus, G_DEV};
 use futures::executor::block_on;
 use logger::prelude::*;
 use starcoin_config::NodeConfig;
@@ -14,15 +16,18 @@ use starcoin_service_registry::{
 };
 use std::sync::Arc;
 use std::time::Duration;
-use types::block::BlockTemplate;
+use types::block::{BlockHeader, BlockTemplate};
+use types::genesis_config::ConsensusStrategy;
 
 mod create_block_template;
 pub mod generate_block_event_pacemaker;
 mod metrics;
+mod stats;
 pub mod task;
 
 pub use create_block_template::{BlockBuilderService, BlockTemplateRequest};
 use crypto::HashValue;
+pub use stats::{MinerStats, MinerStatsRequest};
 use std::fmt;
 use thiserror::Error;
 pub use types::block::BlockHeaderExtra;
@@ -45,12 +50,27 @@ impl ServiceRequest for UpdateSubscriberNumRequest {
     type Response = Option<MintBlockEvent>;
 }
 
+/// Mint a block on the current head at once and return its header, the nonce is solved by the
+/// miner itself, so it's only supported by the test and dev networks.
+/// The block of the dummy strategy is sealed by `DevConsensus` instantly.
+#[derive(Debug)]
+pub struct MineOneBlockRequest;
+
+impl ServiceRequest for MineOneBlockRequest {
+    type Response = Result<BlockHeader>;
+}
+
 pub struct MinerService {
     config: Arc<NodeConfig>,
+    pacemaker_strategy: PacemakerStrategy,
     current_task: Option<MintTask>,
+    /// A GenerateBlockEvent which does not break the task is received during the current mint,
+    /// the events are coalesced into one mint after the current one is finished.
+    generate_pending: bool,
     create_block_template_service: ServiceRef<BlockBuilderService>,
     client_subscribers_num: u32,
     metrics: Option<MinerMetrics>,
+    stats: MinerStatsCollector,
 }
 
 impl ServiceRequest for SubmitSealRequest {
@@ -115,11 +135,14 @@ impl ServiceFactory<MinerService> for MinerService {
             .registry()
             .and_then(|registry| MinerMetrics::register(registry).ok());
         Ok(MinerService {
+            pacemaker_strategy: PacemakerStrategy::from_config(&config),
             config,
             current_task: None,
+            generate_pending: false,
             create_block_template_service,
             client_subscribers_num: 0,
             metrics,
+            stats: MinerStatsCollector::default(),
         })
     }
 }
@@ -127,21 +150,30 @@ impl ServiceFactory<MinerService> for MinerService {
 impl ActorService for MinerService {
     fn started(&mut self, ctx: &mut ServiceContext<Self>) -> Result<()> {
         ctx.subscribe::<GenerateBlockEvent>();
+        ctx.subscribe::<SwitchPacemaker>();
         Ok(())
     }
 
     fn stopped(&mut self, ctx: &mut ServiceContext<Self>) -> Result<()> {
         ctx.unsubscribe::<GenerateBlockEvent>();
+        ctx.unsubscribe::<SwitchPacemaker>();
         Ok(())
     }
 }
 
+impl EventHandler<Self, SwitchPacemaker> for MinerService {
+    fn handle_event(&mut self, msg: SwitchPacemaker, _ctx: &mut ServiceContext<MinerService>) {
+        self.pacemaker_strategy = msg.0;
+    }
+}
+
 impl ServiceHandler<Self, SubmitSealRequest> for MinerService {
     fn handle(
         &mut self,
         req: SubmitSealRequest,
         ctx: &mut ServiceContext<MinerService>,
     ) -> Result<HashValue> {
+        self.stats.record_submit();
         self.finish_task(req.nonce, req.extra, req.minting_blob.clone(), ctx)
             .map_err(|e| {
                 warn!(target: "miner", "process seal: {} failed: {}", req, e);
@@ -150,6 +182,42 @@ impl ServiceHandler<Self, SubmitSealRequest> for MinerService {
     }
 }
 
+impl ServiceHandler<Self, MineOneBlockRequest> for MinerService {
+    fn handle(
+        &mut self,
+        _req: MineOneBlockRequest,
+        ctx: &mut ServiceContext<MinerService>,
+    ) -> Result<BlockHeader> {
+        let net = self.config.net();
+        if !net.is_test() && !net.is_dev() {
+            bail!(
+                "Mine one block is not supported by the network {}",
+                net.id()
+            );
+        }
+        // the new task replaces the current one, as a GenerateBlockEvent which breaks the task.
+        self.dispatch_sleep_task(ctx)?;
+        let (minting_blob, strategy, difficulty) = match self.current_task.as_ref() {
+            Some(task) => (
+                task.minting_blob.clone(),
+                task.block_template.strategy,
+                task.block_template.difficulty,
+            ),
+            None => return Err(MinerError::TaskEmptyError.into()),
+        };
+        let time_service = net.time_service();
+        let nonce = match strategy {
+            ConsensusStrategy::Dummy => {
+                G_DEV.solve_consensus_nonce(&minting_blob, difficulty, time_service.as_ref())
+            }
+            strategy => {
+                strategy.solve_consensus_nonce(&minting_blob, difficulty, time_service.as_ref())
+            }
+        };
+        self.seal_task(nonce, BlockHeaderExtra::new([0u8; 4]), minting_blob, ctx)
+    }
+}
+
 // one hour
 const MAX_BLOCK_TIME_GAP: u64 = 3600 * 1000;
 
@@ -159,6 +227,8 @@ impl MinerService {
         ctx: &mut ServiceContext<MinerService>,
         event: GenerateBlockEvent,
     ) -> Result<()> {
+        // the pending event is coalesced into this task, which is on the latest head.
+        self.generate_pending = false;
         //create block template should block_on for avoid mint same block template.
         let response = block_on(async {
             self.create_block_template_service
@@ -171,7 +241,7 @@ impl MinerService {
 
         if !event.skip_empty_block_check
             && (block_template.body.transactions.is_empty()
-            && self.config.miner.is_disable_mint_empty_block()
+            && self.pacemaker_strategy == PacemakerStrategy::OnDemand
             //if block time gap > 3600, force create a empty block for fix https://github.com/starcoinorg/starcoin/issues/3036
             && block_time_gap < MAX_BLOCK_TIME_GAP)
         {
@@ -211,6 +281,7 @@ impl MinerService {
             );
         }
         self.current_task = Some(task);
+        self.stats.record_attempt();
         ctx.broadcast(MintBlockEvent::new(
             parent_hash,
             strategy,
@@ -229,6 +300,17 @@ impl MinerService {
         minting_blob: Vec<u8>,
         ctx: &mut ServiceContext<MinerService>,
     ) -> Result<HashValue> {
+        self.seal_task(nonce, extra, minting_blob, ctx)
+            .map(|header| header.id())
+    }
+
+    fn seal_task(
+        &mut self,
+        nonce: u32,
+        extra: BlockHeaderExtra,
+        minting_blob: Vec<u8>,
+        ctx: &mut ServiceContext<MinerService>,
+    ) -> Result<BlockHeader> {
         match self.current_task.as_ref() {
             Some(task) => {
                 if task.minting_blob != minting_blob {
@@ -251,14 +333,21 @@ impl MinerService {
         }
 
         if let Some(task) = self.current_task.take() {
+            let now = self.config.net().time_service().now_millis();
+            self.stats
+                .record_accept(task.block_template.difficulty, now);
             let block = task.finish(nonce, extra);
-            let block_hash = block.id();
+            let header = block.header().clone();
             info!(target: "miner", "Mint new block: {}", block);
             ctx.broadcast(MinedBlock(Arc::new(block)));
+            if std::mem::take(&mut self.generate_pending) {
+                ctx.notify(GenerateBlockEvent::default());
+            }
             if let Some(metrics) = self.metrics.as_ref() {
                 metrics.block_mint_count.inc();
+                metrics.hash_rate.set(self.stats.hash_rate(now));
             }
-            Ok(block_hash)
+            Ok(header)
         } else {
             Err(MinerError::TaskEmptyError.into())
         }
@@ -267,13 +356,29 @@ impl MinerService {
     pub fn is_minting(&self) -> bool {
         self.current_task.is_some()
     }
+
+    pub fn stats(&mut self) -> MinerStats {
+        let now = self.config.net().time_service().now_millis();
+        self.stats.stats(now)
+    }
+}
+
+impl ServiceHandler<Self, MinerStatsRequest> for MinerService {
+    fn handle(
+        &mut self,
+        _req: MinerStatsRequest,
+        _ctx: &mut ServiceContext<MinerService>,
+    ) -> MinerStats {
+        self.stats()
+    }
 }
 
 impl EventHandler<Self, GenerateBlockEvent> for MinerService {
     fn handle_event(&mut self, event: GenerateBlockEvent, ctx: &mut ServiceContext<MinerService>) {
         debug!("Handle GenerateBlockEvent:{:?}", event);
         if !event.break_current_task && self.is_minting() {
-            debug!("Miner has mint job so just ignore this event.");
+            debug!("Miner has mint job, coalesce this event into a mint after the job.");
+            self.generate_pending = true;
             return;
         }
         if self.config.miner.disable_miner_client() && self.client_subscribers_num == 0 {
@@ -9,6 +9,7 @@ use starcoin_metrics::{
 pub struct MinerMetrics {
     pub block_mint_count: UIntGauge,
     pub block_mint_time: Histogram,
+    pub hash_rate: UIntGauge,
 }
 
 impl MinerMetrics {
@@ -26,9 +27,18 @@ impl MinerMetrics {
             registry,
         )?;
 
+        let hash_rate = register(
+            UIntGauge::with_opts(Opts::new(
+                "miner_hash_rate",
+                "Estimated hash rate of the miner clients",
+            ))?,
+            registry,
+        )?;
+
         Ok(Self {
             block_mint_count,
             block_mint_time,
+            hash_rate,
         })
     }
 }
@@ -0,0 +1,116 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use starcoin_service_registry::ServiceRequest;
+use std::collections::VecDeque;
+use types::U256;
+
+/// The hash rate is estimated from the accepted shares in this window.
+const HASH_RATE_WINDOW: u64 = 10 * 60 * 1000;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MinerStats {
+    /// Count of mint tasks dispatched to the miner clients.
+    pub blocks_attempted: u64,
+    /// Count of blocks sealed by the accepted shares.
+    pub blocks_mined: u64,
+    pub shares_submitted: u64,
+    pub shares_accepted: u64,
+    /// Estimated hashes per second of all connected miner clients.
+    pub hash_rate: u64,
+}
+
+#[derive(Debug)]
+pub struct MinerStatsRequest;
+
+impl ServiceRequest for MinerStatsRequest {
+    type Response = MinerStats;
+}
+
+/// Collect the mining statistics, every accepted share of difficulty `d` is counted as `d` hashes.
+#[derive(Debug, Default)]
+pub(crate) struct MinerStatsCollector {
+    stats: MinerStats,
+    /// (timestamp in milliseconds, difficulty) of the accepted shares in the window.
+    shares: VecDeque<(u64, U256)>,
+}
+
+impl MinerStatsCollector {
+    pub fn record_attempt(&mut self) {
+        self.stats.blocks_attempted = self.stats.blocks_attempted.saturating_add(1);
+    }
+
+    pub fn record_submit(&mut self) {
+        self.stats.shares_submitted = self.stats.shares_submitted.saturating_add(1);
+    }
+
+    pub fn record_accept(&mut self, difficulty: U256, now_millis: u64) {
+        self.stats.shares_accepted = self.stats.shares_accepted.saturating_add(1);
+        self.stats.blocks_mined = self.stats.blocks_mined.saturating_add(1);
+        self.shares.push_back((now_millis, difficulty));
+    }
+
+    pub fn hash_rate(&mut self, now_millis: u64) -> u64 {
+        while let Some((timestamp, _)) = self.shares.front() {
+            if now_millis.saturating_sub(*timestamp) > HASH_RATE_WINDOW {
+                self.shares.pop_front();
+            } else {
+                break;
+            }
+        }
+        let oldest = match self.shares.front() {
+            Some((timestamp, _)) => *timestamp,
+            None => return 0,
+        };
+        let elapsed_secs = std::cmp::max(now_millis.saturating_sub(oldest) / 1000, 1);
+        let total = self
+            .shares
+            .iter()
+            .fold(U256::zero(), |total, (_, difficulty)| {
+                total.saturating_add(*difficulty)
+            });
+        let rate = total / U256::from(elapsed_secs);
+        if rate > U256::from(u64::max_value()) {
+            u64::max_value()
+        } else {
+            rate.low_u64()
+        }
+    }
+
+    pub fn stats(&mut self, now_millis: u64) -> MinerStats {
+        let mut stats = self.stats.clone();
+        stats.hash_rate = self.hash_rate(now_millis);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MinerStatsCollector, HASH_RATE_WINDOW};
+    use types::U256;
+
+    #[test]
+    fn test_miner_stats() {
+        let mut collector = MinerStatsCollector::default();
+        let start = 1_000_000u64;
+        // 10 shares of difficulty 1000, one share per second.
+        for i in 0..10u64 {
+            collector.record_attempt();
+            collector.record_submit();
+            collector.record_accept(U256::from(1000), start + i * 1000);
+        }
+        collector.record_submit();
+
+        let stats = collector.stats(start + 10 * 1000);
+        assert_eq!(stats.blocks_attempted, 10);
+        assert_eq!(stats.blocks_mined, 10);
+        assert_eq!(stats.shares_submitted, 11);
+        assert_eq!(stats.shares_accepted, 10);
+        assert_eq!(stats.hash_rate, 1000);
+
+        // all shares are out of the window.
+        let stats = collector.stats(start + 10 * 1000 + HASH_RATE_WINDOW);
+        assert_eq!(stats.hash_rate, 0);
+        assert_eq!(stats.shares_accepted, 10);
+    }
+}
@@ -3,13 +3,19 @@
 
 use crate::metrics::MinerMetrics;
 use crate::BlockHeaderExtra;
+use logger::prelude::*;
+use logger::structured_log::{block_logger, Logger};
 use starcoin_metrics::HistogramTimer;
+use std::time::Instant;
 use types::block::{Block, BlockTemplate};
 
 pub struct MintTask {
     pub(crate) minting_blob: Vec<u8>,
     pub(crate) block_template: BlockTemplate,
     metrics_timer: Option<HistogramTimer>,
+    /// The records of the task carry the fields of the block, from dispatch to seal.
+    logger: Logger,
+    created_at: Instant,
 }
 
 impl std::fmt::Debug for MintTask {
@@ -32,15 +38,29 @@ impl MintTask {
         let metrics_timer = metrics
             .as_ref()
             .map(|metrics| metrics.block_mint_time.start_timer());
+        let logger = block_logger(
+            block_template.number,
+            block_template.parent_hash,
+            block_template.body.transactions.len(),
+        );
+        slog_info!(logger, "mint task created";
+            "difficulty" => block_template.difficulty.to_string(),
+        );
         MintTask {
             minting_blob,
             block_template,
             metrics_timer,
+            logger,
+            created_at: Instant::now(),
         }
     }
 
     pub fn finish(self, nonce: u32, extra: BlockHeaderExtra) -> Block {
         let block = self.block_template.into_block(nonce, extra);
+        slog_info!(self.logger, "block mined";
+            "block_id" => block.id().to_string(),
+            "mint_ms" => self.created_at.elapsed().as_millis() as u64,
+        );
         if let Some(metrics_timer) = self.metrics_timer {
             metrics_timer.observe_duration();
         }
@@ -2,18 +2,31 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use consensus::Consensus;
+use futures::StreamExt;
 use starcoin_account_service::AccountService;
-use starcoin_config::NodeConfig;
+use starcoin_chain::{BlockChain, ChainReader, ChainWriter};
+use starcoin_config::{BuiltinNetworkID, ChainNetwork, NodeConfig, StarcoinOpt};
 use starcoin_genesis::Genesis;
+use starcoin_miner::generate_block_event_pacemaker::{
+    GenerateBlockEventPacemaker, PacemakerStrategy, SwitchPacemaker,
+};
 use starcoin_miner::{
-    BlockBuilderService, BlockHeaderExtra, BlockTemplateRequest, MinerService, SubmitSealRequest,
+    BlockBuilderService, BlockHeaderExtra, BlockTemplateRequest, MineOneBlockRequest, MinedBlock,
+    MinerService, SubmitSealRequest,
 };
+use starcoin_service_registry::bus::{Bus, BusService};
 use starcoin_service_registry::{RegistryAsyncService, RegistryService};
 use starcoin_storage::BlockStore;
 use starcoin_txpool::TxPoolService;
+use starcoin_txpool_api::PropagateTransactions;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
+use types::block::{Block, BlockBody, ExecutedBlock};
+use types::genesis_config::ConsensusStrategy;
+use types::startup_info::ChainStatus;
+use types::sync_status::SyncStatus;
+use types::system_events::{MintBlockEvent, NewHeadBlock, SyncStatusChangeEvent};
 use types::{system_events::GenerateBlockEvent, U256};
 
 #[stest::test]
@@ -76,3 +89,351 @@ async fn test_miner_service() {
 
     registry.shutdown_system().await.unwrap();
 }
+
+#[stest::test]
+async fn test_mine_one_block() {
+    let config = Arc::new(NodeConfig::random_for_test());
+    let registry = RegistryService::launch();
+    registry.put_shared(config.clone()).await.unwrap();
+    let (storage, _chain_info, genesis) = Genesis::init_storage_for_test(config.net()).unwrap();
+    registry.put_shared(storage.clone()).await.unwrap();
+    let genesis_hash = genesis.block().id();
+    let chain_header = storage
+        .get_block_header_by_hash(genesis_hash)
+        .unwrap()
+        .unwrap();
+    let txpool = TxPoolService::new(config.clone(), storage.clone(), chain_header, None);
+    registry.put_shared(txpool).await.unwrap();
+    registry
+        .register_mocker(AccountService::mock().unwrap())
+        .await
+        .unwrap();
+    let builder = registry.register::<BlockBuilderService>().await.unwrap();
+    let miner = registry.register::<MinerService>().await.unwrap();
+    let bus = registry.service_ref::<BusService>().await.unwrap();
+    let mut mined_blocks = bus.channel::<MinedBlock>().await.unwrap();
+
+    let mut chain = BlockChain::new(
+        config.net().time_service(),
+        genesis_hash,
+        storage.clone(),
+        None,
+    )
+    .unwrap();
+    let mut headers = vec![];
+    for _ in 0..2 {
+        let header = miner.send(MineOneBlockRequest).await.unwrap().unwrap();
+        let block = mined_blocks.next().await.unwrap().0;
+        assert_eq!(block.header(), &header);
+        let executed_block = chain.apply(block.as_ref().clone()).unwrap();
+        // the builder follows the new head before the next request.
+        builder
+            .notify(NewHeadBlock(Arc::new(executed_block)))
+            .unwrap();
+        headers.push(header);
+    }
+    assert_eq!(chain.current_header().number(), 2);
+    assert_eq!(headers[0].number(), 1);
+    assert_eq!(headers[1].parent_hash(), headers[0].id());
+    assert_eq!(chain.current_header(), headers[1]);
+
+    registry.shutdown_system().await.unwrap();
+}
+
+#[stest::test]
+async fn test_pacemaker_txn_event_flood() {
+    let mut config = NodeConfig::random_for_test();
+    config.miner.disable_mint_empty_block = Some(true);
+    config.miner.pacemaker_event_capacity = Some(16);
+    let registry = RegistryService::launch();
+    registry.put_shared(Arc::new(config)).await.unwrap();
+    let bus = registry.service_ref::<BusService>().await.unwrap();
+    let mut generate_events = bus.channel::<GenerateBlockEvent>().await.unwrap();
+    let pacemaker = registry
+        .register::<GenerateBlockEventPacemaker>()
+        .await
+        .unwrap();
+
+    let mut sync_status = SyncStatus::new(ChainStatus::random());
+    sync_status.sync_done();
+    pacemaker
+        .notify(SyncStatusChangeEvent(sync_status))
+        .unwrap();
+    // the synced event triggers once.
+    generate_events.next().await.unwrap();
+
+    let start = Instant::now();
+    let total = 100_000;
+    let mut dropped = 0;
+    for _ in 0..total {
+        if pacemaker
+            .notify(PropagateTransactions::new(vec![]))
+            .is_err()
+        {
+            dropped += 1;
+        }
+    }
+    let elapsed = start.elapsed();
+    // the mailbox is bounded, so the events beyond the capacity are dropped.
+    assert!(dropped > 0);
+
+    // the first event triggers promptly.
+    generate_events.next().await.unwrap();
+    sleep(Duration::from_millis(500)).await;
+    let mut triggered = 1u128;
+    while let Ok(Some(_)) = generate_events.try_next() {
+        triggered += 1;
+    }
+    // the events are coalesced into at most one trigger per 100ms window, and a trailing one.
+    let windows = (elapsed + Duration::from_millis(500)).as_millis() / 100 + 2;
+    assert!(
+        triggered <= windows,
+        "triggered: {}, windows: {}",
+        triggered,
+        windows
+    );
+    assert!(triggered < (total - dropped) as u128);
+
+    registry.shutdown_system().await.unwrap();
+}
+
+#[stest::test]
+async fn test_pacemaker_new_head() {
+    let registry = RegistryService::launch();
+    registry
+        .put_shared(Arc::new(NodeConfig::random_for_test()))
+        .await
+        .unwrap();
+    let bus = registry.service_ref::<BusService>().await.unwrap();
+    let mut generate_events = bus.channel::<GenerateBlockEvent>().await.unwrap();
+    let pacemaker = registry
+        .register::<GenerateBlockEventPacemaker>()
+        .await
+        .unwrap();
+
+    let mut sync_status = SyncStatus::new(ChainStatus::random());
+    sync_status.sync_done();
+    pacemaker
+        .notify(SyncStatusChangeEvent(sync_status))
+        .unwrap();
+    assert!(!generate_events.next().await.unwrap().break_current_task);
+
+    let new_head = || {
+        let status = ChainStatus::random();
+        NewHeadBlock(Arc::new(ExecutedBlock::new(
+            Block::new(status.head, BlockBody::new_empty()),
+            status.info,
+        )))
+    };
+    pacemaker.notify(new_head()).unwrap();
+    let head = new_head();
+    pacemaker.notify(head.clone()).unwrap();
+    // the repeated head is not a head change.
+    pacemaker.notify(head).unwrap();
+
+    // every head change breaks the current task.
+    for _ in 0..2 {
+        assert!(generate_events.next().await.unwrap().break_current_task);
+    }
+    sleep(Duration::from_millis(200)).await;
+    assert!(generate_events.try_next().is_err());
+
+    registry.shutdown_system().await.unwrap();
+}
+
+#[stest::test]
+async fn test_switch_pacemaker() {
+    let mut config = NodeConfig::random_for_test();
+    config.miner.disable_mint_empty_block = Some(false);
+    let registry = RegistryService::launch();
+    registry.put_shared(Arc::new(config)).await.unwrap();
+    let bus = registry.service_ref::<BusService>().await.unwrap();
+    let mut generate_events = bus.channel::<GenerateBlockEvent>().await.unwrap();
+    let pacemaker = registry
+        .register::<GenerateBlockEventPacemaker>()
+        .await
+        .unwrap();
+
+    let mut sync_status = SyncStatus::new(ChainStatus::random());
+    sync_status.sync_done();
+    pacemaker
+        .notify(SyncStatusChangeEvent(sync_status))
+        .unwrap();
+    generate_events.next().await.unwrap();
+
+    // the continuous pacemaker does not follow the transactions.
+    bus.broadcast(PropagateTransactions::new(vec![])).unwrap();
+    sleep(Duration::from_millis(200)).await;
+    assert!(generate_events.try_next().is_err());
+
+    bus.broadcast(SwitchPacemaker(PacemakerStrategy::OnDemand))
+        .unwrap();
+    sleep(Duration::from_millis(200)).await;
+    bus.broadcast(PropagateTransactions::new(vec![])).unwrap();
+    let event = generate_events.next().await.unwrap();
+    assert!(!event.break_current_task);
+
+    bus.broadcast(SwitchPacemaker(PacemakerStrategy::Continuous))
+        .unwrap();
+    sleep(Duration::from_millis(200)).await;
+    bus.broadcast(PropagateTransactions::new(vec![])).unwrap();
+    sleep(Duration::from_millis(200)).await;
+    assert!(generate_events.try_next().is_err());
+
+    registry.shutdown_system().await.unwrap();
+}
+
+#[stest::test]
+async fn test_miner_empty_pool() {
+    for disable_mint_empty_block in [true, false] {
+        let mut config = NodeConfig::random_for_test();
+        config.miner.disable_mint_empty_block = Some(disable_mint_empty_block);
+        let registry = RegistryService::launch();
+        let node_config = Arc::new(config.clone());
+        registry.put_shared(node_config.clone()).await.unwrap();
+        let (storage, _chain_info, genesis) = Genesis::init_storage_for_test(config.net()).unwrap();
+        registry.put_shared(storage.clone()).await.unwrap();
+        let chain_header = storage
+            .get_block_header_by_hash(genesis.block().id())
+            .unwrap()
+            .unwrap();
+        let txpool = TxPoolService::new(node_config, storage, chain_header, None);
+        registry.put_shared(txpool).await.unwrap();
+        registry
+            .register_mocker(AccountService::mock().unwrap())
+            .await
+            .unwrap();
+        registry.register::<BlockBuilderService>().await.unwrap();
+        let bus = registry.service_ref::<BusService>().await.unwrap();
+        let mut mint_events = bus.channel::<MintBlockEvent>().await.unwrap();
+        let miner = registry.register::<MinerService>().await.unwrap();
+
+        // the pool is empty, so the block template has no txn.
+        miner.notify(GenerateBlockEvent::new_break(false)).unwrap();
+        sleep(Duration::from_millis(500)).await;
+        let minted = mint_events.try_next().ok().flatten();
+        if disable_mint_empty_block {
+            assert!(minted.is_none(), "should skip the empty block");
+        } else {
+            assert_eq!(minted.expect("should mint the empty block").block_number, 1);
+        }
+
+        registry.shutdown_system().await.unwrap();
+    }
+}
+
+#[stest::test]
+async fn test_miner_coalesce_generate_events() {
+    let mut config = NodeConfig::random_for_test();
+    config.miner.disable_mint_empty_block = Some(false);
+    let registry = RegistryService::launch();
+    let node_config = Arc::new(config.clone());
+    registry.put_shared(node_config.clone()).await.unwrap();
+    let (storage, _chain_info, genesis) = Genesis::init_storage_for_test(config.net()).unwrap();
+    registry.put_shared(storage.clone()).await.unwrap();
+    let chain_header = storage
+        .get_block_header_by_hash(genesis.block().id())
+        .unwrap()
+        .unwrap();
+    let txpool = TxPoolService::new(node_config, storage, chain_header, None);
+    registry.put_shared(txpool).await.unwrap();
+    registry
+        .register_mocker(AccountService::mock().unwrap())
+        .await
+        .unwrap();
+    registry.register::<BlockBuilderService>().await.unwrap();
+    let bus = registry.service_ref::<BusService>().await.unwrap();
+    let mut mint_events = bus.channel::<MintBlockEvent>().await.unwrap();
+    let mut mined_blocks = bus.channel::<MinedBlock>().await.unwrap();
+    let miner = registry.register::<MinerService>().await.unwrap();
+
+    miner.notify(GenerateBlockEvent::new_break(false)).unwrap();
+    let event = mint_events.next().await.unwrap();
+    // the events during the mint do not start another mint.
+    for _ in 0..10 {
+        miner.notify(GenerateBlockEvent::default()).unwrap();
+    }
+    sleep(Duration::from_millis(300)).await;
+    assert!(mint_events.try_next().is_err());
+
+    miner
+        .send(SubmitSealRequest::new(
+            event.minting_blob,
+            0,
+            BlockHeaderExtra::new([0u8; 4]),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    mined_blocks.next().await.unwrap();
+    // the events are coalesced into a single mint after the current one.
+    mint_events.next().await.unwrap();
+    sleep(Duration::from_millis(300)).await;
+    assert!(mint_events.try_next().is_err());
+
+    registry.shutdown_system().await.unwrap();
+}
+
+#[stest::test]
+async fn test_mine_block_under_dev_consensus() {
+    // the consensus is selected by the genesis config of the network at runtime.
+    let main_net = ChainNetwork::new_builtin(BuiltinNetworkID::Main);
+    assert_eq!(
+        main_net.genesis_config().consensus(),
+        ConsensusStrategy::CryptoNight
+    );
+    let opt = StarcoinOpt {
+        net: Some(BuiltinNetworkID::Dev.into()),
+        base_data_dir: Some("TMP".into()),
+        ..StarcoinOpt::default()
+    };
+    let config = Arc::new(NodeConfig::load_with_opt(&opt).unwrap());
+    assert_eq!(
+        config.net().genesis_config().consensus(),
+        ConsensusStrategy::Dummy
+    );
+
+    let registry = RegistryService::launch();
+    registry.put_shared(config.clone()).await.unwrap();
+    let (storage, _chain_info, genesis) = Genesis::init_storage_for_test(config.net()).unwrap();
+    registry.put_shared(storage.clone()).await.unwrap();
+    let genesis_hash = genesis.block().id();
+    let chain_header = storage
+        .get_block_header_by_hash(genesis_hash)
+        .unwrap()
+        .unwrap();
+    let txpool = TxPoolService::new(config.clone(), storage.clone(), chain_header, None);
+    registry.put_shared(txpool).await.unwrap();
+    registry
+        .register_mocker(AccountService::mock().unwrap())
+        .await
+        .unwrap();
+    registry.register::<BlockBuilderService>().await.unwrap();
+    let bus = registry.service_ref::<BusService>().await.unwrap();
+    let mut mint_events = bus.channel::<MintBlockEvent>().await.unwrap();
+    let mut mined_blocks = bus.channel::<MinedBlock>().await.unwrap();
+    let miner = registry.register::<MinerService>().await.unwrap();
+
+    miner.notify(GenerateBlockEvent::new(false, true)).unwrap();
+    let event = mint_events.next().await.unwrap();
+    assert_eq!(event.strategy, ConsensusStrategy::Dummy);
+    // any nonce is a solution of the dummy consensus, no pow is needed.
+    let block_id = miner
+        .send(SubmitSealRequest::new(
+            event.minting_blob,
+            0,
+            BlockHeaderExtra::new([0u8; 4]),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    let block = mined_blocks.next().await.unwrap().0;
+    assert_eq!(block.id(), block_id);
+
+    let mut chain =
+        BlockChain::new(config.net().time_service(), genesis_hash, storage, None).unwrap();
+    chain.apply(block.as_ref().clone()).unwrap();
+    assert_eq!(chain.current_header().id(), block_id);
+
+    registry.shutdown_system().await.unwrap();
+}
@@ -44,6 +44,7 @@ use starcoin_storage::errors::StorageInitError;
 use starcoin_storage::metrics::StorageMetrics;
 use starcoin_storage::storage::StorageInstance;
 use starcoin_storage::{BlockStore, Storage};
+use starcoin_stratum::getwork::{GetWorkService, GetWorkServiceFactory};
 use starcoin_stratum::service::{StratumService, StratumServiceFactory};
 use starcoin_stratum::stratum::{Stratum, StratumFactory};
 use starcoin_sync::announcement::AnnouncementService;
@@ -54,17 +55,26 @@ use starcoin_sync::verified_rpc_client::VerifiedRpcClient;
 use starcoin_txpool::TxPoolActorService;
 use starcoin_types::system_events::{SystemShutdown, SystemStarted};
 use starcoin_vm_runtime::metrics::VMMetrics;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 pub struct NodeService {
     registry: ServiceRef<RegistryService>,
+    /// The path of the cache snapshot and the storage to save it on shutdown.
+    cache_snapshot: Option<(PathBuf, StorageInstance)>,
 }
 
 impl ServiceFactory<Self> for NodeService {
     fn create(ctx: &mut ServiceContext<NodeService>) -> Result<NodeService> {
+        let config = ctx.get_shared::<Arc<NodeConfig>>()?;
+        let cache_snapshot = match config.storage.cache_snapshot_path() {
+            Some(path) => Some((path, ctx.get_shared::<StorageInstance>()?)),
+            None => None,
+        };
         Ok(Self {
             registry: ctx.registry_ref().clone(),
+            cache_snapshot,
         })
     }
 }
@@ -77,6 +87,12 @@ impl ActorService for NodeService {
 
     fn stopped(&mut self, ctx: &mut ServiceContext<Self>) -> Result<()> {
         ctx.unsubscribe::<SystemShutdown>();
+        if let Some((path, storage_instance)) = self.cache_snapshot.as_ref() {
+            match storage_instance.save_cache_snapshot(path) {
+                Ok(saved) => info!("Save {} cache entries to snapshot {:?}", saved, path),
+                Err(e) => warn!("Save cache snapshot to {:?} error: {}", path, e),
+            }
+        }
         Ok(())
     }
 }
@@ -213,6 +229,7 @@ impl NodeService {
         logger_handle: Arc<LoggerHandle>,
     ) -> Result<NodeHandle, NodeStartError> {
         info!("Final data-dir is : {:?}", config.data_dir());
+        config.validate().map_err(NodeStartError::Other)?;
         if let Some(log_path) = config.logger.get_log_path() {
             info!("Write log to file: {:?}", log_path);
             logger_handle.enable_file(
@@ -306,6 +323,13 @@ impl NodeService {
         let start_time = SystemTime::now();
         storage_instance.check_upgrade()?;
         let upgrade_time = SystemTime::now().duration_since(start_time)?;
+        if let Some(path) = config.storage.cache_snapshot_path() {
+            // a broken snapshot only makes the cache cold, the node can still start.
+            if let Err(e) = storage_instance.load_cache_snapshot(&path) {
+                warn!("Load cache snapshot from {:?} error: {}", path, e);
+            }
+        }
+        registry.put_shared(storage_instance.clone()).await?;
         let storage = Arc::new(Storage::new(storage_instance)?);
         registry.put_shared(storage.clone()).await?;
         let (chain_info, genesis) =
@@ -402,6 +426,9 @@ impl NodeService {
         registry
             .register_by_factory::<StratumService, StratumServiceFactory>()
             .await?;
+        registry
+            .register_by_factory::<GetWorkService, GetWorkServiceFactory>()
+            .await?;
 
         // start metrics server
         if !config.metrics.disable_metrics() {
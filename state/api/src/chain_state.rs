@@ -93,6 +93,26 @@ impl StateProof {
             self.account_state.as_ref(),
         )
     }
+
+    /// verify the account state blob of `account_address` with `expected_root_hash`,
+    /// the `account_state_blob` None means the account does not exist.
+    /// It only depends on the proof, so light clients can verify it without the state db.
+    pub fn verify_account_state(
+        &self,
+        expected_root_hash: HashValue,
+        account_address: AccountAddress,
+        account_state_blob: Option<&[u8]>,
+    ) -> Result<()> {
+        ensure!(
+            self.account_state.as_ref().map(|blob| blob.as_ref()) == account_state_blob,
+            "account state in proof mismatch with the account state blob"
+        );
+        self.account_proof.verify(
+            expected_root_hash,
+            account_address.key_hash(),
+            self.account_state.as_ref(),
+        )
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
@@ -10,6 +10,7 @@ mod state_tree;
 mod state_tree_test;
 
 pub use starcoin_state_store_api::{StateNode, StateNodeStore};
+pub use state_tree::collect_state_nodes;
 pub use state_tree::AccountStateSetIterator;
 pub use state_tree::StateTree;
 pub use state_tree::StorageTreeReader;
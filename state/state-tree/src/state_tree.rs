@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{format_err, Result};
 use forkable_jellyfish_merkle::blob::Blob;
 use forkable_jellyfish_merkle::iterator::{JellyfishMerkleIntoIterator, JellyfishMerkleIterator};
 use forkable_jellyfish_merkle::node_type::{Node, NodeKey};
@@ -16,7 +16,7 @@ use starcoin_types::account_state::AccountState;
 use starcoin_types::language_storage::StructTag;
 use starcoin_types::state_set::{AccountStateSet, StateSet};
 use starcoin_vm_types::access_path::ModuleName;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::convert::TryInto;
 use std::ops::DerefMut;
 use std::sync::Arc;
@@ -335,6 +335,32 @@ where
     //     todo!()
     // }
 
+    /// Collect the hashes of the nodes reachable from the committed root into `nodes`, the
+    /// subtrees whose root is already in `nodes` are skipped. The blob of every newly reached
+    /// leaf is passed to `on_leaf`, so the trees nested in the blobs can be collected too.
+    pub fn collect_nodes<F>(&self, nodes: &mut HashSet<HashValue>, mut on_leaf: F) -> Result<()>
+    where
+        F: FnMut(&Blob) -> Result<()>,
+    {
+        let mut pending = vec![*self.storage_root_hash.read()];
+        while let Some(hash) = pending.pop() {
+            if hash == *SPARSE_MERKLE_PLACEHOLDER_HASH || !nodes.insert(hash) {
+                continue;
+            }
+            let node: Node<K> = self
+                .storage
+                .get(&hash)?
+                .ok_or_else(|| format_err!("Can not find state node {}", hash))?
+                .try_into()?;
+            match node {
+                Node::Internal(internal) => pending.extend(internal.all_child()),
+                Node::Leaf(leaf) => on_leaf(leaf.blob())?,
+                Node::Null => {}
+            }
+        }
+        Ok(())
+    }
+
     pub fn is_genesis(&self) -> bool {
         self.root_hash() == *SPARSE_MERKLE_PLACEHOLDER_HASH
     }
@@ -455,3 +481,29 @@ impl Iterator for AccountStateSetIterator {
         None
     }
 }
+
+/// Collect the hashes of the state nodes reachable from the global `state_root` into `nodes`,
+/// including the nodes of the code and resource trees of every account.
+pub fn collect_state_nodes(
+    store: Arc<dyn StateNodeStore>,
+    state_root: HashValue,
+    nodes: &mut HashSet<HashValue>,
+) -> Result<()> {
+    let mut account_states = vec![];
+    StateTree::<AccountAddress>::new(store.clone(), Some(state_root)).collect_nodes(
+        nodes,
+        |blob| {
+            account_states.push(AccountState::try_from(blob.as_ref())?);
+            Ok(())
+        },
+    )?;
+    for account_state in account_states {
+        if let Some(code_root) = account_state.code_root() {
+            StateTree::<ModuleName>::new(store.clone(), Some(code_root))
+                .collect_nodes(nodes, |_| Ok(()))?;
+        }
+        StateTree::<StructTag>::new(store.clone(), Some(account_state.resource_root()))
+            .collect_nodes(nodes, |_| Ok(()))?;
+    }
+    Ok(())
+}
@@ -210,10 +210,14 @@ pub struct ChainStateDB {
     ///global state tree.
     state_tree: StateTree<AccountAddress>,
     cache: Mutex<LruCache<AccountAddress, CacheItem>>,
+    /// The values read by `StateView::get`, None if the read cache is disabled. The written
+    /// access paths are dropped from it, and it's cleared by `commit` and `flush`.
+    read_cache: Option<Mutex<LruCache<AccessPath, Option<Vec<u8>>>>>,
     updates: RwLock<HashSet<AccountAddress>>,
 }
 
 static G_DEFAULT_CACHE_SIZE: usize = 10240;
+static G_DEFAULT_READ_CACHE_SIZE: usize = 1024;
 
 impl ChainStateDB {
     pub fn mock() -> Self {
@@ -225,10 +229,27 @@ impl ChainStateDB {
             store: store.clone(),
             state_tree: StateTree::new(store, root_hash),
             cache: Mutex::new(LruCache::new(G_DEFAULT_CACHE_SIZE)),
+            read_cache: Some(Mutex::new(LruCache::new(G_DEFAULT_READ_CACHE_SIZE))),
             updates: RwLock::new(HashSet::new()),
         }
     }
 
+    /// Set the capacity of the read cache, 0 disables it.
+    pub fn with_read_cache_size(mut self, size: usize) -> Self {
+        self.read_cache = if size == 0 {
+            None
+        } else {
+            Some(Mutex::new(LruCache::new(size)))
+        };
+        self
+    }
+
+    fn clear_read_cache(&self) {
+        if let Some(read_cache) = self.read_cache.as_ref() {
+            read_cache.lock().clear();
+        }
+    }
+
     /// Fork a new statedb base current statedb
     pub fn fork(&self) -> Self {
         Self::new(self.store.clone(), Some(self.state_root()))
@@ -240,10 +261,40 @@ impl ChainStateDB {
             store: self.store.clone(),
             state_tree: StateTree::new(self.store.clone(), Some(state_root)),
             cache: Mutex::new(LruCache::new(G_DEFAULT_CACHE_SIZE)),
+            read_cache: Some(Mutex::new(LruCache::new(G_DEFAULT_READ_CACHE_SIZE))),
             updates: RwLock::new(HashSet::new()),
         }
     }
 
+    /// return the account state blob of `account_address` with the proof to the state root,
+    /// the proof of an absent account is a non-existence proof.
+    /// NOTICE: Any un-committed modification will not visible to the method.
+    pub fn get_account_state_with_proof(
+        &self,
+        account_address: &AccountAddress,
+    ) -> Result<(Option<Vec<u8>>, StateProof)> {
+        let (account_state, account_proof) = self.state_tree.get_with_proof(account_address)?;
+        let proof = StateProof::new(
+            account_state.clone(),
+            account_proof,
+            SparseMerkleProof::default(),
+        );
+        Ok((account_state, proof))
+    }
+
+    /// return the account state blobs of `account_addresses` in the order of the addresses,
+    /// the absent accounts are `None`.
+    /// NOTICE: Any un-committed modification will not visible to the method.
+    pub fn get_account_states(
+        &self,
+        account_addresses: &[AccountAddress],
+    ) -> Result<Vec<Option<Vec<u8>>>> {
+        account_addresses
+            .iter()
+            .map(|account_address| Ok(self.state_tree.get_with_proof(account_address)?.0))
+            .collect()
+    }
+
     fn new_state_tree<K: RawKey>(&self, root_hash: HashValue) -> StateTree<K> {
         StateTree::new(self.store.clone(), Some(root_hash))
     }
@@ -310,13 +361,23 @@ impl ChainStateDB {
 
 impl StateView for ChainStateDB {
     fn get(&self, access_path: &AccessPath) -> Result<Option<Vec<u8>>> {
+        if let Some(read_cache) = self.read_cache.as_ref() {
+            if let Some(value) = read_cache.lock().get(access_path) {
+                return Ok(value.clone());
+            }
+        }
         let account_address = &access_path.address;
         let data_path = &access_path.path;
-        self.get_account_state_object_option(account_address)
+        let value = self
+            .get_account_state_object_option(account_address)
             .and_then(|account_state| match account_state {
                 Some(account_state) => account_state.get(data_path),
                 None => Ok(None),
-            })
+            })?;
+        if let Some(read_cache) = self.read_cache.as_ref() {
+            read_cache.lock().put(access_path.clone(), value.clone());
+        }
+        Ok(value)
     }
 
     fn is_genesis(&self) -> bool {
@@ -467,6 +528,7 @@ impl ChainStateWriter for ChainStateDB {
         }
         self.state_tree.commit()?;
         self.state_tree.flush()?;
+        self.clear_read_cache();
         Ok(())
     }
 
@@ -475,6 +537,9 @@ impl ChainStateWriter for ChainStateDB {
         for (access_path, write_op) in write_set {
             //update self updates record
             locks.insert(access_path.address);
+            if let Some(read_cache) = self.read_cache.as_ref() {
+                read_cache.lock().pop(&access_path);
+            }
             let (account_address, data_path) = access_path.into_inner();
             match write_op {
                 WriteOp::Value(value) => {
@@ -499,6 +564,7 @@ impl ChainStateWriter for ChainStateDB {
             let state = account_state_object.commit()?;
             self.state_tree.put(*address, state.try_into()?);
         }
+        self.clear_read_cache();
         self.state_tree.commit()
     }
 
@@ -511,6 +577,7 @@ impl ChainStateWriter for ChainStateDB {
             account_state_object.flush()?;
         }
         locks.clear();
+        self.clear_read_cache();
         // self tree flush
         self.state_tree.flush()
     }
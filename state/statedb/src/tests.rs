@@ -1,9 +1,11 @@
 use super::*;
 use starcoin_state_tree::mock::MockStateNodeStore;
+use starcoin_state_tree::StateNode;
 use starcoin_types::write_set::{WriteOp, WriteSet, WriteSetMut};
 use starcoin_vm_types::account_config::AccountResource;
 use starcoin_vm_types::move_resource::MoveResource;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 fn random_bytes() -> Vec<u8> {
     HashValue::random().to_vec()
@@ -134,3 +136,65 @@ fn test_state_db_dump_iter() -> Result<()> {
     assert_eq!(kv1, kv2);
     Ok(())
 }
+
+/// The store counts the node reads.
+#[derive(Default)]
+struct CountingStore {
+    store: MockStateNodeStore,
+    reads: AtomicUsize,
+}
+
+impl StateNodeStore for CountingStore {
+    fn get(&self, hash: &HashValue) -> Result<Option<StateNode>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.store.get(hash)
+    }
+
+    fn put(&self, key: HashValue, node: StateNode) -> Result<()> {
+        self.store.put(key, node)
+    }
+
+    fn write_nodes(&self, nodes: BTreeMap<HashValue, StateNode>) -> Result<()> {
+        self.store.write_nodes(nodes)
+    }
+}
+
+#[test]
+fn test_state_db_read_cache() -> Result<()> {
+    let store = Arc::new(CountingStore::default());
+    let access_path = AccessPath::random_resource();
+    let state0 = random_bytes();
+    let state_root = {
+        let chain_state_db = ChainStateDB::new(store.clone(), None);
+        chain_state_db.apply_write_set(to_write_set(access_path.clone(), state0.clone()))?;
+        let state_root = chain_state_db.commit()?;
+        chain_state_db.flush()?;
+        state_root
+    };
+
+    let chain_state_db = ChainStateDB::new(store.clone(), Some(state_root));
+    assert_eq!(chain_state_db.get(&access_path)?, Some(state0.clone()));
+    let reads = store.reads.load(Ordering::SeqCst);
+    assert!(reads > 0);
+    for _ in 0..10 {
+        assert_eq!(chain_state_db.get(&access_path)?, Some(state0.clone()));
+    }
+    assert_eq!(store.reads.load(Ordering::SeqCst), reads);
+
+    // the written value is visible before and after the commit.
+    let state1 = random_bytes();
+    chain_state_db.apply_write_set(to_write_set(access_path.clone(), state1.clone()))?;
+    assert_eq!(chain_state_db.get(&access_path)?, Some(state1.clone()));
+    chain_state_db.commit()?;
+    assert_eq!(chain_state_db.get(&access_path)?, Some(state1.clone()));
+    chain_state_db.flush()?;
+    assert_eq!(chain_state_db.get(&access_path)?, Some(state1));
+
+    // every read goes to the store without the read cache.
+    let chain_state_db = ChainStateDB::new(store.clone(), Some(state_root)).with_read_cache_size(0);
+    assert_eq!(chain_state_db.get(&access_path)?, Some(state0.clone()));
+    let reads = store.reads.load(Ordering::SeqCst);
+    assert_eq!(chain_state_db.get(&access_path)?, Some(state0));
+    assert!(store.reads.load(Ordering::SeqCst) > reads);
+    Ok(())
+}
@@ -3,13 +3,25 @@
 
 use crate::storage::{CodecWriteBatch, KeyCodec, ValueCodec, WriteOp};
 use anyhow::Result;
+use bcs_ext::BCSCodec;
+use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct WriteBatch {
     pub rows: Vec<(Vec<u8>, WriteOp<Vec<u8>>)>,
 }
 
+/// The stable binary format of a batch for the log shipping, the variant is the format version.
+/// A row with None value is a deletion.
+#[derive(Serialize, Deserialize)]
+enum EncodedWriteBatch {
+    V1 {
+        prefix_name: String,
+        rows: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    },
+}
+
 impl WriteBatch {
     /// Creates an empty batch.
     pub fn new() -> Self {
@@ -37,6 +49,42 @@ impl WriteBatch {
         self.rows.clear();
         Ok(())
     }
+
+    /// Encode the batch with the column family `prefix_name` it writes to,
+    /// so it can be replayed on another store by `decode`.
+    pub fn encode(&self, prefix_name: &str) -> Result<Vec<u8>> {
+        EncodedWriteBatch::V1 {
+            prefix_name: prefix_name.to_string(),
+            rows: self
+                .rows
+                .iter()
+                .map(|(key, write_op)| {
+                    let value = match write_op {
+                        WriteOp::Value(value) => Some(value.clone()),
+                        WriteOp::Deletion => None,
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        }
+        .encode()
+    }
+
+    /// Decode the batch and the column family of it from the bytes of `encode`.
+    pub fn decode(data: &[u8]) -> Result<(String, Self)> {
+        match EncodedWriteBatch::decode(data)? {
+            EncodedWriteBatch::V1 { prefix_name, rows } => {
+                let rows = rows
+                    .into_iter()
+                    .map(|(key, value)| match value {
+                        Some(value) => (key, WriteOp::Value(value)),
+                        None => (key, WriteOp::Deletion),
+                    })
+                    .collect();
+                Ok((prefix_name, Self::new_with_rows(rows)))
+            }
+        }
+    }
 }
 
 impl<K, V> TryFrom<CodecWriteBatch<K, V>> for WriteBatch
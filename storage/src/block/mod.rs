@@ -1,13 +1,13 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
-use crate::define_storage;
 use crate::storage::{CodecKVStore, StorageInstance, ValueCodec};
+use crate::{define_storage, impl_bcs_value_codec};
 use crate::{
     BLOCK_BODY_PREFIX_NAME, BLOCK_HEADER_PREFIX_NAME, BLOCK_PREFIX_NAME,
     BLOCK_TRANSACTIONS_PREFIX_NAME, BLOCK_TRANSACTION_INFOS_PREFIX_NAME, FAILED_BLOCK_PREFIX_NAME,
 };
 use anyhow::{bail, Result};
-use bcs_ext::{BCSCodec, Sample};
+use bcs_ext::Sample;
 use crypto::HashValue;
 use logger::prelude::*;
 use network_types::peer_info::PeerId;
@@ -117,54 +117,7 @@ pub struct BlockStorage {
     failed_block_storage: FailedBlockStorage,
 }
 
-impl ValueCodec for Block {
-    fn encode_value(&self) -> Result<Vec<u8>> {
-        self.encode()
-    }
-
-    fn decode_value(data: &[u8]) -> Result<Self> {
-        Self::decode(data)
-    }
-}
-
-impl ValueCodec for BlockHeader {
-    fn encode_value(&self) -> Result<Vec<u8>> {
-        self.encode()
-    }
-
-    fn decode_value(data: &[u8]) -> Result<Self> {
-        Self::decode(data)
-    }
-}
-
-impl ValueCodec for BlockBody {
-    fn encode_value(&self) -> Result<Vec<u8>> {
-        self.encode()
-    }
-
-    fn decode_value(data: &[u8]) -> Result<Self> {
-        Self::decode(data)
-    }
-}
-
-impl ValueCodec for OldFailedBlock {
-    fn encode_value(&self) -> Result<Vec<u8>> {
-        self.encode()
-    }
-
-    fn decode_value(data: &[u8]) -> Result<Self> {
-        Self::decode(data)
-    }
-}
-impl ValueCodec for FailedBlock {
-    fn encode_value(&self) -> Result<Vec<u8>> {
-        self.encode()
-    }
-
-    fn decode_value(data: &[u8]) -> Result<Self> {
-        Self::decode(data)
-    }
-}
+impl_bcs_value_codec!(Block, BlockHeader, BlockBody, OldFailedBlock, FailedBlock);
 
 impl BlockStorage {
     pub fn new(instance: StorageInstance) -> Self {
@@ -232,10 +185,22 @@ impl BlockStorage {
         self.block_txn_infos_store.remove(block_id)
     }
 
+    /// Delete the blocks, txns and txn infos of `block_ids` in batches, but keep the headers.
+    pub fn delete_block_bodies(&self, block_ids: Vec<HashValue>) -> Result<()> {
+        self.body_store.delete_all(block_ids.clone())?;
+        self.block_store.delete_all(block_ids.clone())?;
+        self.block_txns_store.delete_all(block_ids.clone())?;
+        self.block_txn_infos_store.delete_all(block_ids)
+    }
+
     pub fn get_block_header_by_hash(&self, block_id: HashValue) -> Result<Option<BlockHeader>> {
         self.header_store.get(block_id)
     }
 
+    pub fn get_block_headers(&self, ids: Vec<HashValue>) -> Result<Vec<Option<BlockHeader>>> {
+        self.header_store.multiple_get(ids)
+    }
+
     pub fn get_block_by_hash(&self, block_id: HashValue) -> Result<Option<Block>> {
         self.get(block_id)
     }
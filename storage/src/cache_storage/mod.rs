@@ -2,30 +2,253 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::batch::WriteBatch;
+use crate::db_storage::DBStorage;
 use crate::metrics::{record_metrics, StorageMetrics};
 use crate::storage::{InnerStore, WriteOp};
 use anyhow::{Error, Result};
+use bcs_ext::BCSCodec;
+use logger::prelude::*;
 use lru::LruCache;
 use parking_lot::Mutex;
 use starcoin_config::DEFAULT_CACHE_SIZE;
+use std::path::Path;
+
+/// The separator between the prefix name and the key of a cache key. The column family names
+/// never contain it, so the column families which are prefixes of others, such as `block` and
+/// `block_header`, can not collide.
+const KEY_SEPARATOR: u8 = 0;
+
+/// The lru cache with the total size of the cached values,
+/// the least recently used entries are evicted when the size exceeds the byte budget.
+struct SizedLruCache {
+    cache: LruCache<Vec<u8>, Vec<u8>>,
+    bytes: usize,
+    byte_budget: Option<usize>,
+}
+
+impl SizedLruCache {
+    fn new(size: usize) -> Self {
+        Self {
+            cache: LruCache::new(size),
+            bytes: 0,
+            byte_budget: None,
+        }
+    }
+
+    fn put(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.bytes = self.bytes.saturating_add(value.len());
+        // the replaced value of the same key, or the entry evicted by the capacity.
+        if let Some((_, old_value)) = self.cache.push(key, value) {
+            self.bytes = self.bytes.saturating_sub(old_value.len());
+        }
+        self.evict();
+    }
+
+    fn pop(&mut self, key: Vec<u8>) -> Option<Vec<u8>> {
+        let value = self.cache.pop(&key);
+        if let Some(value) = value.as_ref() {
+            self.bytes = self.bytes.saturating_sub(value.len());
+        }
+        value
+    }
+
+    /// Evict the lru entries until the size is within the budget,
+    /// the latest entry is kept even if it exceeds the budget alone.
+    fn evict(&mut self) {
+        if let Some(byte_budget) = self.byte_budget {
+            while self.bytes > byte_budget && self.cache.len() > 1 {
+                match self.cache.pop_lru() {
+                    Some((_, value)) => self.bytes = self.bytes.saturating_sub(value.len()),
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
 pub struct CacheStorage {
-    cache: Mutex<LruCache<Vec<u8>, Vec<u8>>>,
+    cache: Mutex<SizedLruCache>,
+    /// Serialize the write-throughs, see `write_through`.
+    write_lock: Mutex<()>,
     metrics: Option<StorageMetrics>,
 }
 
 impl CacheStorage {
     pub fn new(metrics: Option<StorageMetrics>) -> Self {
         CacheStorage {
-            cache: Mutex::new(LruCache::new(DEFAULT_CACHE_SIZE)),
+            cache: Mutex::new(SizedLruCache::new(DEFAULT_CACHE_SIZE)),
+            write_lock: Mutex::new(()),
             metrics,
         }
     }
     pub fn new_with_capacity(size: usize, metrics: Option<StorageMetrics>) -> Self {
         CacheStorage {
-            cache: Mutex::new(LruCache::new(size)),
+            cache: Mutex::new(SizedLruCache::new(size)),
+            write_lock: Mutex::new(()),
             metrics,
         }
     }
+
+    /// Bound the total size of the cached values in bytes, besides the count of entries.
+    pub fn with_byte_budget(self, bytes: usize) -> Self {
+        {
+            let mut cache = self.cache.lock();
+            cache.byte_budget = Some(bytes);
+            cache.evict();
+        }
+        self
+    }
+
+    /// Write the `batch` by `write_db` first, and then to the cache only if it succeeded.
+    /// The write-throughs are serialized, otherwise two writes of the same key may reach the db
+    /// and the cache in different orders, and leave a stale value in the cache.
+    ///
+    /// The db is authoritative and the cache is only a performance layer, so a failed db write
+    /// is returned as the error, but a failed cache write after it is not: it's logged, the keys
+    /// of the batch are dropped from the cache so the reads fall through to the db, and Ok is
+    /// returned.
+    pub fn write_through<F>(&self, prefix_name: &str, batch: WriteBatch, write_db: F) -> Result<()>
+    where
+        F: FnOnce(WriteBatch) -> Result<()>,
+    {
+        self.write_through_with(prefix_name, batch, write_db, |batch| {
+            self.write_batch(prefix_name, batch)
+        })
+    }
+
+    pub(crate) fn write_through_with<F, C>(
+        &self,
+        prefix_name: &str,
+        batch: WriteBatch,
+        write_db: F,
+        write_cache: C,
+    ) -> Result<()>
+    where
+        F: FnOnce(WriteBatch) -> Result<()>,
+        C: FnOnce(WriteBatch) -> Result<()>,
+    {
+        let _guard = self.write_lock.lock();
+        write_db(batch.clone())?;
+        if let Err(e) = write_cache(batch.clone()) {
+            warn!(
+                "Write cache of {} failed after the db write, drop the keys from cache: {:?}",
+                prefix_name, e
+            );
+            let mut cache = self.cache.lock();
+            for (key, _) in batch.rows {
+                cache.pop(compose_key(prefix_name.to_string(), key));
+            }
+        }
+        Ok(())
+    }
+
+    /// The cached entries of `prefix_name` whose keys start with `key_prefix`, ordered by key.
+    pub fn scan_prefix(&self, prefix_name: &str, key_prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let compose_prefix = compose_key(prefix_name.to_string(), key_prefix.to_vec());
+        // the key without the prefix name and the separator.
+        let key_start = prefix_name.len().saturating_add(1);
+        let mut entries: Vec<(Vec<u8>, Vec<u8>)> = self
+            .cache
+            .lock()
+            .cache
+            .iter()
+            .filter(|(key, _)| key.starts_with(&compose_prefix))
+            .map(|(key, value)| (key[key_start..].to_vec(), value.clone()))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    /// The cached entries of `prefix_name` whose keys are in `start..end`, ordered by key.
+    pub fn scan_range(
+        &self,
+        prefix_name: &str,
+        start: &[u8],
+        end: &[u8],
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let compose_prefix = compose_key(prefix_name.to_string(), vec![]);
+        let mut entries: Vec<(Vec<u8>, Vec<u8>)> = self
+            .cache
+            .lock()
+            .cache
+            .iter()
+            .filter(|(key, _)| key.starts_with(&compose_prefix))
+            .map(|(key, value)| (key[compose_prefix.len()..].to_vec(), value.clone()))
+            .filter(|(key, _)| key.as_slice() >= start && key.as_slice() < end)
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    /// Remove the key and return its cached value before the removal.
+    pub fn remove_returning(&self, prefix_name: &str, key: Vec<u8>) -> Option<Vec<u8>> {
+        let mut cache = self.cache.lock();
+        let value = cache.pop(compose_key(prefix_name.to_string(), key));
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.cache_items.set(cache.cache.len() as u64);
+        }
+        value
+    }
+
+    /// The total size of the cached values in bytes.
+    pub fn get_bytes(&self) -> usize {
+        self.cache.lock().bytes
+    }
+
+    /// Write the cached entries to the file at `path`, from the least recently used one to the
+    /// most, so the recency is kept after loading. Return the count of the saved entries.
+    pub fn save_snapshot(&self, path: &Path) -> Result<usize> {
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = self
+            .cache
+            .lock()
+            .cache
+            .iter()
+            .rev()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        // write to a temp file first, so a crash during saving never leaves a broken snapshot.
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, entries.encode()?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(entries.len())
+    }
+
+    /// Load the entries of the snapshot at `path` into the cache. The entry which does not match
+    /// the value in `db` is stale, it is dropped. Return the count of the loaded entries.
+    pub fn load_snapshot(&self, path: &Path, db: &DBStorage) -> Result<usize> {
+        let entries = Vec::<(Vec<u8>, Vec<u8>)>::decode(std::fs::read(path)?.as_slice())?;
+        let total = entries.len();
+        let mut loaded = 0usize;
+        for (key, value) in entries {
+            if Self::match_db(&key, &value, db)? {
+                self.cache.lock().put(key, value);
+                loaded = loaded.saturating_add(1);
+            }
+        }
+        info!(
+            "Load cache snapshot from {:?}, loaded: {}, stale: {}",
+            path,
+            loaded,
+            total.saturating_sub(loaded)
+        );
+        Ok(loaded)
+    }
+
+    /// The cache key is composed by prefix name, separator and key, the key of an old snapshot
+    /// without separator never matches, so it's dropped as stale.
+    fn match_db(compose_key: &[u8], value: &[u8], db: &DBStorage) -> Result<bool> {
+        for prefix_name in db.column_families() {
+            if let Some(key) = compose_key
+                .strip_prefix(prefix_name.as_bytes())
+                .and_then(|key| key.strip_prefix(&[KEY_SEPARATOR]))
+            {
+                if db.get(prefix_name, key.to_vec())?.as_deref() == Some(value) {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
 }
 
 impl Default for CacheStorage {
@@ -40,6 +263,7 @@ impl InnerStore for CacheStorage {
             Ok(self
                 .cache
                 .lock()
+                .cache
                 .get(&compose_key(prefix_name.to_string(), key))
                 .cloned())
         })
@@ -51,7 +275,7 @@ impl InnerStore for CacheStorage {
         let mut cache = self.cache.lock();
         cache.put(compose_key(prefix_name.to_string(), key), value);
         if let Some(metrics) = self.metrics.as_ref() {
-            metrics.cache_items.set(cache.len() as u64);
+            metrics.cache_items.set(cache.cache.len() as u64);
         }
         Ok(())
     }
@@ -61,6 +285,7 @@ impl InnerStore for CacheStorage {
             Ok(self
                 .cache
                 .lock()
+                .cache
                 .contains(&compose_key(prefix_name.to_string(), key)))
         })
     }
@@ -68,34 +293,45 @@ impl InnerStore for CacheStorage {
         // remove record_metrics for performance
         // record_metrics add in write_batch to reduce Instant::now system call
         let mut cache = self.cache.lock();
-        cache.pop(&compose_key(prefix_name.to_string(), key));
+        cache.pop(compose_key(prefix_name.to_string(), key));
         if let Some(metrics) = self.metrics.as_ref() {
-            metrics.cache_items.set(cache.len() as u64);
+            metrics.cache_items.set(cache.cache.len() as u64);
         }
         Ok(())
     }
 
     fn write_batch(&self, prefix_name: &str, batch: WriteBatch) -> Result<()> {
         record_metrics("cache", prefix_name, "write_batch", self.metrics.as_ref()).call(|| {
-            for (key, write_op) in &batch.rows {
+            // hold the lock during the batch, so the readers never see a partial batch.
+            let mut cache = self.cache.lock();
+            for (key, write_op) in batch.rows {
+                let key = compose_key(prefix_name.to_string(), key);
                 match write_op {
-                    WriteOp::Value(value) => self.put(prefix_name, key.to_vec(), value.to_vec())?,
-                    WriteOp::Deletion => self.remove(prefix_name, key.to_vec())?,
+                    WriteOp::Value(value) => {
+                        cache.put(key, value);
+                    }
+                    WriteOp::Deletion => {
+                        cache.pop(key);
+                    }
                 };
             }
+            if let Some(metrics) = self.metrics.as_ref() {
+                metrics.cache_items.set(cache.cache.len() as u64);
+            }
             Ok(())
         })
     }
 
     fn get_len(&self) -> Result<u64, Error> {
-        Ok(self.cache.lock().len() as u64)
+        Ok(self.cache.lock().cache.len() as u64)
     }
 
     fn keys(&self) -> Result<Vec<Vec<u8>>, Error> {
         let mut all_keys = vec![];
-        for (key, _) in self.cache.lock().iter() {
+        for (key, _) in self.cache.lock().cache.iter() {
             all_keys.push(key.to_vec());
         }
+        all_keys.sort();
         Ok(all_keys)
     }
 
@@ -112,6 +348,7 @@ impl InnerStore for CacheStorage {
         let mut result = vec![];
         for key in keys.into_iter() {
             let item = cache
+                .cache
                 .get(&compose_key(prefix_name.to_string(), key))
                 .cloned();
             result.push(item);
@@ -120,10 +357,11 @@ impl InnerStore for CacheStorage {
     }
 }
 
-fn compose_key(prefix_name: String, source_key: Vec<u8>) -> Vec<u8> {
+pub(crate) fn compose_key(prefix_name: String, source_key: Vec<u8>) -> Vec<u8> {
     let temp_vec = prefix_name.as_bytes().to_vec();
-    let mut compose = Vec::with_capacity(temp_vec.len() + source_key.len());
+    let mut compose = Vec::with_capacity(temp_vec.len() + source_key.len() + 1);
     compose.extend(temp_vec);
+    compose.push(KEY_SEPARATOR);
     compose.extend(source_key);
     compose
 }
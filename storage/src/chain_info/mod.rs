@@ -1,11 +1,13 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::storage::{ColumnFamily, InnerStorage, KVStore};
+use crate::storage::{ColumnFamily, InnerStorage, KVStore, WriteBatch};
 use crate::{StorageVersion, CHAIN_INFO_PREFIX_NAME};
-use anyhow::Result;
+use anyhow::{ensure, Result};
+use bcs_ext::BCSCodec;
 use crypto::HashValue;
-use starcoin_types::startup_info::{SnapshotRange, StartupInfo};
+use starcoin_types::block::BlockNumber;
+use starcoin_types::startup_info::{ChainStatus, SnapshotRange, StartupInfo};
 use std::convert::{TryFrom, TryInto};
 
 #[derive(Clone)]
@@ -27,6 +29,9 @@ impl ChainInfoStorage {
     const GENESIS_KEY: &'static str = "genesis";
     const STORAGE_VERSION_KEY: &'static str = "storage_version";
     const SNAPSHOT_RANGE_KEY: &'static str = "snapshot_height";
+    const MAIN_HEAD_STATUS_KEY: &'static str = "main_head_status";
+    const FINALIZED_NUMBER_KEY: &'static str = "finalized_number";
+    const PRUNED_NUMBER_KEY: &'static str = "pruned_number";
 
     pub fn get_startup_info(&self) -> Result<Option<StartupInfo>> {
         self.get(Self::STARTUP_INFO_KEY.as_bytes())
@@ -36,11 +41,58 @@ impl ChainInfoStorage {
             })
     }
 
+    /// The status of the previous main head is removed, it no longer matches the startup info.
     pub fn save_startup_info(&self, startup_info: StartupInfo) -> Result<()> {
-        self.put_sync(
+        let mut batch = WriteBatch::new();
+        batch.put(
             Self::STARTUP_INFO_KEY.as_bytes().to_vec(),
             startup_info.try_into()?,
-        )
+        )?;
+        batch.delete(Self::MAIN_HEAD_STATUS_KEY.as_bytes().to_vec())?;
+        self.write_batch_sync(batch)
+    }
+
+    /// Get the startup info with the status of its main head, both are read at once,
+    /// return None if the head status is not saved by `save_main_head`.
+    pub fn get_main_head(&self) -> Result<Option<(StartupInfo, ChainStatus)>> {
+        let mut values = self.multiple_get(vec![
+            Self::STARTUP_INFO_KEY.as_bytes().to_vec(),
+            Self::MAIN_HEAD_STATUS_KEY.as_bytes().to_vec(),
+        ])?;
+        let head_status = values.pop().flatten();
+        let startup_info = values.pop().flatten();
+        match (startup_info, head_status) {
+            (Some(startup_info), Some(head_status)) => Ok(Some((
+                startup_info.try_into()?,
+                ChainStatus::decode(head_status.as_slice())?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Swap the main head, the startup info and the head status are committed in one batch,
+    /// so the readers of `get_main_head` never see a half updated head.
+    pub fn save_main_head(
+        &self,
+        startup_info: StartupInfo,
+        head_status: ChainStatus,
+    ) -> Result<()> {
+        ensure!(
+            startup_info.main == head_status.head().id(),
+            "The main head {} of startup info mismatch with the head status {}",
+            startup_info.main,
+            head_status.head().id()
+        );
+        let mut batch = WriteBatch::new();
+        batch.put(
+            Self::STARTUP_INFO_KEY.as_bytes().to_vec(),
+            startup_info.try_into()?,
+        )?;
+        batch.put(
+            Self::MAIN_HEAD_STATUS_KEY.as_bytes().to_vec(),
+            head_status.encode()?,
+        )?;
+        self.write_batch_sync(batch)
     }
 
     pub fn get_genesis(&self) -> Result<Option<HashValue>> {
@@ -95,4 +147,34 @@ impl ChainInfoStorage {
             snapshot_range.try_into()?,
         )
     }
+
+    pub fn get_finalized_number(&self) -> Result<Option<BlockNumber>> {
+        self.get(Self::FINALIZED_NUMBER_KEY.as_bytes())
+            .and_then(|bytes| match bytes {
+                Some(bytes) => Ok(Some(BlockNumber::decode(bytes.as_slice())?)),
+                None => Ok(None),
+            })
+    }
+
+    pub fn save_finalized_number(&self, number: BlockNumber) -> Result<()> {
+        self.put_sync(
+            Self::FINALIZED_NUMBER_KEY.as_bytes().to_vec(),
+            number.encode()?,
+        )
+    }
+
+    pub fn get_pruned_number(&self) -> Result<Option<BlockNumber>> {
+        self.get(Self::PRUNED_NUMBER_KEY.as_bytes())
+            .and_then(|bytes| match bytes {
+                Some(bytes) => Ok(Some(BlockNumber::decode(bytes.as_slice())?)),
+                None => Ok(None),
+            })
+    }
+
+    pub fn save_pruned_number(&self, number: BlockNumber) -> Result<()> {
+        self.put_sync(
+            Self::PRUNED_NUMBER_KEY.as_bytes().to_vec(),
+            number.encode()?,
+        )
+    }
 }
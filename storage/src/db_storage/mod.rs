@@ -2,25 +2,158 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::batch::WriteBatch;
-use crate::errors::StorageInitError;
+use crate::cache_storage::compose_key;
+use crate::errors::{StorageError, StorageInitError};
 use crate::metrics::{record_metrics, StorageMetrics};
 use crate::storage::{ColumnFamilyName, InnerStore, KeyCodec, ValueCodec, WriteOp};
-use crate::{StorageVersion, DEFAULT_PREFIX_NAME};
+use crate::{ColumnFamily, StorageVersion, DEFAULT_PREFIX_NAME};
 use anyhow::{ensure, format_err, Error, Result};
-use rocksdb::{Options, ReadOptions, WriteBatch as DBWriteBatch, WriteOptions, DB};
-use starcoin_config::{check_open_fds_limit, RocksdbConfig};
-use std::collections::HashSet;
+use logger::prelude::*;
+use parking_lot::Mutex;
+pub use rocksdb::DBCompressionType;
+use rocksdb::{
+    BlockBasedOptions, Cache, Options, ReadOptions, WriteBatch as DBWriteBatch, WriteOptions, DB,
+};
+use starcoin_config::{check_open_fds_limit, RocksdbConfig, WalMode};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 use std::iter;
 use std::marker::PhantomData;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 const RES_FDS: u64 = 4096;
+/// The key count of a column family is persisted in the default column family with this prefix.
+const CF_LEN_KEY_PREFIX: &[u8] = b"cf_len:";
+/// The max keys read from every column family by the self-test on open.
+const SELF_TEST_SCAN_KEYS: usize = 100;
+
+/// RocksDB options of a column family, the default options keep the RocksDB defaults with Lz4 compression.
+#[derive(Clone, Debug)]
+pub struct ColumnFamilyOptions {
+    /// Size in bytes of the LRU block cache of this column family.
+    pub block_cache_size: Option<usize>,
+    /// Size in bytes of the memtable of this column family.
+    pub write_buffer_size: Option<usize>,
+    pub compression: DBCompressionType,
+    /// Bits per key of the bloom filter, no bloom filter if None.
+    pub bloom_filter_bits: Option<f64>,
+}
+
+impl Default for ColumnFamilyOptions {
+    fn default() -> Self {
+        Self {
+            block_cache_size: None,
+            write_buffer_size: None,
+            compression: DBCompressionType::Lz4,
+            bloom_filter_bits: None,
+        }
+    }
+}
+
+impl ColumnFamilyOptions {
+    fn to_rocksdb_options(&self) -> Result<Options> {
+        let mut cf_opts = Options::default();
+        cf_opts.set_compression_type(self.compression);
+        if let Some(write_buffer_size) = self.write_buffer_size {
+            cf_opts.set_write_buffer_size(write_buffer_size);
+        }
+        if self.block_cache_size.is_some() || self.bloom_filter_bits.is_some() {
+            let mut table_opts = BlockBasedOptions::default();
+            if let Some(block_cache_size) = self.block_cache_size {
+                table_opts.set_block_cache(&Cache::new_lru_cache(block_cache_size)?);
+            }
+            if let Some(bits) = self.bloom_filter_bits {
+                table_opts.set_bloom_filter(bits as _, false);
+            }
+            cf_opts.set_block_based_table_factory(&table_opts);
+        }
+        Ok(cf_opts)
+    }
+}
+
+/// Retry the idempotent reads on the transient errors, the backoff is doubled on every retry.
+/// The writes are not retried, a write may be applied even if it returns an error.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadRetryPolicy {
+    max_retries: u32,
+    backoff: Duration,
+}
+
+impl ReadRetryPolicy {
+    pub fn new(max_retries: u32, backoff: Duration) -> Self {
+        Self {
+            max_retries,
+            backoff,
+        }
+    }
+
+    pub fn from_config(config: &RocksdbConfig) -> Self {
+        Self::new(
+            config.read_retries,
+            Duration::from_millis(config.read_retry_backoff),
+        )
+    }
+
+    pub fn call<T, F>(&self, mut read: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T, StorageError>,
+    {
+        let mut retries = 0u32;
+        let mut backoff = self.backoff;
+        loop {
+            match read() {
+                Ok(value) => return Ok(value),
+                Err(err) if err.is_transient() && retries < self.max_retries => {
+                    retries = retries.saturating_add(1);
+                    warn!(
+                        "Retry the read after {:?}, retries: {}, error: {}",
+                        backoff, retries, err
+                    );
+                    std::thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+/// The metrics of a column family, a snapshot taken by `DBStorage::cf_metrics`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ColumnFamilyMetrics {
+    /// The reads since the db is opened, every key of a `multi_get` is counted.
+    pub read_ops: u64,
+    /// The writes since the db is opened, every row of a batch is counted.
+    pub write_ops: u64,
+    /// The approximate size in bytes of the sst files, the memtables are not included.
+    pub size_bytes: u64,
+    /// The key count of the column family, estimated by RocksDB for the default column family.
+    pub entries: u64,
+}
+
+#[derive(Default)]
+struct ColumnFamilyOps {
+    reads: AtomicU64,
+    writes: AtomicU64,
+}
 
 #[allow(clippy::upper_case_acronyms)]
 pub struct DBStorage {
     db: DB,
     cfs: Vec<ColumnFamilyName>,
     metrics: Option<StorageMetrics>,
+    /// The key count of every column family, updated in the same db write as the keys.
+    /// `None` if the count is found inconsistent, it's reconciled by scanning the column family
+    /// on `get_len`.
+    cf_lens: HashMap<ColumnFamilyName, Mutex<Option<u64>>>,
+    readonly: bool,
+    read_retry: ReadRetryPolicy,
+    wal_mode: WalMode,
+    cf_ops: HashMap<ColumnFamilyName, ColumnFamilyOps>,
+    /// Serialize the `remove_returning` of the default column family, which has no key count lock.
+    default_remove_lock: Mutex<()>,
 }
 
 impl DBStorage {
@@ -48,6 +181,26 @@ impl DBStorage {
         readonly: bool,
         rocksdb_config: RocksdbConfig,
         metrics: Option<StorageMetrics>,
+    ) -> Result<Self> {
+        Self::open_with_cfs_options(
+            root_path,
+            column_families,
+            HashMap::new(),
+            readonly,
+            rocksdb_config,
+            metrics,
+        )
+    }
+
+    /// Open the db with the options of column families, the column family not in `cf_options`
+    /// is opened with the default `ColumnFamilyOptions`.
+    pub fn open_with_cfs_options(
+        root_path: impl AsRef<Path>,
+        column_families: Vec<ColumnFamilyName>,
+        cf_options: HashMap<ColumnFamilyName, ColumnFamilyOptions>,
+        readonly: bool,
+        rocksdb_config: RocksdbConfig,
+        metrics: Option<StorageMetrics>,
     ) -> Result<Self> {
         let path = root_path.as_ref();
 
@@ -58,7 +211,19 @@ impl DBStorage {
                 "Duplicate column family name found.",
             );
         }
-        if Self::db_exists(path) {
+        let unregistered_cfs: Vec<_> = column_families
+            .iter()
+            .filter(|cf_name| ColumnFamily::from_name(cf_name).is_none())
+            .collect();
+        ensure!(
+            unregistered_cfs.is_empty(),
+            StorageInitError::StorageCheckError(format_err!(
+                "ColumnFamily {:?} is not registered in ColumnFamily.",
+                unregistered_cfs
+            ))
+        );
+        let db_exists = Self::db_exists(path);
+        if db_exists {
             let cf_vec = Self::list_cf(path)?;
             let mut db_cfs_set: HashSet<_> = cf_vec.iter().collect();
             db_cfs_set.remove(&DEFAULT_PREFIX_NAME.to_string());
@@ -93,41 +258,146 @@ impl DBStorage {
         } else {
             rocksdb_opts.create_if_missing(true);
             rocksdb_opts.create_missing_column_families(true);
-            Self::open_inner(&rocksdb_opts, path, column_families.clone())?
+            Self::open_inner(&rocksdb_opts, path, column_families.clone(), &cf_options)?
         };
         check_open_fds_limit(rocksdb_config.max_open_files as u64 + RES_FDS)?;
-        Ok(DBStorage {
+        let cf_lens = Self::load_cf_lens(&db, &column_families, db_exists, readonly)?;
+        let cf_ops = column_families
+            .iter()
+            .map(|cf_name| (*cf_name, ColumnFamilyOps::default()))
+            .collect();
+        let storage = DBStorage {
             db,
             cfs: column_families,
             metrics,
-        })
+            cf_lens,
+            readonly,
+            read_retry: ReadRetryPolicy::from_config(&rocksdb_config),
+            wal_mode: rocksdb_config.wal_mode,
+            cf_ops,
+            default_remove_lock: Mutex::new(()),
+        };
+        if db_exists && rocksdb_config.self_test {
+            storage.self_test()?;
+        }
+        Ok(storage)
+    }
+
+    /// Scan the first keys of every column family with the checksums verified. The key counts
+    /// in the default column family are already decoded by `load_cf_lens`, so a corrupted db
+    /// fails at open instead of deep in a later read.
+    pub fn self_test(&self) -> Result<()> {
+        for cf_name in &self.cfs {
+            let cf_handle = self.get_cf_handle(cf_name)?;
+            let mut read_opts = ReadOptions::default();
+            read_opts.set_verify_checksums(true);
+            read_opts.fill_cache(false);
+            let mut iter = self.db.raw_iterator_cf_opt(cf_handle, read_opts);
+            iter.seek_to_first();
+            let mut scanned = 0usize;
+            while iter.valid() && scanned < SELF_TEST_SCAN_KEYS {
+                scanned = scanned.saturating_add(1);
+                iter.next();
+            }
+            iter.status().map_err(|e| {
+                StorageInitError::StorageCheckError(format_err!(
+                    "ColumnFamily {} self-test failed: {}",
+                    cf_name,
+                    e
+                ))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Load the persisted key counts, the count of a new db is 0. The count of a db written
+    /// before the counts are persisted is reconciled by scanning the column family once, and
+    /// persisted unless the db is opened readonly.
+    fn load_cf_lens(
+        db: &DB,
+        column_families: &[ColumnFamilyName],
+        db_exists: bool,
+        readonly: bool,
+    ) -> Result<HashMap<ColumnFamilyName, Mutex<Option<u64>>>> {
+        let default_cf = db
+            .cf_handle(DEFAULT_PREFIX_NAME)
+            .ok_or_else(|| format_err!("DB::cf_handle not found for default column family"))?;
+        column_families
+            .iter()
+            .filter(|cf_name| **cf_name != DEFAULT_PREFIX_NAME)
+            .map(|cf_name| {
+                let len = match db.get_cf(default_cf, Self::cf_len_key(cf_name))? {
+                    Some(value) => Self::decode_cf_len(&value)?,
+                    None if db_exists => {
+                        let cf_handle = db.cf_handle(cf_name).ok_or_else(|| {
+                            format_err!("DB::cf_handle not found for column family {}", cf_name)
+                        })?;
+                        let len = Self::scan_cf_len(db, cf_handle)?;
+                        if !readonly {
+                            db.put_cf(default_cf, Self::cf_len_key(cf_name), len.to_be_bytes())?;
+                        }
+                        len
+                    }
+                    None => 0,
+                };
+                Ok((*cf_name, Mutex::new(Some(len))))
+            })
+            .collect()
+    }
+
+    /// Count the keys of the column family by a full scan.
+    fn scan_cf_len(db: &DB, cf_handle: &rocksdb::ColumnFamily) -> Result<u64> {
+        let mut iter = db.raw_iterator_cf(cf_handle);
+        iter.seek_to_first();
+        let mut len = 0u64;
+        while iter.valid() {
+            len = len.saturating_add(1);
+            iter.next();
+        }
+        iter.status()?;
+        Ok(len)
+    }
+
+    pub(crate) fn cf_len_key(cf_name: &str) -> Vec<u8> {
+        [CF_LEN_KEY_PREFIX, cf_name.as_bytes()].concat()
+    }
+
+    fn decode_cf_len(value: &[u8]) -> Result<u64> {
+        Ok(u64::from_be_bytes(value.try_into().map_err(|_| {
+            format_err!("invalid column family length: {:?}", value)
+        })?))
     }
 
     fn open_inner(
         opts: &Options,
         path: impl AsRef<Path>,
         column_families: Vec<ColumnFamilyName>,
+        cf_options: &HashMap<ColumnFamilyName, ColumnFamilyOptions>,
     ) -> Result<DB> {
-        let inner = rocksdb::DB::open_cf_descriptors(
-            opts,
-            path,
-            column_families.iter().map(|cf_name| {
-                let mut cf_opts = rocksdb::Options::default();
-                cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
-                /*
-                cf_opts.set_compression_per_level(&[
-                    rocksdb::DBCompressionType::None,
-                    rocksdb::DBCompressionType::None,
-                    rocksdb::DBCompressionType::Lz4,
-                    rocksdb::DBCompressionType::Lz4,
-                    rocksdb::DBCompressionType::Lz4,
-                    rocksdb::DBCompressionType::Lz4,
-                    rocksdb::DBCompressionType::Lz4,
-                ]);
-                */
-                rocksdb::ColumnFamilyDescriptor::new((*cf_name).to_string(), cf_opts)
-            }),
-        )?;
+        let default_cf_options = ColumnFamilyOptions::default();
+        let mut descriptors = Vec::with_capacity(column_families.len());
+        for cf_name in column_families {
+            let cf_opts = cf_options
+                .get(cf_name)
+                .unwrap_or(&default_cf_options)
+                .to_rocksdb_options()?;
+            /*
+            cf_opts.set_compression_per_level(&[
+                rocksdb::DBCompressionType::None,
+                rocksdb::DBCompressionType::None,
+                rocksdb::DBCompressionType::Lz4,
+                rocksdb::DBCompressionType::Lz4,
+                rocksdb::DBCompressionType::Lz4,
+                rocksdb::DBCompressionType::Lz4,
+                rocksdb::DBCompressionType::Lz4,
+            ]);
+            */
+            descriptors.push(rocksdb::ColumnFamilyDescriptor::new(
+                cf_name.to_string(),
+                cf_opts,
+            ));
+        }
+        let inner = rocksdb::DB::open_cf_descriptors(opts, path, descriptors)?;
         Ok(inner)
     }
 
@@ -147,8 +417,15 @@ impl DBStorage {
     }
 
     pub fn drop_cf(&mut self) -> Result<(), Error> {
+        let default_cf = self.get_cf_handle(DEFAULT_PREFIX_NAME)?;
+        for cf in &self.cfs {
+            self.db.delete_cf(default_cf, Self::cf_len_key(cf))?;
+        }
         for cf in self.cfs.clone() {
             self.db.drop_cf(cf)?;
+            if let Some(cf_len) = self.cf_lens.get(cf) {
+                *cf_len.lock() = None;
+            }
         }
         Ok(())
     }
@@ -161,6 +438,15 @@ impl DBStorage {
                     self.db.drop_cf(name)?;
                     let opt = Options::default();
                     self.db.create_cf(name, &opt)?;
+                    if let Some(cf_len) = self.cf_lens.get(name) {
+                        let default_cf = self
+                            .db
+                            .cf_handle(DEFAULT_PREFIX_NAME)
+                            .ok_or_else(|| format_err!("default column family not found"))?;
+                        self.db
+                            .put_cf(default_cf, Self::cf_len_key(name), 0u64.to_be_bytes())?;
+                        *cf_len.lock() = Some(0);
+                    }
                     break;
                 }
             }
@@ -168,8 +454,7 @@ impl DBStorage {
         Ok(())
     }
 
-    /// Flushes all memtable data. This is only used for testing `get_approximate_sizes_cf` in unit
-    /// tests.
+    /// Flushes all memtable data of every column family.
     pub fn flush_all(&self) -> Result<()> {
         for cf_name in &self.cfs {
             let cf_handle = self.get_cf_handle(cf_name)?;
@@ -178,6 +463,87 @@ impl DBStorage {
         Ok(())
     }
 
+    /// Make the written data durable by the WAL mode. The writes of the `Sync` mode are durable
+    /// once they return, the WAL of the `Async` mode is synced to disk. Only without the WAL the
+    /// memtables are flushed, which leaves a small sst file for every column family.
+    pub fn flush(&self) -> Result<()> {
+        match self.wal_mode {
+            WalMode::Sync => Ok(()),
+            WalMode::Async => Ok(self.db.flush_wal(true)?),
+            WalMode::Disabled => self.flush_all(),
+        }
+    }
+
+    pub fn wal_mode(&self) -> WalMode {
+        self.wal_mode
+    }
+
+    fn record_reads(&self, prefix_name: &str, count: usize) {
+        if let Some(ops) = self.cf_ops.get(prefix_name) {
+            ops.reads.fetch_add(count as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn record_writes(&self, prefix_name: &str, count: usize) {
+        if let Some(ops) = self.cf_ops.get(prefix_name) {
+            ops.writes.fetch_add(count as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// A snapshot of the metrics of every column family, the size is read from the RocksDB
+    /// properties, so the writes not flushed from the memtables are not in it.
+    pub fn cf_metrics(&self) -> Result<HashMap<ColumnFamilyName, ColumnFamilyMetrics>> {
+        let mut metrics = HashMap::with_capacity(self.cfs.len());
+        for cf_name in &self.cfs {
+            let cf_handle = self.get_cf_handle(cf_name)?;
+            let size_bytes = self
+                .db
+                .property_int_value_cf(cf_handle, "rocksdb.total-sst-files-size")?
+                .unwrap_or(0);
+            let entries = if self.cf_lens.contains_key(cf_name) {
+                self.get_cf_len(cf_name)?
+            } else {
+                self.db
+                    .property_int_value_cf(cf_handle, "rocksdb.estimate-num-keys")?
+                    .unwrap_or(0)
+            };
+            let (read_ops, write_ops) = match self.cf_ops.get(cf_name) {
+                Some(ops) => (
+                    ops.reads.load(Ordering::Relaxed),
+                    ops.writes.load(Ordering::Relaxed),
+                ),
+                None => (0, 0),
+            };
+            metrics.insert(
+                *cf_name,
+                ColumnFamilyMetrics {
+                    read_ops,
+                    write_ops,
+                    size_bytes,
+                    entries,
+                },
+            );
+        }
+        Ok(metrics)
+    }
+
+    /// Compact the keys in `[start, end)` of the column family, `None` means the start or end of
+    /// the column family. The tombstones of the deleted keys are dropped by the compaction.
+    pub fn compact_range(
+        &self,
+        prefix_name: &str,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> Result<()> {
+        let cf_handle = self.get_cf_handle(prefix_name)?;
+        self.db.compact_range_cf(cf_handle, start, end);
+        Ok(())
+    }
+
+    pub(crate) fn column_families(&self) -> &[ColumnFamilyName] {
+        &self.cfs
+    }
+
     /// List cf
     pub fn list_cf(path: impl AsRef<Path>) -> Result<Vec<String>, Error> {
         Ok(rocksdb::DB::list_cf(&rocksdb::Options::default(), path)?)
@@ -197,12 +563,34 @@ impl DBStorage {
         })
     }
 
-    fn default_write_options() -> WriteOptions {
+    /// The options of a write, `sync` requires the write is durable once it returns.
+    fn write_options(&self, sync: bool) -> WriteOptions {
         let mut opts = WriteOptions::new();
-        opts.set_sync(false);
+        match self.wal_mode {
+            WalMode::Async => opts.set_sync(sync),
+            WalMode::Sync => opts.set_sync(true),
+            WalMode::Disabled => opts.disable_wal(true),
+        }
         opts
     }
 
+    fn write_opt(&self, db_batch: DBWriteBatch, sync: bool) -> Result<()> {
+        Ok(self.db.write_opt(db_batch, &self.write_options(sync))?)
+    }
+
+    /// Without the WAL, a sync write is only durable after the memtables of the column family
+    /// and the key counts are flushed. It's called after the key count lock is released, so the
+    /// other writes of the column family do not wait for the flush.
+    fn flush_if_sync(&self, prefix_name: &str, sync: bool) -> Result<()> {
+        if sync && self.wal_mode == WalMode::Disabled {
+            self.db.flush_cf(self.get_cf_handle(prefix_name)?)?;
+            if prefix_name != DEFAULT_PREFIX_NAME {
+                self.db.flush_cf(self.get_cf_handle(DEFAULT_PREFIX_NAME)?)?;
+            }
+        }
+        Ok(())
+    }
+
     fn gen_rocksdb_options(config: &RocksdbConfig) -> Options {
         let mut db_opts = Options::default();
         db_opts.set_max_open_files(config.max_open_files);
@@ -253,10 +641,207 @@ impl DBStorage {
         self.iter_with_direction(prefix_name, ScanDirection::Backward)
     }
 
-    fn sync_write_options() -> WriteOptions {
-        let mut opts = WriteOptions::new();
-        opts.set_sync(true);
-        opts
+    /// Write the `batch` to the column family, the key count of the column family is updated in the
+    /// same db write, so the count is consistent with the keys after a crash.
+    fn write_batch_with_len(&self, prefix_name: &str, batch: WriteBatch, sync: bool) -> Result<()> {
+        let cf_handle = self.get_cf_handle(prefix_name)?;
+        self.record_writes(prefix_name, batch.rows.len());
+        let mut db_batch = DBWriteBatch::default();
+        for (key, write_op) in &batch.rows {
+            match write_op {
+                WriteOp::Value(value) => db_batch.put_cf(cf_handle, key, value),
+                WriteOp::Deletion => db_batch.delete_cf(cf_handle, key),
+            };
+        }
+        match self.cf_lens.get(prefix_name) {
+            Some(cf_len) => {
+                // hold the lock of the column family until the write is done, so the concurrent
+                // writes of the column family do not lose the updates of the count. The writes
+                // of the other column families do not wait for it.
+                let mut cf_len = cf_len.lock();
+                let new_len = match *cf_len {
+                    Some(len) => self.len_after_batch(cf_handle, len, &batch)?,
+                    None => None,
+                };
+                self.put_cf_len(&mut db_batch, prefix_name, new_len)?;
+                self.write_opt(db_batch, sync)?;
+                *cf_len = new_len;
+            }
+            None => self.write_opt(db_batch, sync)?,
+        }
+        self.flush_if_sync(prefix_name, sync)
+    }
+
+    /// Write the key count of the column family in `db_batch`, the unknown count is deleted.
+    fn put_cf_len(
+        &self,
+        db_batch: &mut DBWriteBatch,
+        prefix_name: &str,
+        new_len: Option<u64>,
+    ) -> Result<()> {
+        let default_cf = self.get_cf_handle(DEFAULT_PREFIX_NAME)?;
+        match new_len {
+            Some(new_len) => db_batch.put_cf(
+                default_cf,
+                Self::cf_len_key(prefix_name),
+                new_len.to_be_bytes(),
+            ),
+            None => db_batch.delete_cf(default_cf, Self::cf_len_key(prefix_name)),
+        }
+        Ok(())
+    }
+
+    /// Remove the key and return its value before the removal. Only the lock of the key count
+    /// of the column family is held from the read to the delete, every write of the column family
+    /// takes it for the count, so no write of the column family is between them, and the other
+    /// column families are not blocked. The default column family has no key count, its lock only
+    /// serializes the removals, a write of the key between the read and the delete is lost.
+    pub fn remove_returning(&self, prefix_name: &str, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        let cf_handle = self.get_cf_handle(prefix_name)?;
+        let mut db_batch = DBWriteBatch::default();
+        db_batch.delete_cf(cf_handle, key.as_slice());
+        match self.cf_lens.get(prefix_name) {
+            Some(cf_len) => {
+                let mut cf_len = cf_len.lock();
+                let value = self.get_for_remove(prefix_name, cf_handle, &key)?;
+                if value.is_some() {
+                    let new_len = cf_len.and_then(|len| len.checked_sub(1));
+                    self.put_cf_len(&mut db_batch, prefix_name, new_len)?;
+                    self.write_opt(db_batch, false)?;
+                    *cf_len = new_len;
+                }
+                Ok(value)
+            }
+            None => {
+                let _guard = self.default_remove_lock.lock();
+                let value = self.get_for_remove(prefix_name, cf_handle, &key)?;
+                if value.is_some() {
+                    self.write_opt(db_batch, false)?;
+                }
+                Ok(value)
+            }
+        }
+    }
+
+    /// Read the value of the key to remove, the removal is recorded as a write if it exists.
+    fn get_for_remove(
+        &self,
+        prefix_name: &str,
+        cf_handle: &rocksdb::ColumnFamily,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>> {
+        self.record_reads(prefix_name, 1);
+        let value = self.db.get_cf(cf_handle, key)?;
+        if value.is_some() {
+            self.record_writes(prefix_name, 1);
+        }
+        Ok(value)
+    }
+
+    /// The key count after the `batch` is written, `None` if the count is found inconsistent.
+    fn len_after_batch(
+        &self,
+        cf_handle: &rocksdb::ColumnFamily,
+        len: u64,
+        batch: &WriteBatch,
+    ) -> Result<Option<u64>> {
+        let mut len = len;
+        // a key may be written more than once in a batch.
+        let mut exists: HashMap<&[u8], bool> = HashMap::new();
+        for (key, write_op) in &batch.rows {
+            let existed = match exists.get(key.as_slice()) {
+                Some(existed) => *existed,
+                None => self.db.get_pinned_cf(cf_handle, key)?.is_some(),
+            };
+            let exist = matches!(write_op, WriteOp::Value(_));
+            if !existed && exist {
+                len = len.saturating_add(1);
+            } else if existed && !exist {
+                match len.checked_sub(1) {
+                    Some(new_len) => len = new_len,
+                    None => return Ok(None),
+                }
+            }
+            exists.insert(key.as_slice(), exist);
+        }
+        Ok(Some(len))
+    }
+
+    /// Get the key count of the column family, the inconsistent count is reconciled by a full
+    /// scan.
+    pub fn get_cf_len(&self, cf_name: &str) -> Result<u64> {
+        let cf_len = self
+            .cf_lens
+            .get(cf_name)
+            .ok_or_else(|| format_err!("no key count of column family {}", cf_name))?;
+        let mut cf_len = cf_len.lock();
+        if let Some(len) = *cf_len {
+            return Ok(len);
+        }
+        let len = Self::scan_cf_len(&self.db, self.get_cf_handle(cf_name)?)?;
+        if !self.readonly {
+            let default_cf = self.get_cf_handle(DEFAULT_PREFIX_NAME)?;
+            self.db
+                .put_cf(default_cf, Self::cf_len_key(cf_name), len.to_be_bytes())?;
+        }
+        *cf_len = Some(len);
+        Ok(len)
+    }
+
+    /// Count the keys of the column family which start with `key_prefix`, by a prefix scan.
+    pub fn count_prefix(&self, cf_name: &str, key_prefix: &[u8]) -> Result<u64> {
+        let cf_handle = self.get_cf_handle(cf_name)?;
+        let mut iter = self.db.raw_iterator_cf(cf_handle);
+        iter.seek(key_prefix);
+        let mut count = 0u64;
+        while let Some(key) = iter.key() {
+            if !key.starts_with(key_prefix) {
+                break;
+            }
+            count = count.saturating_add(1);
+            iter.next();
+        }
+        iter.status()?;
+        Ok(count)
+    }
+
+    /// The entries of the column family whose keys start with `key_prefix`, ordered by key.
+    pub fn scan_prefix(&self, cf_name: &str, key_prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let cf_handle = self.get_cf_handle(cf_name)?;
+        let mut iter = self.db.raw_iterator_cf(cf_handle);
+        iter.seek(key_prefix);
+        let mut entries = vec![];
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if !key.starts_with(key_prefix) {
+                break;
+            }
+            entries.push((key.to_vec(), value.to_vec()));
+            iter.next();
+        }
+        iter.status()?;
+        Ok(entries)
+    }
+
+    /// The entries of the column family whose keys are in `start..end`, ordered by key.
+    pub fn scan_range(
+        &self,
+        cf_name: &str,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let cf_handle = self.get_cf_handle(cf_name)?;
+        let mut iter = self.db.raw_iterator_cf(cf_handle);
+        iter.seek(start);
+        let mut entries = vec![];
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if key >= end {
+                break;
+            }
+            entries.push((key.to_vec(), value.to_vec()));
+            iter.next();
+        }
+        iter.status()?;
+        Ok(entries)
     }
 }
 
@@ -345,8 +930,12 @@ impl InnerStore for DBStorage {
     fn get(&self, prefix_name: &str, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
         record_metrics("db", prefix_name, "get", self.metrics.as_ref()).call(|| {
             let cf_handle = self.get_cf_handle(prefix_name)?;
-            let result = self.db.get_cf(cf_handle, key.as_slice())?;
-            Ok(result)
+            self.record_reads(prefix_name, 1);
+            self.read_retry.call(|| {
+                self.db
+                    .get_cf(cf_handle, key.as_slice())
+                    .map_err(StorageError::from)
+            })
         })
     }
 
@@ -359,10 +948,11 @@ impl InnerStore for DBStorage {
         }
 
         record_metrics("db", prefix_name, "put", self.metrics.as_ref()).call(|| {
-            let cf_handle = self.get_cf_handle(prefix_name)?;
-            self.db
-                .put_cf_opt(cf_handle, &key, &value, &Self::default_write_options())?;
-            Ok(())
+            self.write_batch_with_len(
+                prefix_name,
+                WriteBatch::new_with_rows(vec![(key, WriteOp::Value(value))]),
+                false,
+            )
         })
     }
 
@@ -376,35 +966,45 @@ impl InnerStore for DBStorage {
     }
     fn remove(&self, prefix_name: &str, key: Vec<u8>) -> Result<()> {
         record_metrics("db", prefix_name, "remove", self.metrics.as_ref()).call(|| {
-            let cf_handle = self.get_cf_handle(prefix_name)?;
-            self.db.delete_cf(cf_handle, &key)?;
-            Ok(())
+            self.write_batch_with_len(
+                prefix_name,
+                WriteBatch::new_with_rows(vec![(key, WriteOp::Deletion)]),
+                false,
+            )
         })
     }
 
     /// Writes a group of records wrapped in a WriteBatch.
     fn write_batch(&self, prefix_name: &str, batch: WriteBatch) -> Result<()> {
-        record_metrics("db", prefix_name, "write_batch", self.metrics.as_ref()).call(|| {
-            let mut db_batch = DBWriteBatch::default();
-            let cf_handle = self.get_cf_handle(prefix_name)?;
-            for (key, write_op) in &batch.rows {
-                match write_op {
-                    WriteOp::Value(value) => db_batch.put_cf(cf_handle, key, value),
-                    WriteOp::Deletion => db_batch.delete_cf(cf_handle, key),
-                };
-            }
-            self.db
-                .write_opt(db_batch, &Self::default_write_options())?;
-            Ok(())
-        })
+        record_metrics("db", prefix_name, "write_batch", self.metrics.as_ref())
+            .call(|| self.write_batch_with_len(prefix_name, batch, false))
     }
 
+    /// The count of keys of all the column families except the default one.
     fn get_len(&self) -> Result<u64> {
-        unimplemented!()
+        let mut len = 0u64;
+        for cf_name in self.cf_lens.keys() {
+            len = len.saturating_add(self.get_cf_len(cf_name)?);
+        }
+        Ok(len)
     }
 
+    /// The keys of all the column families composed the same as `CacheStorage`, by full scans.
     fn keys(&self) -> Result<Vec<Vec<u8>>> {
-        unimplemented!()
+        let mut all_keys = vec![];
+        for cf_name in &self.cfs {
+            let cf_handle = self.get_cf_handle(cf_name)?;
+            let mut iter = self.db.raw_iterator_cf(cf_handle);
+            iter.seek_to_first();
+            while let Some(key) = iter.key() {
+                all_keys.push(compose_key(cf_name.to_string(), key.to_vec()));
+                iter.next();
+            }
+            iter.status()?;
+        }
+        // the column families are not in name order.
+        all_keys.sort();
+        Ok(all_keys)
     }
 
     fn put_sync(&self, prefix_name: &str, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
@@ -416,31 +1016,23 @@ impl InnerStore for DBStorage {
         }
 
         record_metrics("db", prefix_name, "put_sync", self.metrics.as_ref()).call(|| {
-            let cf_handle = self.get_cf_handle(prefix_name)?;
-            self.db
-                .put_cf_opt(cf_handle, &key, &value, &Self::sync_write_options())?;
-            Ok(())
+            self.write_batch_with_len(
+                prefix_name,
+                WriteBatch::new_with_rows(vec![(key, WriteOp::Value(value))]),
+                true,
+            )
         })
     }
 
     fn write_batch_sync(&self, prefix_name: &str, batch: WriteBatch) -> Result<()> {
-        record_metrics("db", prefix_name, "write_batch_sync", self.metrics.as_ref()).call(|| {
-            let mut db_batch = DBWriteBatch::default();
-            let cf_handle = self.get_cf_handle(prefix_name)?;
-            for (key, write_op) in &batch.rows {
-                match write_op {
-                    WriteOp::Value(value) => db_batch.put_cf(cf_handle, key, value),
-                    WriteOp::Deletion => db_batch.delete_cf(cf_handle, key),
-                };
-            }
-            self.db.write_opt(db_batch, &Self::sync_write_options())?;
-            Ok(())
-        })
+        record_metrics("db", prefix_name, "write_batch_sync", self.metrics.as_ref())
+            .call(|| self.write_batch_with_len(prefix_name, batch, true))
     }
 
     fn multi_get(&self, prefix_name: &str, keys: Vec<Vec<u8>>) -> Result<Vec<Option<Vec<u8>>>> {
         record_metrics("db", prefix_name, "multi_get", self.metrics.as_ref()).call(|| {
             let cf_handle = self.get_cf_handle(prefix_name)?;
+            self.record_reads(prefix_name, keys.len());
             let cf_handles = iter::repeat(&cf_handle)
                 .take(keys.len())
                 .collect::<Vec<_>>();
@@ -450,13 +1042,13 @@ impl InnerStore for DBStorage {
                 .map(|(key, handle)| (handle, key.as_slice()))
                 .collect::<Vec<_>>();
 
-            let result = self.db.multi_get_cf(keys_multi);
-            let mut res = vec![];
-            for item in result {
-                let item = item?;
-                res.push(item);
-            }
-            Ok(res)
+            self.read_retry.call(|| {
+                self.db
+                    .multi_get_cf(keys_multi.clone())
+                    .into_iter()
+                    .map(|item| item.map_err(StorageError::from))
+                    .collect()
+            })
         })
     }
 }
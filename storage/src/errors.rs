@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use anyhow::Error;
+use rocksdb::ErrorKind;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -9,3 +10,34 @@ pub enum StorageInitError {
     #[error("Storage check error {0:?}.")]
     StorageCheckError(Error),
 }
+
+/// The classification of the storage errors, a transient error may succeed if it's retried,
+/// such as the busy error of RocksDB during a heavy compaction.
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Transient storage error {0:?}.")]
+    Transient(Error),
+    #[error("Storage error {0:?}.")]
+    Fatal(Error),
+}
+
+impl StorageError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, StorageError::Transient(_))
+    }
+}
+
+impl From<rocksdb::Error> for StorageError {
+    fn from(err: rocksdb::Error) -> Self {
+        let transient = match err.kind() {
+            ErrorKind::Busy | ErrorKind::TryAgain | ErrorKind::TimedOut => true,
+            ErrorKind::IOError => err.to_string().contains("Resource temporarily unavailable"),
+            _ => false,
+        };
+        if transient {
+            StorageError::Transient(err.into())
+        } else {
+            StorageError::Fatal(err.into())
+        }
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::define_storage;
+use crate::storage::{CodecKVStore, CodecWriteBatch, KeyCodec, SchemaStorage, ValueCodec};
+use crate::{impl_bcs_value_codec, EventIndexStore, EVENT_INDEX_PREFIX_NAME};
+use anyhow::{ensure, format_err, Result};
+use crypto::HashValue;
+use serde::{Deserialize, Serialize};
+use starcoin_types::account_address::AccountAddress;
+use starcoin_types::block::BlockNumber;
+use starcoin_types::contract_event::ContractEvent;
+use starcoin_types::event::EventKey;
+use std::collections::BTreeMap;
+
+/// The position of an event on the chain, the event is the `event_index`th event of the txn.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct EventIndex {
+    pub event_key: EventKey,
+    pub txn_info_id: HashValue,
+    pub transaction_global_index: u64,
+    pub event_index: u32,
+}
+
+impl EventIndex {
+    /// The indexes of the `events` emitted by the txn `txn_info_id`.
+    pub fn new_txn_indexes(
+        txn_info_id: HashValue,
+        transaction_global_index: u64,
+        events: &[ContractEvent],
+    ) -> Vec<EventIndex> {
+        events
+            .iter()
+            .enumerate()
+            .map(|(event_index, event)| EventIndex {
+                event_key: *event.key(),
+                txn_info_id,
+                transaction_global_index,
+                event_index: event_index as u32,
+            })
+            .collect()
+    }
+}
+
+/// The events of block `block_id`, emitted by the event keys created by `address`.
+/// The key is encoded as `address ‖ block_number ‖ block_id` with the number in big endian, so
+/// the keys of an address are ordered by the block number, and a range of blocks is read by a
+/// single range scan. The blocks of the forks have the same numbers, the block id keeps them
+/// apart.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct EventIndexKey {
+    pub address: AccountAddress,
+    pub block_number: BlockNumber,
+    pub block_id: HashValue,
+}
+
+impl EventIndexKey {
+    pub fn new(address: AccountAddress, block_number: BlockNumber, block_id: HashValue) -> Self {
+        Self {
+            address,
+            block_number,
+            block_id,
+        }
+    }
+
+    /// The encoded key which is before all the keys of `address` at `block_number`.
+    fn range_bound(address: AccountAddress, block_number: BlockNumber) -> Vec<u8> {
+        let mut key = address.to_vec();
+        key.extend(block_number.to_be_bytes());
+        key
+    }
+}
+
+impl KeyCodec for EventIndexKey {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        let mut key = Self::range_bound(self.address, self.block_number);
+        key.extend(self.block_id.to_vec());
+        Ok(key)
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        ensure!(
+            data.len() == AccountAddress::LENGTH + NUMBER_LENGTH + HashValue::LENGTH,
+            "invalid event index key length: {}",
+            data.len()
+        );
+        let (address, data) = data.split_at(AccountAddress::LENGTH);
+        let (block_number, block_id) = data.split_at(NUMBER_LENGTH);
+        Ok(Self {
+            address: AccountAddress::from_bytes(address)
+                .map_err(|e| format_err!("invalid event index key address: {:?}", e))?,
+            block_number: BlockNumber::from_be_bytes(block_number.try_into()?),
+            block_id: HashValue::from_slice(block_id)?,
+        })
+    }
+}
+
+const NUMBER_LENGTH: usize = std::mem::size_of::<BlockNumber>();
+
+define_storage!(
+    EventIndexStorage,
+    EventIndexKey,
+    Vec<EventIndex>,
+    EVENT_INDEX_PREFIX_NAME
+);
+
+impl_bcs_value_codec!(Vec<EventIndex>);
+
+impl EventIndexStore for EventIndexStorage {
+    fn save_event_indexes(
+        &self,
+        block_number: BlockNumber,
+        block_id: HashValue,
+        indexes: Vec<EventIndex>,
+    ) -> Result<()> {
+        let mut by_address: BTreeMap<AccountAddress, Vec<EventIndex>> = BTreeMap::new();
+        for index in indexes {
+            by_address
+                .entry(index.event_key.get_creator_address())
+                .or_default()
+                .push(index);
+        }
+        self.write_batch(CodecWriteBatch::new_puts(
+            by_address
+                .into_iter()
+                .map(|(address, indexes)| {
+                    (EventIndexKey::new(address, block_number, block_id), indexes)
+                })
+                .collect(),
+        ))
+    }
+
+    fn get_event_indexes(
+        &self,
+        address: AccountAddress,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Vec<(EventIndexKey, Vec<EventIndex>)>> {
+        if from > to {
+            return Ok(vec![]);
+        }
+        let start = EventIndexKey::range_bound(address, from);
+        let end = match to.checked_add(1) {
+            Some(to) => EventIndexKey::range_bound(address, to),
+            // all the keys of the address at the max block number are before the next address.
+            None => {
+                let mut end = EventIndexKey::range_bound(address, to);
+                end.extend([u8::MAX; HashValue::LENGTH + 1]);
+                end
+            }
+        };
+        let store = self.get_store();
+        store
+            .storage()
+            .scan_range(store.prefix_name, &start, &end)?
+            .into_iter()
+            .map(|(key, value)| {
+                Ok((
+                    EventIndexKey::decode_key(&key)?,
+                    Vec::<EventIndex>::decode_value(&value)?,
+                ))
+            })
+            .collect()
+    }
+}
@@ -4,31 +4,38 @@
 use crate::accumulator::{
     AccumulatorStorage, BlockAccumulatorStorage, TransactionAccumulatorStorage,
 };
+use crate::batch::WriteBatch;
 use crate::block::BlockStorage;
 use crate::block_info::{BlockInfoStorage, BlockInfoStore};
 use crate::chain_info::ChainInfoStorage;
 use crate::contract_event::ContractEventStorage;
+use crate::event_index::{EventIndex, EventIndexKey, EventIndexStorage};
 use crate::state_node::StateStorage;
-use crate::storage::{CodecKVStore, CodecWriteBatch, ColumnFamilyName, StorageInstance};
+use crate::storage::{
+    CodecKVStore, CodecWriteBatch, ColumnFamilyName, InnerStore, StorageInstance, WriteOp,
+};
 use crate::transaction::TransactionStorage;
 use crate::transaction_info::{TransactionInfoHashStorage, TransactionInfoStorage};
-use anyhow::{bail, format_err, Error, Result};
+use anyhow::{bail, ensure, format_err, Error, Result};
 use crypto::HashValue;
 use network_types::peer_info::PeerId;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use starcoin_accumulator::node::AccumulatorStoreType;
 use starcoin_accumulator::AccumulatorTreeStore;
 use starcoin_state_store_api::{StateNode, StateNodeStore};
+use starcoin_types::account_address::AccountAddress;
 use starcoin_types::contract_event::ContractEvent;
 use starcoin_types::startup_info::{ChainInfo, ChainStatus, SnapshotRange};
 use starcoin_types::transaction::{RichTransactionInfo, Transaction};
 use starcoin_types::{
-    block::{Block, BlockBody, BlockHeader, BlockInfo},
+    block::{Block, BlockBody, BlockHeader, BlockInfo, BlockNumber},
     startup_info::StartupInfo,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
+use std::iter;
 use std::sync::Arc;
 
 pub mod accumulator;
@@ -40,7 +47,10 @@ pub mod chain_info;
 pub mod contract_event;
 pub mod db_storage;
 pub mod errors;
+pub mod event_index;
+pub mod memory_storage;
 pub mod metrics;
+pub mod overlay_storage;
 pub mod state_node;
 pub mod storage;
 #[cfg(test)]
@@ -51,6 +61,9 @@ mod upgrade;
 
 #[macro_use]
 pub mod storage_macros;
+// used by the exported macros.
+#[doc(hidden)]
+pub use {anyhow, bcs_ext};
 pub const DEFAULT_PREFIX_NAME: ColumnFamilyName = "default";
 pub const BLOCK_ACCUMULATOR_NODE_PREFIX_NAME: ColumnFamilyName = "acc_node_block";
 pub const TRANSACTION_ACCUMULATOR_NODE_PREFIX_NAME: ColumnFamilyName = "acc_node_transaction";
@@ -69,6 +82,99 @@ pub const TRANSACTION_INFO_PREFIX_NAME_V2: ColumnFamilyName = "transaction_info_
 pub const TRANSACTION_INFO_HASH_PREFIX_NAME: ColumnFamilyName = "transaction_info_hash";
 pub const CONTRACT_EVENT_PREFIX_NAME: ColumnFamilyName = "contract_event";
 pub const FAILED_BLOCK_PREFIX_NAME: ColumnFamilyName = "failed_block";
+pub const EVENT_INDEX_PREFIX_NAME: ColumnFamilyName = "event_index";
+
+/// The registry of the column families, `DBStorage` rejects the column family which is not
+/// registered here when the db is opened, so a mistyped name fails at startup instead of
+/// reading or writing an unexpected column family.
+/// The column families of the account db are registered too, it's a `DBStorage` as well.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ColumnFamily {
+    Default,
+    BlockAccumulatorNode,
+    TransactionAccumulatorNode,
+    Block,
+    BlockHeader,
+    BlockBody,
+    BlockInfo,
+    BlockTransactions,
+    BlockTransactionInfos,
+    StateNode,
+    ChainInfo,
+    Transaction,
+    TransactionInfo,
+    TransactionInfoV2,
+    TransactionInfoHash,
+    ContractEvent,
+    FailedBlock,
+    EventIndex,
+    AccountSettings,
+    EncryptedPrivateKey,
+    PublicKey,
+    AcceptedToken,
+    AccountGlobal,
+}
+
+impl ColumnFamily {
+    pub const ALL: [ColumnFamily; 23] = [
+        ColumnFamily::Default,
+        ColumnFamily::BlockAccumulatorNode,
+        ColumnFamily::TransactionAccumulatorNode,
+        ColumnFamily::Block,
+        ColumnFamily::BlockHeader,
+        ColumnFamily::BlockBody,
+        ColumnFamily::BlockInfo,
+        ColumnFamily::BlockTransactions,
+        ColumnFamily::BlockTransactionInfos,
+        ColumnFamily::StateNode,
+        ColumnFamily::ChainInfo,
+        ColumnFamily::Transaction,
+        ColumnFamily::TransactionInfo,
+        ColumnFamily::TransactionInfoV2,
+        ColumnFamily::TransactionInfoHash,
+        ColumnFamily::ContractEvent,
+        ColumnFamily::FailedBlock,
+        ColumnFamily::EventIndex,
+        ColumnFamily::AccountSettings,
+        ColumnFamily::EncryptedPrivateKey,
+        ColumnFamily::PublicKey,
+        ColumnFamily::AcceptedToken,
+        ColumnFamily::AccountGlobal,
+    ];
+
+    pub const fn name(&self) -> ColumnFamilyName {
+        match self {
+            ColumnFamily::Default => DEFAULT_PREFIX_NAME,
+            ColumnFamily::BlockAccumulatorNode => BLOCK_ACCUMULATOR_NODE_PREFIX_NAME,
+            ColumnFamily::TransactionAccumulatorNode => TRANSACTION_ACCUMULATOR_NODE_PREFIX_NAME,
+            ColumnFamily::Block => BLOCK_PREFIX_NAME,
+            ColumnFamily::BlockHeader => BLOCK_HEADER_PREFIX_NAME,
+            ColumnFamily::BlockBody => BLOCK_BODY_PREFIX_NAME,
+            ColumnFamily::BlockInfo => BLOCK_INFO_PREFIX_NAME,
+            ColumnFamily::BlockTransactions => BLOCK_TRANSACTIONS_PREFIX_NAME,
+            ColumnFamily::BlockTransactionInfos => BLOCK_TRANSACTION_INFOS_PREFIX_NAME,
+            ColumnFamily::StateNode => STATE_NODE_PREFIX_NAME,
+            ColumnFamily::ChainInfo => CHAIN_INFO_PREFIX_NAME,
+            ColumnFamily::Transaction => TRANSACTION_PREFIX_NAME,
+            ColumnFamily::TransactionInfo => TRANSACTION_INFO_PREFIX_NAME,
+            ColumnFamily::TransactionInfoV2 => TRANSACTION_INFO_PREFIX_NAME_V2,
+            ColumnFamily::TransactionInfoHash => TRANSACTION_INFO_HASH_PREFIX_NAME,
+            ColumnFamily::ContractEvent => CONTRACT_EVENT_PREFIX_NAME,
+            ColumnFamily::FailedBlock => FAILED_BLOCK_PREFIX_NAME,
+            ColumnFamily::EventIndex => EVENT_INDEX_PREFIX_NAME,
+            ColumnFamily::AccountSettings => "account_settings",
+            ColumnFamily::EncryptedPrivateKey => "encrypted_private_key",
+            ColumnFamily::PublicKey => "public_key",
+            ColumnFamily::AcceptedToken => "accepted_token",
+            ColumnFamily::AccountGlobal => "global",
+        }
+    }
+
+    /// Get the registered column family by name, return None if the name is not registered.
+    pub fn from_name(name: &str) -> Option<ColumnFamily> {
+        Self::ALL.iter().find(|cf| cf.name() == name).copied()
+    }
+}
 
 ///db storage use prefix_name vec to init
 /// Please note that adding a prefix needs to be added in vec simultaneously, remember！！
@@ -133,17 +239,40 @@ static VEC_PREFIX_NAME_V3: Lazy<Vec<ColumnFamilyName>> = Lazy::new(|| {
         FAILED_BLOCK_PREFIX_NAME,
     ]
 });
+
+static VEC_PREFIX_NAME_V4: Lazy<Vec<ColumnFamilyName>> = Lazy::new(|| {
+    vec![
+        BLOCK_ACCUMULATOR_NODE_PREFIX_NAME,
+        TRANSACTION_ACCUMULATOR_NODE_PREFIX_NAME,
+        BLOCK_PREFIX_NAME,
+        BLOCK_HEADER_PREFIX_NAME,
+        BLOCK_BODY_PREFIX_NAME, // unused column
+        BLOCK_INFO_PREFIX_NAME,
+        BLOCK_TRANSACTIONS_PREFIX_NAME,
+        BLOCK_TRANSACTION_INFOS_PREFIX_NAME,
+        STATE_NODE_PREFIX_NAME,
+        CHAIN_INFO_PREFIX_NAME,
+        TRANSACTION_PREFIX_NAME,
+        TRANSACTION_INFO_PREFIX_NAME, // unused column
+        TRANSACTION_INFO_PREFIX_NAME_V2,
+        TRANSACTION_INFO_HASH_PREFIX_NAME,
+        CONTRACT_EVENT_PREFIX_NAME,
+        FAILED_BLOCK_PREFIX_NAME,
+        EVENT_INDEX_PREFIX_NAME,
+    ]
+});
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, IntoPrimitive, TryFromPrimitive)]
 #[repr(u8)]
 pub enum StorageVersion {
     V1 = 1,
     V2 = 2,
     V3 = 3,
+    V4 = 4,
 }
 
 impl StorageVersion {
     pub fn current_version() -> StorageVersion {
-        StorageVersion::V3
+        StorageVersion::V4
     }
 
     pub fn get_column_family_names(&self) -> &'static [ColumnFamilyName] {
@@ -151,6 +280,7 @@ impl StorageVersion {
             StorageVersion::V1 => &VEC_PREFIX_NAME_V1,
             StorageVersion::V2 => &VEC_PREFIX_NAME_V2,
             StorageVersion::V3 => &VEC_PREFIX_NAME_V3,
+            StorageVersion::V4 => &VEC_PREFIX_NAME_V4,
         }
     }
 }
@@ -159,6 +289,10 @@ pub trait BlockStore {
     fn get_startup_info(&self) -> Result<Option<StartupInfo>>;
     fn save_startup_info(&self, startup_info: StartupInfo) -> Result<()>;
 
+    fn get_main_head(&self) -> Result<Option<(StartupInfo, ChainStatus)>>;
+
+    fn save_main_head(&self, startup_info: StartupInfo, head_status: ChainStatus) -> Result<()>;
+
     fn get_genesis(&self) -> Result<Option<HashValue>>;
 
     fn save_genesis(&self, genesis_hash: HashValue) -> Result<()>;
@@ -176,8 +310,20 @@ pub trait BlockStore {
     /// delete_block will delete block data, txns and txn infos.
     fn delete_block(&self, block_id: HashValue) -> Result<()>;
 
+    /// delete_block_bodies will delete the block data, txns and txn infos of `block_ids`,
+    /// the block headers are kept.
+    fn delete_block_bodies(&self, block_ids: Vec<HashValue>) -> Result<()>;
+
+    /// Compact the column families of the deleted block bodies, so the space is reclaimed.
+    fn compact_block_bodies(&self) -> Result<()>;
+
     fn get_block_header_by_hash(&self, block_id: HashValue) -> Result<Option<BlockHeader>>;
 
+    fn get_block_headers(&self, ids: Vec<HashValue>) -> Result<Vec<Option<BlockHeader>>>;
+
+    /// The ids of all the stored block headers, of the main chain and the branches.
+    fn get_block_header_ids(&self) -> Result<Vec<HashValue>>;
+
     fn get_block_by_hash(&self, block_id: HashValue) -> Result<Option<Block>>;
 
     fn save_block_transaction_ids(
@@ -214,6 +360,15 @@ pub trait BlockStore {
 
     fn get_snapshot_range(&self) -> Result<Option<SnapshotRange>>;
     fn save_snapshot_range(&self, snapshot_height: SnapshotRange) -> Result<()>;
+
+    /// The highest finalized block number of the main chain, the main chain never rolls back
+    /// the blocks below or at it.
+    fn get_finalized_number(&self) -> Result<Option<BlockNumber>>;
+    fn save_finalized_number(&self, number: BlockNumber) -> Result<()>;
+
+    /// The height the main chain is pruned below, the blocks below it have no bodies.
+    fn get_pruned_number(&self) -> Result<Option<BlockNumber>>;
+    fn save_pruned_number(&self, number: BlockNumber) -> Result<()>;
 }
 
 pub trait BlockTransactionInfoStore {
@@ -246,6 +401,27 @@ pub trait ContractEventStore {
     fn get_contract_events(&self, txn_info_id: HashValue) -> Result<Option<Vec<ContractEvent>>>;
 }
 
+pub trait EventIndexStore {
+    /// Save the indexes of the events emitted in block `block_id`,
+    /// they are grouped by the creator address of the event key.
+    fn save_event_indexes(
+        &self,
+        block_number: BlockNumber,
+        block_id: HashValue,
+        indexes: Vec<EventIndex>,
+    ) -> Result<()>;
+
+    /// Get the indexes of the events created by `address` in the blocks from number `from` to
+    /// `to`, both inclusive, ordered by the block number. The blocks of all the forks are
+    /// returned, the caller should check the block id.
+    fn get_event_indexes(
+        &self,
+        address: AccountAddress,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Vec<(EventIndexKey, Vec<EventIndex>)>>;
+}
+
 pub trait TransactionStore {
     fn get_transaction(&self, txn_hash: HashValue) -> Result<Option<Transaction>>;
     fn save_transaction(&self, txn_info: Transaction) -> Result<()>;
@@ -265,8 +441,13 @@ pub struct Storage {
     transaction_accumulator_storage: AccumulatorStorage<TransactionAccumulatorStorage>,
     block_info_storage: BlockInfoStorage,
     event_storage: ContractEventStorage,
+    event_index_storage: EventIndexStorage,
     chain_info_storage: ChainInfoStorage,
-    // instance: StorageInstance,
+    instance: StorageInstance,
+    /// The state nodes written since the running `prune_state_nodes` started, `None` if no
+    /// prune is running. They are kept by the prune, they may be unreachable from the roots
+    /// it collected.
+    state_prune_written: Arc<Mutex<Option<HashSet<HashValue>>>>,
 }
 
 impl Storage {
@@ -284,12 +465,23 @@ impl Storage {
                 AccumulatorStorage::new_transaction_accumulator_storage(instance.clone()),
             block_info_storage: BlockInfoStorage::new(instance.clone()),
             event_storage: ContractEventStorage::new(instance.clone()),
-            chain_info_storage: ChainInfoStorage::new(instance),
-            // instance,
+            event_index_storage: EventIndexStorage::new(instance.clone()),
+            chain_info_storage: ChainInfoStorage::new(instance.clone()),
+            instance,
+            state_prune_written: Arc::new(Mutex::new(None)),
         };
         Ok(storage)
     }
 
+    /// Record the state nodes to write if a prune is running. They are recorded before they are
+    /// written, and a delete batch of the prune is filtered and written under the same lock, so
+    /// the prune never deletes a node written after it started.
+    fn record_state_prune_written(&self, keys: impl Iterator<Item = HashValue>) {
+        if let Some(written) = self.state_prune_written.lock().as_mut() {
+            written.extend(keys);
+        }
+    }
+
     pub fn get_block_accumulator_storage(&self) -> AccumulatorStorage<BlockAccumulatorStorage> {
         self.block_accumulator_storage.clone()
     }
@@ -307,10 +499,12 @@ impl StateNodeStore for Storage {
     }
 
     fn put(&self, key: HashValue, node: StateNode) -> Result<()> {
+        self.record_state_prune_written(iter::once(key));
         self.state_node_storage.put(key, node)
     }
 
     fn write_nodes(&self, nodes: BTreeMap<HashValue, StateNode>) -> Result<()> {
+        self.record_state_prune_written(nodes.keys().copied());
         let batch = CodecWriteBatch::new_puts(nodes.into_iter().collect());
         self.state_node_storage.write_batch(batch)
     }
@@ -336,6 +530,15 @@ impl BlockStore for Storage {
         self.chain_info_storage.save_startup_info(startup_info)
     }
 
+    fn get_main_head(&self) -> Result<Option<(StartupInfo, ChainStatus)>> {
+        self.chain_info_storage.get_main_head()
+    }
+
+    fn save_main_head(&self, startup_info: StartupInfo, head_status: ChainStatus) -> Result<()> {
+        self.chain_info_storage
+            .save_main_head(startup_info, head_status)
+    }
+
     fn get_genesis(&self) -> Result<Option<HashValue>> {
         self.chain_info_storage.get_genesis()
     }
@@ -387,10 +590,35 @@ impl BlockStore for Storage {
         self.block_storage.delete_block(block_id)
     }
 
+    fn delete_block_bodies(&self, block_ids: Vec<HashValue>) -> Result<()> {
+        self.block_storage.delete_block_bodies(block_ids)
+    }
+
+    fn compact_block_bodies(&self) -> Result<()> {
+        // the block ids are hashes, so the deleted keys spread over the whole column family.
+        for prefix_name in [
+            BLOCK_PREFIX_NAME,
+            BLOCK_BODY_PREFIX_NAME,
+            BLOCK_TRANSACTIONS_PREFIX_NAME,
+            BLOCK_TRANSACTION_INFOS_PREFIX_NAME,
+        ] {
+            self.instance.compact_range(prefix_name, None, None)?;
+        }
+        Ok(())
+    }
+
     fn get_block_header_by_hash(&self, block_id: HashValue) -> Result<Option<BlockHeader>> {
         self.block_storage.get_block_header_by_hash(block_id)
     }
 
+    fn get_block_headers(&self, ids: Vec<HashValue>) -> Result<Vec<Option<BlockHeader>>> {
+        self.block_storage.get_block_headers(ids)
+    }
+
+    fn get_block_header_ids(&self) -> Result<Vec<HashValue>> {
+        self.block_storage.get_headers()
+    }
+
     fn get_block_by_hash(&self, block_id: HashValue) -> Result<Option<Block>> {
         self.block_storage.get_block_by_hash(block_id)
     }
@@ -451,6 +679,22 @@ impl BlockStore for Storage {
     fn save_snapshot_range(&self, snapshot_range: SnapshotRange) -> Result<()> {
         self.chain_info_storage.save_snapshot_range(snapshot_range)
     }
+
+    fn get_finalized_number(&self) -> Result<Option<BlockNumber>> {
+        self.chain_info_storage.get_finalized_number()
+    }
+
+    fn save_finalized_number(&self, number: BlockNumber) -> Result<()> {
+        self.chain_info_storage.save_finalized_number(number)
+    }
+
+    fn get_pruned_number(&self) -> Result<Option<BlockNumber>> {
+        self.chain_info_storage.get_pruned_number()
+    }
+
+    fn save_pruned_number(&self, number: BlockNumber) -> Result<()> {
+        self.chain_info_storage.save_pruned_number(number)
+    }
 }
 
 impl BlockInfoStore for Storage {
@@ -533,6 +777,28 @@ impl ContractEventStore for Storage {
     }
 }
 
+impl EventIndexStore for Storage {
+    fn save_event_indexes(
+        &self,
+        block_number: BlockNumber,
+        block_id: HashValue,
+        indexes: Vec<EventIndex>,
+    ) -> Result<(), Error> {
+        self.event_index_storage
+            .save_event_indexes(block_number, block_id, indexes)
+    }
+
+    fn get_event_indexes(
+        &self,
+        address: AccountAddress,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Vec<(EventIndexKey, Vec<EventIndex>)>, Error> {
+        self.event_index_storage
+            .get_event_indexes(address, from, to)
+    }
+}
+
 impl TransactionStore for Storage {
     fn get_transaction(&self, txn_hash: HashValue) -> Result<Option<Transaction>, Error> {
         self.transaction_storage.get(txn_hash)
@@ -562,6 +828,7 @@ pub trait Store:
     + TransactionStore
     + BlockTransactionInfoStore
     + ContractEventStore
+    + EventIndexStore
     + IntoSuper<dyn StateNodeStore>
 {
     fn get_transaction_info_by_block_and_index(
@@ -601,6 +868,18 @@ pub trait Store:
         &self,
         accumulator_type: AccumulatorStoreType,
     ) -> Arc<dyn AccumulatorTreeStore>;
+
+    /// Flush the written data to the persistent storage, if the storage has one.
+    fn flush(&self) -> Result<()>;
+
+    /// Delete the state nodes unreachable from the roots to keep and compact them, return the
+    /// count of the deleted nodes. The nodes are shared by the state roots and the accounts, so
+    /// `collect_reachable` collects all the nodes reachable from the roots to keep. The blocks
+    /// may be applied during the call, the nodes written after it started are kept.
+    fn prune_state_nodes(
+        &self,
+        collect_reachable: &mut dyn FnMut(&mut HashSet<HashValue>) -> Result<()>,
+    ) -> Result<u64>;
 }
 
 pub trait IntoSuper<Super: ?Sized> {
@@ -625,6 +904,41 @@ impl<'a, T: 'a + StateNodeStore> IntoSuper<dyn StateNodeStore + 'a> for T {
     }
 }
 
+impl Storage {
+    /// Collect the reachable nodes and delete the others in batches of `retain_keys`. The nodes
+    /// written since the prune started are filtered out of every batch under the lock.
+    fn sweep_state_nodes(
+        &self,
+        collect_reachable: &mut dyn FnMut(&mut HashSet<HashValue>) -> Result<()>,
+    ) -> Result<u64> {
+        let mut reachable = HashSet::new();
+        collect_reachable(&mut reachable)?;
+        self.instance.retain_keys(
+            STATE_NODE_PREFIX_NAME,
+            |key| {
+                HashValue::from_slice(key)
+                    .map(|hash| reachable.contains(&hash))
+                    .unwrap_or(true)
+            },
+            |keys| {
+                let written = self.state_prune_written.lock();
+                let deletes = keys
+                    .into_iter()
+                    .filter(|key| match (written.as_ref(), HashValue::from_slice(key)) {
+                        (Some(written), Ok(hash)) => !written.contains(&hash),
+                        _ => true,
+                    })
+                    .map(|key| (key, WriteOp::Deletion))
+                    .collect::<Vec<_>>();
+                let count = deletes.len() as u64;
+                self.instance
+                    .write_batch(STATE_NODE_PREFIX_NAME, WriteBatch::new_with_rows(deletes))?;
+                Ok(count)
+            },
+        )
+    }
+}
+
 impl Store for Storage {
     fn get_accumulator_store(
         &self,
@@ -637,4 +951,25 @@ impl Store for Storage {
             }
         }
     }
+
+    fn flush(&self) -> Result<()> {
+        self.instance.flush()
+    }
+
+    fn prune_state_nodes(
+        &self,
+        collect_reachable: &mut dyn FnMut(&mut HashSet<HashValue>) -> Result<()>,
+    ) -> Result<u64> {
+        {
+            let mut written = self.state_prune_written.lock();
+            ensure!(written.is_none(), "The state nodes are being pruned");
+            *written = Some(HashSet::new());
+        }
+        let result = self.sweep_state_nodes(collect_reachable);
+        *self.state_prune_written.lock() = None;
+        let deleted = result?;
+        self.instance
+            .compact_range(STATE_NODE_PREFIX_NAME, None, None)?;
+        Ok(deleted)
+    }
 }
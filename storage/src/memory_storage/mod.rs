@@ -0,0 +1,170 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::batch::WriteBatch;
+use crate::cache_storage::compose_key;
+use crate::storage::{InnerStore, WriteOp};
+use anyhow::{Error, Result};
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+
+/// A pure in-memory storage without eviction, every column family is kept in its own map.
+/// Unlike `CacheStorage`, nothing is dropped when the storage grows, so it's suitable for tests
+/// and the ephemeral nodes which do not want a db on disk.
+/// The keys returned by `keys` are composed by prefix name and key the same as `CacheStorage`.
+#[derive(Default)]
+pub struct MemoryStorage {
+    columns: RwLock<HashMap<String, BTreeMap<Vec<u8>, Vec<u8>>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the keys of `prefix_name` which start with `key_prefix`.
+    pub fn count_prefix(&self, prefix_name: &str, key_prefix: &[u8]) -> u64 {
+        self.columns
+            .read()
+            .get(prefix_name)
+            .map(|column| {
+                column
+                    .range(key_prefix.to_vec()..)
+                    .take_while(|(key, _)| key.starts_with(key_prefix))
+                    .count() as u64
+            })
+            .unwrap_or(0)
+    }
+
+    /// The entries of `prefix_name` whose keys start with `key_prefix`, ordered by key.
+    pub fn scan_prefix(&self, prefix_name: &str, key_prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.columns
+            .read()
+            .get(prefix_name)
+            .map(|column| {
+                column
+                    .range(key_prefix.to_vec()..)
+                    .take_while(|(key, _)| key.starts_with(key_prefix))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The entries of `prefix_name` whose keys are in `start..end`, ordered by key.
+    pub fn scan_range(
+        &self,
+        prefix_name: &str,
+        start: &[u8],
+        end: &[u8],
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        if start >= end {
+            return vec![];
+        }
+        self.columns
+            .read()
+            .get(prefix_name)
+            .map(|column| {
+                column
+                    .range(start.to_vec()..end.to_vec())
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Remove the key and return its value before the removal.
+    pub fn remove_returning(&self, prefix_name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        self.columns
+            .write()
+            .get_mut(prefix_name)
+            .and_then(|column| column.remove(key))
+    }
+}
+
+impl InnerStore for MemoryStorage {
+    fn get(&self, prefix_name: &str, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        Ok(self
+            .columns
+            .read()
+            .get(prefix_name)
+            .and_then(|column| column.get(&key).cloned()))
+    }
+
+    fn put(&self, prefix_name: &str, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.columns
+            .write()
+            .entry(prefix_name.to_string())
+            .or_default()
+            .insert(key, value);
+        Ok(())
+    }
+
+    fn contains_key(&self, prefix_name: &str, key: Vec<u8>) -> Result<bool> {
+        Ok(self
+            .columns
+            .read()
+            .get(prefix_name)
+            .map(|column| column.contains_key(&key))
+            .unwrap_or(false))
+    }
+
+    fn remove(&self, prefix_name: &str, key: Vec<u8>) -> Result<()> {
+        if let Some(column) = self.columns.write().get_mut(prefix_name) {
+            column.remove(&key);
+        }
+        Ok(())
+    }
+
+    fn write_batch(&self, prefix_name: &str, batch: WriteBatch) -> Result<()> {
+        // hold the lock during the batch, so the batch is applied atomically.
+        let mut columns = self.columns.write();
+        let column = columns.entry(prefix_name.to_string()).or_default();
+        for (key, write_op) in batch.rows {
+            match write_op {
+                WriteOp::Value(value) => column.insert(key, value),
+                WriteOp::Deletion => column.remove(&key),
+            };
+        }
+        Ok(())
+    }
+
+    fn get_len(&self) -> Result<u64, Error> {
+        Ok(self
+            .columns
+            .read()
+            .values()
+            .map(|column| column.len() as u64)
+            .sum())
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>, Error> {
+        let columns = self.columns.read();
+        let mut all_keys = vec![];
+        for (prefix_name, column) in columns.iter() {
+            for key in column.keys() {
+                all_keys.push(compose_key(prefix_name.clone(), key.clone()));
+            }
+        }
+        // the column families are in a hash map, sort the keys of all of them.
+        all_keys.sort();
+        Ok(all_keys)
+    }
+
+    fn put_sync(&self, prefix_name: &str, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.put(prefix_name, key, value)
+    }
+
+    fn write_batch_sync(&self, prefix_name: &str, batch: WriteBatch) -> Result<()> {
+        self.write_batch(prefix_name, batch)
+    }
+
+    fn multi_get(&self, prefix_name: &str, keys: Vec<Vec<u8>>) -> Result<Vec<Option<Vec<u8>>>> {
+        let columns = self.columns.read();
+        let column = columns.get(prefix_name);
+        Ok(keys
+            .iter()
+            .map(|key| column.and_then(|column| column.get(key).cloned()))
+            .collect())
+    }
+}
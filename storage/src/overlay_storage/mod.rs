@@ -0,0 +1,135 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::batch::WriteBatch;
+use crate::storage::WriteOp;
+use anyhow::Result;
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+
+/// The pending writes of an overlay storage instance, every column family is kept in its own map.
+/// A deletion is kept as a `WriteOp::Deletion`, so it masks the value of the base storage.
+#[derive(Default)]
+pub struct OverlayStorage {
+    columns: RwLock<HashMap<String, BTreeMap<Vec<u8>, WriteOp<Vec<u8>>>>>,
+}
+
+impl OverlayStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the pending write of the key, None means the key is not written in the overlay.
+    pub fn get(&self, prefix_name: &str, key: &[u8]) -> Option<WriteOp<Vec<u8>>> {
+        self.columns
+            .read()
+            .get(prefix_name)
+            .and_then(|column| column.get(key).cloned())
+    }
+
+    pub fn write_batch(&self, prefix_name: &str, batch: WriteBatch) {
+        let mut columns = self.columns.write();
+        let column = columns.entry(prefix_name.to_string()).or_default();
+        for (key, write_op) in batch.rows {
+            column.insert(key, write_op);
+        }
+    }
+
+    /// Write a deletion of the key and return its value before the removal, the value of the key
+    /// not written in the overlay is read by `base_get`. The lock is held from the read to the
+    /// write, so no write to the overlay is between them.
+    pub fn remove_returning<F>(
+        &self,
+        prefix_name: &str,
+        key: Vec<u8>,
+        base_get: F,
+    ) -> Result<Option<Vec<u8>>>
+    where
+        F: FnOnce(Vec<u8>) -> Result<Option<Vec<u8>>>,
+    {
+        let mut columns = self.columns.write();
+        let column = columns.entry(prefix_name.to_string()).or_default();
+        let value = match column.get(&key) {
+            Some(WriteOp::Value(value)) => Some(value.clone()),
+            Some(WriteOp::Deletion) => None,
+            None => base_get(key.clone())?,
+        };
+        column.insert(key, WriteOp::Deletion);
+        Ok(value)
+    }
+
+    pub fn prefix_names(&self) -> Vec<String> {
+        self.columns.read().keys().cloned().collect()
+    }
+
+    /// The pending writes of `prefix_name` whose keys start with `key_prefix`.
+    pub fn changes(
+        &self,
+        prefix_name: &str,
+        key_prefix: &[u8],
+    ) -> Vec<(Vec<u8>, WriteOp<Vec<u8>>)> {
+        self.columns
+            .read()
+            .get(prefix_name)
+            .map(|column| {
+                column
+                    .range(key_prefix.to_vec()..)
+                    .take_while(|(key, _)| key.starts_with(key_prefix))
+                    .map(|(key, write_op)| (key.clone(), write_op.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The pending writes of `prefix_name` whose keys are in `start..end`.
+    pub fn changes_in_range(
+        &self,
+        prefix_name: &str,
+        start: &[u8],
+        end: &[u8],
+    ) -> Vec<(Vec<u8>, WriteOp<Vec<u8>>)> {
+        if start >= end {
+            return vec![];
+        }
+        self.columns
+            .read()
+            .get(prefix_name)
+            .map(|column| {
+                column
+                    .range(start.to_vec()..end.to_vec())
+                    .map(|(key, write_op)| (key.clone(), write_op.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Write the pending writes of every column family by `write`, a column family is removed
+    /// from the overlay once its batch is written. The lock is held during the commit, so no
+    /// write to the overlay is lost, and a failed column family is kept for the retry.
+    pub fn commit<F>(&self, mut write: F) -> Result<()>
+    where
+        F: FnMut(&str, WriteBatch) -> Result<()>,
+    {
+        let mut columns = self.columns.write();
+        let prefix_names: Vec<String> = columns.keys().cloned().collect();
+        for prefix_name in prefix_names {
+            if let Some(column) = columns.get(&prefix_name) {
+                let rows = column
+                    .iter()
+                    .map(|(key, write_op)| (key.clone(), write_op.clone()))
+                    .collect();
+                write(&prefix_name, WriteBatch::new_with_rows(rows))?;
+            }
+            columns.remove(&prefix_name);
+        }
+        Ok(())
+    }
+
+    pub fn discard(&self) {
+        self.columns.write().clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.read().values().all(|column| column.is_empty())
+    }
+}
@@ -2,20 +2,27 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub use crate::batch::WriteBatch;
-use crate::cache_storage::CacheStorage;
+use crate::cache_storage::{compose_key, CacheStorage};
 use crate::db_storage::{DBStorage, SchemaIterator};
+use crate::memory_storage::MemoryStorage;
+use crate::overlay_storage::OverlayStorage;
 use crate::upgrade::DBUpgrade;
 use anyhow::{bail, format_err, Result};
 use byteorder::{BigEndian, ReadBytesExt};
 use crypto::HashValue;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryInto;
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::path::Path;
 use std::sync::Arc;
 
 /// Type alias to improve readability.
 pub type ColumnFamilyName = &'static str;
 
+/// Max count of keys passed to the `delete` of `retain_keys` at once.
+const RETAIN_BATCH_SIZE: usize = 1000;
+
 #[allow(clippy::upper_case_acronyms)]
 pub trait KVStore: Send + Sync {
     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
@@ -25,6 +32,7 @@ pub trait KVStore: Send + Sync {
     fn remove(&self, key: Vec<u8>) -> Result<()>;
     fn write_batch(&self, batch: WriteBatch) -> Result<()>;
     fn get_len(&self) -> Result<u64>;
+    /// The keys of the store in lexicographic order, the same for every `StorageInstance`.
     fn keys(&self) -> Result<Vec<Vec<u8>>>;
     fn put_sync(&self, key: Vec<u8>, value: Vec<u8>) -> Result<()>;
     fn write_batch_sync(&self, batch: WriteBatch) -> Result<()>;
@@ -37,36 +45,77 @@ pub trait InnerStore: Send + Sync {
     fn remove(&self, prefix_name: &str, key: Vec<u8>) -> Result<()>;
     fn write_batch(&self, prefix_name: &str, batch: WriteBatch) -> Result<()>;
     fn get_len(&self) -> Result<u64>;
+    /// The keys of all the column families composed by the prefix name, a separator and the key,
+    /// in lexicographic order.
     fn keys(&self) -> Result<Vec<Vec<u8>>>;
     fn put_sync(&self, prefix_name: &str, key: Vec<u8>, value: Vec<u8>) -> Result<()>;
     fn write_batch_sync(&self, prefix_name: &str, batch: WriteBatch) -> Result<()>;
     fn multi_get(&self, prefix_name: &str, keys: Vec<Vec<u8>>) -> Result<Vec<Option<Vec<u8>>>>;
 }
 
+/// Where the value of a `get` came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ReadSource {
+    /// Served by the in-memory layer in front of the store, the cache or the overlay.
+    Cache,
+    /// Served by the backing store, the db or the memory storage.
+    Db,
+    /// The key does not exist.
+    Missing,
+}
+
 ///Storage instance type define
 #[derive(Clone)]
-#[allow(clippy::upper_case_acronyms)]
 pub enum StorageInstance {
-    CACHE {
+    Cache {
         cache: Arc<CacheStorage>,
     },
-    DB {
+    Db {
         db: Arc<DBStorage>,
     },
+    /// The cache is written through, every write goes to the db first and then to the cache.
+    /// It reads its own writes: once a write returns Ok, the cache never has a value older than
+    /// the db, so `get` returns the value of the most recent successful write, whether it's
+    /// found in the cache or in the db.
+    /// A failed db write fails the write, but a failed cache write after a successful db write
+    /// only drops the keys from the cache, see `CacheStorage::write_through`.
     CacheAndDb {
         cache: Arc<CacheStorage>,
         db: Arc<DBStorage>,
     },
+    /// In memory storage without eviction, for tests and ephemeral nodes.
+    Memory {
+        memory: Arc<MemoryStorage>,
+    },
+    /// A throwaway layer for the speculative execution, the writes are kept in the memory
+    /// overlay and the reads fall through to the base for the keys not written in the overlay.
+    /// The overlay is dropped by `discard_overlay` or written to the base by `commit_overlay`.
+    Overlay {
+        base: Arc<StorageInstance>,
+        overlay: Arc<OverlayStorage>,
+    },
 }
 
 impl StorageInstance {
     pub fn new_cache_instance() -> Self {
-        StorageInstance::CACHE {
+        StorageInstance::Cache {
             cache: Arc::new(CacheStorage::new(None)),
         }
     }
+    pub fn new_memory_instance() -> Self {
+        StorageInstance::Memory {
+            memory: Arc::new(MemoryStorage::new()),
+        }
+    }
     pub fn new_db_instance(db: DBStorage) -> Self {
-        Self::DB { db: Arc::new(db) }
+        Self::Db { db: Arc::new(db) }
+    }
+
+    pub fn new_overlay_instance(base: StorageInstance) -> Self {
+        Self::Overlay {
+            base: Arc::new(base),
+            overlay: Arc::new(OverlayStorage::new()),
+        }
     }
 
     pub fn new_cache_and_db_instance(cache: CacheStorage, db: DBStorage) -> Self {
@@ -78,7 +127,7 @@ impl StorageInstance {
 
     pub fn cache(&self) -> Option<Arc<CacheStorage>> {
         match self {
-            StorageInstance::CACHE { cache } | StorageInstance::CacheAndDb { cache, db: _ } => {
+            StorageInstance::Cache { cache } | StorageInstance::CacheAndDb { cache, db: _ } => {
                 Some(cache.clone())
             }
             _ => None,
@@ -87,7 +136,7 @@ impl StorageInstance {
 
     pub fn db(&self) -> Option<&DBStorage> {
         match self {
-            StorageInstance::DB { db } | StorageInstance::CacheAndDb { cache: _, db } => {
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => {
                 Some(db.as_ref())
             }
             _ => None,
@@ -97,59 +146,346 @@ impl StorageInstance {
     // make sure Arc::strong_count(&db) == 1 unless will get None
     pub fn db_mut(&mut self) -> Option<&mut DBStorage> {
         match self {
-            StorageInstance::DB { db } | StorageInstance::CacheAndDb { cache: _, db } => {
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => {
                 Arc::get_mut(db)
             }
             _ => None,
         }
     }
 
+    /// Write the overlay to the base, every column family is written in a single batch, so it's
+    /// committed atomically. The committed column families are removed from the overlay, so a
+    /// failed commit can be retried. Do nothing for the other instances.
+    pub fn commit_overlay(&self) -> Result<()> {
+        match self {
+            StorageInstance::Overlay { base, overlay } => {
+                overlay.commit(|prefix_name, batch| base.write_batch(prefix_name, batch))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Drop all the writes of the overlay, do nothing for the other instances.
+    pub fn discard_overlay(&self) {
+        if let StorageInstance::Overlay { base: _, overlay } = self {
+            overlay.discard();
+        }
+    }
+
+    /// Apply the pending writes of the overlay whose keys start with `key_prefix` to the count
+    /// of the same keys in the base.
+    fn count_with_overlay(
+        base: &StorageInstance,
+        overlay: &OverlayStorage,
+        prefix_name: &str,
+        key_prefix: &[u8],
+        base_count: u64,
+    ) -> Result<u64> {
+        let mut count = base_count;
+        for (key, write_op) in overlay.changes(prefix_name, key_prefix) {
+            let in_base = base.contains_key(prefix_name, key)?;
+            match write_op {
+                WriteOp::Value(_) if !in_base => count = count.saturating_add(1),
+                WriteOp::Deletion if in_base => count = count.saturating_sub(1),
+                _ => {}
+            }
+        }
+        Ok(count)
+    }
+
     pub fn check_upgrade(&mut self) -> Result<()> {
         DBUpgrade::check_upgrade(self)
     }
-}
 
-impl InnerStore for StorageInstance {
-    fn get(&self, prefix_name: &str, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+    /// Save the cache of the `CacheAndDb` instance to a snapshot file, do nothing for the others.
+    pub fn save_cache_snapshot(&self, path: &Path) -> Result<usize> {
+        match self {
+            StorageInstance::CacheAndDb { cache, db: _ } => cache.save_snapshot(path),
+            _ => Ok(0),
+        }
+    }
+
+    /// Warm the cache of the `CacheAndDb` instance by the snapshot file if it exists,
+    /// the entries are validated against the db.
+    pub fn load_cache_snapshot(&self, path: &Path) -> Result<usize> {
+        match self {
+            StorageInstance::CacheAndDb { cache, db } if path.exists() => {
+                cache.load_snapshot(path, db)
+            }
+            _ => Ok(0),
+        }
+    }
+
+    /// Make the writes of the db durable by its WAL mode, the cache and memory storages have
+    /// nothing to flush.
+    pub fn flush(&self) -> Result<()> {
+        match self {
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => db.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Put all the `kvs` to `prefix_name` in a single batch. For the `CacheAndDb` instance, the
+    /// cache is only updated after the db write committed, so a failed write leaves both untouched.
+    pub fn put_all(&self, prefix_name: &str, kvs: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        let rows = kvs
+            .into_iter()
+            .map(|(key, value)| (key, WriteOp::Value(value)))
+            .collect();
+        self.write_batch(prefix_name, WriteBatch::new_with_rows(rows))
+    }
+
+    /// Count the keys of `prefix_name` without reading the values. The cache storage may have
+    /// evicted some entries, so it is not supported.
+    pub fn count(&self, prefix_name: &str) -> Result<u64> {
+        match self {
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => {
+                db.get_cf_len(prefix_name)
+            }
+            StorageInstance::Memory { memory } => Ok(memory.count_prefix(prefix_name, &[])),
+            StorageInstance::Overlay { base, overlay } => {
+                let base_count = base.count(prefix_name)?;
+                Self::count_with_overlay(base, overlay, prefix_name, &[], base_count)
+            }
+            StorageInstance::Cache { cache: _ } => {
+                bail!("Only support count on db or memory storage instance")
+            }
+        }
+    }
+
+    /// Count the keys of `prefix_name` which start with `key_prefix`, by a prefix scan.
+    pub fn count_prefix(&self, prefix_name: &str, key_prefix: &[u8]) -> Result<u64> {
+        match self {
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => {
+                db.count_prefix(prefix_name, key_prefix)
+            }
+            StorageInstance::Memory { memory } => Ok(memory.count_prefix(prefix_name, key_prefix)),
+            StorageInstance::Overlay { base, overlay } => {
+                let base_count = base.count_prefix(prefix_name, key_prefix)?;
+                Self::count_with_overlay(base, overlay, prefix_name, key_prefix, base_count)
+            }
+            StorageInstance::Cache { cache: _ } => {
+                bail!("Only support count on db or memory storage instance")
+            }
+        }
+    }
+
+    /// The entries of `prefix_name` whose keys start with `key_prefix`, in lexicographic key
+    /// order for every instance. The cache instance only has the entries not evicted.
+    pub fn scan_prefix(
+        &self,
+        prefix_name: &str,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
         match self {
-            StorageInstance::CACHE { cache } => cache.get(prefix_name, key),
-            StorageInstance::DB { db } => db.get(prefix_name, key),
+            StorageInstance::Cache { cache } => Ok(cache.scan_prefix(prefix_name, key_prefix)),
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => {
+                db.scan_prefix(prefix_name, key_prefix)
+            }
+            StorageInstance::Memory { memory } => Ok(memory.scan_prefix(prefix_name, key_prefix)),
+            StorageInstance::Overlay { base, overlay } => {
+                let mut entries: BTreeMap<Vec<u8>, Vec<u8>> = base
+                    .scan_prefix(prefix_name, key_prefix)?
+                    .into_iter()
+                    .collect();
+                for (key, write_op) in overlay.changes(prefix_name, key_prefix) {
+                    match write_op {
+                        WriteOp::Value(value) => entries.insert(key, value),
+                        WriteOp::Deletion => entries.remove(&key),
+                    };
+                }
+                Ok(entries.into_iter().collect())
+            }
+        }
+    }
+
+    /// The entries of `prefix_name` whose keys are in `start..end`, in lexicographic key order
+    /// for every instance. The cache instance only has the entries not evicted.
+    pub fn scan_range(
+        &self,
+        prefix_name: &str,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        match self {
+            StorageInstance::Cache { cache } => Ok(cache.scan_range(prefix_name, start, end)),
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => {
+                db.scan_range(prefix_name, start, end)
+            }
+            StorageInstance::Memory { memory } => Ok(memory.scan_range(prefix_name, start, end)),
+            StorageInstance::Overlay { base, overlay } => {
+                let mut entries: BTreeMap<Vec<u8>, Vec<u8>> = base
+                    .scan_range(prefix_name, start, end)?
+                    .into_iter()
+                    .collect();
+                for (key, write_op) in overlay.changes_in_range(prefix_name, start, end) {
+                    match write_op {
+                        WriteOp::Value(value) => entries.insert(key, value),
+                        WriteOp::Deletion => entries.remove(&key),
+                    };
+                }
+                Ok(entries.into_iter().collect())
+            }
+        }
+    }
+
+    /// Remove the key and return its value before the removal, no write of the key is between the
+    /// read and the removal.
+    pub fn remove_returning(&self, prefix_name: &str, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        match self {
+            StorageInstance::Cache { cache } => Ok(cache.remove_returning(prefix_name, key)),
+            StorageInstance::Db { db } => db.remove_returning(prefix_name, key),
+            StorageInstance::Memory { memory } => {
+                Ok(memory.remove_returning(prefix_name, key.as_slice()))
+            }
+            StorageInstance::Overlay { base, overlay } => {
+                overlay.remove_returning(prefix_name, key, |key| base.get(prefix_name, key))
+            }
+            StorageInstance::CacheAndDb { cache, db } => {
+                let mut value = None;
+                cache.write_through(
+                    prefix_name,
+                    WriteBatch::new_with_rows(vec![(key.clone(), WriteOp::Deletion)]),
+                    |_batch| {
+                        value = db.remove_returning(prefix_name, key)?;
+                        Ok(())
+                    },
+                )?;
+                Ok(value)
+            }
+        }
+    }
+
+    /// Get the value of the key and the layer which served it, `get` is the same without the
+    /// source.
+    pub(crate) fn get_with_source(
+        &self,
+        prefix_name: &str,
+        key: Vec<u8>,
+    ) -> Result<(Option<Vec<u8>>, ReadSource)> {
+        let (value, source) = match self {
+            StorageInstance::Cache { cache } => (cache.get(prefix_name, key)?, ReadSource::Cache),
+            StorageInstance::Db { db } => (db.get(prefix_name, key)?, ReadSource::Db),
+            StorageInstance::Memory { memory } => (memory.get(prefix_name, key)?, ReadSource::Db),
+            StorageInstance::Overlay { base, overlay } => match overlay.get(prefix_name, &key) {
+                Some(WriteOp::Value(value)) => (Some(value), ReadSource::Cache),
+                Some(WriteOp::Deletion) => (None, ReadSource::Missing),
+                None => return base.get_with_source(prefix_name, key),
+            },
             StorageInstance::CacheAndDb { cache, db } => {
                 // first get from cache
-                // if from cache get non-existent, query from db
+                // if from cache get non-existent, query from db, the db is never older than
+                // the cache, so it's safe to fall back to the db even if the cache failed.
                 if let Ok(Some(value)) = cache.get(prefix_name, key.clone()) {
-                    Ok(Some(value))
+                    (Some(value), ReadSource::Cache)
                 } else {
-                    match db.get(prefix_name, key)? {
-                        Some(value) => {
-                            // cache.put_obj(prefix_name, key, CacheObject::Value(value.clone()))?;
-                            Ok(Some(value))
-                        }
-                        None => {
-                            // put null vec to cache for avoid repeatedly querying non-existent data from db
-                            // cache.put_obj(prefix_name, key, CACHE_NONE_OBJECT.clone())?;
-                            Ok(None)
-                        }
+                    // the missing value is not put to the cache, so the non-existent key is
+                    // always queried from db.
+                    (db.get(prefix_name, key)?, ReadSource::Db)
+                }
+            }
+        };
+        match value {
+            Some(value) => Ok((Some(value), source)),
+            None => Ok((None, ReadSource::Missing)),
+        }
+    }
+
+    /// Compact the range of the column family in the db, the cache and memory storages do not
+    /// keep the tombstones of deleted keys, so there is nothing to compact.
+    pub fn compact_range(
+        &self,
+        prefix_name: &str,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> Result<()> {
+        match self {
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => {
+                db.compact_range(prefix_name, start, end)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Walk the keys of `prefix_name` and pass the ones `keep` returns false for to `delete` in
+    /// batches, return the sum of the counts returned by `delete`. The db is walked by an
+    /// iterator instead of loading all the keys, the keys written during the walk are not
+    /// visited.
+    pub fn retain_keys<F, D>(&self, prefix_name: &str, keep: F, mut delete: D) -> Result<u64>
+    where
+        F: Fn(&[u8]) -> bool,
+        D: FnMut(Vec<Vec<u8>>) -> Result<u64>,
+    {
+        let mut deleted = 0u64;
+        let mut deletes = Vec::with_capacity(RETAIN_BATCH_SIZE);
+        let mut push = |key: Vec<u8>, deletes: &mut Vec<Vec<u8>>| -> Result<()> {
+            deletes.push(key);
+            if deletes.len() >= RETAIN_BATCH_SIZE {
+                deleted = deleted.saturating_add(delete(std::mem::take(deletes))?);
+            }
+            Ok(())
+        };
+        match self {
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => {
+                let mut iter = db.iter::<Vec<u8>, Vec<u8>>(prefix_name)?;
+                iter.seek_to_first();
+                for item in iter {
+                    let (key, _value) = item?;
+                    if !keep(&key) {
+                        push(key, &mut deletes)?;
+                    }
+                }
+            }
+            _ => {
+                for (key, _value) in self.scan_prefix(prefix_name, &[])? {
+                    if !keep(&key) {
+                        push(key, &mut deletes)?;
                     }
                 }
             }
         }
+        if !deletes.is_empty() {
+            deleted = deleted.saturating_add(delete(deletes)?);
+        }
+        Ok(deleted)
+    }
+}
+
+impl InnerStore for StorageInstance {
+    fn get(&self, prefix_name: &str, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        self.get_with_source(prefix_name, key)
+            .map(|(value, _source)| value)
     }
 
     fn put(&self, prefix_name: &str, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
         match self {
-            StorageInstance::CACHE { cache } => cache.put(prefix_name, key, value),
-            StorageInstance::DB { db } => db.put(prefix_name, key, value),
-            StorageInstance::CacheAndDb { cache, db } => db
-                .put(prefix_name, key.clone(), value.clone())
-                .and_then(|_| cache.put(prefix_name, key, value)),
+            StorageInstance::Cache { cache } => cache.put(prefix_name, key, value),
+            StorageInstance::Db { db } => db.put(prefix_name, key, value),
+            StorageInstance::Memory { memory } => memory.put(prefix_name, key, value),
+            StorageInstance::Overlay { base: _, overlay } => {
+                overlay.write_batch(
+                    prefix_name,
+                    WriteBatch::new_with_rows(vec![(key, WriteOp::Value(value))]),
+                );
+                Ok(())
+            }
+            StorageInstance::CacheAndDb { cache, db } => cache.write_through(
+                prefix_name,
+                WriteBatch::new_with_rows(vec![(key, WriteOp::Value(value))]),
+                |batch| db.write_batch(prefix_name, batch),
+            ),
         }
     }
 
     fn contains_key(&self, prefix_name: &str, key: Vec<u8>) -> Result<bool> {
         match self {
-            StorageInstance::CACHE { cache } => cache.contains_key(prefix_name, key),
-            StorageInstance::DB { db } => db.contains_key(prefix_name, key),
+            StorageInstance::Cache { cache } => cache.contains_key(prefix_name, key),
+            StorageInstance::Db { db } => db.contains_key(prefix_name, key),
+            StorageInstance::Memory { memory } => memory.contains_key(prefix_name, key),
+            StorageInstance::Overlay { base, overlay } => match overlay.get(prefix_name, &key) {
+                Some(write_op) => Ok(write_op != WriteOp::Deletion),
+                None => base.contains_key(prefix_name, key),
+            },
             StorageInstance::CacheAndDb { cache, db } => {
                 match cache.contains_key(prefix_name, key.clone()) {
                     Ok(true) => Ok(true),
@@ -161,72 +497,118 @@ impl InnerStore for StorageInstance {
 
     fn remove(&self, prefix_name: &str, key: Vec<u8>) -> Result<()> {
         match self {
-            StorageInstance::CACHE { cache } => cache.remove(prefix_name, key),
-            StorageInstance::DB { db } => db.remove(prefix_name, key),
-            StorageInstance::CacheAndDb { cache, db } => {
-                match db.remove(prefix_name, key.clone()) {
-                    Ok(_) => cache.remove(prefix_name, key),
-                    _ => bail!("db storage remove error."),
-                }
+            StorageInstance::Cache { cache } => cache.remove(prefix_name, key),
+            StorageInstance::Db { db } => db.remove(prefix_name, key),
+            StorageInstance::Memory { memory } => memory.remove(prefix_name, key),
+            StorageInstance::Overlay { base: _, overlay } => {
+                overlay.write_batch(
+                    prefix_name,
+                    WriteBatch::new_with_rows(vec![(key, WriteOp::Deletion)]),
+                );
+                Ok(())
             }
+            StorageInstance::CacheAndDb { cache, db } => cache.write_through(
+                prefix_name,
+                WriteBatch::new_with_rows(vec![(key, WriteOp::Deletion)]),
+                |batch| db.write_batch(prefix_name, batch),
+            ),
         }
     }
 
     fn write_batch(&self, prefix_name: &str, batch: WriteBatch) -> Result<()> {
         match self {
-            StorageInstance::CACHE { cache } => cache.write_batch(prefix_name, batch),
-            StorageInstance::DB { db } => db.write_batch(prefix_name, batch),
+            StorageInstance::Cache { cache } => cache.write_batch(prefix_name, batch),
+            StorageInstance::Db { db } => db.write_batch(prefix_name, batch),
+            StorageInstance::Memory { memory } => memory.write_batch(prefix_name, batch),
+            StorageInstance::Overlay { base: _, overlay } => {
+                overlay.write_batch(prefix_name, batch);
+                Ok(())
+            }
             StorageInstance::CacheAndDb { cache, db } => {
-                match db.write_batch(prefix_name, batch.clone()) {
-                    Ok(_) => cache.write_batch(prefix_name, batch),
-                    Err(err) => bail!("write batch db error: {}", err),
-                }
+                cache.write_through(prefix_name, batch, |batch| {
+                    db.write_batch(prefix_name, batch)
+                        .map_err(|err| format_err!("write batch db error: {}", err))
+                })
             }
         }
     }
     fn get_len(&self) -> Result<u64> {
         match self {
-            StorageInstance::CACHE { cache } => cache.get_len(),
-            StorageInstance::CacheAndDb { cache, db: _ } => cache.get_len(),
-            _ => bail!("DB instance not support get length method!"),
+            StorageInstance::Cache { cache } => cache.get_len(),
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => {
+                db.get_len()
+            }
+            StorageInstance::Memory { memory } => memory.get_len(),
+            StorageInstance::Overlay { base, overlay } => {
+                let mut len = base.get_len()?;
+                for prefix_name in overlay.prefix_names() {
+                    len = Self::count_with_overlay(base, overlay, &prefix_name, &[], len)?;
+                }
+                Ok(len)
+            }
         }
     }
 
     fn keys(&self) -> Result<Vec<Vec<u8>>> {
         match self {
-            StorageInstance::CACHE { cache } => cache.keys(),
-            StorageInstance::CacheAndDb { cache, db: _ } => cache.keys(),
-            _ => bail!("DB instance not support keys method!"),
+            StorageInstance::Cache { cache } => cache.keys(),
+            // the cache may have evicted some keys, the db has all of them.
+            StorageInstance::Db { db } | StorageInstance::CacheAndDb { cache: _, db } => db.keys(),
+            StorageInstance::Memory { memory } => memory.keys(),
+            StorageInstance::Overlay { base, overlay } => {
+                let mut keys: BTreeSet<Vec<u8>> = base.keys()?.into_iter().collect();
+                for prefix_name in overlay.prefix_names() {
+                    for (key, write_op) in overlay.changes(&prefix_name, &[]) {
+                        let key = compose_key(prefix_name.clone(), key);
+                        match write_op {
+                            WriteOp::Value(_) => keys.insert(key),
+                            WriteOp::Deletion => keys.remove(&key),
+                        };
+                    }
+                }
+                Ok(keys.into_iter().collect())
+            }
         }
     }
 
     fn put_sync(&self, prefix_name: &str, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
         match self {
-            StorageInstance::CACHE { cache } => cache.put(prefix_name, key, value),
-            StorageInstance::DB { db } => db.put_sync(prefix_name, key, value),
-            StorageInstance::CacheAndDb { cache, db } => db
-                .put_sync(prefix_name, key.clone(), value.clone())
-                .and_then(|_| cache.put(prefix_name, key, value)),
+            StorageInstance::Cache { cache } => cache.put(prefix_name, key, value),
+            StorageInstance::Db { db } => db.put_sync(prefix_name, key, value),
+            StorageInstance::Memory { memory } => memory.put_sync(prefix_name, key, value),
+            StorageInstance::Overlay { .. } => self.put(prefix_name, key, value),
+            StorageInstance::CacheAndDb { cache, db } => cache.write_through(
+                prefix_name,
+                WriteBatch::new_with_rows(vec![(key, WriteOp::Value(value))]),
+                |batch| db.write_batch_sync(prefix_name, batch),
+            ),
         }
     }
 
     fn write_batch_sync(&self, prefix_name: &str, batch: WriteBatch) -> Result<()> {
         match self {
-            StorageInstance::CACHE { cache } => cache.write_batch(prefix_name, batch),
-            StorageInstance::DB { db } => db.write_batch_sync(prefix_name, batch),
+            StorageInstance::Cache { cache } => cache.write_batch(prefix_name, batch),
+            StorageInstance::Db { db } => db.write_batch_sync(prefix_name, batch),
+            StorageInstance::Memory { memory } => memory.write_batch_sync(prefix_name, batch),
+            StorageInstance::Overlay { .. } => self.write_batch(prefix_name, batch),
             StorageInstance::CacheAndDb { cache, db } => {
-                match db.write_batch_sync(prefix_name, batch.clone()) {
-                    Ok(_) => cache.write_batch(prefix_name, batch),
-                    Err(err) => bail!("write batch db error: {}", err),
-                }
+                cache.write_through(prefix_name, batch, |batch| {
+                    db.write_batch_sync(prefix_name, batch)
+                        .map_err(|err| format_err!("write batch db error: {}", err))
+                })
             }
         }
     }
 
     fn multi_get(&self, prefix_name: &str, keys: Vec<Vec<u8>>) -> Result<Vec<Option<Vec<u8>>>> {
         match self {
-            StorageInstance::CACHE { cache } => cache.multi_get(prefix_name, keys),
-            StorageInstance::DB { db } => db.multi_get(prefix_name, keys),
+            StorageInstance::Cache { cache } => cache.multi_get(prefix_name, keys),
+            StorageInstance::Db { db } => db.multi_get(prefix_name, keys),
+            StorageInstance::Memory { memory } => memory.multi_get(prefix_name, keys),
+            StorageInstance::Overlay { .. } => keys
+                .into_iter()
+                .map(|key| self.get(prefix_name, key))
+                .collect(),
             StorageInstance::CacheAndDb { db, .. } => {
                 /* https://github.com/facebook/rocksdb/wiki/Block-Cache#lru-cache
                 * if use multi_get from CacheStorage, cache may evict some records
@@ -331,8 +713,14 @@ where
         self.instance.get_len()
     }
 
+    /// The keys of this column family only.
     fn keys(&self) -> Result<Vec<Vec<u8>>> {
-        self.instance.keys()
+        Ok(self
+            .instance
+            .scan_prefix(self.prefix_name, &[])?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect())
     }
 
     fn put_sync(&self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
@@ -362,7 +750,7 @@ pub trait ValueCodec: Clone + Sized + Debug + std::marker::Send + std::marker::S
     fn decode_value(data: &[u8]) -> Result<Self>;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum WriteOp<V> {
     Value(V),
     Deletion,
@@ -469,12 +857,18 @@ where
 
     fn remove(&self, key: K) -> Result<()>;
 
+    /// Remove the key and return the value before the removal, None if the key is absent.
+    /// Unlike a `get` followed by a `remove`, no write of the key is between them.
+    fn remove_returning(&self, key: K) -> Result<Option<V>>;
+
     fn write_batch(&self, batch: CodecWriteBatch<K, V>) -> Result<()>;
 
+    /// Encode every pair and write them in a single batch.
     fn put_all(&self, kvs: Vec<(K, V)>) -> Result<()> {
         self.write_batch(CodecWriteBatch::new_puts(kvs))
     }
 
+    /// Remove all the keys in a single batch.
     fn delete_all(&self, ks: Vec<K>) -> Result<()> {
         self.write_batch(CodecWriteBatch::new_deletes(ks))
     }
@@ -487,7 +881,15 @@ where
 
     fn get_raw(&self, key: K) -> Result<Option<Vec<u8>>>;
 
+    /// Iterate the column family in lexicographic order of the encoded keys, db instance only.
     fn iter(&self) -> Result<SchemaIterator<K, V>>;
+
+    /// Count the entries of the column family, the values are not decoded.
+    fn count(&self) -> Result<u64>;
+
+    /// Count the entries whose encoded key starts with `key_prefix`, such as the encoded
+    /// address of the keys prefixed by an address.
+    fn count_prefix(&self, key_prefix: &[u8]) -> Result<u64>;
 }
 
 impl KeyCodec for u64 {
@@ -590,6 +992,17 @@ where
         KVStore::remove(self.get_store(), key.encode_key()?)
     }
 
+    fn remove_returning(&self, key: K) -> Result<Option<V>> {
+        let store = self.get_store();
+        match store
+            .storage()
+            .remove_returning(store.prefix_name, key.encode_key()?)?
+        {
+            Some(value) => Ok(Some(<V>::decode_value(value.as_slice())?)),
+            None => Ok(None),
+        }
+    }
+
     fn write_batch(&self, batch: CodecWriteBatch<K, V>) -> Result<()> {
         KVStore::write_batch(self.get_store(), batch.try_into()?)
     }
@@ -621,4 +1034,14 @@ where
             .ok_or_else(|| format_err!("Only support scan on db storage instance"))?;
         db.iter::<K, V>(self.get_store().prefix_name)
     }
+
+    fn count(&self) -> Result<u64> {
+        let store = self.get_store();
+        store.storage().count(store.prefix_name)
+    }
+
+    fn count_prefix(&self, key_prefix: &[u8]) -> Result<u64> {
+        let store = self.get_store();
+        store.storage().count_prefix(store.prefix_name, key_prefix)
+    }
 }
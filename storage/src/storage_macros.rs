@@ -33,3 +33,22 @@ macro_rules! define_storage {
         }
     };
 }
+
+/// Implement `ValueCodec` for the types which are stored by their BCS serialization,
+/// the type must implement `Serialize` and `Deserialize`.
+#[macro_export]
+macro_rules! impl_bcs_value_codec {
+    ($($value_type: ty),+ $(,)?) => {
+        $(
+            impl $crate::storage::ValueCodec for $value_type {
+                fn encode_value(&self) -> $crate::anyhow::Result<Vec<u8>> {
+                    $crate::bcs_ext::to_bytes(self)
+                }
+
+                fn decode_value(data: &[u8]) -> $crate::anyhow::Result<Self> {
+                    $crate::bcs_ext::from_bytes(data)
+                }
+            }
+        )+
+    };
+}
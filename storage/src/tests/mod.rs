@@ -3,4 +3,6 @@
 mod test_accumulator;
 mod test_batch;
 mod test_block;
+mod test_memory_storage;
+mod test_overlay_storage;
 mod test_storage;
@@ -4,8 +4,12 @@
 use crate::batch::WriteBatch;
 use crate::cache_storage::CacheStorage;
 use crate::db_storage::DBStorage;
-use crate::storage::{CodecWriteBatch, InnerStore, ValueCodec};
-use crate::{DEFAULT_PREFIX_NAME, TRANSACTION_INFO_PREFIX_NAME_V2};
+use crate::memory_storage::MemoryStorage;
+use crate::storage::{
+    CodecKVStore, CodecWriteBatch, InnerStore, StorageInstance, ValueCodec, WriteOp,
+};
+use crate::transaction_info::TransactionInfoStorage;
+use crate::{BLOCK_PREFIX_NAME, DEFAULT_PREFIX_NAME, TRANSACTION_INFO_PREFIX_NAME_V2};
 use anyhow::Result;
 use crypto::HashValue;
 use starcoin_config::RocksdbConfig;
@@ -371,3 +375,100 @@ fn test_batch_comm_multi_get() -> Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_codec_put_all() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let instance = StorageInstance::new_cache_and_db_instance(
+        CacheStorage::new(None),
+        DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None)?,
+    );
+    let storage = TransactionInfoStorage::new(instance);
+    let txn_infos = (0..10u64)
+        .map(|i| {
+            RichTransactionInfo::new(
+                HashValue::random(),
+                rand::random(),
+                TransactionInfo::new(
+                    HashValue::random(),
+                    HashValue::zero(),
+                    vec![].as_slice(),
+                    i,
+                    KeptVMStatus::Executed,
+                ),
+                rand::random(),
+                i,
+            )
+        })
+        .collect::<Vec<_>>();
+    storage.put_all(
+        txn_infos
+            .iter()
+            .map(|txn_info| (txn_info.id(), txn_info.clone()))
+            .collect(),
+    )?;
+    for txn_info in &txn_infos {
+        assert_eq!(storage.get(txn_info.id())?, Some(txn_info.clone()));
+    }
+
+    let (removed, kept) = txn_infos.split_at(5);
+    storage.delete_all(removed.iter().map(|txn_info| txn_info.id()).collect())?;
+    for txn_info in removed {
+        assert_eq!(storage.get(txn_info.id())?, None);
+    }
+    for txn_info in kept {
+        assert_eq!(storage.get(txn_info.id())?, Some(txn_info.clone()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_write_batch_codec() -> Result<()> {
+    let mut batch = WriteBatch::new();
+    batch.put(HashValue::random().to_vec(), HashValue::random().to_vec())?;
+    batch.delete(HashValue::random().to_vec())?;
+    batch.put(vec![], vec![])?;
+    let (prefix_name, decoded) = WriteBatch::decode(&batch.encode(BLOCK_PREFIX_NAME)?)?;
+    assert_eq!(prefix_name, BLOCK_PREFIX_NAME);
+    assert_eq!(decoded, batch);
+    assert!(matches!(decoded.rows[1].1, WriteOp::Deletion));
+
+    let (_, decoded) = WriteBatch::decode(&WriteBatch::new().encode(DEFAULT_PREFIX_NAME)?)?;
+    assert!(decoded.rows.is_empty());
+    assert!(WriteBatch::decode(&[0xff]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_write_batch_replay() -> Result<()> {
+    let primary = MemoryStorage::new();
+    let replica = MemoryStorage::new();
+    let deleted_key = HashValue::random().to_vec();
+    for store in [&primary, &replica] {
+        store.put(BLOCK_PREFIX_NAME, deleted_key.clone(), vec![1])?;
+    }
+
+    let mut batch = WriteBatch::new();
+    for _ in 0..10 {
+        batch.put(HashValue::random().to_vec(), HashValue::random().to_vec())?;
+    }
+    batch.delete(deleted_key)?;
+    primary.write_batch(BLOCK_PREFIX_NAME, batch.clone())?;
+
+    let (prefix_name, decoded) = WriteBatch::decode(&batch.encode(BLOCK_PREFIX_NAME)?)?;
+    replica.write_batch(&prefix_name, decoded)?;
+
+    let mut primary_keys = primary.keys()?;
+    let mut replica_keys = replica.keys()?;
+    primary_keys.sort();
+    replica_keys.sort();
+    assert_eq!(primary_keys.len(), 10);
+    assert_eq!(primary_keys, replica_keys);
+    for (key, _) in batch.rows {
+        assert_eq!(
+            primary.get(BLOCK_PREFIX_NAME, key.clone())?,
+            replica.get(BLOCK_PREFIX_NAME, key)?
+        );
+    }
+    Ok(())
+}
@@ -10,11 +10,11 @@ use crypto::HashValue;
 use crate::block::{FailedBlock, OldFailedBlock};
 use crate::cache_storage::CacheStorage;
 use crate::db_storage::DBStorage;
-use crate::storage::StorageInstance;
-use crate::Storage;
+use crate::storage::{StorageInstance, ValueCodec};
+use crate::{BlockStore, Storage};
 use starcoin_config::RocksdbConfig;
 use starcoin_types::account_address::AccountAddress;
-use starcoin_types::block::{Block, BlockBody, BlockHeader, BlockHeaderExtra};
+use starcoin_types::block::{Block, BlockBody, BlockHeader, BlockHeaderBuilder, BlockHeaderExtra};
 use starcoin_types::genesis_config::ChainId;
 use starcoin_types::transaction::SignedUserTransaction;
 use starcoin_uint::U256;
@@ -228,3 +228,37 @@ fn test_save_failed_block() {
     assert_eq!(result.0, block);
     assert_eq!(result.3, "1".to_string());
 }
+
+#[test]
+fn test_block_header_seal_codec() {
+    let header = BlockHeaderBuilder::random()
+        .with_nonce(42)
+        .with_extra(BlockHeaderExtra::new([1u8, 2, 3, 4]))
+        .build();
+    let decoded = BlockHeader::decode_value(&header.encode_value().unwrap()).unwrap();
+    // the seal round-trips, and the id and the pow blob for the seal verification are stable.
+    assert_eq!(decoded, header);
+    assert_eq!(decoded.nonce(), 42);
+    assert_eq!(decoded.extra(), &BlockHeaderExtra::new([1u8, 2, 3, 4]));
+    assert_eq!(decoded.id(), header.id());
+    assert_eq!(decoded.as_pow_header_blob(), header.as_pow_header_blob());
+
+    // the seal is a part of the id, but not of the pow blob.
+    let resealed = header.as_builder().with_nonce(43).build();
+    assert_ne!(resealed.id(), header.id());
+    assert_eq!(resealed.as_pow_header_blob(), header.as_pow_header_blob());
+
+    let tmpdir = starcoin_config::temp_dir();
+    let storage = Storage::new(StorageInstance::new_db_instance(
+        DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None).unwrap(),
+    ))
+    .unwrap();
+    storage.block_storage.save_header(header.clone()).unwrap();
+    let saved = storage
+        .get_block_header_by_hash(header.id())
+        .unwrap()
+        .unwrap();
+    assert_eq!(saved.nonce(), header.nonce());
+    assert_eq!(saved.extra(), header.extra());
+    assert_eq!(saved.id(), header.id());
+}
@@ -0,0 +1,122 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::batch::WriteBatch;
+use crate::memory_storage::MemoryStorage;
+use crate::storage::{InnerStore, StorageInstance};
+use crate::{BlockStore, Storage, BLOCK_PREFIX_NAME, DEFAULT_PREFIX_NAME};
+use crypto::HashValue;
+use starcoin_types::block::BlockHeader;
+
+#[test]
+fn test_memory_storage_put_get_remove() {
+    let storage = MemoryStorage::new();
+    let key = HashValue::random().to_vec();
+    let value = HashValue::random().to_vec();
+    assert!(!storage
+        .contains_key(DEFAULT_PREFIX_NAME, key.clone())
+        .unwrap());
+    assert_eq!(storage.get(DEFAULT_PREFIX_NAME, key.clone()).unwrap(), None);
+
+    storage
+        .put(DEFAULT_PREFIX_NAME, key.clone(), value.clone())
+        .unwrap();
+    assert!(storage
+        .contains_key(DEFAULT_PREFIX_NAME, key.clone())
+        .unwrap());
+    assert_eq!(
+        storage.get(DEFAULT_PREFIX_NAME, key.clone()).unwrap(),
+        Some(value.clone())
+    );
+    // the column families are isolated.
+    assert_eq!(storage.get(BLOCK_PREFIX_NAME, key.clone()).unwrap(), None);
+
+    storage.remove(DEFAULT_PREFIX_NAME, key.clone()).unwrap();
+    assert_eq!(storage.get(DEFAULT_PREFIX_NAME, key.clone()).unwrap(), None);
+    // remove a non-existent key is ok.
+    storage.remove(BLOCK_PREFIX_NAME, key).unwrap();
+
+    let key = HashValue::random().to_vec();
+    storage
+        .put_sync(DEFAULT_PREFIX_NAME, key.clone(), value.clone())
+        .unwrap();
+    assert_eq!(storage.get(DEFAULT_PREFIX_NAME, key).unwrap(), Some(value));
+}
+
+#[test]
+fn test_memory_storage_batch() {
+    let storage = MemoryStorage::new();
+    let key1 = HashValue::random().to_vec();
+    let key2 = HashValue::random().to_vec();
+    let key3 = HashValue::random().to_vec();
+    let value = HashValue::random().to_vec();
+    storage
+        .put(DEFAULT_PREFIX_NAME, key1.clone(), value.clone())
+        .unwrap();
+
+    let mut batch = WriteBatch::new();
+    batch.put(key2.clone(), value.clone()).unwrap();
+    batch.delete(key1.clone()).unwrap();
+    storage.write_batch(DEFAULT_PREFIX_NAME, batch).unwrap();
+    assert_eq!(
+        storage.get(DEFAULT_PREFIX_NAME, key1.clone()).unwrap(),
+        None
+    );
+    assert_eq!(
+        storage.get(DEFAULT_PREFIX_NAME, key2.clone()).unwrap(),
+        Some(value.clone())
+    );
+
+    let mut batch = WriteBatch::new();
+    batch.put(key3.clone(), value.clone()).unwrap();
+    storage.write_batch_sync(BLOCK_PREFIX_NAME, batch).unwrap();
+
+    let result = storage
+        .multi_get(DEFAULT_PREFIX_NAME, vec![key1, key2, key3.clone()])
+        .unwrap();
+    assert_eq!(result, vec![None, Some(value.clone()), None]);
+    let result = storage.multi_get(BLOCK_PREFIX_NAME, vec![key3]).unwrap();
+    assert_eq!(result, vec![Some(value)]);
+}
+
+#[test]
+fn test_memory_storage_len_and_keys() {
+    let storage = MemoryStorage::new();
+    assert_eq!(storage.get_len().unwrap(), 0);
+    assert!(storage.keys().unwrap().is_empty());
+
+    let key1 = HashValue::random().to_vec();
+    let key2 = HashValue::random().to_vec();
+    let value = HashValue::random().to_vec();
+    storage
+        .put(DEFAULT_PREFIX_NAME, key1.clone(), value.clone())
+        .unwrap();
+    storage.put(BLOCK_PREFIX_NAME, key2.clone(), value).unwrap();
+    assert_eq!(storage.get_len().unwrap(), 2);
+
+    // the keys are composed with the separator, and ordered.
+    let keys = storage.keys().unwrap();
+    let mut expect = vec![
+        [DEFAULT_PREFIX_NAME.as_bytes(), &[0u8][..], key1.as_slice()].concat(),
+        [BLOCK_PREFIX_NAME.as_bytes(), &[0u8][..], key2.as_slice()].concat(),
+    ];
+    expect.sort();
+    assert_eq!(keys, expect);
+
+    storage.remove(DEFAULT_PREFIX_NAME, key1).unwrap();
+    assert_eq!(storage.get_len().unwrap(), 1);
+}
+
+#[test]
+fn test_memory_storage_instance() {
+    let storage = Storage::new(StorageInstance::new_memory_instance()).unwrap();
+    let block_header = BlockHeader::random();
+    storage
+        .block_storage
+        .save_header(block_header.clone())
+        .unwrap();
+    assert_eq!(
+        storage.get_block_header_by_hash(block_header.id()).unwrap(),
+        Some(block_header)
+    );
+}
@@ -0,0 +1,140 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::db_storage::DBStorage;
+use crate::storage::{InnerStore, StorageInstance, WriteBatch};
+use crate::{BlockStore, Storage, BLOCK_PREFIX_NAME, DEFAULT_PREFIX_NAME};
+use crypto::HashValue;
+use starcoin_config::RocksdbConfig;
+use starcoin_types::block::BlockHeader;
+
+#[test]
+fn test_overlay_storage_read_and_mask() {
+    let base = StorageInstance::new_memory_instance();
+    let key1 = HashValue::random().to_vec();
+    let key2 = HashValue::random().to_vec();
+    let key3 = HashValue::random().to_vec();
+    let value1 = HashValue::random().to_vec();
+    let value2 = HashValue::random().to_vec();
+    base.put(DEFAULT_PREFIX_NAME, key1.clone(), value1.clone())
+        .unwrap();
+    base.put(DEFAULT_PREFIX_NAME, key2.clone(), value1.clone())
+        .unwrap();
+
+    let overlay = StorageInstance::new_overlay_instance(base.clone());
+    // the keys not written in the overlay fall through to the base.
+    assert_eq!(
+        overlay.get(DEFAULT_PREFIX_NAME, key1.clone()).unwrap(),
+        Some(value1.clone())
+    );
+    assert_eq!(overlay.get(BLOCK_PREFIX_NAME, key1.clone()).unwrap(), None);
+
+    // the writes of the overlay mask the base, and the base is untouched.
+    overlay
+        .put(DEFAULT_PREFIX_NAME, key1.clone(), value2.clone())
+        .unwrap();
+    overlay.remove(DEFAULT_PREFIX_NAME, key2.clone()).unwrap();
+    let mut batch = WriteBatch::new();
+    batch.put(key3.clone(), value2.clone()).unwrap();
+    overlay.write_batch(DEFAULT_PREFIX_NAME, batch).unwrap();
+    assert_eq!(
+        overlay
+            .multi_get(
+                DEFAULT_PREFIX_NAME,
+                vec![key1.clone(), key2.clone(), key3.clone()],
+            )
+            .unwrap(),
+        vec![Some(value2.clone()), None, Some(value2)]
+    );
+    assert!(!overlay
+        .contains_key(DEFAULT_PREFIX_NAME, key2.clone())
+        .unwrap());
+    assert!(overlay
+        .contains_key(DEFAULT_PREFIX_NAME, key3.clone())
+        .unwrap());
+    assert_eq!(overlay.count(DEFAULT_PREFIX_NAME).unwrap(), 2);
+    assert_eq!(overlay.get_len().unwrap(), 2);
+
+    assert_eq!(
+        base.get(DEFAULT_PREFIX_NAME, key1).unwrap(),
+        Some(value1.clone())
+    );
+    assert_eq!(base.get(DEFAULT_PREFIX_NAME, key2).unwrap(), Some(value1));
+    assert_eq!(base.get(DEFAULT_PREFIX_NAME, key3).unwrap(), None);
+}
+
+#[test]
+fn test_overlay_storage_discard_and_commit() {
+    let tmpdir = starcoin_config::temp_dir();
+    let db = DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None).unwrap();
+    let base = StorageInstance::new_db_instance(db);
+    let key1 = HashValue::random().to_vec();
+    let key2 = HashValue::random().to_vec();
+    let value = HashValue::random().to_vec();
+    base.put(DEFAULT_PREFIX_NAME, key1.clone(), value.clone())
+        .unwrap();
+
+    let overlay = StorageInstance::new_overlay_instance(base.clone());
+    overlay.remove(DEFAULT_PREFIX_NAME, key1.clone()).unwrap();
+    overlay
+        .put(BLOCK_PREFIX_NAME, key2.clone(), value.clone())
+        .unwrap();
+    // the discarded writes never reach the base.
+    overlay.discard_overlay();
+    assert_eq!(
+        overlay.get(DEFAULT_PREFIX_NAME, key1.clone()).unwrap(),
+        Some(value.clone())
+    );
+    assert_eq!(overlay.get(BLOCK_PREFIX_NAME, key2.clone()).unwrap(), None);
+    overlay.commit_overlay().unwrap();
+    assert_eq!(
+        base.get(DEFAULT_PREFIX_NAME, key1.clone()).unwrap(),
+        Some(value.clone())
+    );
+    assert_eq!(base.get(BLOCK_PREFIX_NAME, key2.clone()).unwrap(), None);
+
+    // the committed writes are visible in the base, and the overlay is empty after commit.
+    overlay.remove(DEFAULT_PREFIX_NAME, key1.clone()).unwrap();
+    overlay
+        .put(BLOCK_PREFIX_NAME, key2.clone(), value.clone())
+        .unwrap();
+    overlay.commit_overlay().unwrap();
+    assert_eq!(base.get(DEFAULT_PREFIX_NAME, key1.clone()).unwrap(), None);
+    assert_eq!(
+        base.get(BLOCK_PREFIX_NAME, key2.clone()).unwrap(),
+        Some(value.clone())
+    );
+    base.put(DEFAULT_PREFIX_NAME, key1.clone(), value.clone())
+        .unwrap();
+    assert_eq!(overlay.get(DEFAULT_PREFIX_NAME, key1).unwrap(), Some(value));
+}
+
+#[test]
+fn test_overlay_storage_instance() {
+    let base = StorageInstance::new_memory_instance();
+    let overlay = StorageInstance::new_overlay_instance(base.clone());
+    let storage = Storage::new(overlay.clone()).unwrap();
+    let block_header = BlockHeader::random();
+    storage
+        .block_storage
+        .save_header(block_header.clone())
+        .unwrap();
+    assert_eq!(
+        storage.get_block_header_by_hash(block_header.id()).unwrap(),
+        Some(block_header.clone())
+    );
+    let base_storage = Storage::new(base).unwrap();
+    assert_eq!(
+        base_storage
+            .get_block_header_by_hash(block_header.id())
+            .unwrap(),
+        None
+    );
+    overlay.commit_overlay().unwrap();
+    assert_eq!(
+        base_storage
+            .get_block_header_by_hash(block_header.id())
+            .unwrap(),
+        Some(block_header)
+    );
+}
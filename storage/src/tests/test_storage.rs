@@ -3,26 +3,44 @@
 
 extern crate chrono;
 
+use crate::block::BlockHeaderStorage;
 use crate::cache_storage::CacheStorage;
-use crate::db_storage::DBStorage;
-use crate::storage::{CodecKVStore, InnerStore, StorageInstance, ValueCodec};
+use crate::chain_info::ChainInfoStorage;
+use crate::db_storage::{ColumnFamilyOptions, DBCompressionType, DBStorage, ReadRetryPolicy};
+use crate::errors::{StorageError, StorageInitError};
+use crate::event_index::{EventIndex, EventIndexKey, EventIndexStorage};
+use crate::storage::{
+    CodecKVStore, InnerStore, ReadSource, StorageInstance, ValueCodec, WriteBatch, WriteOp,
+};
 use crate::transaction_info::{BlockTransactionInfo, OldTransactionInfoStorage};
+use crate::upgrade::{DBUpgrade, Migration};
 use crate::{
-    BlockInfoStore, BlockStore, BlockTransactionInfoStore, Storage, StorageVersion,
-    TransactionStore, DEFAULT_PREFIX_NAME, TRANSACTION_INFO_PREFIX_NAME,
-    TRANSACTION_INFO_PREFIX_NAME_V2,
+    define_storage, impl_bcs_value_codec, BlockInfoStore, BlockStore, BlockTransactionInfoStore,
+    ColumnFamily, ContractEventStore, EventIndexStore, Storage, StorageVersion, Store,
+    TransactionStore, BLOCK_HEADER_PREFIX_NAME, BLOCK_PREFIX_NAME, DEFAULT_PREFIX_NAME,
+    TRANSACTION_INFO_PREFIX_NAME, TRANSACTION_INFO_PREFIX_NAME_V2, TRANSACTION_PREFIX_NAME,
 };
-use anyhow::Result;
+use anyhow::{format_err, Result};
 use crypto::HashValue;
+use serde::{Deserialize, Serialize};
 use starcoin_accumulator::accumulator_info::AccumulatorInfo;
-use starcoin_config::RocksdbConfig;
+use starcoin_config::{RocksdbConfig, WalMode};
+use starcoin_state_store_api::{StateNode, StateNodeStore};
+use starcoin_types::account_address::AccountAddress;
 use starcoin_types::block::{Block, BlockBody, BlockHeader, BlockInfo};
-use starcoin_types::startup_info::SnapshotRange;
+use starcoin_types::contract_event::ContractEvent;
+use starcoin_types::event::EventKey;
+use starcoin_types::language_storage::TypeTag;
+use starcoin_types::startup_info::{ChainStatus, SnapshotRange, StartupInfo};
 use starcoin_types::transaction::{
     RichTransactionInfo, SignedUserTransaction, Transaction, TransactionInfo,
 };
 use starcoin_types::vm_error::KeptVMStatus;
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[test]
 fn test_reopen() {
@@ -47,6 +65,41 @@ fn test_reopen() {
     }
 }
 
+#[test]
+fn test_open_with_cfs_options() {
+    let tmpdir = starcoin_config::temp_dir();
+    let mut cf_options = HashMap::new();
+    cf_options.insert(
+        BLOCK_PREFIX_NAME,
+        ColumnFamilyOptions {
+            block_cache_size: Some(8 * 1024 * 1024),
+            write_buffer_size: Some(4 * 1024 * 1024),
+            compression: DBCompressionType::None,
+            bloom_filter_bits: Some(10.0),
+        },
+    );
+    let db = DBStorage::open_with_cfs_options(
+        tmpdir.path(),
+        StorageVersion::current_version()
+            .get_column_family_names()
+            .to_vec(),
+        cf_options,
+        false,
+        RocksdbConfig::default(),
+        None,
+    )
+    .unwrap();
+    let key = HashValue::random();
+    let value = HashValue::random();
+    for prefix_name in [BLOCK_PREFIX_NAME, DEFAULT_PREFIX_NAME] {
+        db.put(prefix_name, key.to_vec(), value.to_vec()).unwrap();
+        assert_eq!(
+            db.get(prefix_name, key.to_vec()).unwrap(),
+            Some(value.to_vec())
+        );
+    }
+}
+
 #[test]
 fn test_open_read_only() {
     let tmpdir = starcoin_config::temp_dir();
@@ -72,6 +125,77 @@ fn test_open_read_only() {
     assert_eq!(result, Some(value.to_vec()));
 }
 
+#[test]
+fn test_read_retry_policy() {
+    let policy = ReadRetryPolicy::new(3, Duration::from_millis(1));
+    // the mock read fails twice by the transient error, then succeeds.
+    let mut attempts = 0;
+    let value = policy
+        .call(|| {
+            attempts += 1;
+            if attempts <= 2 {
+                Err(StorageError::Transient(format_err!("busy")))
+            } else {
+                Ok(attempts)
+            }
+        })
+        .unwrap();
+    assert_eq!(value, 3);
+
+    // the fatal error is not retried.
+    let mut attempts = 0;
+    let result: Result<()> = policy.call(|| {
+        attempts += 1;
+        Err(StorageError::Fatal(format_err!("corruption")))
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts, 1);
+
+    // give up after the max retries.
+    let mut attempts = 0;
+    let result: Result<()> = policy.call(|| {
+        attempts += 1;
+        Err(StorageError::Transient(format_err!("busy")))
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts, 4);
+}
+
+#[test]
+fn test_open_with_unregistered_cf() {
+    for version in [
+        StorageVersion::V1,
+        StorageVersion::V2,
+        StorageVersion::V3,
+        StorageVersion::V4,
+    ] {
+        for cf_name in version.get_column_family_names() {
+            assert!(ColumnFamily::from_name(cf_name).is_some(), "{}", cf_name);
+        }
+    }
+    assert_eq!(
+        ColumnFamily::from_name(BLOCK_PREFIX_NAME),
+        Some(ColumnFamily::Block)
+    );
+
+    let tmpdir = starcoin_config::temp_dir();
+    let mut column_families = StorageVersion::current_version()
+        .get_column_family_names()
+        .to_vec();
+    column_families.push("blocks");
+    let result = DBStorage::open_with_cfs(
+        tmpdir.path(),
+        column_families,
+        false,
+        RocksdbConfig::default(),
+        None,
+    );
+    let err = result
+        .err()
+        .expect("the unregistered cf should be rejected");
+    assert!(err.to_string().contains("blocks"), "{}", err);
+}
+
 #[test]
 fn test_storage() {
     let tmpdir = starcoin_config::temp_dir();
@@ -367,6 +491,55 @@ pub fn test_db_upgrade() -> Result<()> {
     Ok(())
 }
 
+fn upgrade_test_value(instance: &mut StorageInstance) -> Result<()> {
+    let value = instance
+        .get(DEFAULT_PREFIX_NAME, b"test_key".to_vec())?
+        .ok_or_else(|| format_err!("test value should exist"))?;
+    instance.put(
+        DEFAULT_PREFIX_NAME,
+        b"test_key".to_vec(),
+        [value, b"_v2".to_vec()].concat(),
+    )
+}
+
+#[test]
+pub fn test_db_upgrade_with_migration() -> Result<()> {
+    let mut instance = StorageInstance::new_memory_instance();
+    instance.put(DEFAULT_PREFIX_NAME, b"test_key".to_vec(), b"v1".to_vec())?;
+    let migrations = vec![Migration::new(
+        StorageVersion::V1,
+        StorageVersion::V2,
+        upgrade_test_value,
+    )];
+
+    DBUpgrade::check_upgrade_with(&mut instance, StorageVersion::V2, &migrations)?;
+    let chain_info_storage = ChainInfoStorage::new(instance.clone());
+    assert_eq!(
+        chain_info_storage.get_storage_version()?,
+        StorageVersion::V2
+    );
+    assert_eq!(
+        instance.get(DEFAULT_PREFIX_NAME, b"test_key".to_vec())?,
+        Some(b"v1_v2".to_vec())
+    );
+
+    // the migration is not run again on the upgraded storage.
+    DBUpgrade::check_upgrade_with(&mut instance, StorageVersion::V2, &migrations)?;
+    assert_eq!(
+        instance.get(DEFAULT_PREFIX_NAME, b"test_key".to_vec())?,
+        Some(b"v1_v2".to_vec())
+    );
+
+    // missing migration from V2 to V3.
+    let result = DBUpgrade::check_upgrade_with(&mut instance, StorageVersion::V3, &migrations);
+    assert!(result.is_err());
+    // the storage is newer than the code.
+    chain_info_storage.set_storage_version(StorageVersion::V3)?;
+    let result = DBUpgrade::check_upgrade_with(&mut instance, StorageVersion::V2, &migrations);
+    assert!(result.is_err());
+    Ok(())
+}
+
 #[test]
 pub fn test_snapshot_range() -> Result<()> {
     let tmpdir = starcoin_config::temp_dir();
@@ -470,3 +643,885 @@ pub fn test_cache_evict_multi_get() -> Result<()> {
     assert_eq!(infos.get(2).unwrap().clone().unwrap(), transaction_info3);
     Ok(())
 }
+
+#[test]
+fn test_cache_snapshot() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let snapshot_path = tmpdir.path().join("cache.snapshot");
+    let instance = StorageInstance::new_cache_and_db_instance(
+        CacheStorage::new(None),
+        DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None)?,
+    );
+    let keys = (0..10)
+        .map(|_| HashValue::random().to_vec())
+        .collect::<Vec<_>>();
+    for key in &keys {
+        instance.put(BLOCK_PREFIX_NAME, key.clone(), key.clone())?;
+    }
+    assert_eq!(instance.save_cache_snapshot(&snapshot_path)?, keys.len());
+
+    // the db changes after the snapshot, so the snapshot entry of keys[0] is stale.
+    let db = instance.db().unwrap();
+    db.put(BLOCK_PREFIX_NAME, keys[0].clone(), vec![0u8])?;
+
+    let cache = CacheStorage::new(None);
+    assert_eq!(cache.load_snapshot(&snapshot_path, db)?, keys.len() - 1);
+    // read the cache directly, the db is not touched.
+    assert_eq!(cache.get(BLOCK_PREFIX_NAME, keys[0].clone())?, None);
+    for key in &keys[1..] {
+        assert_eq!(
+            cache.get(BLOCK_PREFIX_NAME, key.clone())?,
+            Some(key.clone())
+        );
+    }
+
+    // a missing snapshot is skipped.
+    let missing = tmpdir.path().join("missing.snapshot");
+    assert_eq!(instance.load_cache_snapshot(&missing)?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_cache_byte_budget() -> Result<()> {
+    let budget = 1000;
+    let cache = CacheStorage::new(None).with_byte_budget(budget);
+    let key = |name: &str| name.as_bytes().to_vec();
+    cache.put(DEFAULT_PREFIX_NAME, key("large1"), vec![1u8; 600])?;
+    for i in 0..10 {
+        cache.put(
+            DEFAULT_PREFIX_NAME,
+            key(&format!("small{}", i)),
+            vec![0u8; 10],
+        )?;
+    }
+    assert_eq!(cache.get_bytes(), 700);
+    // touch the large value, so it's more recently used than the small values.
+    assert!(cache.get(DEFAULT_PREFIX_NAME, key("large1"))?.is_some());
+
+    cache.put(DEFAULT_PREFIX_NAME, key("large2"), vec![2u8; 400])?;
+    assert!(cache.get_bytes() <= budget);
+    assert!(cache.contains_key(DEFAULT_PREFIX_NAME, key("large1"))?);
+    assert!(cache.contains_key(DEFAULT_PREFIX_NAME, key("large2"))?);
+    for i in 0..10 {
+        assert!(!cache.contains_key(DEFAULT_PREFIX_NAME, key(&format!("small{}", i)))?);
+    }
+
+    cache.put(DEFAULT_PREFIX_NAME, key("large3"), vec![3u8; 500])?;
+    assert!(cache.get_bytes() <= budget);
+    assert!(!cache.contains_key(DEFAULT_PREFIX_NAME, key("large1"))?);
+    assert!(cache.contains_key(DEFAULT_PREFIX_NAME, key("large2"))?);
+    assert!(cache.contains_key(DEFAULT_PREFIX_NAME, key("large3"))?);
+
+    // replace and remove update the accounting.
+    cache.put(DEFAULT_PREFIX_NAME, key("large3"), vec![3u8; 100])?;
+    cache.remove(DEFAULT_PREFIX_NAME, key("large2"))?;
+    assert_eq!(cache.get_bytes(), 100);
+    Ok(())
+}
+
+#[test]
+fn test_cache_column_family_isolation() -> Result<()> {
+    let cache = CacheStorage::new(None);
+    let key = HashValue::random().to_vec();
+    cache.put(BLOCK_PREFIX_NAME, key.clone(), vec![1])?;
+    cache.put(DEFAULT_PREFIX_NAME, key.clone(), vec![2])?;
+    assert_eq!(cache.get(BLOCK_PREFIX_NAME, key.clone())?, Some(vec![1]));
+    assert_eq!(cache.get(DEFAULT_PREFIX_NAME, key.clone())?, Some(vec![2]));
+
+    // `block` is a prefix of `block_header`, the keys of them must not collide either.
+    let block_key = [b"_header".as_ref(), key.as_slice()].concat();
+    cache.put(BLOCK_HEADER_PREFIX_NAME, key.clone(), vec![3])?;
+    cache.put(BLOCK_PREFIX_NAME, block_key.clone(), vec![4])?;
+    assert_eq!(
+        cache.get(BLOCK_HEADER_PREFIX_NAME, key.clone())?,
+        Some(vec![3])
+    );
+    assert_eq!(cache.get(BLOCK_PREFIX_NAME, block_key)?, Some(vec![4]));
+
+    cache.remove(DEFAULT_PREFIX_NAME, key.clone())?;
+    assert_eq!(cache.get(DEFAULT_PREFIX_NAME, key.clone())?, None);
+    assert_eq!(cache.get(BLOCK_PREFIX_NAME, key)?, Some(vec![1]));
+    Ok(())
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct CodecValue {
+    name: String,
+    numbers: Vec<u64>,
+    hash: Option<HashValue>,
+}
+
+impl_bcs_value_codec!(CodecValue);
+define_storage!(
+    CodecValueStorage,
+    HashValue,
+    CodecValue,
+    DEFAULT_PREFIX_NAME
+);
+
+#[test]
+fn test_bcs_value_codec() -> Result<()> {
+    let storage = CodecValueStorage::new(StorageInstance::new_cache_instance());
+    let key = HashValue::random();
+    let value = CodecValue {
+        name: "test".to_string(),
+        numbers: vec![1, 2, 3],
+        hash: Some(HashValue::random()),
+    };
+    storage.put(key, value.clone())?;
+    assert_eq!(storage.get(key)?, Some(value.clone()));
+    assert_eq!(storage.get_raw(key)?, Some(bcs_ext::to_bytes(&value)?));
+    assert_eq!(storage.get(HashValue::random())?, None);
+    Ok(())
+}
+
+#[test]
+fn test_storage_flush() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let instances = vec![
+        StorageInstance::new_cache_instance(),
+        StorageInstance::new_memory_instance(),
+        StorageInstance::new_db_instance(DBStorage::new(
+            tmpdir.path().join("db"),
+            RocksdbConfig::default(),
+            None,
+        )?),
+        StorageInstance::new_cache_and_db_instance(
+            CacheStorage::new(None),
+            DBStorage::new(
+                tmpdir.path().join("cache_and_db"),
+                RocksdbConfig::default(),
+                None,
+            )?,
+        ),
+    ];
+    for instance in instances {
+        let storage = Storage::new(instance)?;
+        let block_header = BlockHeader::random();
+        storage.block_storage.save_header(block_header.clone())?;
+        storage.flush()?;
+        assert_eq!(
+            storage.get_block_header_by_hash(block_header.id())?,
+            Some(block_header)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_db_wal_mode() -> Result<()> {
+    for wal_mode in [WalMode::Async, WalMode::Sync, WalMode::Disabled] {
+        let tmpdir = starcoin_config::temp_dir();
+        let rocksdb_config = RocksdbConfig {
+            wal_mode,
+            ..Default::default()
+        };
+        let key1 = HashValue::random().to_vec();
+        let key2 = HashValue::random().to_vec();
+        let key3 = HashValue::random().to_vec();
+        let value = HashValue::random().to_vec();
+        {
+            let db = DBStorage::new(tmpdir.path(), rocksdb_config, None)?;
+            assert_eq!(db.wal_mode(), wal_mode);
+            db.put(DEFAULT_PREFIX_NAME, key1.clone(), value.clone())?;
+            db.put_sync(BLOCK_PREFIX_NAME, key2.clone(), value.clone())?;
+            let mut batch = WriteBatch::new();
+            batch.put(key3.clone(), value.clone())?;
+            db.write_batch(BLOCK_PREFIX_NAME, batch)?;
+            assert_eq!(
+                db.get(DEFAULT_PREFIX_NAME, key1.clone())?,
+                Some(value.clone())
+            );
+            assert_eq!(
+                db.get(BLOCK_PREFIX_NAME, key3.clone())?,
+                Some(value.clone())
+            );
+            StorageInstance::new_db_instance(db).flush()?;
+        }
+        // the flushed writes survive the reopen in every mode.
+        let db = DBStorage::new(tmpdir.path(), rocksdb_config, None)?;
+        assert_eq!(db.get(DEFAULT_PREFIX_NAME, key1)?, Some(value.clone()));
+        assert_eq!(db.get(BLOCK_PREFIX_NAME, key2)?, Some(value.clone()));
+        assert_eq!(db.get(BLOCK_PREFIX_NAME, key3)?, Some(value));
+        assert_eq!(db.get_cf_len(BLOCK_PREFIX_NAME)?, 2);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_db_self_test() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let rocksdb_config = RocksdbConfig {
+        self_test: true,
+        ..Default::default()
+    };
+    {
+        let db = DBStorage::new(tmpdir.path(), rocksdb_config, None)?;
+        let mut batch = WriteBatch::new();
+        for _ in 0..1000 {
+            batch.put(HashValue::random().to_vec(), HashValue::random().to_vec())?;
+        }
+        db.write_batch(BLOCK_PREFIX_NAME, batch)?;
+        db.flush_all()?;
+    }
+    // the healthy db passes the self-test.
+    drop(DBStorage::new(tmpdir.path(), rocksdb_config, None)?);
+
+    // corrupt the first data block of the largest sst file, it's of the block column family.
+    let sst_path = std::fs::read_dir(tmpdir.path().join("starcoindb"))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map(|ext| ext == "sst").unwrap_or(false))
+        .max_by_key(|path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+        .ok_or_else(|| format_err!("no sst file found"))?;
+    let mut content = std::fs::read(&sst_path)?;
+    content[100..200].fill(0xff);
+    std::fs::write(&sst_path, content)?;
+
+    // the open without the self-test does not read the data blocks, so it does not find it.
+    drop(DBStorage::new(
+        tmpdir.path(),
+        RocksdbConfig::default(),
+        None,
+    )?);
+    let err = DBStorage::new(tmpdir.path(), rocksdb_config, None)
+        .err()
+        .expect("the self-test should find the corrupted column family");
+    assert!(err.downcast_ref::<StorageInitError>().is_some());
+    assert!(err.to_string().contains(BLOCK_PREFIX_NAME));
+    Ok(())
+}
+
+#[test]
+fn test_db_cf_metrics() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let db = DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None)?;
+    for _ in 0..10 {
+        db.put(
+            BLOCK_PREFIX_NAME,
+            HashValue::random().to_vec(),
+            HashValue::random().to_vec(),
+        )?;
+    }
+    let keys = (0..5)
+        .map(|_| HashValue::random().to_vec())
+        .collect::<Vec<_>>();
+    let mut batch = WriteBatch::new();
+    for key in &keys {
+        batch.put(key.clone(), HashValue::random().to_vec())?;
+    }
+    db.write_batch(BLOCK_HEADER_PREFIX_NAME, batch)?;
+    for key in &keys[0..3] {
+        assert!(db.get(BLOCK_HEADER_PREFIX_NAME, key.clone())?.is_some());
+    }
+    db.multi_get(BLOCK_HEADER_PREFIX_NAME, keys[3..].to_vec())?;
+    // the size is of the sst files.
+    db.flush_all()?;
+
+    let metrics = db.cf_metrics()?;
+    let block_metrics = &metrics[BLOCK_PREFIX_NAME];
+    assert_eq!(block_metrics.write_ops, 10);
+    assert_eq!(block_metrics.read_ops, 0);
+    assert_eq!(block_metrics.entries, 10);
+    assert!(block_metrics.size_bytes > 0);
+    let header_metrics = &metrics[BLOCK_HEADER_PREFIX_NAME];
+    assert_eq!(header_metrics.write_ops, 5);
+    assert_eq!(header_metrics.read_ops, 5);
+    assert_eq!(header_metrics.entries, 5);
+    assert!(header_metrics.size_bytes > 0);
+    assert_eq!(metrics[TRANSACTION_PREFIX_NAME].write_ops, 0);
+    Ok(())
+}
+
+#[test]
+fn test_cache_and_db_put_all() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let cache = Arc::new(CacheStorage::new(None));
+    let instance = StorageInstance::CacheAndDb {
+        cache: cache.clone(),
+        db: Arc::new(DBStorage::new(
+            tmpdir.path(),
+            RocksdbConfig::default(),
+            None,
+        )?),
+    };
+    let kvs = (0..10)
+        .map(|_| (HashValue::random().to_vec(), HashValue::random().to_vec()))
+        .collect::<Vec<_>>();
+
+    // the db has no such column family, the write fails and the cache is untouched.
+    assert!(instance.put_all("unknown_cf", kvs.clone()).is_err());
+    assert!(instance.put("unknown_cf", vec![1], vec![1]).is_err());
+    assert!(instance.remove("unknown_cf", vec![1]).is_err());
+    assert_eq!(cache.get_len()?, 0);
+
+    instance.put_all(DEFAULT_PREFIX_NAME, kvs.clone())?;
+    for (key, value) in kvs {
+        assert_eq!(
+            cache.get(DEFAULT_PREFIX_NAME, key.clone())?,
+            Some(value.clone())
+        );
+        assert_eq!(
+            instance.db().unwrap().get(DEFAULT_PREFIX_NAME, key)?,
+            Some(value)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_cache_and_db_read_your_writes() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let db = Arc::new(DBStorage::new(
+        tmpdir.path(),
+        RocksdbConfig::default(),
+        None,
+    )?);
+    let instance = StorageInstance::CacheAndDb {
+        cache: Arc::new(CacheStorage::new(None)),
+        db: db.clone(),
+    };
+    let shared_key = HashValue::random().to_vec();
+    let writers = (0..8u8)
+        .map(|id| {
+            let instance = instance.clone();
+            let shared_key = shared_key.clone();
+            std::thread::spawn(move || -> Result<()> {
+                let key = HashValue::random().to_vec();
+                for i in 0..200u32 {
+                    let value = [vec![id], i.to_be_bytes().to_vec()].concat();
+                    instance.put(BLOCK_PREFIX_NAME, key.clone(), value.clone())?;
+                    assert_eq!(instance.get(BLOCK_PREFIX_NAME, key.clone())?, Some(value));
+                    // all the writers race on the shared key.
+                    instance.put(BLOCK_PREFIX_NAME, shared_key.clone(), vec![id])?;
+                }
+                instance.remove(BLOCK_PREFIX_NAME, key.clone())?;
+                assert_eq!(instance.get(BLOCK_PREFIX_NAME, key)?, None);
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+    for writer in writers {
+        writer.join().unwrap()?;
+    }
+    // the cache ends with the same value as the db.
+    assert_eq!(
+        instance.get(BLOCK_PREFIX_NAME, shared_key.clone())?,
+        db.get(BLOCK_PREFIX_NAME, shared_key)?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cache_and_db_read_source() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let db = Arc::new(DBStorage::new(
+        tmpdir.path(),
+        RocksdbConfig::default(),
+        None,
+    )?);
+    let instance = StorageInstance::CacheAndDb {
+        cache: Arc::new(CacheStorage::new(None)),
+        db: db.clone(),
+    };
+    // the cold key is only in the db.
+    let cold_key = HashValue::random().to_vec();
+    db.put(BLOCK_PREFIX_NAME, cold_key.clone(), vec![1])?;
+    assert_eq!(
+        instance.get_with_source(BLOCK_PREFIX_NAME, cold_key)?,
+        (Some(vec![1]), ReadSource::Db)
+    );
+
+    // the warm key is written through the cache.
+    let warm_key = HashValue::random().to_vec();
+    instance.put(BLOCK_PREFIX_NAME, warm_key.clone(), vec![2])?;
+    assert_eq!(
+        instance.get_with_source(BLOCK_PREFIX_NAME, warm_key.clone())?,
+        (Some(vec![2]), ReadSource::Cache)
+    );
+    instance.remove(BLOCK_PREFIX_NAME, warm_key.clone())?;
+    assert_eq!(
+        instance.get_with_source(BLOCK_PREFIX_NAME, warm_key)?,
+        (None, ReadSource::Missing)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cache_and_db_cache_write_failure() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let db = Arc::new(DBStorage::new(
+        tmpdir.path(),
+        RocksdbConfig::default(),
+        None,
+    )?);
+    let cache = Arc::new(CacheStorage::new(None));
+    let instance = StorageInstance::CacheAndDb {
+        cache: cache.clone(),
+        db: db.clone(),
+    };
+    let key = HashValue::random().to_vec();
+    instance.put(BLOCK_PREFIX_NAME, key.clone(), vec![1])?;
+    assert_eq!(cache.get(BLOCK_PREFIX_NAME, key.clone())?, Some(vec![1]));
+
+    // the cache write fails after the db write, the write still succeeds.
+    cache.write_through_with(
+        BLOCK_PREFIX_NAME,
+        WriteBatch::new_with_rows(vec![(key.clone(), WriteOp::Value(vec![2]))]),
+        |batch| db.write_batch(BLOCK_PREFIX_NAME, batch),
+        |_batch| Err(format_err!("injected cache failure")),
+    )?;
+    // the stale value is dropped from the cache, so the read falls through to the db.
+    assert_eq!(cache.get(BLOCK_PREFIX_NAME, key.clone())?, None);
+    assert_eq!(db.get(BLOCK_PREFIX_NAME, key.clone())?, Some(vec![2]));
+    assert_eq!(instance.get(BLOCK_PREFIX_NAME, key.clone())?, Some(vec![2]));
+
+    // the db write failure is fatal, and the cache is not touched.
+    instance.put(BLOCK_PREFIX_NAME, key.clone(), vec![3])?;
+    assert!(cache
+        .write_through(
+            BLOCK_PREFIX_NAME,
+            WriteBatch::new_with_rows(vec![(key.clone(), WriteOp::Value(vec![4]))]),
+            |_batch| Err(format_err!("injected db failure")),
+        )
+        .is_err());
+    assert_eq!(cache.get(BLOCK_PREFIX_NAME, key.clone())?, Some(vec![3]));
+    assert_eq!(db.get(BLOCK_PREFIX_NAME, key)?, Some(vec![3]));
+    Ok(())
+}
+
+#[test]
+fn test_db_get_len() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let keys = (0..10)
+        .map(|_| HashValue::random().to_vec())
+        .collect::<Vec<_>>();
+    let value = HashValue::random().to_vec();
+    {
+        let db = DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None)?;
+        assert_eq!(db.get_len()?, 0);
+        for key in &keys {
+            db.put(BLOCK_PREFIX_NAME, key.clone(), value.clone())?;
+        }
+        // overwrite a key and remove an absent key do not change the count.
+        db.put(BLOCK_PREFIX_NAME, keys[0].clone(), value.clone())?;
+        db.remove(BLOCK_PREFIX_NAME, HashValue::random().to_vec())?;
+        db.remove(BLOCK_PREFIX_NAME, keys[1].clone())?;
+        assert_eq!(db.get_len()?, 9);
+
+        let mut batch = WriteBatch::new();
+        batch.put(keys[1].clone(), value.clone())?;
+        batch.delete(keys[1].clone())?;
+        batch.put(keys[1].clone(), value.clone())?;
+        batch.delete(keys[2].clone())?;
+        db.write_batch(BLOCK_PREFIX_NAME, batch)?;
+        db.put_sync(TRANSACTION_PREFIX_NAME, keys[0].clone(), value.clone())?;
+        // the keys of the default column family are not counted.
+        db.put(DEFAULT_PREFIX_NAME, keys[0].clone(), value.clone())?;
+        assert_eq!(db.get_len()?, 10);
+    }
+    {
+        let db = DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None)?;
+        assert_eq!(db.get_len()?, 10);
+        // lose the persisted count, it's reconciled by scanning the column family.
+        db.remove(
+            DEFAULT_PREFIX_NAME,
+            DBStorage::cf_len_key(BLOCK_PREFIX_NAME),
+        )?;
+    }
+    let db = DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None)?;
+    // the lost count is reconciled and persisted again on open.
+    assert!(db
+        .get(
+            DEFAULT_PREFIX_NAME,
+            DBStorage::cf_len_key(BLOCK_PREFIX_NAME)
+        )?
+        .is_some());
+    assert_eq!(db.get_len()?, 10);
+    db.remove(BLOCK_PREFIX_NAME, keys[3].clone())?;
+    let instance = StorageInstance::new_db_instance(db);
+    assert_eq!(instance.get_len()?, 9);
+    Ok(())
+}
+
+#[test]
+fn test_compact_block_bodies() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let storage = Storage::new(StorageInstance::new_db_instance(DBStorage::new(
+        tmpdir.path(),
+        RocksdbConfig::default(),
+        None,
+    )?))?;
+    let blocks = (0..10)
+        .map(|_| Block::new(BlockHeader::random(), BlockBody::new_empty()))
+        .collect::<Vec<_>>();
+    for block in &blocks {
+        storage.commit_block(block.clone())?;
+    }
+    storage.flush()?;
+    storage.delete_block_bodies(blocks.iter().map(|block| block.id()).collect())?;
+    storage.compact_block_bodies()?;
+    for block in &blocks {
+        assert!(storage.get_block_by_hash(block.id())?.is_none());
+        assert_eq!(
+            storage.get_block_header_by_hash(block.id())?,
+            Some(block.header().clone())
+        );
+    }
+
+    // nothing to compact for the cache.
+    let storage = Storage::new(StorageInstance::new_cache_instance())?;
+    storage.compact_block_bodies()?;
+    Ok(())
+}
+
+#[test]
+fn test_swap_main_head() -> Result<()> {
+    let storage = Arc::new(Storage::new(StorageInstance::new_cache_instance())?);
+    let head_status = ChainStatus::random();
+    let startup_info = StartupInfo::new(head_status.head().id());
+    assert!(storage.get_main_head()?.is_none());
+    assert!(storage
+        .save_main_head(StartupInfo::new(HashValue::random()), head_status.clone())
+        .is_err());
+    storage.save_main_head(startup_info.clone(), head_status.clone())?;
+    assert_eq!(
+        storage.get_main_head()?,
+        Some((startup_info.clone(), head_status))
+    );
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let storage = storage.clone();
+        let stop = stop.clone();
+        std::thread::spawn(move || -> Result<u64> {
+            let mut reads = 0u64;
+            while !stop.load(Ordering::SeqCst) {
+                let (startup_info, head_status) = storage
+                    .get_main_head()?
+                    .ok_or_else(|| format_err!("main head should exist"))?;
+                assert_eq!(startup_info.main, head_status.head().id());
+                reads += 1;
+            }
+            Ok(reads)
+        })
+    };
+    for _ in 0..1000 {
+        let head_status = ChainStatus::random();
+        storage.save_main_head(StartupInfo::new(head_status.head().id()), head_status)?;
+    }
+    stop.store(true, Ordering::SeqCst);
+    assert!(reader.join().unwrap()? > 0);
+
+    // save the startup info only drops the head status of the previous head.
+    storage.save_startup_info(startup_info.clone())?;
+    assert!(storage.get_main_head()?.is_none());
+    assert_eq!(storage.get_startup_info()?, Some(startup_info));
+    Ok(())
+}
+
+#[test]
+fn test_codec_storage_count() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let db = DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None)?;
+    let instances = vec![
+        StorageInstance::new_db_instance(db),
+        StorageInstance::new_memory_instance(),
+    ];
+    let address1 = AccountAddress::random();
+    let address2 = AccountAddress::random();
+    for instance in instances {
+        let storage = EventIndexStorage::new(instance);
+        assert_eq!(storage.count()?, 0);
+        let kvs = (0..5)
+            .map(|number| EventIndexKey::new(address1, number, HashValue::random()))
+            .chain((0..3).map(|number| EventIndexKey::new(address2, number, HashValue::random())))
+            .map(|key| (key, vec![]))
+            .collect::<Vec<_>>();
+        let removed = kvs[0].0;
+        storage.put_all(kvs)?;
+        assert_eq!(storage.count()?, 8);
+        assert_eq!(storage.count_prefix(address1.as_ref())?, 5);
+        assert_eq!(storage.count_prefix(address2.as_ref())?, 3);
+        assert_eq!(storage.count_prefix(&[])?, 8);
+        assert_eq!(storage.count_prefix(AccountAddress::random().as_ref())?, 0);
+
+        CodecKVStore::remove(&storage, removed)?;
+        assert_eq!(storage.count()?, 7);
+        assert_eq!(storage.count_prefix(address1.as_ref())?, 4);
+    }
+    // the cache storage may evict entries, so the count is not supported.
+    let storage = EventIndexStorage::new(StorageInstance::new_cache_instance());
+    assert!(storage.count().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_event_index_range() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let instances = vec![
+        StorageInstance::new_db_instance(DBStorage::new(
+            tmpdir.path(),
+            RocksdbConfig::default(),
+            None,
+        )?),
+        StorageInstance::new_memory_instance(),
+        StorageInstance::new_overlay_instance(StorageInstance::new_memory_instance()),
+    ];
+    let address1 = AccountAddress::random();
+    let address2 = AccountAddress::random();
+    let mut keys: Vec<EventIndexKey> = (0..10)
+        .map(|number| EventIndexKey::new(address1, number, HashValue::random()))
+        .collect();
+    // a fork block at the same number, and the blocks of another address.
+    keys.push(EventIndexKey::new(address1, 5, HashValue::random()));
+    keys.push(EventIndexKey::new(address1, u64::MAX, HashValue::random()));
+    keys.push(EventIndexKey::new(address2, 3, HashValue::random()));
+    keys.push(EventIndexKey::new(address2, 4, HashValue::random()));
+    for instance in instances {
+        let storage = EventIndexStorage::new(instance);
+        storage.put_all(keys.iter().map(|key| (*key, vec![])).collect())?;
+
+        let numbers: Vec<_> = storage
+            .get_event_indexes(address1, 3, 6)?
+            .into_iter()
+            .map(|(key, _)| {
+                assert_eq!(key.address, address1);
+                key.block_number
+            })
+            .collect();
+        assert_eq!(numbers, vec![3, 4, 5, 5, 6]);
+        assert_eq!(storage.get_event_indexes(address2, 0, 10)?.len(), 2);
+        assert_eq!(
+            storage
+                .get_event_indexes(address1, u64::MAX, u64::MAX)?
+                .len(),
+            1
+        );
+        assert!(storage.get_event_indexes(address1, 6, 3)?.is_empty());
+        assert!(storage
+            .get_event_indexes(AccountAddress::random(), 0, u64::MAX)?
+            .is_empty());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_db_upgrade_index_events() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let mut instance = StorageInstance::new_db_instance(DBStorage::new(
+        tmpdir.path(),
+        RocksdbConfig::default(),
+        None,
+    )?);
+    let storage = Storage::new(instance.clone())?;
+    let header_storage = BlockHeaderStorage::new(instance.clone());
+    let address = AccountAddress::random();
+
+    let header = BlockHeader::random();
+    let txn_info = RichTransactionInfo::new(
+        header.id(),
+        header.number(),
+        TransactionInfo::new(
+            HashValue::random(),
+            HashValue::zero(),
+            vec![].as_slice(),
+            0,
+            KeptVMStatus::Executed,
+        ),
+        0,
+        7,
+    );
+    let events: Vec<ContractEvent> = (0..2)
+        .map(|salt| {
+            ContractEvent::new(
+                EventKey::new_from_address(&address, salt),
+                0,
+                TypeTag::Bool,
+                vec![],
+            )
+        })
+        .collect();
+    header_storage.put(header.id(), header.clone())?;
+    storage.save_transaction_infos(vec![txn_info.clone()])?;
+    storage.save_contract_events(txn_info.id(), events.clone())?;
+    storage.save_block_txn_info_ids(header.id(), vec![txn_info.id()])?;
+    // the block is not executed, it has no txn infos.
+    let not_executed = BlockHeader::random();
+    header_storage.put(not_executed.id(), not_executed)?;
+
+    ChainInfoStorage::new(instance.clone()).set_storage_version(StorageVersion::V3)?;
+    DBUpgrade::check_upgrade(&mut instance)?;
+    assert_eq!(
+        ChainInfoStorage::new(instance.clone()).get_storage_version()?,
+        StorageVersion::V4
+    );
+    assert_eq!(
+        storage.get_event_indexes(address, header.number(), header.number())?,
+        vec![(
+            EventIndexKey::new(address, header.number(), header.id()),
+            EventIndex::new_txn_indexes(txn_info.id(), 7, &events),
+        )]
+    );
+    assert_eq!(EventIndexStorage::new(instance).count()?, 1);
+    Ok(())
+}
+
+#[test]
+fn test_codec_storage_remove_returning() -> Result<()> {
+    let tmpdir1 = starcoin_config::temp_dir();
+    let tmpdir2 = starcoin_config::temp_dir();
+    let instances = vec![
+        StorageInstance::new_db_instance(DBStorage::new(
+            tmpdir1.path(),
+            RocksdbConfig::default(),
+            None,
+        )?),
+        StorageInstance::new_cache_and_db_instance(
+            CacheStorage::new(None),
+            DBStorage::new(tmpdir2.path(), RocksdbConfig::default(), None)?,
+        ),
+        StorageInstance::new_memory_instance(),
+        StorageInstance::new_cache_instance(),
+        StorageInstance::new_overlay_instance(StorageInstance::new_memory_instance()),
+    ];
+    for instance in instances {
+        let storage = BlockHeaderStorage::new(instance);
+        let header = BlockHeader::random();
+        storage.put(header.id(), header.clone())?;
+        assert_eq!(storage.remove_returning(header.id())?, Some(header.clone()));
+        assert_eq!(storage.get(header.id())?, None);
+        // the absent key returns None.
+        assert_eq!(storage.remove_returning(header.id())?, None);
+        assert_eq!(storage.remove_returning(HashValue::random())?, None);
+    }
+
+    // the key count of the db is updated by the removal.
+    let tmpdir = starcoin_config::temp_dir();
+    let db = DBStorage::new(tmpdir.path(), RocksdbConfig::default(), None)?;
+    let storage = BlockHeaderStorage::new(StorageInstance::new_db_instance(db));
+    let header = BlockHeader::random();
+    storage.put(header.id(), header.clone())?;
+    assert_eq!(storage.count()?, 1);
+    storage.remove_returning(header.id())?;
+    assert_eq!(storage.count()?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_db_remove_returning_concurrent_put() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let db = Arc::new(DBStorage::new(
+        tmpdir.path(),
+        RocksdbConfig::default(),
+        None,
+    )?);
+    let key = b"key".to_vec();
+    let done = Arc::new(AtomicBool::new(false));
+    let remover = {
+        let db = db.clone();
+        let key = key.clone();
+        let done = done.clone();
+        std::thread::spawn(move || -> Result<Vec<u64>> {
+            let mut removed = vec![];
+            while !done.load(Ordering::SeqCst) {
+                if let Some(value) = db.remove_returning(BLOCK_PREFIX_NAME, key.clone())? {
+                    let bytes: [u8; 8] = value.as_slice().try_into()?;
+                    removed.push(u64::from_be_bytes(bytes));
+                }
+            }
+            Ok(removed)
+        })
+    };
+    // the values observed as deleted right after the put.
+    let mut deleted = vec![];
+    for i in 0..10000u64 {
+        db.put(BLOCK_PREFIX_NAME, key.clone(), i.to_be_bytes().to_vec())?;
+        if db.get(BLOCK_PREFIX_NAME, key.clone())?.is_none() {
+            deleted.push(i);
+        }
+    }
+    done.store(true, Ordering::SeqCst);
+    let removed = remover.join().expect("remover should not panic")?;
+    // every value is removed at most once, and a deleted value is always returned.
+    let mut removed_set = std::collections::HashSet::new();
+    for value in &removed {
+        assert!(removed_set.insert(*value), "value {} removed twice", value);
+    }
+    for value in deleted {
+        assert!(removed_set.contains(&value), "value {} is lost", value);
+    }
+    // the key count is kept exact by the concurrent writes.
+    assert_eq!(
+        db.get_cf_len(BLOCK_PREFIX_NAME)?,
+        db.count_prefix(BLOCK_PREFIX_NAME, &[])?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_keys_and_scan_order() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let cache = StorageInstance::new_cache_instance();
+    let db = StorageInstance::new_db_instance(DBStorage::new(
+        tmpdir.path(),
+        RocksdbConfig::default(),
+        None,
+    )?);
+    let headers: Vec<BlockHeader> = (0..20).map(|_| BlockHeader::random()).collect();
+    let mut expect: Vec<HashValue> = headers.iter().map(|header| header.id()).collect();
+    expect.sort();
+    let scan_prefix = expect[0].to_vec()[0..1].to_vec();
+
+    let mut results = vec![];
+    for instance in [cache, db] {
+        let storage = BlockHeaderStorage::new(instance.clone());
+        for header in &headers {
+            storage.put(header.id(), header.clone())?;
+        }
+        let keys = storage.keys()?;
+        let scanned: Vec<Vec<u8>> = instance
+            .scan_prefix(BLOCK_HEADER_PREFIX_NAME, &scan_prefix)?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, expect);
+        assert!(!scanned.is_empty());
+        assert!(scanned.iter().all(|key| key.starts_with(&scan_prefix)));
+        results.push((keys, scanned));
+    }
+    // the cache-only and the db-backed instances return the same order.
+    assert_eq!(results[0], results[1]);
+    Ok(())
+}
+
+#[test]
+fn test_prune_state_nodes_keeps_written_nodes() -> Result<()> {
+    let tmpdir = starcoin_config::temp_dir();
+    let storage = Storage::new(StorageInstance::new_db_instance(DBStorage::new(
+        tmpdir.path(),
+        RocksdbConfig::default(),
+        None,
+    )?))?;
+    let node = StateNode(HashValue::random().to_vec());
+    let kept = HashValue::random();
+    let stale = HashValue::random();
+    let written = HashValue::random();
+    StateNodeStore::put(&storage, kept, node.clone())?;
+    StateNodeStore::put(&storage, stale, node.clone())?;
+    let deleted = storage.prune_state_nodes(&mut |reachable| {
+        reachable.insert(kept);
+        // a block applied during the prune writes a node unreachable from the collected roots.
+        let nodes = vec![(written, node.clone())].into_iter().collect();
+        StateNodeStore::write_nodes(&storage, nodes)?;
+        // only one prune runs at a time.
+        assert!(storage.prune_state_nodes(&mut |_| Ok(())).is_err());
+        Ok(())
+    })?;
+    assert_eq!(deleted, 1);
+    assert!(StateNodeStore::get(&storage, &kept)?.is_some());
+    assert!(StateNodeStore::get(&storage, &written)?.is_some());
+    assert!(StateNodeStore::get(&storage, &stale)?.is_none());
+    // the written nodes are not kept by the later prunes.
+    assert_eq!(storage.prune_state_nodes(&mut |_| Ok(()))?, 2);
+    Ok(())
+}
@@ -102,11 +102,12 @@ impl TransactionInfoStorage {
         &self,
         vec_txn_info: Vec<RichTransactionInfo>,
     ) -> Result<(), Error> {
-        let mut batch = CodecWriteBatch::new();
-        for txn_info in vec_txn_info {
-            batch.put(txn_info.id(), txn_info)?;
-        }
-        self.write_batch(batch)
+        self.put_all(
+            vec_txn_info
+                .into_iter()
+                .map(|txn_info| (txn_info.id(), txn_info))
+                .collect(),
+        )
     }
 
     pub(crate) fn get_transaction_infos(
@@ -1,36 +1,86 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::block::BlockStorage;
+use crate::block::{BlockHeaderStorage, BlockStorage};
 use crate::block_info::BlockInfoStorage;
 use crate::chain_info::ChainInfoStorage;
+use crate::event_index::EventIndex;
 use crate::transaction::TransactionStorage;
 use crate::transaction_info::OldTransactionInfoStorage;
 use crate::transaction_info::TransactionInfoStorage;
 use crate::{
-    CodecKVStore, RichTransactionInfo, StorageInstance, StorageVersion, TransactionStore,
-    BLOCK_BODY_PREFIX_NAME, TRANSACTION_INFO_PREFIX_NAME,
+    BlockTransactionInfoStore, CodecKVStore, ContractEventStore, EventIndexStore,
+    RichTransactionInfo, Storage, StorageInstance, StorageVersion, TransactionStore,
+    BLOCK_BODY_PREFIX_NAME, EVENT_INDEX_PREFIX_NAME, TRANSACTION_INFO_PREFIX_NAME,
 };
 use anyhow::{bail, ensure, format_err, Result};
 use logger::prelude::{debug, info, warn};
 use starcoin_types::transaction::Transaction;
 use std::cmp::Ordering;
 
+/// A migration upgrades the storage from version `from` to version `to`.
+#[derive(Clone, Copy)]
+pub struct Migration {
+    pub from: StorageVersion,
+    pub to: StorageVersion,
+    pub migrate: fn(&mut StorageInstance) -> Result<()>,
+}
+
+impl Migration {
+    pub fn new(
+        from: StorageVersion,
+        to: StorageVersion,
+        migrate: fn(&mut StorageInstance) -> Result<()>,
+    ) -> Self {
+        Self { from, to, migrate }
+    }
+}
+
 pub struct DBUpgrade;
 
 impl DBUpgrade {
+    /// The registered migrations, a new storage version should add its migration here.
+    pub fn migrations() -> Vec<Migration> {
+        vec![
+            Migration::new(
+                StorageVersion::V1,
+                StorageVersion::V2,
+                Self::db_upgrade_v1_v2,
+            ),
+            Migration::new(
+                StorageVersion::V2,
+                StorageVersion::V3,
+                Self::db_upgrade_v2_v3,
+            ),
+            Migration::new(
+                StorageVersion::V3,
+                StorageVersion::V4,
+                Self::db_upgrade_v3_v4,
+            ),
+        ]
+    }
+
     pub fn check_upgrade(instance: &mut StorageInstance) -> Result<()> {
+        Self::check_upgrade_with(
+            instance,
+            StorageVersion::current_version(),
+            &Self::migrations(),
+        )
+    }
+
+    pub fn check_upgrade_with(
+        instance: &mut StorageInstance,
+        version_in_code: StorageVersion,
+        migrations: &[Migration],
+    ) -> Result<()> {
         let version_in_db = {
             let chain_info_storage = ChainInfoStorage::new(instance.clone());
             chain_info_storage.get_storage_version()?
         };
         // make sure Arc::strong_count(&instance) == 1
-        let version_in_code = StorageVersion::current_version();
         match version_in_db.cmp(&version_in_code) {
             Ordering::Less => {
-                Self::do_upgrade(version_in_db, version_in_code, instance)?;
-                let chain_info_storage = ChainInfoStorage::new(instance.clone());
-                chain_info_storage.set_storage_version(version_in_code)?;
+                Self::do_upgrade(version_in_db, version_in_code, instance, migrations)?;
             }
             Ordering::Equal => {
                 debug!(
@@ -151,33 +201,91 @@ impl DBUpgrade {
         Ok(())
     }
 
+    fn db_upgrade_v3_v4(instance: &mut StorageInstance) -> Result<()> {
+        // the column event_index is created when the db is opened,
+        // index the events of the blocks executed before, the blocks of the forks included.
+        info!("add column {}", EVENT_INDEX_PREFIX_NAME);
+        let storage = Storage::new(instance.clone())?;
+        let block_storage = BlockStorage::new(instance.clone());
+        let block_header_storage = BlockHeaderStorage::new(instance.clone());
+        let mut iter = block_header_storage.iter()?;
+        iter.seek_to_first();
+        let mut processed_count = 0;
+        for item in iter {
+            let (block_id, header) = item?;
+            let txn_info_ids = match block_storage.get_transaction_info_ids(block_id)? {
+                Some(txn_info_ids) => txn_info_ids,
+                None => {
+                    debug!(
+                        "block {} is not executed, skip indexing its events",
+                        block_id
+                    );
+                    continue;
+                }
+            };
+            let mut event_indexes = vec![];
+            for txn_info_id in txn_info_ids {
+                let txn_info = match storage.get_transaction_info(txn_info_id)? {
+                    Some(txn_info) => txn_info,
+                    None => {
+                        warn!(
+                            "Can not find txn info by id: {}, skip its events",
+                            txn_info_id
+                        );
+                        continue;
+                    }
+                };
+                let events = storage
+                    .get_contract_events(txn_info_id)?
+                    .unwrap_or_default();
+                event_indexes.extend(EventIndex::new_txn_indexes(
+                    txn_info_id,
+                    txn_info.transaction_global_index,
+                    &events,
+                ));
+            }
+            storage.save_event_indexes(header.number(), block_id, event_indexes)?;
+            processed_count += 1;
+            if processed_count % 10000 == 0 {
+                info!("indexed the events of blocks: {}", processed_count);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the migrations in order of version, the storage version is saved after every migration,
+    /// so an interrupted upgrade continues from the last finished migration.
     pub fn do_upgrade(
         version_in_db: StorageVersion,
         version_in_code: StorageVersion,
         instance: &mut StorageInstance,
+        migrations: &[Migration],
     ) -> Result<()> {
         info!(
             "Upgrade db from {:?} to {:?}",
             version_in_db, version_in_code
         );
-        match (version_in_db, version_in_code) {
-            (StorageVersion::V1, StorageVersion::V2) => {
-                Self::db_upgrade_v1_v2(instance)?;
-            }
-
-            (StorageVersion::V1, StorageVersion::V3) => {
-                Self::db_upgrade_v1_v2(instance)?;
-                Self::db_upgrade_v2_v3(instance)?;
-            }
-
-            (StorageVersion::V2, StorageVersion::V3) => {
-                Self::db_upgrade_v2_v3(instance)?;
-            }
-            _ => bail!(
-                "Can not upgrade db from {:?} to {:?}",
-                version_in_db,
-                version_in_code
-            ),
+        let mut version = version_in_db;
+        while version < version_in_code {
+            let migration = migrations
+                .iter()
+                .find(|migration| {
+                    migration.from == version
+                        && migration.to > version
+                        && migration.to <= version_in_code
+                })
+                .ok_or_else(|| {
+                    format_err!(
+                        "Can not upgrade db from {:?} to {:?}, missing migration from {:?}",
+                        version_in_db,
+                        version_in_code,
+                        version
+                    )
+                })?;
+            (migration.migrate)(instance)?;
+            ChainInfoStorage::new(instance.clone()).set_storage_version(migration.to)?;
+            info!("Upgrade db from {:?} to {:?} done", version, migration.to);
+            version = migration.to;
         }
         Ok(())
     }
@@ -0,0 +1,200 @@
+use crate::rpc::{ShareRequest, Status, StratumJob, SubmitResult, SubmitShareEvent};
+use crate::stratum::Stratum;
+use anyhow::Result;
+use futures::{FutureExt, TryFutureExt};
+use jsonrpc_core::{BoxFuture, ErrorCode, IoHandler};
+use jsonrpc_derive::rpc;
+use starcoin_config::NodeConfig;
+use starcoin_logger::prelude::*;
+use starcoin_service_registry::{
+    ActorService, ServiceContext, ServiceFactory, ServiceRef, ServiceRequest,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// The getwork clients have no session, so all of them share this worker id.
+pub const GETWORK_WORKER_ID: [u8; 4] = [0xff; 4];
+
+#[derive(Debug)]
+pub(crate) struct GetWorkRequest;
+
+impl ServiceRequest for GetWorkRequest {
+    type Response = Result<Option<StratumJob>>;
+}
+
+/// A plain http alternative of the stratum protocol, the clients poll the job by `getwork`
+/// instead of subscribing it, and the shares are validated the same as the stratum shares.
+#[rpc]
+pub trait GetWorkRpc {
+    /// Get the current job, return None if the miner has no job.
+    #[rpc(name = "getwork")]
+    fn get_work(&self) -> BoxFuture<jsonrpc_core::Result<Option<StratumJob>>>;
+
+    /// Submit the nonce of the job, `share.id` is the worker id of the job.
+    #[rpc(name = "submitwork")]
+    fn submit_work(&self, share: ShareRequest) -> BoxFuture<jsonrpc_core::Result<SubmitResult>>;
+}
+
+pub struct GetWorkRpcImpl {
+    service: ServiceRef<Stratum>,
+}
+
+impl GetWorkRpcImpl {
+    pub fn new(s: ServiceRef<Stratum>) -> Self {
+        Self { service: s }
+    }
+}
+
+fn map_err(e: anyhow::Error) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: ErrorCode::InvalidParams,
+        message: e.to_string(),
+        data: None,
+    }
+}
+
+impl GetWorkRpc for GetWorkRpcImpl {
+    fn get_work(&self) -> BoxFuture<jsonrpc_core::Result<Option<StratumJob>>> {
+        let service = self.service.clone();
+        let fut = async move { service.send(GetWorkRequest).await? }.map_err(map_err);
+        Box::pin(fut.boxed())
+    }
+
+    fn submit_work(&self, share: ShareRequest) -> BoxFuture<jsonrpc_core::Result<SubmitResult>> {
+        let service = self.service.clone();
+        let fut = async move {
+            service.send(SubmitShareEvent(share)).await??;
+            Ok(SubmitResult {
+                result: Status {
+                    status: "OK".to_string(),
+                },
+            })
+        }
+        .map_err(map_err);
+        Box::pin(fut.boxed())
+    }
+}
+
+pub struct GetWorkService {
+    address: Option<SocketAddr>,
+    http: Option<jsonrpc_http_server::Server>,
+}
+
+impl ActorService for GetWorkService {
+    fn started(&mut self, ctx: &mut ServiceContext<Self>) -> Result<()> {
+        if let Some(address) = self.address {
+            let mut io = IoHandler::default();
+            let stratum = ctx.service_ref::<Stratum>()?.clone();
+            io.extend_with(GetWorkRpcImpl::new(stratum).to_delegate());
+            let server = jsonrpc_http_server::ServerBuilder::new(io).start_http(&address)?;
+            info!(target: "stratum", "Getwork server listen on {}", address);
+            self.http = Some(server);
+        }
+        Ok(())
+    }
+    fn stopped(&mut self, _ctx: &mut ServiceContext<Self>) -> Result<()> {
+        if let Some(http) = self.http.take() {
+            http.close();
+            info!(target: "stratum", "Getwork server closed");
+        }
+        Ok(())
+    }
+}
+
+pub struct GetWorkServiceFactory;
+
+impl ServiceFactory<GetWorkService> for GetWorkServiceFactory {
+    fn create(ctx: &mut ServiceContext<GetWorkService>) -> Result<GetWorkService> {
+        let config = ctx.get_shared::<Arc<NodeConfig>>()?;
+        Ok(GetWorkService {
+            address: config.stratum.get_getwork_address(),
+            http: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GetWorkService, GetWorkServiceFactory, GETWORK_WORKER_ID};
+    use crate::rpc::ShareRequest;
+    use crate::stratum::{Stratum, StratumFactory};
+    use crate::GetWorkRpcClient;
+    use jsonrpc_core_client::transports::http;
+    use starcoin_config::{get_random_available_port, NodeConfig};
+    use starcoin_crypto::HashValue;
+    use starcoin_miner::{MinerService, SubmitSealRequest, UpdateSubscriberNumRequest};
+    use starcoin_service_registry::mocker::mock;
+    use starcoin_service_registry::{RegistryAsyncService, RegistryService};
+    use starcoin_types::block::BlockHeaderExtra;
+    use starcoin_types::genesis_config::ConsensusStrategy;
+    use starcoin_types::system_events::MintBlockEvent;
+    use starcoin_types::U256;
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+
+    #[stest::test]
+    async fn test_getwork() {
+        let mut config = NodeConfig::random_for_test();
+        let port = get_random_available_port();
+        config.stratum.getwork_port = Some(port);
+        let registry = RegistryService::launch();
+        registry.put_shared(Arc::new(config)).await.unwrap();
+
+        // the job of difficulty 1, every nonce is a solution.
+        let job = MintBlockEvent::new(
+            Default::default(),
+            ConsensusStrategy::Keccak,
+            vec![1u8; 76],
+            U256::one(),
+            1,
+            None,
+        );
+        let minting_blob = job.minting_blob.clone();
+        let (seal_tx, seal_rx) = mpsc::channel();
+        registry
+            .register_mocker::<MinerService, _>(mock(move |r, _| {
+                if r.downcast_ref::<UpdateSubscriberNumRequest>().is_some() {
+                    Box::new(Some(job.clone()))
+                } else {
+                    let seal = r.downcast::<SubmitSealRequest>().unwrap();
+                    seal_tx.send(*seal).unwrap();
+                    Box::new(anyhow::Result::Ok(HashValue::zero()))
+                }
+            }))
+            .await
+            .unwrap();
+        registry
+            .register_by_factory::<Stratum, StratumFactory>()
+            .await
+            .unwrap();
+        registry
+            .register_by_factory::<GetWorkService, GetWorkServiceFactory>()
+            .await
+            .unwrap();
+
+        let client: GetWorkRpcClient = http::connect(&format!("http://127.0.0.1:{}", port))
+            .await
+            .unwrap();
+        let work = client.get_work().await.unwrap().unwrap();
+        assert_eq!(work.job_id, hex::encode(&minting_blob[0..8]));
+        assert_eq!(work.id, hex::encode(GETWORK_WORKER_ID));
+        assert_eq!(work.difficulty().unwrap(), U256::one());
+
+        let share = ShareRequest {
+            id: work.id.clone(),
+            job_id: work.job_id.clone(),
+            nonce: "01000000".to_string(),
+            result: String::new(),
+        };
+        client.submit_work(share.clone()).await.unwrap();
+        let seal = seal_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(seal.nonce, 1);
+        assert_eq!(seal.extra, BlockHeaderExtra::new(GETWORK_WORKER_ID));
+        assert_eq!(seal.minting_blob, minting_blob);
+
+        // the duplicate share is rejected by the share validator.
+        assert!(client.submit_work(share).await.is_err());
+
+        registry.shutdown_system().await.unwrap();
+    }
+}
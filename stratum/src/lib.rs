@@ -1,12 +1,18 @@
 use starcoin_types::U256;
 
+pub mod getwork;
+pub mod nonce_range;
 pub mod rpc;
 pub mod service;
+pub mod share;
 pub mod stratum;
+pub mod worker;
+pub use crate::getwork::gen_client::Client as GetWorkRpcClient;
 pub use crate::rpc::gen_client::Client as StratumRpcClient;
 pub use anyhow::Result;
 
 pub fn difficulty_to_target_hex(difficulty: U256) -> String {
+    let difficulty = std::cmp::max(difficulty, U256::one());
     let target = format!("{:x}", U256::from(u64::max_value()) / difficulty);
     let mut temp = "0".repeat(16 - target.len());
     temp.push_str(&target);
@@ -0,0 +1,82 @@
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
+
+/// Split the u32 nonce space into disjoint ranges, one for every connected worker,
+/// so workers do not grind the same nonces.
+/// The ranges are derived from the current worker set, so they are re-divided
+/// whenever a worker is added or removed.
+#[derive(Clone, Debug, Default)]
+pub struct NonceRanges {
+    workers: BTreeSet<u32>,
+}
+
+impl NonceRanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_worker(&mut self, worker: u32) {
+        self.workers.insert(worker);
+    }
+
+    pub fn remove_worker(&mut self, worker: u32) {
+        self.workers.remove(&worker);
+    }
+
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    /// Get the nonce range of `worker`, return None if the worker is not registered.
+    pub fn range_of(&self, worker: u32) -> Option<RangeInclusive<u32>> {
+        let index = self.workers.iter().position(|id| *id == worker)?;
+        Some(Self::partition(index as u64, self.workers.len() as u64))
+    }
+
+    fn partition(index: u64, total: u64) -> RangeInclusive<u32> {
+        let space = u64::from(u32::max_value()) + 1;
+        let span = space / total;
+        let start = span * index;
+        let end = if index == total - 1 {
+            space - 1
+        } else {
+            start + span - 1
+        };
+        (start as u32)..=(end as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NonceRanges;
+
+    #[test]
+    fn test_nonce_ranges_partition() {
+        let mut ranges = NonceRanges::new();
+        for worker in [3, 1, 2] {
+            ranges.add_worker(worker);
+        }
+        let mut assigned: Vec<_> = [1, 2, 3]
+            .iter()
+            .map(|worker| ranges.range_of(*worker).unwrap())
+            .collect();
+        assigned.sort_by_key(|range| *range.start());
+        assert_eq!(*assigned[0].start(), 0);
+        assert_eq!(*assigned[2].end(), u32::max_value());
+        for pair in assigned.windows(2) {
+            assert_eq!(u64::from(*pair[0].end()) + 1, u64::from(*pair[1].start()));
+        }
+
+        ranges.remove_worker(2);
+        assert!(ranges.range_of(2).is_none());
+        let first = ranges.range_of(1).unwrap();
+        let second = ranges.range_of(3).unwrap();
+        assert_eq!(*first.start(), 0);
+        assert_eq!(u64::from(*first.end()) + 1, u64::from(*second.start()));
+        assert_eq!(*second.end(), u32::max_value());
+    }
+}
@@ -1,5 +1,5 @@
-use crate::difficulty_to_target_hex;
 use crate::stratum::Stratum;
+use crate::{difficulty_to_target_hex, target_hex_to_difficulty};
 use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
 use futures::FutureExt;
 use futures::TryFutureExt;
@@ -14,9 +14,11 @@ use starcoin_miner::SubmitSealRequest as MinerSubmitSealRequest;
 use starcoin_service_registry::{ServiceRef, ServiceRequest};
 use starcoin_types::block::BlockHeaderExtra;
 use starcoin_types::system_events::MintBlockEvent;
+use starcoin_types::U256;
 use std::borrow::BorrowMut;
 use std::convert::TryInto;
 use std::io::Write;
+use std::ops::RangeInclusive;
 use std::sync::mpsc::TrySendError;
 use std::sync::Arc;
 
@@ -190,6 +192,12 @@ pub struct StratumJob {
     pub target: String,
     pub job_id: String,
     pub blob: String,
+    /// The inclusive nonce range `[start, end]` assigned to this worker.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce_range: Option<(u32, u32)>,
+    /// The job is on a new chain head, the previous jobs are stale and should be abandoned.
+    #[serde(default)]
+    pub clean_jobs: bool,
 }
 
 impl StratumJob {
@@ -202,10 +210,20 @@ impl StratumJob {
 
         Ok(BlockHeaderExtra::new(extra))
     }
+
+    /// The difficulty derived from the job target, a share is valid if its hash meets this difficulty.
+    pub fn difficulty(&self) -> anyhow::Result<U256> {
+        target_hex_to_difficulty(&self.target)
+    }
 }
 
 impl StratumJobResponse {
-    pub fn from(e: &MintBlockEvent, login: Option<LoginRequest>, worker_id: [u8; 4]) -> Self {
+    pub fn from(
+        e: &MintBlockEvent,
+        login: Option<LoginRequest>,
+        worker_id: [u8; 4],
+        nonce_range: Option<RangeInclusive<u32>>,
+    ) -> Self {
         let mut minting_blob = e.minting_blob.clone();
         let _ = minting_blob[35..39].borrow_mut().write_all(&worker_id);
         let worker_id_hex = hex::encode(&worker_id);
@@ -220,6 +238,8 @@ impl StratumJobResponse {
                 target: difficulty_to_target_hex(e.difficulty),
                 job_id,
                 blob: hex::encode(&minting_blob),
+                nonce_range: nonce_range.map(|range| (*range.start(), *range.end())),
+                clean_jobs: false,
             },
         }
     }
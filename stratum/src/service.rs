@@ -3,47 +3,343 @@ use crate::stratum::Stratum;
 use anyhow::Result;
 use jsonrpc_core::MetaIoHandler;
 use jsonrpc_pubsub::Session;
-use starcoin_config::NodeConfig;
-use starcoin_service_registry::{ActorService, ServiceContext, ServiceFactory};
+use starcoin_config::{NodeConfig, StratumConfig};
+use starcoin_logger::prelude::*;
+use starcoin_service_registry::{
+    ActorService, EventHandler, RegistryAsyncService, ServiceContext, ServiceFactory,
+    ServiceHandler, ServiceRef, ServiceRequest,
+};
+use std::net::SocketAddr;
 use std::sync::Arc;
 
+/// Get the address the stratum server actually listens on, None if the server is disabled or
+/// not listening yet.
+#[derive(Debug)]
+pub struct StratumAddressRequest;
+
+impl ServiceRequest for StratumAddressRequest {
+    type Response = Option<SocketAddr>;
+}
+
 pub struct StratumService {
-    config: Arc<NodeConfig>,
+    config: StratumConfig,
+    /// The listen address is resolved once, so a restarted service binds the same port.
+    address: Option<SocketAddr>,
     tcp: Option<jsonrpc_tcp_server::Server>,
 }
 
+fn start_server(
+    stratum: ServiceRef<Stratum>,
+    address: &SocketAddr,
+) -> std::io::Result<jsonrpc_tcp_server::Server> {
+    let mut io = MetaIoHandler::default();
+    let rpc = StratumRpcImpl::new(stratum);
+    let apis = rpc.to_delegate();
+    io.extend_with(apis);
+    jsonrpc_tcp_server::ServerBuilder::with_meta_extractor(
+        io,
+        move |context: &jsonrpc_tcp_server::RequestContext| {
+            Metadata::new(Arc::new(Session::new(context.sender.clone())))
+        },
+    )
+    .start(address)
+}
+
+/// Bind port 0 of `address` and read the port the OS assigned, which is available now.
+fn available_address(address: SocketAddr) -> std::io::Result<SocketAddr> {
+    std::net::TcpListener::bind(address)?.local_addr()
+}
+
+/// Retry to bind the stratum address, `retry` starts from 0.
+#[derive(Debug, Clone)]
+struct BindRetryEvent {
+    retry: u64,
+}
+
+impl StratumService {
+    /// Bind the address, schedule a retry with backoff on failure, then fall back to an available
+    /// port if `bind_fallback` is enabled. The retries run on the arbiter without blocking it.
+    fn bind(
+        &mut self,
+        ctx: &mut ServiceContext<Self>,
+        address: SocketAddr,
+        retry: u64,
+    ) -> Result<()> {
+        let stratum = ctx.service_ref::<Stratum>()?.clone();
+        match start_server(stratum.clone(), &address) {
+            Ok(server) => self.listen(address, server),
+            Err(e) if retry < self.config.bind_retry_times() => {
+                let delay = self.config.bind_retry_delay(retry);
+                warn!(target: "stratum", "Stratum server fail to listen on {}: {}, retry after {:?}", address, e, delay);
+                ctx.run_later(delay, move |ctx| {
+                    ctx.notify(BindRetryEvent { retry: retry + 1 })
+                });
+            }
+            Err(e) if self.config.bind_fallback() => {
+                let fallback = available_address(self.config.get_fallback_address())?;
+                warn!(target: "stratum", "Stratum server fail to listen on {}: {}, fall back to {}", address, e, fallback);
+                self.listen(fallback, start_server(stratum, &fallback)?);
+            }
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+
+    fn listen(&mut self, address: SocketAddr, server: jsonrpc_tcp_server::Server) {
+        info!(target: "stratum", "Stratum server listen on {}", address);
+        self.address = Some(address);
+        self.tcp = Some(server);
+    }
+}
+
 impl ActorService for StratumService {
     fn started(&mut self, ctx: &mut ServiceContext<Self>) -> Result<()> {
-        if let Some(address) = self.config.stratum.get_address() {
-            let mut io = MetaIoHandler::default();
-            let stratum = ctx.service_ref::<Stratum>()?.clone();
-            let rpc = StratumRpcImpl::new(stratum);
-            let apis = rpc.to_delegate();
-            io.extend_with(apis);
-            let server = jsonrpc_tcp_server::ServerBuilder::with_meta_extractor(
-                io,
-                move |context: &jsonrpc_tcp_server::RequestContext| {
-                    Metadata::new(Arc::new(Session::new(context.sender.clone())))
-                },
-            )
-            .start(&address)?;
-            self.tcp = Some(server);
+        if let Some(address) = self.address {
+            self.bind(ctx, address, 0)?;
         }
         Ok(())
     }
     fn stopped(&mut self, _ctx: &mut ServiceContext<Self>) -> Result<()> {
         if let Some(tcp) = self.tcp.take() {
-            tcp.close()
+            tcp.close();
+            info!(target: "stratum", "Stratum server closed");
         }
         Ok(())
     }
 }
 
+impl EventHandler<Self, BindRetryEvent> for StratumService {
+    fn handle_event(&mut self, msg: BindRetryEvent, ctx: &mut ServiceContext<StratumService>) {
+        let address = match (&self.tcp, self.address) {
+            (None, Some(address)) => address,
+            _ => return,
+        };
+        if let Err(e) = self.bind(ctx, address, msg.retry) {
+            error!(target: "stratum", "Stratum server fail to listen on {}: {}, stop the service", address, e);
+            let registry = ctx.registry_ref().clone();
+            ctx.spawn(async move {
+                if let Err(e) = registry.stop_service(StratumService::service_name()).await {
+                    error!(target: "stratum", "Stop the stratum service error: {}", e);
+                }
+            });
+        }
+    }
+}
+
 pub struct StratumServiceFactory;
 
 impl ServiceFactory<StratumService> for StratumServiceFactory {
     fn create(ctx: &mut ServiceContext<StratumService>) -> Result<StratumService> {
         let config = ctx.get_shared::<Arc<NodeConfig>>()?;
-        Ok(StratumService { config, tcp: None })
+        Ok(StratumService {
+            config: config.stratum.clone(),
+            address: config.stratum.get_address(),
+            tcp: None,
+        })
+    }
+}
+
+impl ServiceHandler<Self, StratumAddressRequest> for StratumService {
+    fn handle(
+        &mut self,
+        _msg: StratumAddressRequest,
+        _ctx: &mut ServiceContext<StratumService>,
+    ) -> Option<SocketAddr> {
+        self.tcp.as_ref().and(self.address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StratumAddressRequest, StratumService, StratumServiceFactory};
+    use crate::rpc::{ShareRequest, StratumJob, SubmitShareEvent};
+    use crate::stratum::{Stratum, StratumFactory};
+    use futures_timer::Delay;
+    use starcoin_config::{get_random_available_port, NodeConfig};
+    use starcoin_crypto::HashValue;
+    use starcoin_miner::{MinerService, UpdateSubscriberNumRequest};
+    use starcoin_service_registry::mocker::mock;
+    use starcoin_service_registry::{
+        ActorService, RegistryAsyncService, RegistryService, ServiceStatus,
+    };
+    use starcoin_types::block::{Block, BlockBody, ExecutedBlock};
+    use starcoin_types::genesis_config::ConsensusStrategy;
+    use starcoin_types::startup_info::ChainStatus;
+    use starcoin_types::system_events::{MintBlockEvent, NewHeadBlock};
+    use starcoin_types::U256;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[stest::test]
+    async fn test_stratum_service_restart() {
+        let mut config = NodeConfig::random_for_test();
+        let port = get_random_available_port();
+        config.stratum.port = Some(port);
+        let registry = RegistryService::launch();
+        registry.put_shared(Arc::new(config)).await.unwrap();
+        registry
+            .register_mocker::<Stratum, _>(mock(|_, _| Box::new(())))
+            .await
+            .unwrap();
+        registry
+            .register_by_factory::<StratumService, StratumServiceFactory>()
+            .await
+            .unwrap();
+        let address = format!("127.0.0.1:{}", port);
+        assert!(TcpStream::connect(&address).is_ok());
+
+        registry
+            .stop_service(StratumService::service_name())
+            .await
+            .unwrap();
+        // the port must be reusable after the service stopped.
+        registry
+            .start_service(StratumService::service_name())
+            .await
+            .unwrap();
+        assert!(TcpStream::connect(&address).is_ok());
+
+        registry.shutdown_system().await.unwrap();
+    }
+
+    #[stest::test]
+    async fn test_stratum_service_bind_fallback() {
+        // occupy the configured port.
+        let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        for bind_fallback in [false, true] {
+            let mut config = NodeConfig::random_for_test();
+            config.stratum.port = Some(port);
+            config.stratum.bind_retry_times = Some(1);
+            config.stratum.bind_fallback = Some(bind_fallback);
+            let registry = RegistryService::launch();
+            registry.put_shared(Arc::new(config)).await.unwrap();
+            registry
+                .register_mocker::<Stratum, _>(mock(|_, _| Box::new(())))
+                .await
+                .unwrap();
+            let service = registry
+                .register_by_factory::<StratumService, StratumServiceFactory>()
+                .await
+                .unwrap();
+            // the retries run after the service started.
+            let mut bound = None;
+            let mut status = ServiceStatus::Started;
+            for _ in 0..50 {
+                bound = service.send(StratumAddressRequest).await.unwrap();
+                status = registry
+                    .check_service_status(StratumService::service_name())
+                    .await
+                    .unwrap();
+                if bound.is_some() || status == ServiceStatus::Stopped {
+                    break;
+                }
+                Delay::new(Duration::from_millis(100)).await;
+            }
+            if bind_fallback {
+                let address = bound.unwrap();
+                assert_ne!(address.port(), port);
+                assert!(TcpStream::connect(format!("127.0.0.1:{}", address.port())).is_ok());
+            } else {
+                // the service stops after the retries, instead of panicking.
+                assert!(bound.is_none());
+                assert_eq!(status, ServiceStatus::Stopped);
+            }
+            registry.shutdown_system().await.unwrap();
+        }
+        drop(listener);
+    }
+
+    fn job_of(parent_hash: HashValue, blob: u8) -> MintBlockEvent {
+        MintBlockEvent::new(
+            parent_hash,
+            ConsensusStrategy::Keccak,
+            vec![blob; 76],
+            U256::one(),
+            1,
+            None,
+        )
+    }
+
+    /// Read the lines until a job notification.
+    fn read_job(reader: &mut BufReader<TcpStream>) -> StratumJob {
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let message: serde_json::Value = serde_json::from_str(&line).unwrap();
+            if let Some(job) = message.pointer("/params/result/job") {
+                return serde_json::from_value(job.clone()).unwrap();
+            }
+        }
+    }
+
+    #[stest::test]
+    async fn test_stratum_new_head_job() {
+        let mut config = NodeConfig::random_for_test();
+        let port = get_random_available_port();
+        config.stratum.port = Some(port);
+        let registry = RegistryService::launch();
+        registry.put_shared(Arc::new(config)).await.unwrap();
+
+        let current_job = Arc::new(Mutex::new(job_of(HashValue::random(), 1)));
+        let miner_job = current_job.clone();
+        registry
+            .register_mocker::<MinerService, _>(mock(move |r, _| {
+                assert!(r.downcast_ref::<UpdateSubscriberNumRequest>().is_some());
+                Box::new(Some(miner_job.lock().unwrap().clone()))
+            }))
+            .await
+            .unwrap();
+        let stratum = registry
+            .register_by_factory::<Stratum, StratumFactory>()
+            .await
+            .unwrap();
+        registry
+            .register_by_factory::<StratumService, StratumServiceFactory>()
+            .await
+            .unwrap();
+
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        stream
+            .write_all(
+                b"{\"jsonrpc\":\"2.0\",\"method\":\"login\",\"id\":1,\
+                \"params\":{\"login\":\"test\",\"pass\":\"test\",\"agent\":\"test\"}}\n",
+            )
+            .unwrap();
+        let old_job = read_job(&mut reader);
+        assert!(!old_job.clean_jobs);
+
+        let status = ChainStatus::random();
+        let head = NewHeadBlock(Arc::new(ExecutedBlock::new(
+            Block::new(status.head, BlockBody::new_empty()),
+            status.info,
+        )));
+        *current_job.lock().unwrap() = job_of(head.0.header().id(), 2);
+        stratum.notify(head).unwrap();
+
+        let new_job = read_job(&mut reader);
+        assert!(new_job.clean_jobs);
+        assert_ne!(new_job.job_id, old_job.job_id);
+
+        // the share of the superseded job is rejected.
+        let share = ShareRequest {
+            id: old_job.id,
+            job_id: old_job.job_id,
+            nonce: "01000000".to_string(),
+            result: String::new(),
+        };
+        assert!(stratum
+            .send(SubmitShareEvent(share))
+            .await
+            .unwrap()
+            .is_err());
+
+        registry.shutdown_system().await.unwrap();
     }
 }
@@ -0,0 +1,85 @@
+use anyhow::{format_err, Result};
+use consensus::Consensus;
+use starcoin_types::block::BlockHeaderExtra;
+use starcoin_types::system_events::MintBlockEvent;
+use std::collections::HashSet;
+
+/// Check the shares submitted by workers before they are sent to miner to seal a block.
+/// A share is rejected if its pow hash does not meet the target of current job,
+/// or the same nonce has been submitted for the job before.
+#[derive(Clone, Debug, Default)]
+pub struct ShareValidator {
+    job_id: String,
+    submitted: HashSet<(u32, BlockHeaderExtra)>,
+}
+
+impl ShareValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn validate(
+        &mut self,
+        job: &MintBlockEvent,
+        job_id: &str,
+        nonce: u32,
+        extra: BlockHeaderExtra,
+    ) -> Result<()> {
+        if self.job_id != job_id {
+            self.job_id = job_id.to_string();
+            self.submitted.clear();
+        }
+        if self.submitted.contains(&(nonce, extra)) {
+            return Err(format_err!(
+                "Duplicate share for job {}, nonce: {}, worker: {}",
+                job_id,
+                nonce,
+                extra
+            ));
+        }
+        job.strategy
+            .verify_blob(job.minting_blob.clone(), nonce, extra, job.difficulty)
+            .map_err(|e| format_err!("Invalid share from worker {}: {}", extra, e))?;
+        self.submitted.insert((nonce, extra));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShareValidator;
+    use starcoin_types::block::BlockHeaderExtra;
+    use starcoin_types::genesis_config::ConsensusStrategy;
+    use starcoin_types::system_events::MintBlockEvent;
+    use starcoin_types::U256;
+
+    fn job(difficulty: U256) -> MintBlockEvent {
+        MintBlockEvent::new(
+            Default::default(),
+            ConsensusStrategy::Keccak,
+            vec![0u8; 76],
+            difficulty,
+            1,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_share_validate() {
+        let mut validator = ShareValidator::new();
+        let extra = BlockHeaderExtra::new([1, 2, 3, 4]);
+        let easy_job = job(U256::one());
+        let job_id = hex::encode(&easy_job.minting_blob[0..8]);
+
+        assert!(validator.validate(&easy_job, &job_id, 1, extra).is_ok());
+        // duplicate nonce of the same job
+        assert!(validator.validate(&easy_job, &job_id, 1, extra).is_err());
+        // same nonce from another worker
+        let other = BlockHeaderExtra::new([4, 3, 2, 1]);
+        assert!(validator.validate(&easy_job, &job_id, 1, other).is_ok());
+
+        // the hash can not meet the target of max difficulty.
+        let hard_job = job(U256::max_value());
+        assert!(validator.validate(&hard_job, "new_job", 1, extra).is_err());
+    }
+}
@@ -1,8 +1,14 @@
+use crate::getwork::{GetWorkRequest, GETWORK_WORKER_ID};
+use crate::nonce_range::NonceRanges;
 use crate::rpc::*;
-use anyhow::Result;
+use crate::share::ShareValidator;
+use crate::worker::{WorkerRegistry, WorkerStats, WorkerStatsRequest};
+use anyhow::{bail, format_err, Result};
 use futures::channel::mpsc;
 use futures::StreamExt;
 use jsonrpc_pubsub::SubscriptionId;
+use starcoin_config::NodeConfig;
+use starcoin_crypto::HashValue;
 use starcoin_logger::prelude::*;
 use starcoin_miner::{
     MinerService, SubmitSealRequest as MinerSubmitSealRequest, UpdateSubscriberNumRequest,
@@ -10,51 +16,166 @@ use starcoin_miner::{
 use starcoin_service_registry::{
     ActorService, EventHandler, ServiceContext, ServiceFactory, ServiceHandler, ServiceRef,
 };
-use starcoin_types::system_events::MintBlockEvent;
+use starcoin_types::system_events::{MintBlockEvent, NewHeadBlock};
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::sync::atomic;
+use std::sync::Arc;
+
+/// The pending jobs of a worker session, a newer job supersedes the older ones, so a worker
+/// falling this far behind is stuck and dropped, instead of queueing the jobs without bound.
+const JOB_QUEUE_SIZE: usize = 8;
 
 pub struct Stratum {
     uid: atomic::AtomicU32,
-    mint_block_subscribers: HashMap<u32, (mpsc::UnboundedSender<StratumJobResponse>, LoginRequest)>,
+    /// Every worker session forwards its job queue to the connection in its own task, so the
+    /// jobs are only queued here and a slow connection never blocks the others or the miner.
+    mint_block_subscribers: HashMap<u32, (mpsc::Sender<StratumJobResponse>, LoginRequest)>,
+    nonce_ranges: NonceRanges,
+    share_validator: ShareValidator,
+    /// The workers logged in, the shares of the other workers are rejected.
+    workers: WorkerRegistry,
     miner_service: ServiceRef<MinerService>,
+    /// The getwork clients are counted as one subscriber, so the miner keeps minting for them.
+    getwork_enabled: bool,
+    /// The latest chain head, the shares of the jobs on the other parents are stale.
+    head: Option<HashValue>,
+    /// The parent of the last job sent to the workers.
+    job_parent: Option<HashValue>,
 }
 
+/// The getwork clients have no login, they share a worker with a subscription id never assigned.
+const GETWORK_SUB_ID: u32 = 0;
+
 impl Stratum {
-    fn new(miner_service: ServiceRef<MinerService>) -> Self {
+    fn new(miner_service: ServiceRef<MinerService>, getwork_enabled: bool) -> Self {
+        let mut workers = WorkerRegistry::new();
+        if getwork_enabled {
+            workers.authorize(GETWORK_SUB_ID, GETWORK_WORKER_ID, "getwork");
+        }
         Self {
             miner_service,
+            getwork_enabled,
             uid: atomic::AtomicU32::new(1),
             mint_block_subscribers: Default::default(),
+            nonce_ranges: NonceRanges::new(),
+            share_validator: ShareValidator::new(),
+            workers,
+            head: None,
+            job_parent: None,
         }
     }
     fn next_id(&self) -> u32 {
         self.uid.fetch_add(1, atomic::Ordering::SeqCst)
     }
+    fn subscribers_num(&self) -> u32 {
+        self.mint_block_subscribers.len() as u32 + u32::from(self.getwork_enabled)
+    }
     fn sync_current_job(&mut self) -> Result<Option<MintBlockEvent>> {
         let service = self.miner_service.clone();
-        let subscribers_num = self.mint_block_subscribers.len() as u32;
+        let subscribers_num = self.subscribers_num();
         futures::executor::block_on(service.send(UpdateSubscriberNumRequest {
             number: Some(subscribers_num),
         }))
     }
+    /// Record the parent of the job to send, return true if the parent is changed.
+    fn update_job_parent(&mut self, parent_hash: HashValue) -> bool {
+        let changed = self
+            .job_parent
+            .map_or(false, |job_parent| job_parent != parent_hash);
+        self.job_parent = Some(parent_hash);
+        changed
+    }
+
+    /// Send the job to all subscribers, the job on a new parent is sent with `clean_jobs`.
     fn send_to_all(&mut self, event: MintBlockEvent) {
+        let clean_jobs = self.update_job_parent(event.parent_hash);
+        self.send_to_others(event, None, clean_jobs)
+    }
+
+    /// Send the job to all subscribers except `exclude`, every subscriber gets the job with its own nonce range.
+    fn send_to_others(&mut self, event: MintBlockEvent, exclude: Option<u32>, clean_jobs: bool) {
         let mut remove_outdated = vec![];
-        for (id, (ch, login)) in self.mint_block_subscribers.iter() {
+        for (id, (ch, login)) in self.mint_block_subscribers.iter_mut() {
+            if Some(*id) == exclude {
+                continue;
+            }
             let worker_id = login.get_worker_id(*id);
-            let job = StratumJobResponse::from(&event, None, worker_id);
-            if let Err(err) = ch.unbounded_send(job) {
+            let nonce_range = self.nonce_ranges.range_of(*id);
+            let mut job = StratumJobResponse::from(&event, None, worker_id, nonce_range);
+            job.job.clean_jobs = clean_jobs;
+            if let Err(err) = ch.try_send(job) {
                 if err.is_disconnected() {
                     remove_outdated.push(*id);
                 } else if err.is_full() {
-                    error!(target: "stratum", "subscription {:?} fail to new messages, channel is full", id);
+                    warn!(target: "stratum", "subscription {:?} is stuck, {} jobs are not sent, drop it", id, JOB_QUEUE_SIZE);
+                    remove_outdated.push(*id);
                 }
             }
         }
         for id in remove_outdated {
-            self.mint_block_subscribers.remove(&id);
+            self.remove_worker(id);
+        }
+    }
+
+    /// Add the worker session of the login, the worker is authorized to submit shares.
+    fn add_worker(
+        &mut self,
+        sub_id: u32,
+        sender: mpsc::Sender<StratumJobResponse>,
+        login: LoginRequest,
+    ) {
+        self.workers
+            .authorize(sub_id, login.get_worker_id(sub_id), &login.login);
+        self.mint_block_subscribers.insert(sub_id, (sender, login));
+        self.nonce_ranges.add_worker(sub_id);
+    }
+
+    fn remove_worker(&mut self, sub_id: u32) {
+        self.mint_block_subscribers.remove(&sub_id);
+        self.nonce_ranges.remove_worker(sub_id);
+        self.workers.remove(sub_id);
+    }
+
+    /// Validate the share of an authorized worker and send it to the miner, the share is counted
+    /// in the stats of the worker.
+    fn submit_share(&mut self, share: ShareRequest) -> Result<()> {
+        let worker_id: [u8; 4] = hex::decode(&share.id)
+            .ok()
+            .and_then(|id| id.try_into().ok())
+            .filter(|worker_id| self.workers.is_authorized(worker_id))
+            .ok_or_else(|| format_err!("unauthorized worker {}", share.id))?;
+        let result = self.validate_and_seal(share);
+        self.workers.record_share(&worker_id, result.is_ok());
+        result
+    }
+
+    fn validate_and_seal(&mut self, share: ShareRequest) -> Result<()> {
+        if let Some(current_mint_event) = self.sync_current_job()? {
+            let job_id = hex::encode(&current_mint_event.minting_blob[0..8]);
+            let submit_job_id = share.job_id.clone();
+            if submit_job_id != job_id {
+                bail!("stale job {}, the current job is {}", submit_job_id, job_id);
+            };
+            if self
+                .head
+                .map_or(false, |head| head != current_mint_event.parent_hash)
+            {
+                bail!("stale job {}, the chain head has changed", job_id);
+            }
+            let mut seal: MinerSubmitSealRequest = share.try_into()?;
+            if let Err(e) =
+                self.share_validator
+                    .validate(&current_mint_event, &job_id, seal.nonce, seal.extra)
+            {
+                warn!(target: "stratum", "reject share: {}", e);
+                return Err(e);
+            }
+
+            seal.minting_blob = current_mint_event.minting_blob;
+            self.miner_service.try_send(seal)?;
         }
+        Ok(())
     }
 }
 
@@ -62,11 +183,18 @@ impl ActorService for Stratum {
     fn started(&mut self, ctx: &mut ServiceContext<Self>) -> Result<()> {
         ctx.set_mailbox_capacity(1024);
         ctx.subscribe::<MintBlockEvent>();
+        ctx.subscribe::<NewHeadBlock>();
         Ok(())
     }
 
     fn stopped(&mut self, ctx: &mut ServiceContext<Self>) -> Result<()> {
         ctx.unsubscribe::<MintBlockEvent>();
+        ctx.unsubscribe::<NewHeadBlock>();
+        // drop the senders, so the job stream of every worker session is terminated.
+        let ids: Vec<u32> = self.mint_block_subscribers.keys().copied().collect();
+        for id in ids {
+            self.remove_worker(id);
+        }
         Ok(())
     }
 }
@@ -77,6 +205,23 @@ impl EventHandler<Self, MintBlockEvent> for Stratum {
     }
 }
 
+impl EventHandler<Self, NewHeadBlock> for Stratum {
+    fn handle_event(&mut self, msg: NewHeadBlock, _ctx: &mut ServiceContext<Stratum>) {
+        let head = msg.0.header().id();
+        self.head = Some(head);
+        if self.mint_block_subscribers.is_empty() || self.job_parent == Some(head) {
+            return;
+        }
+        // push the job at once if the miner is already on the new head,
+        // otherwise the job is pushed when the miner broadcasts it.
+        match self.sync_current_job() {
+            Ok(Some(event)) if event.parent_hash == head => self.send_to_all(event),
+            Ok(_) => {}
+            Err(e) => warn!(target: "stratum", "Failed to get the job of new head: {}", e),
+        }
+    }
+}
+
 impl ServiceHandler<Self, Unsubscribe> for Stratum {
     fn handle(&mut self, msg: Unsubscribe, _ctx: &mut ServiceContext<Self>) {
         if let SubscriptionId::Number(id) = &msg.0 {
@@ -84,11 +229,11 @@ impl ServiceHandler<Self, Unsubscribe> for Stratum {
                 if self
                     .miner_service
                     .try_send(UpdateSubscriberNumRequest {
-                        number: Some(self.mint_block_subscribers.len() as u32 - 1),
+                        number: Some(self.subscribers_num().saturating_sub(1)),
                     })
                     .is_ok()
                 {
-                    self.mint_block_subscribers.remove(&id);
+                    self.remove_worker(id);
                     self.uid.fetch_sub(1, atomic::Ordering::SeqCst);
                     return;
                 }
@@ -102,10 +247,9 @@ impl ServiceHandler<Self, SubscribeJobEvent> for Stratum {
     fn handle(&mut self, msg: SubscribeJobEvent, ctx: &mut ServiceContext<Self>) {
         info!(target: "stratum", "receive subscribe event {:?}", msg);
         let SubscribeJobEvent(subscriber, login) = msg;
-        let (sender, receiver) = mpsc::unbounded();
+        let (mut sender, receiver) = mpsc::channel(JOB_QUEUE_SIZE);
         let sub_id = self.next_id();
-        self.mint_block_subscribers
-            .insert(sub_id, (sender.clone(), login.clone()));
+        self.add_worker(sub_id, sender.clone(), login.clone());
         ctx.spawn(async move {
             if let Ok(sink) = subscriber
                 .assign_id_async(SubscriptionId::Number(sub_id as u64))
@@ -128,10 +272,15 @@ impl ServiceHandler<Self, SubscribeJobEvent> for Stratum {
             }
         });
         if let Ok(Some(event)) = self.sync_current_job() {
+            // the nonce space is re-divided, so the other workers need the current job with new range.
+            let clean_jobs = self.update_job_parent(event.parent_hash);
+            self.send_to_others(event.clone(), Some(sub_id), clean_jobs);
+            let nonce_range = self.nonce_ranges.range_of(sub_id);
             ctx.spawn(async move {
                 let worker_id = login.get_worker_id(sub_id);
-                let stratum_result = StratumJobResponse::from(&event, Some(login), worker_id);
-                if let Err(err) = sender.unbounded_send(stratum_result) {
+                let stratum_result =
+                    StratumJobResponse::from(&event, Some(login), worker_id, nonce_range);
+                if let Err(err) = sender.try_send(stratum_result) {
                     error!(target: "stratum", "Failed to send MintBlockEvent: {}", err);
                 }
             });
@@ -144,19 +293,29 @@ impl ServiceHandler<Self, SubscribeJobEvent> for Stratum {
 impl ServiceHandler<Self, SubmitShareEvent> for Stratum {
     fn handle(&mut self, msg: SubmitShareEvent, _ctx: &mut ServiceContext<Self>) -> Result<()> {
         info!(target: "stratum", "received submit share event:{:?}", &msg.0);
-        if let Some(current_mint_event) = self.sync_current_job()? {
-            let job_id = hex::encode(&current_mint_event.minting_blob[0..8]);
-            let submit_job_id = msg.0.job_id.clone();
-            if submit_job_id != job_id {
-                warn!(target: "stratum", "received job mismatch with current job,{},{}", submit_job_id, job_id);
-                return Ok(());
-            };
-            let mut seal: MinerSubmitSealRequest = msg.0.try_into()?;
+        self.submit_share(msg.0)
+    }
+}
 
-            seal.minting_blob = current_mint_event.minting_blob;
-            self.miner_service.try_send(seal)?;
-        }
-        Ok(())
+impl ServiceHandler<Self, WorkerStatsRequest> for Stratum {
+    fn handle(
+        &mut self,
+        _msg: WorkerStatsRequest,
+        _ctx: &mut ServiceContext<Self>,
+    ) -> Vec<WorkerStats> {
+        self.workers.workers()
+    }
+}
+
+impl ServiceHandler<Self, GetWorkRequest> for Stratum {
+    fn handle(
+        &mut self,
+        _msg: GetWorkRequest,
+        _ctx: &mut ServiceContext<Self>,
+    ) -> Result<Option<StratumJob>> {
+        Ok(self
+            .sync_current_job()?
+            .map(|event| StratumJobResponse::from(&event, None, GETWORK_WORKER_ID, None).job))
     }
 }
 
@@ -164,7 +323,227 @@ pub struct StratumFactory;
 
 impl ServiceFactory<Stratum> for StratumFactory {
     fn create(ctx: &mut ServiceContext<Stratum>) -> Result<Stratum> {
+        let config = ctx.get_shared::<Arc<NodeConfig>>()?;
         let miner_service = ctx.service_ref::<MinerService>()?.clone();
-        Ok(Stratum::new(miner_service))
+        Ok(Stratum::new(
+            miner_service,
+            config.stratum.get_getwork_address().is_some(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Stratum, StratumFactory, JOB_QUEUE_SIZE};
+    use crate::difficulty_to_target_hex;
+    use crate::getwork::GETWORK_WORKER_ID;
+    use crate::rpc::{
+        LoginRequest, ShareRequest, StratumJob, StratumJobResponse, SubscribeJobEvent,
+    };
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+    use jsonrpc_pubsub::typed::Subscriber;
+    use starcoin_config::NodeConfig;
+    use starcoin_crypto::HashValue;
+    use starcoin_miner::{MinerService, UpdateSubscriberNumRequest};
+    use starcoin_service_registry::bus::{Bus, BusService};
+    use starcoin_service_registry::mocker::mock;
+    use starcoin_service_registry::{RegistryAsyncService, RegistryService};
+    use starcoin_types::genesis_config::ConsensusStrategy;
+    use starcoin_types::system_events::MintBlockEvent;
+    use starcoin_types::U256;
+    use std::sync::Arc;
+
+    fn job_of(blob: u8) -> MintBlockEvent {
+        MintBlockEvent::new(
+            HashValue::random(),
+            ConsensusStrategy::Keccak,
+            vec![blob; 76],
+            U256::one(),
+            1,
+            None,
+        )
+    }
+
+    fn login_of(name: &str) -> LoginRequest {
+        LoginRequest {
+            login: name.into(),
+            pass: "test".into(),
+            agent: "test".into(),
+            algo: None,
+        }
+    }
+
+    #[stest::test]
+    async fn test_worker_stats() {
+        let registry = RegistryService::launch();
+        // the job of difficulty 1, every nonce is a solution.
+        let job = job_of(1);
+        let current_job = job.clone();
+        let miner_service = registry
+            .register_mocker::<MinerService, _>(mock(move |r, _| {
+                if r.downcast_ref::<UpdateSubscriberNumRequest>().is_some() {
+                    Box::new(Some(current_job.clone()))
+                } else {
+                    Box::new(anyhow::Result::Ok(HashValue::zero()))
+                }
+            }))
+            .await
+            .unwrap();
+        let mut stratum = Stratum::new(miner_service, false);
+        let (sender, _receiver) = mpsc::channel(JOB_QUEUE_SIZE);
+        let (alice, bob) = (login_of("alice"), login_of("bob"));
+        stratum.add_worker(1, sender.clone(), alice.clone());
+        stratum.add_worker(2, sender, bob.clone());
+
+        let job_id = hex::encode(&job.minting_blob[0..8]);
+        let share = |worker_id: [u8; 4], nonce: &str| ShareRequest {
+            id: hex::encode(worker_id),
+            job_id: job_id.clone(),
+            nonce: nonce.into(),
+            result: String::new(),
+        };
+        let (alice_id, bob_id) = (alice.get_worker_id(1), bob.get_worker_id(2));
+        assert!(stratum.submit_share(share(alice_id, "01000000")).is_ok());
+        // the duplicate share is rejected.
+        assert!(stratum.submit_share(share(alice_id, "01000000")).is_err());
+        assert!(stratum.submit_share(share(bob_id, "02000000")).is_ok());
+        // the worker without login is unauthorized.
+        assert!(stratum
+            .submit_share(share(GETWORK_WORKER_ID, "03000000"))
+            .is_err());
+
+        let mut workers = stratum.workers.workers();
+        workers.sort_by(|a, b| a.login.cmp(&b.login));
+        assert_eq!(workers.len(), 2);
+        assert_eq!(workers[0].login, "alice");
+        assert_eq!(workers[0].worker_id, hex::encode(alice_id));
+        assert_eq!(workers[0].shares_submitted, 2);
+        assert_eq!(workers[0].shares_accepted, 1);
+        assert_eq!(workers[0].shares_rejected, 1);
+        assert_eq!(workers[1].login, "bob");
+        assert_eq!(workers[1].shares_submitted, 1);
+        assert_eq!(workers[1].shares_accepted, 1);
+        assert_eq!(workers[1].shares_rejected, 0);
+
+        // the shares are unauthorized after the worker is removed.
+        stratum.remove_worker(2);
+        assert!(stratum.submit_share(share(bob_id, "04000000")).is_err());
+        assert_eq!(stratum.workers.workers().len(), 1);
+
+        registry.shutdown_system().await.unwrap();
+    }
+
+    /// Read the job of the next notification sent to the subscriber.
+    async fn next_job(notifications: &mut mpsc::UnboundedReceiver<String>) -> StratumJob {
+        let notification: serde_json::Value =
+            serde_json::from_str(&notifications.next().await.unwrap()).unwrap();
+        let response: StratumJobResponse =
+            serde_json::from_value(notification["params"]["result"].clone()).unwrap();
+        response.job
+    }
+
+    #[stest::test]
+    async fn test_job_target() {
+        let registry = RegistryService::launch();
+        registry
+            .put_shared(Arc::new(NodeConfig::random_for_test()))
+            .await
+            .unwrap();
+        let job = MintBlockEvent::new(
+            HashValue::random(),
+            ConsensusStrategy::Keccak,
+            vec![1u8; 76],
+            U256::from(4096u64),
+            1,
+            None,
+        );
+        let current_job = job.clone();
+        registry
+            .register_mocker::<MinerService, _>(mock(move |_, _| {
+                Box::new(Some(current_job.clone()))
+            }))
+            .await
+            .unwrap();
+        let stratum = registry
+            .register_by_factory::<Stratum, StratumFactory>()
+            .await
+            .unwrap();
+
+        // the worker gets the current job once it logs in.
+        let (subscriber, _id, mut notifications) = Subscriber::new_test("job");
+        stratum
+            .send(SubscribeJobEvent(subscriber, login_of("alice")))
+            .await
+            .unwrap();
+        let stratum_job = next_job(&mut notifications).await;
+        assert_eq!(stratum_job.job_id, hex::encode(&job.minting_blob[0..8]));
+        assert_eq!(stratum_job.target, difficulty_to_target_hex(job.difficulty));
+        assert_eq!(stratum_job.difficulty().unwrap(), job.difficulty);
+
+        // the job broadcast by the miner has the target of its own difficulty.
+        let job = MintBlockEvent::new(
+            HashValue::random(),
+            ConsensusStrategy::Keccak,
+            vec![2u8; 76],
+            U256::from(1024u64),
+            2,
+            None,
+        );
+        let bus = registry.service_ref::<BusService>().await.unwrap();
+        bus.broadcast(job.clone()).unwrap();
+        let stratum_job = next_job(&mut notifications).await;
+        assert_eq!(stratum_job.job_id, hex::encode(&job.minting_blob[0..8]));
+        assert_eq!(stratum_job.target, difficulty_to_target_hex(job.difficulty));
+        assert_eq!(stratum_job.difficulty().unwrap(), job.difficulty);
+
+        registry.shutdown_system().await.unwrap();
+    }
+
+    #[stest::test]
+    async fn test_stuck_worker_not_block_others() {
+        let registry = RegistryService::launch();
+        let miner_service = registry
+            .register_mocker::<MinerService, _>(mock(|_, _| Box::new(())))
+            .await
+            .unwrap();
+        let mut stratum = Stratum::new(miner_service, false);
+        let login = LoginRequest {
+            login: "test".into(),
+            pass: "test".into(),
+            agent: "test".into(),
+            algo: None,
+        };
+        let (fast_sender, mut fast_receiver) = mpsc::channel(JOB_QUEUE_SIZE);
+        let (slow_sender, mut slow_receiver) = mpsc::channel(JOB_QUEUE_SIZE);
+        for (id, sender) in [(1, fast_sender), (2, slow_sender)] {
+            stratum
+                .mint_block_subscribers
+                .insert(id, (sender, login.clone()));
+            stratum.nonce_ranges.add_worker(id);
+        }
+
+        // the slow worker never takes its jobs, the fast one gets every job at once.
+        for round in 0..(JOB_QUEUE_SIZE as u8 * 2) {
+            stratum.send_to_all(job_of(round));
+            let job = fast_receiver.try_next().unwrap().unwrap();
+            assert_eq!(job.job.job_id, hex::encode([round; 8]));
+        }
+        // the stuck worker is dropped once its queue is full, the queued jobs are kept.
+        assert!(stratum.mint_block_subscribers.contains_key(&1));
+        assert!(!stratum.mint_block_subscribers.contains_key(&2));
+        let mut queued = 0;
+        while let Ok(Some(_)) = slow_receiver.try_next() {
+            queued += 1;
+        }
+        // the channel has a slot for its sender besides the buffer.
+        assert_eq!(queued, JOB_QUEUE_SIZE + 1);
+
+        // the closed session is pruned.
+        drop(fast_receiver);
+        stratum.send_to_all(job_of(0));
+        assert!(stratum.mint_block_subscribers.is_empty());
+
+        registry.shutdown_system().await.unwrap();
     }
 }
@@ -0,0 +1,82 @@
+use starcoin_service_registry::ServiceRequest;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The share accounting of an authorized worker.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkerStats {
+    /// The hex of the worker id, the same as the `id` of its jobs and shares.
+    pub worker_id: String,
+    /// The login name the worker is authorized by.
+    pub login: String,
+    /// The unix timestamp in seconds of the login.
+    pub connected_at: u64,
+    pub shares_submitted: u64,
+    pub shares_accepted: u64,
+    pub shares_rejected: u64,
+}
+
+/// The workers authorized by the login, only their shares are accepted.
+#[derive(Clone, Debug, Default)]
+pub struct WorkerRegistry {
+    /// The worker id to the subscription id and stats of the worker.
+    workers: HashMap<[u8; 4], (u32, WorkerStats)>,
+}
+
+impl WorkerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn authorize(&mut self, sub_id: u32, worker_id: [u8; 4], login: &str) {
+        let connected_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let stats = WorkerStats {
+            worker_id: hex::encode(worker_id),
+            login: login.to_string(),
+            connected_at,
+            shares_submitted: 0,
+            shares_accepted: 0,
+            shares_rejected: 0,
+        };
+        self.workers.insert(worker_id, (sub_id, stats));
+    }
+
+    /// Remove the worker of the subscription.
+    pub fn remove(&mut self, sub_id: u32) {
+        self.workers.retain(|_, (id, _)| *id != sub_id);
+    }
+
+    pub fn is_authorized(&self, worker_id: &[u8; 4]) -> bool {
+        self.workers.contains_key(worker_id)
+    }
+
+    /// Count a share of the worker, do nothing if the worker is not authorized.
+    pub fn record_share(&mut self, worker_id: &[u8; 4], accepted: bool) {
+        if let Some((_, stats)) = self.workers.get_mut(worker_id) {
+            stats.shares_submitted = stats.shares_submitted.saturating_add(1);
+            if accepted {
+                stats.shares_accepted = stats.shares_accepted.saturating_add(1);
+            } else {
+                stats.shares_rejected = stats.shares_rejected.saturating_add(1);
+            }
+        }
+    }
+
+    pub fn workers(&self) -> Vec<WorkerStats> {
+        self.workers
+            .values()
+            .map(|(_, stats)| stats.clone())
+            .collect()
+    }
+}
+
+/// Get the stats of all the authorized workers.
+#[derive(Debug)]
+pub struct WorkerStatsRequest;
+
+impl ServiceRequest for WorkerStatsRequest {
+    type Response = Vec<WorkerStats>;
+}
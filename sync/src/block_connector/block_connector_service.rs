@@ -1,7 +1,9 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::block_connector::{ExecuteRequest, ResetRequest, WriteBlockChainService};
+use crate::block_connector::{
+    ExecuteRequest, FinalizeRequest, ResetRequest, WriteBlockChainService,
+};
 use crate::sync::{CheckSyncEvent, SyncService};
 use crate::tasks::{BlockConnectedEvent, BlockDiskCheckEvent};
 use anyhow::{format_err, Result};
@@ -16,7 +18,7 @@ use starcoin_service_registry::{
 };
 use starcoin_storage::{BlockStore, Storage};
 use starcoin_sync_api::PeerNewBlock;
-use starcoin_types::block::ExecutedBlock;
+use starcoin_types::block::{BlockNumber, ExecutedBlock};
 use starcoin_types::sync_status::SyncStatus;
 use starcoin_types::system_events::{MinedBlock, SyncStatusChangeEvent, SystemShutdown};
 use std::sync::Arc;
@@ -267,6 +269,16 @@ impl ServiceHandler<Self, ResetRequest> for BlockConnectorService {
     }
 }
 
+impl ServiceHandler<Self, FinalizeRequest> for BlockConnectorService {
+    fn handle(
+        &mut self,
+        msg: FinalizeRequest,
+        _ctx: &mut ServiceContext<BlockConnectorService>,
+    ) -> Result<BlockNumber> {
+        self.chain_service.finalize(msg.number)
+    }
+}
+
 impl ServiceHandler<Self, ExecuteRequest> for BlockConnectorService {
     fn handle(
         &mut self,
@@ -3,7 +3,7 @@
 
 use starcoin_crypto::HashValue;
 use starcoin_service_registry::ServiceRequest;
-use starcoin_types::block::{Block, ExecutedBlock};
+use starcoin_types::block::{Block, BlockNumber, ExecutedBlock};
 
 mod block_connector_service;
 mod metrics;
@@ -32,6 +32,16 @@ impl ServiceRequest for ResetRequest {
     type Response = anyhow::Result<()>;
 }
 
+/// Finalize the main chain up to the block `number`, the response is the finalized number.
+#[derive(Debug, Clone)]
+pub struct FinalizeRequest {
+    pub number: BlockNumber,
+}
+
+impl ServiceRequest for FinalizeRequest {
+    type Response = anyhow::Result<BlockNumber>;
+}
+
 #[derive(Debug, Clone)]
 pub struct ExecuteRequest {
     pub block: Block,
@@ -10,10 +10,11 @@ use starcoin_chain_service::WriteableChainService;
 use starcoin_genesis::Genesis as StarcoinGenesis;
 use starcoin_service_registry::bus::BusService;
 use starcoin_service_registry::{RegistryAsyncService, RegistryService};
+use starcoin_storage::block_info::BlockInfoStore;
 use starcoin_storage::Store;
 use starcoin_time_service::TimeService;
 use starcoin_txpool_mock_service::MockTxPoolService;
-use starcoin_types::block::Block;
+use starcoin_types::block::{Block, BlockHeader};
 use starcoin_types::startup_info::StartupInfo;
 use std::sync::Arc;
 
@@ -242,3 +243,192 @@ async fn test_block_chain_reset() -> anyhow::Result<()> {
         .is_some());
     Ok(())
 }
+
+fn try_connect_fork_blocks(
+    fork_number: u64,
+    node_config: Arc<NodeConfig>,
+    times: u64,
+    writeable_block_chain_service: &mut WriteBlockChainService<MockTxPoolService>,
+) -> Vec<(Block, anyhow::Result<()>)> {
+    let miner_account = AccountInfo::random();
+    let net = node_config.net();
+    let mut parent_id = writeable_block_chain_service
+        .get_main()
+        .get_header_by_number(fork_number)
+        .unwrap()
+        .unwrap()
+        .id();
+    let mut results = vec![];
+    for _i in 0..times {
+        let block_chain = BlockChain::new(
+            net.time_service(),
+            parent_id,
+            writeable_block_chain_service.get_main().get_storage(),
+            None,
+        )
+        .unwrap();
+        let (block_template, _) = block_chain
+            .create_block_template(*miner_account.address(), None, Vec::new(), vec![], None)
+            .unwrap();
+        let block = block_chain
+            .consensus()
+            .create_block(block_template, net.time_service().as_ref())
+            .unwrap();
+        parent_id = block.id();
+        let result = writeable_block_chain_service.try_connect(block.clone());
+        results.push((block, result));
+    }
+    results
+}
+
+/// Assert the fork blocks lighter than the main chain are accepted into a branch without
+/// switching the head, and all the blocks from the first heavier one are rejected by `reason`.
+fn assert_fork_rejected(
+    results: &[(Block, anyhow::Result<()>)],
+    reason: &str,
+    writeable_block_chain_service: &WriteBlockChainService<MockTxPoolService>,
+    main_head: &BlockHeader,
+) {
+    let rejected_from = results
+        .iter()
+        .position(|(_, result)| result.is_err())
+        .expect("the fork should be rejected");
+    // the first fork block is far lighter than the main chain.
+    assert!(rejected_from > 0);
+    let main = writeable_block_chain_service.get_main();
+    for (block, result) in &results[..rejected_from] {
+        assert!(result.is_ok());
+        assert!(main
+            .get_storage()
+            .get_block_info(block.id())
+            .unwrap()
+            .is_some());
+        assert!(!main.exist_block(block.id()).unwrap());
+    }
+    for (_, result) in &results[rejected_from..] {
+        let err = result.as_ref().unwrap_err().to_string();
+        assert!(err.contains(reason), "unexpected error: {}", err);
+    }
+    assert_eq!(main.current_header().id(), main_head.id());
+}
+
+#[stest::test(timeout = 120)]
+async fn test_block_chain_max_reorg_depth() {
+    let times = 10;
+    let (mut writeable_block_chain_service, node_config, _) = create_writeable_block_chain().await;
+    writeable_block_chain_service.set_max_reorg_depth(3);
+    let net = node_config.net();
+    gen_blocks(
+        times,
+        &mut writeable_block_chain_service,
+        net.time_service().as_ref(),
+    );
+    let main_head = writeable_block_chain_service.get_main().current_header();
+    assert_eq!(main_head.number(), times);
+
+    // switching to the fork of block 2 requires rolling back 8 blocks.
+    let results = try_connect_fork_blocks(
+        2,
+        node_config.clone(),
+        2 * times,
+        &mut writeable_block_chain_service,
+    );
+    assert_fork_rejected(
+        &results,
+        "exceeds the max reorg depth",
+        &writeable_block_chain_service,
+        &main_head,
+    );
+
+    // switching to the fork of block 8 only rolls back 2 blocks.
+    let results =
+        try_connect_fork_blocks(8, node_config, times, &mut writeable_block_chain_service);
+    assert!(results.iter().all(|(_, result)| result.is_ok()));
+    assert_eq!(
+        writeable_block_chain_service
+            .get_main()
+            .current_header()
+            .number(),
+        8 + times
+    );
+}
+
+#[stest::test(timeout = 120)]
+async fn test_block_chain_finalized() -> anyhow::Result<()> {
+    let times = 10;
+    let (mut writeable_block_chain_service, node_config, _) = create_writeable_block_chain().await;
+    let net = node_config.net();
+    gen_blocks(
+        times,
+        &mut writeable_block_chain_service,
+        net.time_service().as_ref(),
+    );
+    let main_head = writeable_block_chain_service.get_main().current_header();
+    assert_eq!(writeable_block_chain_service.finalize(5)?, 5);
+
+    // the fork of block 2 is below the finalized block.
+    let results = try_connect_fork_blocks(
+        2,
+        node_config.clone(),
+        2 * times,
+        &mut writeable_block_chain_service,
+    );
+    assert_fork_rejected(
+        &results,
+        "below the finalized number",
+        &writeable_block_chain_service,
+        &main_head,
+    );
+    let block = writeable_block_chain_service
+        .get_main()
+        .get_block_by_number(3)?
+        .unwrap();
+    assert!(writeable_block_chain_service.reset(block.id()).is_err());
+
+    // the fork of block 6 is above the finalized block.
+    let results =
+        try_connect_fork_blocks(6, node_config, times, &mut writeable_block_chain_service);
+    assert!(results.iter().all(|(_, result)| result.is_ok()));
+    assert_eq!(
+        writeable_block_chain_service
+            .get_main()
+            .current_header()
+            .number(),
+        6 + times
+    );
+    Ok(())
+}
+
+#[stest::test(timeout = 120)]
+async fn test_block_chain_auto_finalize() -> anyhow::Result<()> {
+    let times = 10;
+    let (mut writeable_block_chain_service, node_config, _) = create_writeable_block_chain().await;
+    writeable_block_chain_service.set_max_reorg_depth(3);
+    let net = node_config.net();
+    gen_blocks(
+        times,
+        &mut writeable_block_chain_service,
+        net.time_service().as_ref(),
+    );
+    // nothing is finalized without the explicit request by default.
+    assert_eq!(
+        writeable_block_chain_service
+            .get_main()
+            .finalized_number()?,
+        0
+    );
+
+    writeable_block_chain_service.set_auto_finalize(true);
+    gen_blocks(
+        1,
+        &mut writeable_block_chain_service,
+        net.time_service().as_ref(),
+    );
+    assert_eq!(
+        writeable_block_chain_service
+            .get_main()
+            .finalized_number()?,
+        times + 1 - 3
+    );
+    Ok(())
+}
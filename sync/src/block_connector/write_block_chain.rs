@@ -2,11 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::block_connector::metrics::ChainMetrics;
-use anyhow::{format_err, Result};
+use anyhow::{ensure, format_err, Result};
 use config::NodeConfig;
 use executor::VMMetrics;
 use logger::prelude::*;
-use starcoin_chain::BlockChain;
+use starcoin_chain::{BlockChain, ForkChoice, TotalDifficultyForkChoice};
 use starcoin_chain_api::{ChainReader, ChainWriter, ConnectBlockError, WriteableChainService};
 use starcoin_crypto::HashValue;
 use starcoin_service_registry::bus::{Bus, BusService};
@@ -15,7 +15,7 @@ use starcoin_storage::Store;
 use starcoin_txpool_api::TxPoolSyncService;
 use starcoin_types::block::BlockInfo;
 use starcoin_types::{
-    block::{Block, BlockHeader, ExecutedBlock},
+    block::{Block, BlockHeader, BlockNumber, ExecutedBlock},
     startup_info::StartupInfo,
     system_events::{NewBranch, NewHeadBlock},
 };
@@ -36,6 +36,9 @@ where
     bus: ServiceRef<BusService>,
     metrics: Option<ChainMetrics>,
     vm_metrics: Option<VMMetrics>,
+    fork_choice: Arc<dyn ForkChoice>,
+    max_reorg_depth: u64,
+    auto_finalize: bool,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -106,16 +109,23 @@ where
         vm_metrics: Option<VMMetrics>,
     ) -> Result<Self> {
         let net = config.net();
-        let main = BlockChain::new(
+        let mut main = BlockChain::new(
             net.time_service(),
             startup_info.main,
             storage.clone(),
             vm_metrics.clone(),
         )?;
+        if let Some(max_future_block_time) = config.sync.max_future_block_time() {
+            main.set_max_future_block_time(max_future_block_time);
+        }
+        main.set_difficulty_floor(net.genesis_config().difficulty_floor);
+        main.set_block_bytes_limit(net.genesis_config().block_bytes_limit);
         let metrics = config
             .metrics
             .registry()
             .and_then(|registry| ChainMetrics::register(registry).ok());
+        let max_reorg_depth = config.sync.max_reorg_depth();
+        let auto_finalize = config.sync.auto_finalize();
 
         Ok(Self {
             config,
@@ -126,9 +136,27 @@ where
             bus,
             metrics,
             vm_metrics,
+            fork_choice: Arc::new(TotalDifficultyForkChoice),
+            max_reorg_depth,
+            auto_finalize,
         })
     }
 
+    /// Replace the default heaviest chain rule.
+    pub fn set_fork_choice(&mut self, fork_choice: Arc<dyn ForkChoice>) {
+        self.fork_choice = fork_choice;
+    }
+
+    /// Override the `max_reorg_depth` of the sync config.
+    pub fn set_max_reorg_depth(&mut self, max_reorg_depth: u64) {
+        self.max_reorg_depth = max_reorg_depth;
+    }
+
+    /// Override the `auto_finalize` of the sync config.
+    pub fn set_auto_finalize(&mut self, auto_finalize: bool) {
+        self.auto_finalize = auto_finalize;
+    }
+
     fn find_or_fork(
         &self,
         header: &BlockHeader,
@@ -139,28 +167,33 @@ where
             if self.is_main_head(&header.parent_hash()) {
                 None
             } else {
-                let net = self.config.net();
-                Some(BlockChain::new(
-                    net.time_service(),
-                    block_id,
-                    self.storage.clone(),
-                    self.vm_metrics.clone(),
-                )?)
+                Some(self.open_chain(block_id)?)
             }
         } else if self.block_exist(header.parent_hash())? {
-            let net = self.config.net();
-            Some(BlockChain::new(
-                net.time_service(),
-                header.parent_hash(),
-                self.storage.clone(),
-                self.vm_metrics.clone(),
-            )?)
+            Some(self.open_chain(header.parent_hash())?)
         } else {
             None
         };
         Ok((block_info, block_chain))
     }
 
+    /// Open the chain at `head_id`, with the max future timestamp skew of the sync config, and
+    /// the difficulty floor and block bytes limit of the genesis config.
+    fn open_chain(&self, head_id: HashValue) -> Result<BlockChain> {
+        let mut chain = BlockChain::new(
+            self.config.net().time_service(),
+            head_id,
+            self.storage.clone(),
+            self.vm_metrics.clone(),
+        )?;
+        if let Some(max_future_block_time) = self.config.sync.max_future_block_time() {
+            chain.set_max_future_block_time(max_future_block_time);
+        }
+        chain.set_difficulty_floor(self.config.net().genesis_config().difficulty_floor);
+        chain.set_block_bytes_limit(self.config.net().genesis_config().block_bytes_limit);
+        Ok(chain)
+    }
+
     fn block_exist(&self, block_id: HashValue) -> Result<bool> {
         Ok(matches!(self.storage.get_block_info(block_id)?, Some(_)))
     }
@@ -171,17 +204,45 @@ where
 
     pub fn select_head(&mut self, new_branch: BlockChain) -> Result<()> {
         let executed_block = new_branch.head_block();
-        let main_total_difficulty = self.main.get_total_difficulty()?;
-        let branch_total_difficulty = new_branch.get_total_difficulty()?;
+        let main_head = self.main.current_header();
+        let selected = self.fork_choice.select_head(
+            &[main_head.clone(), executed_block.header().clone()],
+            self.storage.as_ref(),
+        )?;
         let parent_is_main_head = self.is_main_head(&executed_block.header().parent_hash());
 
-        if branch_total_difficulty > main_total_difficulty {
+        if selected != main_head.id() {
             let (enacted_count, enacted_blocks, retracted_count, retracted_blocks) =
                 if !parent_is_main_head {
                     self.find_ancestors_from_accumulator(&new_branch)?
                 } else {
                     (1, vec![executed_block.block.clone()], 0, vec![])
                 };
+            // the main chain blocks deeper than the limit are final, never roll back them.
+            if retracted_count > self.max_reorg_depth {
+                error!(
+                    "[chain] Refuse to switch to branch {:?}, it requires rolling back {} blocks, exceeds the max reorg depth {}, please review the branch.",
+                    new_branch.status(),
+                    retracted_count,
+                    self.max_reorg_depth
+                );
+                return Err(format_err!(
+                    "Reorg depth {} exceeds the max reorg depth {}",
+                    retracted_count,
+                    self.max_reorg_depth
+                ));
+            }
+            // the fork point is the highest block kept in the main chain.
+            let fork_number = main_head.number().saturating_sub(retracted_count);
+            let finalized_number = self.main.finalized_number()?;
+            if fork_number < finalized_number {
+                return Err(format_err!(
+                    "Can not switch to branch {:?}, it forks at {} below the finalized number {}",
+                    new_branch.status(),
+                    fork_number,
+                    finalized_number
+                ));
+            }
             self.main = new_branch;
 
             self.do_new_head(
@@ -233,21 +294,39 @@ where
 
         self.broadcast_new_head(executed_block);
 
+        // the blocks deeper than the max reorg depth are never rolled back, so they are final.
+        // The head has switched, a failure only delays the finalization to the next head.
+        if self.auto_finalize {
+            if let Err(e) = self.main.finalize_by_depth(self.max_reorg_depth) {
+                warn!(
+                    "[chain] Finalize the blocks {} below the head failed: {:?}",
+                    self.max_reorg_depth, e
+                );
+            }
+        }
+
         Ok(())
     }
 
+    /// Finalize the main chain up to the block `number`, return the finalized number.
+    pub fn finalize(&mut self, number: BlockNumber) -> Result<BlockNumber> {
+        self.main.finalize(number)
+    }
+
     /// Reset the node to `block_id`, and replay blocks after the block
     pub fn reset(&mut self, block_id: HashValue) -> Result<()> {
         let new_head_block = self
             .main
             .get_block(block_id)?
             .ok_or_else(|| format_err!("Can not find block {} in main chain", block_id,))?;
-        let new_branch = BlockChain::new(
-            self.config.net().time_service(),
+        let finalized_number = self.main.finalized_number()?;
+        ensure!(
+            new_head_block.header().number() >= finalized_number,
+            "Can not reset to block {} below the finalized number {}",
             block_id,
-            self.storage.clone(),
-            self.vm_metrics.clone(),
-        )?;
+            finalized_number
+        );
+        let new_branch = self.open_chain(block_id)?;
 
         // delete block since from block.number + 1 to latest.
         let start = new_head_block.header().number().saturating_add(1);
@@ -279,12 +358,7 @@ where
 
     ///Directly execute the block and save result, do not try to connect.
     pub fn execute(&mut self, block: Block) -> Result<ExecutedBlock> {
-        let chain = BlockChain::new(
-            self.config.net().time_service(),
-            block.header().parent_hash(),
-            self.storage.clone(),
-            self.vm_metrics.clone(),
-        )?;
+        let chain = self.open_chain(block.header().parent_hash())?;
         let verify_block = chain.verify(block)?;
         chain.execute(verify_block)
     }
@@ -295,7 +369,8 @@ where
 
     fn update_startup_info(&mut self, main_head: &BlockHeader) -> Result<()> {
         self.startup_info.update_main(main_head.id());
-        self.storage.save_startup_info(self.startup_info.clone())
+        self.storage
+            .save_main_head(self.startup_info.clone(), self.main.status())
     }
 
     fn commit_2_txpool(&self, enacted: Vec<Block>, retracted: Vec<Block>) {
@@ -3,7 +3,7 @@
 
 use crate::block_connector::BlockConnectorService;
 use crate::sync_metrics::SyncMetrics;
-use crate::tasks::{full_sync_task, AncestorEvent, SyncFetcher};
+use crate::tasks::{full_sync_task, AncestorEvent, SyncChainOptions, SyncFetcher};
 use crate::verified_rpc_client::{RpcVerifyError, VerifiedRpcClient};
 use anyhow::{format_err, Result};
 use config::NodeConfig;
@@ -233,6 +233,7 @@ impl SyncService {
                     self_ref.clone(),
                     network.clone(),
                     config.sync.max_retry_times(),
+                    SyncChainOptions::new(&config),
                     sync_metrics.clone(),
                     vm_metrics.clone(),
                 )?;
@@ -340,7 +340,9 @@ where
         }
     }
 
-    fn finish(self) -> Result<Self::Output> {
+    /// The blocks applied since the last flush of the flush cadence are flushed at the end.
+    fn finish(mut self) -> Result<Self::Output> {
+        self.chain.flush()?;
         Ok(self.chain)
     }
 }
@@ -1,6 +1,6 @@
 use crate::tasks::{
     AccumulatorCollector, BlockAccumulatorSyncTask, BlockCollector, BlockConnectedEventHandle,
-    BlockFetcher, BlockIdFetcher, BlockSyncTask, PeerOperator,
+    BlockFetcher, BlockIdFetcher, BlockSyncTask, PeerOperator, SyncChainOptions,
 };
 use anyhow::format_err;
 use executor::VMMetrics;
@@ -81,6 +81,7 @@ where
         max_retry_times: u64,
         delay_milliseconds_on_error: u64,
         skip_pow_verify_when_sync: bool,
+        chain_options: SyncChainOptions,
         vm_metrics: Option<VMMetrics>,
     ) -> Result<(BlockChain, TaskHandle), TaskError> {
         let buffer_size = self.target.peers.len();
@@ -127,12 +128,13 @@ where
                 self.storage.clone(),
                 1,
             );
-            let chain = BlockChain::new(
+            let mut chain = BlockChain::new(
                 self.time_service.clone(),
                 ancestor.id,
                 self.storage.clone(),
                 vm_metrics,
             )?;
+            chain_options.apply_to(&mut chain);
             let block_collector = BlockCollector::new_with_handle(
                 current_block_info.clone(),
                 self.target.clone(),
@@ -5,6 +5,7 @@ use crate::tasks::block_sync_task::SyncBlockData;
 use crate::tasks::inner_sync_task::InnerSyncTask;
 use crate::verified_rpc_client::{RpcVerifyError, VerifiedRpcClient};
 use anyhow::{format_err, Error, Result};
+use config::NodeConfig;
 use futures::channel::mpsc::UnboundedSender;
 use futures::future::BoxFuture;
 use futures::{FutureExt, TryFutureExt};
@@ -13,7 +14,7 @@ use network_api::{PeerId, PeerProvider, PeerSelector};
 use network_rpc_core::{NetRpcError, RpcErrorCode};
 use starcoin_accumulator::node::AccumulatorStoreType;
 use starcoin_accumulator::MerkleAccumulator;
-use starcoin_chain::{BlockChain, ChainReader};
+use starcoin_chain::{BlockChain, ChainReader, FlushCadence};
 use starcoin_crypto::HashValue;
 use starcoin_service_registry::{ActorService, EventHandler, ServiceRef};
 use starcoin_storage::Store;
@@ -22,6 +23,7 @@ use starcoin_time_service::TimeService;
 use starcoin_types::block::{Block, BlockIdAndNumber, BlockInfo, BlockNumber};
 use starcoin_types::startup_info::ChainStatus;
 use starcoin_types::U256;
+use starcoin_vm_types::genesis_config::{BlockBytesLimit, DifficultyFloor};
 use std::str::FromStr;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
@@ -517,6 +519,40 @@ pub use block_sync_task::{BlockCollector, BlockSyncTask};
 use executor::VMMetrics;
 pub use find_ancestor_task::{AncestorCollector, FindAncestorTask};
 
+/// The options of the chains which apply the synced blocks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SyncChainOptions {
+    pub flush_cadence: FlushCadence,
+    /// The max milliseconds of a block timestamp ahead of the local time, the chain default if
+    /// None.
+    pub max_future_block_time: Option<u64>,
+    pub difficulty_floor: Option<DifficultyFloor>,
+    pub block_bytes_limit: BlockBytesLimit,
+}
+
+impl SyncChainOptions {
+    pub fn new(config: &NodeConfig) -> Self {
+        Self {
+            flush_cadence: FlushCadence {
+                every_blocks: config.sync.flush_every_blocks(),
+                every_bytes: config.sync.flush_every_bytes(),
+            },
+            max_future_block_time: config.sync.max_future_block_time(),
+            difficulty_floor: config.net().genesis_config().difficulty_floor,
+            block_bytes_limit: config.net().genesis_config().block_bytes_limit,
+        }
+    }
+
+    pub(crate) fn apply_to(&self, chain: &mut BlockChain) {
+        chain.set_flush_cadence(self.flush_cadence);
+        if let Some(max_future_block_time) = self.max_future_block_time {
+            chain.set_max_future_block_time(max_future_block_time);
+        }
+        chain.set_difficulty_floor(self.difficulty_floor);
+        chain.set_block_bytes_limit(self.block_bytes_limit);
+    }
+}
+
 pub fn full_sync_task<H, A, F, N>(
     current_block_id: HashValue,
     target: SyncTarget,
@@ -528,6 +564,7 @@ pub fn full_sync_task<H, A, F, N>(
     ancestor_event_handle: A,
     peer_provider: N,
     max_retry_times: u64,
+    chain_options: SyncChainOptions,
     sync_metrics: Option<SyncMetrics>,
     vm_metrics: Option<VMMetrics>,
 ) -> Result<(
@@ -643,6 +680,7 @@ where
                     max_retry_times,
                     delay_milliseconds_on_error,
                     skip_pow_verify,
+                    chain_options,
                     vm_metrics.clone(),
                 )
                 .await?;
@@ -6,7 +6,8 @@ use crate::tasks::block_sync_task::SyncBlockData;
 use crate::tasks::mock::{ErrorStrategy, MockBlockIdFetcher, SyncNodeMocker};
 use crate::tasks::{
     full_sync_task, AccumulatorCollector, AncestorCollector, BlockAccumulatorSyncTask,
-    BlockCollector, BlockFetcher, BlockLocalStore, BlockSyncTask, FindAncestorTask, SyncFetcher,
+    BlockCollector, BlockFetcher, BlockLocalStore, BlockSyncTask, FindAncestorTask,
+    SyncChainOptions, SyncFetcher,
 };
 use crate::verified_rpc_client::RpcVerifyError;
 use anyhow::Context;
@@ -70,6 +71,7 @@ pub async fn test_full_sync_new_node() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -101,6 +103,7 @@ pub async fn test_full_sync_new_node() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -151,6 +154,7 @@ pub async fn test_sync_invalid_target() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -239,6 +243,7 @@ pub async fn test_full_sync_fork() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -272,6 +277,7 @@ pub async fn test_full_sync_fork() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -321,6 +327,7 @@ pub async fn test_full_sync_fork_from_genesis() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -375,6 +382,7 @@ pub async fn test_full_sync_continue() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -410,6 +418,7 @@ pub async fn test_full_sync_continue() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -462,6 +471,7 @@ pub async fn test_full_sync_cancel() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -905,6 +915,7 @@ async fn test_net_rpc_err() -> Result<()> {
         sender_2,
         DummyNetworkService::default(),
         15,
+        SyncChainOptions::default(),
         None,
         None,
     )?;
@@ -8,6 +8,9 @@ use starcoin_chain::ChainWriter;
 use starcoin_config::ChainNetwork;
 use starcoin_consensus::Consensus;
 use starcoin_genesis::Genesis;
+use starcoin_storage::storage::StorageInstance;
+use starcoin_storage::Storage;
+use std::sync::Arc;
 
 pub fn gen_blockchain_for_test(net: &ChainNetwork) -> Result<BlockChain> {
     let (storage, chain_info, _) =
@@ -17,6 +20,16 @@ pub fn gen_blockchain_for_test(net: &ChainNetwork) -> Result<BlockChain> {
     Ok(block_chain)
 }
 
+/// A chain at the genesis of the test network, which uses the dummy consensus, backed by the
+/// `MemoryStorage`, so nothing is evicted from the storage during the test.
+pub fn gen_blockchain_in_memory_for_test() -> Result<BlockChain> {
+    let net = ChainNetwork::new_test();
+    let storage = Arc::new(Storage::new(StorageInstance::new_memory_instance())?);
+    let genesis = Genesis::load_or_build(&net)?;
+    let chain_info = genesis.execute_genesis_block(&net, storage.clone())?;
+    BlockChain::new(net.time_service(), chain_info.head().id(), storage, None)
+}
+
 pub fn gen_blockchain_with_blocks_for_test(count: u64, net: &ChainNetwork) -> Result<BlockChain> {
     let mut block_chain = gen_blockchain_for_test(net)?;
     let miner_account = AccountInfo::random();
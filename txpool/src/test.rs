@@ -24,6 +24,7 @@ use tokio::time::sleep;
 use types::{
     account_address::{self, AccountAddress},
     account_config,
+    block::{Block, BlockBody, BlockHeaderBuilder},
     transaction::{SignedUserTransaction, Transaction, TransactionPayload},
     U256,
 };
@@ -294,6 +295,44 @@ async fn test_txpool_actor_service() {
     sleep(Duration::from_millis(300)).await;
 }
 
+#[stest::test]
+async fn test_evict_txns_of_enacted_blocks() -> Result<()> {
+    let (txpool_service, storage, config, _, _) = test_helper::start_txpool().await;
+    let intermediate_txn = generate_txn(config.clone(), 0);
+    let head_txn = generate_txn(config.clone(), 1);
+    let pending_txn = generate_txn(config, 2);
+    for result in txpool_service.add_txns(vec![
+        intermediate_txn.clone(),
+        head_txn.clone(),
+        pending_txn.clone(),
+    ]) {
+        result?;
+    }
+
+    // keep the chain state and time of the current head, so the cull does not drop the txns.
+    let main = storage.get_startup_info()?.unwrap().main;
+    let head = storage.get_block_header_by_hash(main)?.unwrap();
+    let new_block = |number: u64, txn: SignedUserTransaction| {
+        let header = BlockHeaderBuilder::random()
+            .with_number(number)
+            .with_state_root(head.state_root())
+            .with_timestamp(head.timestamp())
+            .build();
+        Block::new(header, BlockBody::new(vec![txn], None))
+    };
+    let enacted = vec![
+        new_block(head.number() + 1, intermediate_txn.clone()),
+        new_block(head.number() + 2, head_txn.clone()),
+    ];
+    txpool_service.chain_new_block(enacted, vec![])?;
+
+    // the txns of all the enacted blocks are evicted, not only the head's.
+    assert!(txpool_service.find_txn(&intermediate_txn.id()).is_none());
+    assert!(txpool_service.find_txn(&head_txn.id()).is_none());
+    assert!(txpool_service.find_txn(&pending_txn.id()).is_some());
+    Ok(())
+}
+
 fn generate_txn(config: Arc<NodeConfig>, seq: u64) -> SignedUserTransaction {
     let (_private_key, public_key) = KeyGen::from_os_rng().generate_keypair();
     let account_address = account_address::from_public_key(&public_key);
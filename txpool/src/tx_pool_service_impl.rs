@@ -20,6 +20,7 @@ use starcoin_config::NodeConfig;
 use starcoin_executor::VMMetrics;
 use starcoin_statedb::ChainStateDB;
 use starcoin_txpool_api::{TxPoolStatus, TxPoolSyncService};
+use std::collections::HashSet;
 use std::sync::Arc;
 use storage::Store;
 use types::{
@@ -283,6 +284,16 @@ impl Inner {
             .pop()
             .expect("remove should return one result per hash")
     }
+
+    /// Remove the txns applied in a block, return the number of txns removed from the pool.
+    pub(crate) fn remove_applied_txns(&self, txn_hashes: &[HashValue]) -> usize {
+        self.queue
+            .remove(txn_hashes, false)
+            .iter()
+            .filter(|removed| removed.is_some())
+            .count()
+    }
+
     pub(crate) fn get_pending(
         &self,
         max_len: u64,
@@ -335,16 +346,30 @@ impl Inner {
             self.notify_new_chain_header(block.header().clone());
         }
 
+        // evict the txns of all the enacted blocks, not only the head, so the miner does not
+        // select them again before the pool is culled by the new chain state.
+        let enacted_txns: HashSet<HashValue> = enacted
+            .iter()
+            .flat_map(|b| b.transactions().iter().map(|txn| txn.id()))
+            .collect();
+        let removed = self.remove_applied_txns(&enacted_txns.iter().copied().collect::<Vec<_>>());
+        debug!(
+            "Evict {} of {} txns applied in the enacted blocks",
+            removed,
+            enacted_txns.len()
+        );
+
         // remove outdated txns.
         self.cull();
 
-        // import retracted txns.
+        // import retracted txns, except the ones applied again in the enacted blocks.
         let txns = retracted
             .into_iter()
             .flat_map(|b| {
                 let txns: Vec<SignedUserTransaction> = b.into_inner().1.into();
                 txns.into_iter()
             })
+            .filter(|t| !enacted_txns.contains(&t.id()))
             .map(|t| PoolTransaction::Retracted(UnverifiedUserTransaction::from(t)));
         let results = self.queue.import(self.get_pool_client(), txns);
         for result in results {
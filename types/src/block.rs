@@ -584,6 +584,13 @@ impl BlockHeaderBuilder {
         self.buffer.id = Some(self.buffer.crypto_hash());
         self.buffer
     }
+
+    /// Build the header without recomputing the id, the id is kept from `as_builder`, so the
+    /// header may not match its id. Only for testing the id verification.
+    #[cfg(any(test, feature = "fuzzing"))]
+    pub fn build_with_stale_id(self) -> BlockHeader {
+        self.buffer
+    }
 }
 
 #[derive(
@@ -995,6 +1002,32 @@ impl ExecutedBlock {
     pub fn header(&self) -> &BlockHeader {
         self.block.header()
     }
+
+    /// The result of the block execution, read from the block and its info.
+    pub fn apply_result(&self) -> ApplyResult {
+        let header = self.header();
+        ApplyResult {
+            block_id: header.id(),
+            gas_used: header.gas_used(),
+            executed_txns: (self.block.transactions().len() as u64).saturating_add(1),
+            state_root: header.state_root(),
+            accumulator_root: self.block_info.txn_accumulator_info.accumulator_root,
+        }
+    }
+}
+
+/// What applying a block did to the chain, so the callers of `ChainWriter::apply` do not need to
+/// read the storage again.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ApplyResult {
+    pub block_id: HashValue,
+    pub gas_used: u64,
+    /// Count of the txns executed in the block, include the block metadata txn.
+    pub executed_txns: u64,
+    /// The state root after the block.
+    pub state_root: HashValue,
+    /// The root of the txn accumulator after the block.
+    pub accumulator_root: HashValue,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -1029,6 +1062,39 @@ impl Into<(BlockHeader, Vec<BlockHeader>)> for BlockSummary {
     }
 }
 
+/// The frequently queried fields of a block header and its `BlockInfo`, so the clients do not
+/// need to read the whole block.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BlockOverview {
+    pub id: HashValue,
+    pub number: BlockNumber,
+    pub parent_hash: HashValue,
+    pub timestamp: u64,
+    pub state_root: HashValue,
+    pub txn_accumulator_root: HashValue,
+    /// Count of the txns executed in the block, include the block metadata txn.
+    pub txn_count: u64,
+    pub gas_used: u64,
+    #[schemars(with = "String")]
+    pub total_difficulty: U256,
+}
+
+impl BlockOverview {
+    pub fn new(header: &BlockHeader, block_info: &BlockInfo, txn_count: u64) -> Self {
+        Self {
+            id: header.id(),
+            number: header.number(),
+            parent_hash: header.parent_hash(),
+            timestamp: header.timestamp(),
+            state_root: header.state_root(),
+            txn_accumulator_root: header.txn_accumulator_root(),
+            txn_count,
+            gas_used: header.gas_used(),
+            total_difficulty: block_info.total_difficulty,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UncleSummary {
     /// total uncle
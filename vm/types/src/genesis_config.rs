@@ -127,6 +127,69 @@ impl ConsensusStrategy {
     }
 }
 
+/// The floor of the retargeted block difficulty, so a chain restarted after a long stall can not
+/// fall to a trivial difficulty. It only applies from the block `activation_number`, the blocks
+/// before it keep the difficulty they were mined with.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct DifficultyFloor {
+    pub min_difficulty: u64,
+    pub activation_number: u64,
+}
+
+impl DifficultyFloor {
+    pub fn new(min_difficulty: u64, activation_number: u64) -> Self {
+        Self {
+            min_difficulty,
+            activation_number,
+        }
+    }
+
+    /// The min difficulty of the block `number`, None if the floor is not activated at it.
+    pub fn min_difficulty_at(&self, number: u64) -> Option<u64> {
+        if number >= self.activation_number {
+            Some(self.min_difficulty)
+        } else {
+            None
+        }
+    }
+}
+
+/// The default max bytes of the bcs encoded block, so a block always fits in a sync rpc response.
+pub const DEFAULT_MAX_BLOCK_BYTES: u64 = 32 * 1024 * 1024; // 32M
+
+/// The max bytes of the bcs encoded block, the encoding sent on the wire. The block template is
+/// filled up to it and the block verifier rejects the blocks above it, so they always agree. It
+/// only applies from the block `activation_number`, the blocks before it are not limited.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct BlockBytesLimit {
+    pub max_block_bytes: u64,
+    pub activation_number: u64,
+}
+
+impl BlockBytesLimit {
+    pub fn new(max_block_bytes: u64, activation_number: u64) -> Self {
+        Self {
+            max_block_bytes,
+            activation_number,
+        }
+    }
+
+    /// The max bytes of the block `number`, None if the limit is not activated at it.
+    pub fn max_block_bytes_at(&self, number: u64) -> Option<u64> {
+        if number >= self.activation_number {
+            Some(self.max_block_bytes)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for BlockBytesLimit {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BLOCK_BYTES, 0)
+    }
+}
+
 impl Default for ConsensusStrategy {
     fn default() -> Self {
         ConsensusStrategy::Dummy
@@ -828,6 +828,33 @@ impl RichTransactionInfo {
     }
 }
 
+/// `TransactionReceipt` is the execution output of a txn in the block `block_id`,
+/// it keeps the events of the txn, which is only committed by root hash in `TransactionInfo`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TransactionReceipt {
+    pub block_id: HashValue,
+    pub transaction_hash: HashValue,
+    pub gas_used: u64,
+    pub status: KeptVMStatus,
+    pub events: Vec<ContractEvent>,
+}
+
+impl TransactionReceipt {
+    pub fn new(
+        block_id: HashValue,
+        transaction_info: &TransactionInfo,
+        events: Vec<ContractEvent>,
+    ) -> Self {
+        Self {
+            block_id,
+            transaction_hash: transaction_info.transaction_hash(),
+            gas_used: transaction_info.gas_used(),
+            status: transaction_info.status().clone(),
+            events,
+        }
+    }
+}
+
 /// `Transaction` will be the transaction type used internally in the diem node to represent the
 /// transaction to be processed and persisted.
 ///